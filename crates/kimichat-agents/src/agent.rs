@@ -8,7 +8,7 @@ use futures::Stream;
 // Re-export types from kimichat-llm-api
 pub use kimichat_llm_api::{
     LlmClient, ChatMessage, ToolCall, FunctionCall, LlmResponse,
-    TokenUsage, ToolDefinition, StreamingChunk,
+    TokenUsage, ToolDefinition, StreamingChunk, StreamEvent,
 };
 
 /// Agent capabilities
@@ -138,7 +138,19 @@ pub struct ExecutionContext {
     pub terminal_manager: Option<std::sync::Arc<tokio::sync::Mutex<kimichat_terminal::TerminalManager>>>,
     pub skill_registry: Option<std::sync::Arc<kimichat_skills::SkillRegistry>>,
     pub todo_manager: Option<std::sync::Arc<kimichat_todo::TodoManager>>,
+    /// Background jobs started by run_command
+    pub job_manager: Option<std::sync::Arc<kimichat_toolcore::JobManager>>,
+    /// Keeps files as they were before the agents' tools changed them
+    pub checkpoint_store: Option<std::sync::Arc<kimichat_checkpoints::CheckpointStore>>,
     pub cancellation_token: Option<tokio_util::sync::CancellationToken>,
+    /// Directories outside the workspace that file tools may access
+    pub extra_roots: Vec<std::path::PathBuf>,
+    /// Answers policy "ask" decisions for the agents' tool calls
+    pub confirmation_provider: std::sync::Arc<dyn kimichat_toolcore::ConfirmationProvider>,
+    /// Kind of session the agents run in, for policy rules with a session condition
+    pub session_type: Option<kimichat_policy::SessionType>,
+    /// Where agents report permission violations
+    pub visibility_manager: Option<std::sync::Arc<tokio::sync::RwLock<crate::visibility::VisibilityManager>>>,
 }
 
 
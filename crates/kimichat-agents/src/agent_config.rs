@@ -1,7 +1,19 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use kimichat_policy::{ActionType, Decision, PolicyContext, PolicyManager, PolicyRule, SandboxConfig};
 use crate::agent::Capability;
 
+/// Actions that run commands, governed by `command_execution`
+const COMMAND_ACTIONS: [ActionType; 4] = [
+    ActionType::CommandExecution,
+    ActionType::GitOperation,
+    ActionType::TerminalLaunch,
+    ActionType::TerminalInput,
+];
+
+/// Commands that change the machine rather than the workspace
+const SYSTEM_COMMANDS: [&str; 8] = ["sudo", "su", "doas", "systemctl", "shutdown", "reboot", "mount", "chown"];
+
 /// Agent configuration loaded from JSON
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentConfig {
@@ -23,6 +35,81 @@ pub struct AgentPermissions {
     pub command_execution: Vec<String>,
     pub network_access: bool,
     pub system_modification: bool,
+    /// Run this agent's commands in the sandbox with these limits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<SandboxConfig>,
+}
+
+impl AgentPermissions {
+    /// Compile the permissions into policy rules for an agent layer
+    ///
+    /// The rules only deny: an allow rule here lets the action through to the
+    /// shared policy, which still decides.
+    pub fn to_policy_rules(&self) -> Vec<PolicyRule> {
+        let deny = |action: ActionType, pattern: &str, description: String| {
+            PolicyRule::new(action, pattern.to_string(), Decision::Deny).with_description(description)
+        };
+        let mut rules = Vec::new();
+
+        let denied_file_actions: &[ActionType] = match self.file_access {
+            FileAccessLevel::None => &[
+                ActionType::FileRead,
+                ActionType::FileWrite,
+                ActionType::FileEdit,
+                ActionType::FileDelete,
+                ActionType::ApplyEditPlan,
+            ],
+            FileAccessLevel::ReadOnly => &[
+                ActionType::FileWrite,
+                ActionType::FileEdit,
+                ActionType::FileDelete,
+                ActionType::ApplyEditPlan,
+            ],
+            FileAccessLevel::ReadWrite | FileAccessLevel::Unrestricted => &[],
+        };
+        for action in denied_file_actions {
+            rules.push(deny(
+                action.clone(),
+                "*",
+                format!("Agent has {} file access", self.file_access),
+            ));
+        }
+
+        if !self.command_execution.iter().any(|prefix| prefix == "*") {
+            for action in COMMAND_ACTIONS {
+                for prefix in &self.command_execution {
+                    rules.push(
+                        PolicyRule::new(action.clone(), format!("{} *", prefix), Decision::Allow)
+                            .with_description(format!("Agent may run {}", prefix)),
+                    );
+                }
+                let description = if self.command_execution.is_empty() {
+                    "Agent may not run commands".to_string()
+                } else {
+                    format!("Agent may only run: {}", self.command_execution.join(", "))
+                };
+                rules.push(deny(action, "*", description));
+            }
+        }
+
+        if !self.network_access {
+            rules.push(deny(ActionType::NetworkAccess, "*", "Agent has no network access".to_string()));
+        }
+
+        if !self.system_modification {
+            for action in COMMAND_ACTIONS {
+                for command in SYSTEM_COMMANDS {
+                    rules.push(deny(
+                        action.clone(),
+                        &format!("{} *", command),
+                        "Agent may not modify the system".to_string(),
+                    ));
+                }
+            }
+        }
+
+        rules
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -69,21 +156,28 @@ impl AgentConfig {
     }
 
     pub fn can_execute_command(&self, command: &str) -> bool {
-        if self.permissions.command_execution.is_empty() {
-            return false;
-        }
+        let policy = PolicyManager::allow_all().with_agent_layer(self.permissions.to_policy_rules());
+        policy.explain(&ActionType::CommandExecution, command, &PolicyContext::default()).decision == Decision::Allow
+    }
 
-        // Check if command matches any allowed pattern
-        for pattern in &self.permissions.command_execution {
-            if pattern == "*" {
-                return true;
-            }
-            if command.starts_with(pattern) {
-                return true;
-            }
-        }
+    /// Whether `tools` lists the tool, by name or by an entry ending in `*`
+    /// that prefixes it (such as `mcp__github__*` for a server's MCP tools)
+    pub fn allows_tool(&self, tool_name: &str) -> bool {
+        self.tools.iter().any(|allowed| match allowed.strip_suffix('*') {
+            Some(prefix) => tool_name.starts_with(prefix),
+            None => allowed == tool_name,
+        })
+    }
+}
 
-        false
+impl std::fmt::Display for FileAccessLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileAccessLevel::None => write!(f, "no"),
+            FileAccessLevel::ReadOnly => write!(f, "read-only"),
+            FileAccessLevel::ReadWrite => write!(f, "read-write"),
+            FileAccessLevel::Unrestricted => write!(f, "unrestricted"),
+        }
     }
 }
 
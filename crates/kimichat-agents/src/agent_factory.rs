@@ -1,6 +1,8 @@
 use crate::agent::{Agent, ExecutionContext, LlmClient};
 use crate::agent_config::AgentConfig;
+use kimichat_llm_api::client::{MeteredLlmClient, UsageRecorder};
 use kimichat_logging::safe_truncate;
+use kimichat_models::ModelCapabilities;
 use kimichat_toolcore::tool_registry::ToolRegistry;
 use anyhow::Result;
 use std::collections::HashMap;
@@ -11,6 +13,8 @@ use colored::Colorize;
 pub struct AgentFactory {
     tool_registry: Arc<ToolRegistry>,
     llm_clients: HashMap<String, Arc<dyn LlmClient>>,
+    model_capabilities: HashMap<String, ModelCapabilities>,
+    usage_recorder: Option<Arc<dyn UsageRecorder>>,
     policy_manager: kimichat_policy::PolicyManager,
 }
 
@@ -19,6 +23,8 @@ impl AgentFactory {
         Self {
             tool_registry,
             llm_clients: HashMap::new(),
+            model_capabilities: HashMap::new(),
+            usage_recorder: None,
             policy_manager,
         }
     }
@@ -27,6 +33,44 @@ impl AgentFactory {
         self.llm_clients.insert(model, client);
     }
 
+    pub fn register_model_capabilities(&mut self, model: String, capabilities: ModelCapabilities) {
+        self.model_capabilities.insert(model, capabilities);
+    }
+
+    /// Report the token usage of every agent created from now on, keyed by agent name
+    pub fn set_usage_recorder(&mut self, recorder: Arc<dyn UsageRecorder>) {
+        self.usage_recorder = Some(recorder);
+    }
+
+    /// Pick the model an agent runs on
+    ///
+    /// Agents with tools are moved off a model that can't call tools onto the
+    /// first registered model (by name) that can. Models without registered
+    /// capabilities are assumed to support tools.
+    fn select_model<'a>(&'a self, config: &'a AgentConfig) -> &'a str {
+        let supports_tools = |model: &str| {
+            self.model_capabilities.get(model).is_none_or(|caps| caps.supports_tools)
+        };
+
+        if config.tools.is_empty() || supports_tools(&config.model) {
+            return &config.model;
+        }
+
+        let mut candidates: Vec<&String> = self.llm_clients.keys()
+            .filter(|model| supports_tools(model))
+            .collect();
+        candidates.sort();
+
+        match candidates.first() {
+            Some(model) => {
+                eprintln!("{} Agent '{}' needs tools but {} doesn't support them, using {} instead",
+                         "⚠️".yellow(), config.name, config.model, model);
+                model.as_str()
+            }
+            None => &config.model,
+        }
+    }
+
     pub fn create_agent(&self, config: &AgentConfig) -> Result<Box<dyn Agent>> {
         // Validate configuration
         config.validate()
@@ -37,9 +81,18 @@ impl AgentFactory {
                  config.name, config.tools.len(), config.tools);
 
         // Get LLM client for this agent
-        let llm_client = self.llm_clients.get(&config.model)
-            .ok_or_else(|| anyhow::anyhow!("No LLM client available for model: {}", config.model))?
+        let model = self.select_model(config);
+        let mut llm_client = self.llm_clients.get(model)
+            .ok_or_else(|| anyhow::anyhow!("No LLM client available for model: {}", model))?
             .clone();
+        if let Some(recorder) = &self.usage_recorder {
+            llm_client = Arc::new(MeteredLlmClient::new(
+                llm_client,
+                Arc::clone(recorder),
+                config.name.clone(),
+                model.to_string(),
+            ));
+        }
 
         // Create configurable agent
         let agent = ConfigurableAgent::new(
@@ -68,8 +121,9 @@ impl ConfigurableAgent {
         llm_client: Arc<dyn LlmClient>,
         policy_manager: kimichat_policy::PolicyManager,
     ) -> Result<Self> {
-        // Validate that all required tools are available
-        for tool_name in &config.tools {
+        // Validate that all required tools are available; patterns may match
+        // nothing, e.g. when the MCP server they name isn't configured
+        for tool_name in config.tools.iter().filter(|name| !name.ends_with('*')) {
             if !tool_registry.has_tool(tool_name) {
                 return Err(anyhow::anyhow!("Required tool '{}' not found in registry", tool_name));
             }
@@ -90,19 +144,36 @@ impl ConfigurableAgent {
     ) -> crate::agent::AgentResult {
         let start_time = std::time::Instant::now();
 
+        // The agent's declared permissions restrict the shared policy
+        let policy_manager = self
+            .policy_manager
+            .with_agent_layer(self.config.permissions.to_policy_rules());
+        // Each task tracks its own reads, so files changed by the user or other agents count as unseen
+        let read_tracker = std::sync::Arc::new(kimichat_toolcore::ReadTracker::new());
+
         // Prepare tools for this agent
         eprintln!("[DEBUG] Agent '{}' preparing tools from config.tools: {:?}",
                  self.config.name, self.config.tools);
 
+        let mut seen = std::collections::HashSet::new();
         let available_tools: Vec<_> = self.config.tools
             .iter()
-            .filter_map(|tool_name| {
-                let tool = self.tool_registry.get_tool(tool_name);
-                if tool.is_none() {
+            .flat_map(|tool_name| {
+                let tools: Vec<_> = match tool_name.strip_suffix('*') {
+                    Some(prefix) => {
+                        let mut names = self.tool_registry.get_tool_names();
+                        names.retain(|name| name.starts_with(prefix));
+                        names.sort();
+                        names.iter().filter_map(|name| self.tool_registry.get_tool(name)).collect()
+                    }
+                    None => self.tool_registry.get_tool(tool_name).into_iter().collect(),
+                };
+                if tools.is_empty() {
                     eprintln!("[DEBUG] Tool '{}' not found in registry!", tool_name);
                 }
-                tool
+                tools
             })
+            .filter(|tool| seen.insert(tool.name().to_string()))
             .map(|tool| {
                 let openai_def = tool.to_openai_definition();
                 // Extract just the parameters schema from the full definition
@@ -161,6 +232,7 @@ impl ConfigurableAgent {
                 tool_call_id: None,
                 name: None,
                 reasoning: None,
+                reasoning_details: None,
             }
         ];
 
@@ -191,6 +263,7 @@ impl ConfigurableAgent {
             tool_call_id: None,
             name: None,
             reasoning: None,
+            reasoning_details: None,
         });
 
         // Execute with LLM and tool calling loop
@@ -240,6 +313,7 @@ impl ConfigurableAgent {
                     tool_call_id: None,
                     name: None,
                     reasoning: None,
+                    reasoning_details: None,
                 });
                 println!("{} Injected iteration limit warning to model", "⚠️".yellow());
             }
@@ -318,17 +392,38 @@ impl ConfigurableAgent {
                             eprintln!("[DEBUG] Agent '{}' executing tool '{}' (available in config: {})",
                                      self.config.name,
                                      tool_name,
-                                     self.config.tools.contains(&tool_name.to_string()));
-
-                            let tool_result = if let Some(tool) = self.tool_registry.get_tool(tool_name) {
-                                // Parse arguments and execute
-                                match kimichat_toolcore::ToolParameters::from_json(tool_args) {
+                                     self.config.allows_tool(tool_name));
+
+                            // Only the agent's own tools run, whatever the model asks for
+                            let tool_result = if !self.config.allows_tool(tool_name) {
+                                kimichat_toolcore::ToolResult::error(format!(
+                                    "Tool '{}' is not available to agent '{}'",
+                                    tool_name, self.config.name
+                                ))
+                            } else if let Some(tool) = self.tool_registry.get_tool(tool_name) {
+                                // Parse and validate arguments, then execute
+                                let parsed = kimichat_toolcore::ToolParameters::from_json(tool_args)
+                                    .map_err(|e| format!("Failed to parse tool arguments: {}", e))
+                                    .and_then(|mut params| {
+                                        self.tool_registry.validate_arguments(tool_name, &mut params).map(|()| params)
+                                    });
+                                match parsed {
                                     Ok(params) => {
                                         let mut tool_context = kimichat_toolcore::tool_context::ToolContext::new(
                                             context.workspace_dir.clone(),
                                             context.session_id.clone(),
-                                            self.policy_manager.clone(),
-                                        );
+                                            policy_manager.clone(),
+                                        )
+                                        .with_extra_roots(context.extra_roots.clone())
+                                        .with_confirmation_provider(context.confirmation_provider.clone())
+                                        .with_read_tracker(read_tracker.clone())
+                                        .with_policy_context(kimichat_policy::PolicyContext::new(
+                                            Some(self.config.name.clone()),
+                                            context.session_type,
+                                        ));
+                                        if let Some(ref sandbox) = self.config.permissions.sandbox {
+                                            tool_context = tool_context.with_sandbox(sandbox.clone());
+                                        }
                                         if let Some(ref tm) = context.terminal_manager {
                                             tool_context = tool_context.with_terminal_manager(tm.clone());
                                         }
@@ -338,11 +433,15 @@ impl ConfigurableAgent {
                                         if let Some(ref todo_mgr) = context.todo_manager {
                                             tool_context = tool_context.with_todo_manager(todo_mgr.clone());
                                         }
+                                        if let Some(ref job_mgr) = context.job_manager {
+                                            tool_context = tool_context.with_job_manager(job_mgr.clone());
+                                        }
+                                        if let Some(ref checkpoints) = context.checkpoint_store {
+                                            tool_context = tool_context.with_checkpoint_store(checkpoints.clone());
+                                        }
                                         tool.execute(params, &tool_context).await
                                     }
-                                    Err(e) => {
-                                        kimichat_toolcore::ToolResult::error(format!("Failed to parse tool arguments: {}", e))
-                                    }
+                                    Err(e) => kimichat_toolcore::ToolResult::error(e),
                                 }
                             } else {
                                 kimichat_toolcore::ToolResult::error(format!("Tool '{}' not found", tool_name))
@@ -376,8 +475,18 @@ impl ConfigurableAgent {
                                 }
                             }
 
-                            // Add tool result to conversation
-                            let tool_result_content = if tool_result.success {
+                            // Add tool result to conversation; permission violations come
+                            // back as a structured denial the model can act on
+                            let violations = policy_manager.take_violations();
+                            let tool_result_content = if !violations.is_empty() {
+                                if let Some(ref vm) = context.visibility_manager {
+                                    let mut vm = vm.write().await;
+                                    for violation in &violations {
+                                        vm.record_permission_violation(&task.id, &self.config.name, tool_name, violation.clone());
+                                    }
+                                }
+                                permission_denial(&self.config.name, tool_name, &violations)
+                            } else if tool_result.success {
                                 tool_result.content
                             } else {
                                 tool_result.error.unwrap_or_else(|| "Unknown error".to_string())
@@ -390,6 +499,7 @@ impl ConfigurableAgent {
                                 tool_call_id: Some(tool_call.id.clone()),
                                 name: Some(tool_name.clone()),
                                 reasoning: None,
+                                reasoning_details: None,
                             });
                         }
 
@@ -484,4 +594,20 @@ impl Agent for ConfigurableAgent {
     fn required_tools(&self) -> Vec<String> {
         self.config.tools.clone()
     }
-}
\ No newline at end of file
+}
+
+/// Tool result for a call the agent's permissions refused
+fn permission_denial(agent_name: &str, tool_name: &str, violations: &[kimichat_policy::PolicyViolation]) -> String {
+    serde_json::json!({
+        "status": "denied",
+        "tool": tool_name,
+        "agent": agent_name,
+        "violations": violations.iter().map(|violation| serde_json::json!({
+            "action": violation.action,
+            "target": violation.target,
+            "reason": violation.reason,
+        })).collect::<Vec<_>>(),
+        "message": "This agent is not permitted to do this. Do not retry; continue without it or report that it needs another agent.",
+    })
+    .to_string()
+}
@@ -544,7 +544,13 @@ impl PlanningCoordinator {
             terminal_manager: context.terminal_manager.clone(),
             skill_registry: context.skill_registry.clone(),
             todo_manager: context.todo_manager.clone(),
+            job_manager: context.job_manager.clone(),
+            checkpoint_store: context.checkpoint_store.clone(),
             cancellation_token: context.cancellation_token.clone(),
+            extra_roots: context.extra_roots.clone(),
+            confirmation_provider: Arc::clone(&context.confirmation_provider),
+            session_type: context.session_type,
+            visibility_manager: Some(Arc::clone(&self.visibility_manager)),
         };
 
         // Execute task
@@ -579,6 +585,7 @@ impl PlanningCoordinator {
                 tool_call_id: None,
                 name: None,
                 reasoning: None,
+                reasoning_details: None,
             });
         }
 
@@ -46,7 +46,13 @@ pub struct TaskContextBuilder {
     terminal_manager: Option<std::sync::Arc<tokio::sync::Mutex<kimichat_terminal::TerminalManager>>>,
     skill_registry: Option<std::sync::Arc<kimichat_skills::SkillRegistry>>,
     todo_manager: Option<std::sync::Arc<kimichat_todo::TodoManager>>,
+    job_manager: Option<std::sync::Arc<kimichat_toolcore::JobManager>>,
+    checkpoint_store: Option<std::sync::Arc<kimichat_checkpoints::CheckpointStore>>,
     cancellation_token: Option<tokio_util::sync::CancellationToken>,
+    extra_roots: Vec<std::path::PathBuf>,
+    confirmation_provider: std::sync::Arc<dyn kimichat_toolcore::ConfirmationProvider>,
+    session_type: Option<kimichat_policy::SessionType>,
+    visibility_manager: Option<std::sync::Arc<tokio::sync::RwLock<crate::visibility::VisibilityManager>>>,
 }
 
 impl TaskContextBuilder {
@@ -60,7 +66,13 @@ impl TaskContextBuilder {
             terminal_manager: None,
             skill_registry: None,
             todo_manager: None,
+            job_manager: None,
+            checkpoint_store: None,
             cancellation_token: None,
+            extra_roots: Vec::new(),
+            confirmation_provider: std::sync::Arc::new(kimichat_toolcore::TerminalConfirmation),
+            session_type: None,
+            visibility_manager: None,
         }
     }
 
@@ -104,6 +116,36 @@ impl TaskContextBuilder {
         self
     }
 
+    pub fn with_job_manager(mut self, job_manager: std::sync::Arc<kimichat_toolcore::JobManager>) -> Self {
+        self.job_manager = Some(job_manager);
+        self
+    }
+
+    pub fn with_checkpoint_store(mut self, checkpoint_store: std::sync::Arc<kimichat_checkpoints::CheckpointStore>) -> Self {
+        self.checkpoint_store = Some(checkpoint_store);
+        self
+    }
+
+    pub fn with_extra_roots(mut self, extra_roots: Vec<std::path::PathBuf>) -> Self {
+        self.extra_roots = extra_roots;
+        self
+    }
+
+    pub fn with_confirmation_provider(mut self, confirmation_provider: std::sync::Arc<dyn kimichat_toolcore::ConfirmationProvider>) -> Self {
+        self.confirmation_provider = confirmation_provider;
+        self
+    }
+
+    pub fn with_session_type(mut self, session_type: kimichat_policy::SessionType) -> Self {
+        self.session_type = Some(session_type);
+        self
+    }
+
+    pub fn with_visibility_manager(mut self, visibility_manager: std::sync::Arc<tokio::sync::RwLock<crate::visibility::VisibilityManager>>) -> Self {
+        self.visibility_manager = Some(visibility_manager);
+        self
+    }
+
     pub fn build(self) -> Result<crate::agent::ExecutionContext, String> {
         Ok(crate::agent::ExecutionContext {
             workspace_dir: self.workspace_dir.ok_or("workspace_dir is required")?,
@@ -114,7 +156,13 @@ impl TaskContextBuilder {
             terminal_manager: self.terminal_manager,
             skill_registry: self.skill_registry,
             todo_manager: self.todo_manager,
+            job_manager: self.job_manager,
+            checkpoint_store: self.checkpoint_store,
             cancellation_token: self.cancellation_token,
+            extra_roots: self.extra_roots,
+            confirmation_provider: self.confirmation_provider,
+            session_type: self.session_type,
+            visibility_manager: self.visibility_manager,
         })
     }
 }
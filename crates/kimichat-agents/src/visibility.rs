@@ -18,6 +18,8 @@ pub struct VisibilityManager {
     current_phase: ExecutionPhase,
     /// User preferences for verbosity
     verbosity_level: VerbosityLevel,
+    /// Tool calls refused by agent permissions
+    permission_violations: Vec<PermissionViolationEvent>,
 }
 
 #[derive(Debug, Clone)]
@@ -66,6 +68,15 @@ pub enum TaskStatus {
     Cancelled,
 }
 
+#[derive(Debug, Clone)]
+pub struct PermissionViolationEvent {
+    pub task_id: String,
+    pub agent_name: String,
+    pub tool_name: String,
+    pub violation: kimichat_policy::PolicyViolation,
+    pub time: Instant,
+}
+
 #[derive(Debug, Clone)]
 pub struct ExecutionMetrics {
     pub execution_time: Duration,
@@ -127,6 +138,7 @@ impl VisibilityManager {
             },
             current_phase: ExecutionPhase::Planning,
             verbosity_level: VerbosityLevel::Normal,
+            permission_violations: Vec::new(),
         }
     }
     
@@ -319,6 +331,39 @@ impl VisibilityManager {
         }
     }
     
+    /// Record a tool call the agent's permissions refused
+    pub fn record_permission_violation(
+        &mut self,
+        task_id: &str,
+        agent_name: &str,
+        tool_name: &str,
+        violation: kimichat_policy::PolicyViolation,
+    ) {
+        if self.verbosity_level != VerbosityLevel::Minimal {
+            println!(
+                "{} {} {} {} {}",
+                "🚫".red(),
+                agent_name.bright_white(),
+                format!("{} on '{}' denied:", violation.action, violation.target).red(),
+                violation.reason.yellow(),
+                format!("({})", tool_name).bright_black()
+            );
+        }
+
+        self.permission_violations.push(PermissionViolationEvent {
+            task_id: task_id.to_string(),
+            agent_name: agent_name.to_string(),
+            tool_name: tool_name.to_string(),
+            violation,
+            time: Instant::now(),
+        });
+    }
+
+    /// Get all recorded permission violations
+    pub fn get_permission_violations(&self) -> &[PermissionViolationEvent] {
+        &self.permission_violations
+    }
+
     /// Update performance metrics from task
     fn update_performance_metrics_from_task(&mut self, task: &TaskVisibilityEvent, success: bool) {
         self.performance_metrics.total_tasks += 1;
@@ -356,6 +401,9 @@ impl VisibilityManager {
         println!("{} {:.1}%", "Success Rate:".bright_cyan(), 
             (self.performance_metrics.successful_tasks as f32 / self.performance_metrics.total_tasks.max(1) as f32 * 100.0)
         );
+        if !self.permission_violations.is_empty() {
+            println!("{} {}", "Permission Violations:".bright_cyan(), self.permission_violations.len().to_string().bright_red());
+        }
         
         if self.verbosity_level == VerbosityLevel::Detailed || self.verbosity_level == VerbosityLevel::Debug {
             println!();
@@ -0,0 +1,339 @@
+//! Checkpoints of the files the tools change
+//!
+//! Before a tool writes a file, the file's current content is copied into the
+//! checkpoint of the running turn (one user message and everything done to
+//! answer it). Restoring a checkpoint puts every file touched in that turn or
+//! later back the way it was before the turn, and drops those checkpoints.
+//!
+//! Checkpoints live under `~/.okaychat/checkpoints/<session>/<turn>/`: a
+//! `checkpoint.json` manifest plus one copy per file.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const MANIFEST: &str = "checkpoint.json";
+const FILES_DIR: &str = "files";
+
+/// A file as it was before the first change in a turn
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileSnapshot {
+    /// Absolute path of the file
+    pub path: PathBuf,
+    /// Path as shown to the user, relative to the workspace when inside it
+    pub display_path: String,
+    /// Whether the file existed; restoring removes files the turn created
+    pub existed: bool,
+    /// Copy of the content inside the checkpoint directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob: Option<String>,
+}
+
+/// The files one turn changed, as they were before it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub id: u64,
+    pub session_id: String,
+    pub created_at: DateTime<Utc>,
+    /// What started the turn, usually the user's message
+    pub description: String,
+    pub files: Vec<FileSnapshot>,
+}
+
+/// A file put back by a restore
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestoredFile {
+    pub display_path: String,
+    /// The file did not exist before and was removed
+    pub removed: bool,
+}
+
+#[derive(Debug, Default)]
+struct TurnState {
+    turn: u64,
+    description: String,
+    /// Checkpoint of the current turn, once something was saved in it
+    current: Option<Checkpoint>,
+}
+
+/// Checkpoints of one session
+#[derive(Debug)]
+pub struct CheckpointStore {
+    dir: PathBuf,
+    session_id: String,
+    state: Mutex<TurnState>,
+}
+
+impl CheckpointStore {
+    /// Store for a session under `root`; nothing is written until a file is saved
+    pub fn new(root: &Path, session_id: &str) -> Self {
+        Self {
+            dir: root.join(session_id),
+            session_id: session_id.to_string(),
+            state: Mutex::new(TurnState::default()),
+        }
+    }
+
+    /// Store for a session under `~/.okaychat/checkpoints`
+    pub fn for_session(session_id: &str) -> Result<Self> {
+        let root = kimichat_logging::get_okaychat_dir()?.join("checkpoints");
+        Ok(Self::new(&root, session_id))
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// Start a new turn; the files it changes go into a new checkpoint
+    pub fn begin_turn(&self, description: &str) {
+        let mut state = self.state.lock().unwrap();
+        let last = self.ids().ok().and_then(|ids| ids.last().copied()).unwrap_or(0);
+        state.turn = state.turn.max(last) + 1;
+        state.description = description.to_string();
+        state.current = None;
+    }
+
+    /// Save `path` as it is now, unless the current turn already saved it.
+    /// Call this before every change to the file.
+    pub fn save_file(&self, path: &Path, display_path: &str) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.turn == 0 {
+            // Changes made before any turn started, such as in task mode
+            let last = self.ids()?.last().copied().unwrap_or(0);
+            state.turn = last + 1;
+        }
+        let turn = state.turn;
+        let description = state.description.clone();
+        let checkpoint = state.current.get_or_insert_with(|| Checkpoint {
+            id: turn,
+            session_id: self.session_id.clone(),
+            created_at: Utc::now(),
+            description,
+            files: Vec::new(),
+        });
+        if checkpoint.files.iter().any(|file| file.path == path) {
+            return Ok(());
+        }
+
+        let checkpoint_dir = self.dir.join(turn.to_string());
+        let existed = path.is_file();
+        let blob = if existed {
+            let blob = format!("{}/{}", FILES_DIR, checkpoint.files.len());
+            let blob_path = checkpoint_dir.join(&blob);
+            fs::create_dir_all(checkpoint_dir.join(FILES_DIR))
+                .with_context(|| format!("Failed to create {}", checkpoint_dir.display()))?;
+            fs::copy(path, &blob_path).with_context(|| format!("Failed to save {}", path.display()))?;
+            Some(blob)
+        } else {
+            fs::create_dir_all(&checkpoint_dir)
+                .with_context(|| format!("Failed to create {}", checkpoint_dir.display()))?;
+            None
+        };
+        checkpoint.files.push(FileSnapshot {
+            path: path.to_path_buf(),
+            display_path: display_path.to_string(),
+            existed,
+            blob,
+        });
+        fs::write(checkpoint_dir.join(MANIFEST), serde_json::to_string_pretty(checkpoint)?)
+            .with_context(|| format!("Failed to write checkpoint {}", turn))?;
+        Ok(())
+    }
+
+    /// All checkpoints of the session, oldest first
+    pub fn list(&self) -> Result<Vec<Checkpoint>> {
+        self.ids()?.into_iter().map(|id| self.get(id)).collect()
+    }
+
+    pub fn get(&self, id: u64) -> Result<Checkpoint> {
+        let manifest = self.dir.join(id.to_string()).join(MANIFEST);
+        let json = fs::read_to_string(&manifest).with_context(|| format!("No checkpoint {}", id))?;
+        serde_json::from_str(&json).with_context(|| format!("Checkpoint {} is damaged", id))
+    }
+
+    /// Put every file changed in checkpoint `id`'s turn or later back as it was
+    /// before that turn, and drop those checkpoints
+    pub fn restore(&self, id: u64) -> Result<Vec<RestoredFile>> {
+        let mut state = self.state.lock().unwrap();
+        let checkpoints = self.since(id)?;
+
+        let mut restored: BTreeMap<PathBuf, RestoredFile> = BTreeMap::new();
+        // Newest first, so the oldest copy of a file is the one left in place
+        for checkpoint in checkpoints.iter().rev() {
+            let checkpoint_dir = self.dir.join(checkpoint.id.to_string());
+            for file in &checkpoint.files {
+                match &file.blob {
+                    Some(blob) => {
+                        if let Some(parent) = file.path.parent() {
+                            fs::create_dir_all(parent)?;
+                        }
+                        fs::copy(checkpoint_dir.join(blob), &file.path)
+                            .with_context(|| format!("Failed to restore {}", file.path.display()))?;
+                    }
+                    None if file.path.exists() => fs::remove_file(&file.path)
+                        .with_context(|| format!("Failed to remove {}", file.path.display()))?,
+                    None => {}
+                }
+                restored.insert(
+                    file.path.clone(),
+                    RestoredFile { display_path: file.display_path.clone(), removed: !file.existed },
+                );
+            }
+        }
+
+        for checkpoint in &checkpoints {
+            fs::remove_dir_all(self.dir.join(checkpoint.id.to_string()))?;
+        }
+        // The next change starts a fresh checkpoint after the remaining ones
+        state.current = None;
+        state.turn = 0;
+        Ok(restored.into_values().collect())
+    }
+
+    /// Restore the latest checkpoint. Returns its id and the files put back,
+    /// or `None` when there is nothing to undo.
+    pub fn undo(&self) -> Result<Option<(u64, Vec<RestoredFile>)>> {
+        match self.ids()?.last() {
+            Some(&id) => Ok(Some((id, self.restore(id)?))),
+            None => Ok(None),
+        }
+    }
+
+    /// Unified diff from the files as checkpoint `id` would restore them to
+    /// their current content
+    pub fn diff(&self, id: u64) -> Result<String> {
+        let mut before: BTreeMap<PathBuf, (String, Option<Vec<u8>>)> = BTreeMap::new();
+        for checkpoint in self.since(id)?.iter().rev() {
+            let checkpoint_dir = self.dir.join(checkpoint.id.to_string());
+            for file in &checkpoint.files {
+                let content = match &file.blob {
+                    Some(blob) => Some(fs::read(checkpoint_dir.join(blob))?),
+                    None => None,
+                };
+                before.insert(file.path.clone(), (file.display_path.clone(), content));
+            }
+        }
+
+        let mut diff = String::new();
+        for (path, (display_path, old)) in before {
+            let new = fs::read(&path).ok();
+            if old == new {
+                continue;
+            }
+            let old_text = old.as_deref().map(String::from_utf8_lossy).unwrap_or_default();
+            let new_text = new.as_deref().map(String::from_utf8_lossy).unwrap_or_default();
+            let old_header = if old.is_some() { format!("a/{}", display_path) } else { "/dev/null".to_string() };
+            let new_header = if new.is_some() { format!("b/{}", display_path) } else { "/dev/null".to_string() };
+            diff.push_str(
+                &similar::TextDiff::from_lines(old_text.as_ref(), new_text.as_ref())
+                    .unified_diff()
+                    .context_radius(3)
+                    .header(&old_header, &new_header)
+                    .to_string(),
+            );
+        }
+        Ok(diff)
+    }
+
+    /// Checkpoint `id` and every later one, oldest first
+    fn since(&self, id: u64) -> Result<Vec<Checkpoint>> {
+        let ids = self.ids()?;
+        if !ids.contains(&id) {
+            anyhow::bail!("No checkpoint {} in this session", id);
+        }
+        ids.into_iter().filter(|&other| other >= id).map(|other| self.get(other)).collect()
+    }
+
+    fn ids(&self) -> Result<Vec<u64>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", self.dir.display())),
+        };
+        let mut ids: Vec<u64> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().join(MANIFEST).is_file())
+            .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+            .collect();
+        ids.sort_unstable();
+        Ok(ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn setup() -> (TempDir, PathBuf, CheckpointStore) {
+        let temp = TempDir::new().unwrap();
+        let work_dir = temp.path().join("work");
+        fs::create_dir_all(&work_dir).unwrap();
+        let store = CheckpointStore::new(&temp.path().join("checkpoints"), "session-1");
+        (temp, work_dir, store)
+    }
+
+    fn write(store: &CheckpointStore, path: &Path, content: &str) {
+        store.save_file(path, path.file_name().unwrap().to_str().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_undo_restores_and_removes_files() {
+        let (_temp, work_dir, store) = setup();
+        let existing = work_dir.join("main.rs");
+        let created = work_dir.join("new.rs");
+        fs::write(&existing, "original\n").unwrap();
+
+        store.begin_turn("first");
+        write(&store, &existing, "first edit\n");
+        write(&store, &existing, "second edit\n");
+        write(&store, &created, "created\n");
+
+        let checkpoints = store.list().unwrap();
+        assert_eq!(checkpoints.len(), 1);
+        assert_eq!(checkpoints[0].description, "first");
+        assert_eq!(checkpoints[0].files.len(), 2, "a file is saved once per turn");
+
+        let (id, restored) = store.undo().unwrap().unwrap();
+        assert_eq!(id, 1);
+        assert_eq!(restored.len(), 2);
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "original\n");
+        assert!(!created.exists());
+        assert!(store.list().unwrap().is_empty());
+        assert!(store.undo().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_restore_rolls_back_later_turns_and_diffs() {
+        let (_temp, work_dir, store) = setup();
+        let file = work_dir.join("lib.rs");
+        fs::write(&file, "one\n").unwrap();
+
+        store.begin_turn("turn one");
+        write(&store, &file, "two\n");
+        store.begin_turn("turn two");
+        write(&store, &file, "three\n");
+        assert_eq!(store.list().unwrap().iter().map(|c| c.id).collect::<Vec<_>>(), vec![1, 2]);
+
+        let diff = store.diff(1).unwrap();
+        assert!(diff.contains("--- a/lib.rs"));
+        assert!(diff.contains("-one"));
+        assert!(diff.contains("+three"));
+        assert!(store.diff(2).unwrap().contains("-two"));
+        assert!(store.diff(7).is_err());
+
+        store.restore(1).unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "one\n");
+        assert!(store.list().unwrap().is_empty());
+
+        // Numbering continues from the restored turn
+        store.begin_turn("again");
+        write(&store, &file, "four\n");
+        assert_eq!(store.list().unwrap()[0].id, 1);
+    }
+}
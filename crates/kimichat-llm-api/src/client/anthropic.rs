@@ -1,4 +1,4 @@
-use crate::client::{LlmClient, LlmResponse, ChatMessage, ToolDefinition, StreamingChunk, ToolCall, FunctionCall, TokenUsage};
+use crate::client::{ApiError, LlmClient, LlmResponse, ChatMessage, ToolDefinition, StreamingChunk, StreamEvent, ToolCall, FunctionCall, TokenUsage};
 use anyhow::{Result, Context};
 use async_trait::async_trait;
 use serde_json::Value;
@@ -9,6 +9,7 @@ use futures::Stream;
 use futures::StreamExt;
 use async_stream::stream;
 use kimichat_logging::get_logs_dir;
+use kimichat_models::ReasoningConfig;
 
 /// Anthropic LLM client implementation using native Anthropic API
 pub struct AnthropicLlmClient {
@@ -16,6 +17,9 @@ pub struct AnthropicLlmClient {
     model: String,
     base_url: String,
     agent_name: String,
+    max_tokens: usize,
+    prompt_caching: bool,
+    reasoning: Option<ReasoningConfig>,
     client: reqwest::Client,
 }
 
@@ -28,15 +32,38 @@ impl AnthropicLlmClient {
             model,
             base_url,
             agent_name,
+            max_tokens: 4096,
+            prompt_caching: true,
+            reasoning: None,
             client: reqwest::Client::new(),
         }
     }
 
+    /// Set the completion token limit sent with chat requests (defaults to 4096)
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    /// Enable or disable prompt cache breakpoints (enabled by default)
+    pub fn with_prompt_caching(mut self, enabled: bool) -> Self {
+        self.prompt_caching = enabled;
+        self
+    }
+
+    /// Enable extended thinking with the configured budget
+    ///
+    /// The thinking budget comes on top of the completion token limit.
+    pub fn with_reasoning(mut self, reasoning: Option<ReasoningConfig>) -> Self {
+        self.reasoning = reasoning;
+        self
+    }
+
     fn get_messages_url(&self) -> String {
         format!("{}/v1/messages", self.base_url)
     }
 
-    fn convert_messages_to_anthropic_format(&self, messages: Vec<ChatMessage>) -> Vec<Value> {
+    fn convert_messages_to_anthropic_format(&self, messages: Vec<ChatMessage>, thinking: bool) -> Vec<Value> {
         messages.into_iter().filter_map(|msg| {
             // Skip system messages as they should be handled separately
             if msg.role == "system" {
@@ -61,8 +88,12 @@ impl AnthropicLlmClient {
                     })
                 ]
             } else if let Some(tool_calls) = msg.tool_calls {
-                // Assistant message with tool calls
-                let mut content = vec![];
+                // Assistant message with tool calls; signed thinking has to come
+                // back first and unchanged for the model to continue its turn
+                let mut content = match msg.reasoning_details {
+                    Some(blocks) if thinking => blocks,
+                    _ => vec![],
+                };
                 if !msg.content.is_empty() {
                     content.push(serde_json::json!({
                         "type": "text",
@@ -94,6 +125,66 @@ impl AnthropicLlmClient {
         }).collect()
     }
 
+    /// Build a Messages API request with tools, system prompt, thinking and cache breakpoints
+    ///
+    /// With prompt caching on, breakpoints go on the system prompt, the last
+    /// tool (caching the whole tool block) and the last message. Each request
+    /// repeats the previous one's conversation before appending to it, so the
+    /// next turn reads everything up to that last breakpoint from the cache.
+    fn build_chat_request(&self, messages: Vec<ChatMessage>, tools: Vec<ToolDefinition>) -> Value {
+        // Extract system messages and combine them
+        let system_messages: Vec<String> = messages.iter()
+            .filter(|msg| msg.role == "system")
+            .map(|msg| msg.content.clone())
+            .collect();
+
+        let thinking = self.reasoning.filter(|_| !continues_turn_without_thinking(&messages));
+        let mut anthropic_messages = self.convert_messages_to_anthropic_format(messages, thinking.is_some());
+        let mut anthropic_tools = self.convert_tools_to_anthropic_format(tools);
+
+        if self.prompt_caching {
+            if let Some(tool) = anthropic_tools.last_mut() {
+                tool["cache_control"] = cache_control();
+            }
+            let last_block = anthropic_messages.last_mut()
+                .and_then(|message| message["content"].as_array_mut())
+                .and_then(|content| content.last_mut());
+            if let Some(block) = last_block {
+                block["cache_control"] = cache_control();
+            }
+        }
+
+        let mut request = serde_json::json!({
+            "model": self.model,
+            "messages": anthropic_messages,
+            "max_tokens": self.max_tokens,
+            "tools": anthropic_tools,
+            "tool_choice": {"type": "auto"}
+        });
+
+        if let Some(reasoning) = thinking {
+            let budget_tokens = reasoning.budget_tokens();
+            request["thinking"] = serde_json::json!({"type": "enabled", "budget_tokens": budget_tokens});
+            request["max_tokens"] = (self.max_tokens + budget_tokens as usize).into();
+        }
+
+        // Add system message if present
+        if !system_messages.is_empty() {
+            let system_content = system_messages.join("\n\n");
+            request["system"] = if self.prompt_caching {
+                serde_json::json!([{
+                    "type": "text",
+                    "text": system_content,
+                    "cache_control": cache_control()
+                }])
+            } else {
+                Value::String(system_content)
+            };
+        }
+
+        request
+    }
+
     fn convert_tools_to_anthropic_format(&self, tools: Vec<ToolDefinition>) -> Vec<Value> {
         tools.into_iter().map(|tool| {
             serde_json::json!({
@@ -109,6 +200,8 @@ impl AnthropicLlmClient {
         let content = response["content"].as_array().unwrap_or(&empty_vec);
 
         let mut text_content = String::new();
+        let mut reasoning = String::new();
+        let mut reasoning_details = Vec::new();
         let mut tool_calls = Vec::new();
 
         for item in content {
@@ -119,6 +212,11 @@ impl AnthropicLlmClient {
                             text_content.push_str(text);
                         }
                     }
+                    "thinking" => {
+                        reasoning.push_str(item["thinking"].as_str().unwrap_or_default());
+                        reasoning_details.push(item.clone());
+                    }
+                    "redacted_thinking" => reasoning_details.push(item.clone()),
                     "tool_use" => {
                         if let Some(name) = item["name"].as_str() {
                             if let Some(id) = item["id"].as_str() {
@@ -144,7 +242,8 @@ impl AnthropicLlmClient {
             tool_calls: if tool_calls.is_empty() { None } else { Some(tool_calls) },
             tool_call_id: None,
             name: None,
-            reasoning: None,
+            reasoning: if reasoning.is_empty() { None } else { Some(reasoning) },
+            reasoning_details: if reasoning_details.is_empty() { None } else { Some(reasoning_details) },
         }
     }
 }
@@ -152,33 +251,7 @@ impl AnthropicLlmClient {
 #[async_trait]
 impl LlmClient for AnthropicLlmClient {
     async fn chat(&self, messages: Vec<ChatMessage>, tools: Vec<ToolDefinition>) -> Result<LlmResponse> {
-        // Extract system messages and combine them
-        let system_messages: Vec<String> = messages.iter()
-            .filter(|msg| msg.role == "system")
-            .map(|msg| msg.content.clone())
-            .collect();
-
-        let combined_system = if system_messages.is_empty() {
-            None
-        } else {
-            Some(system_messages.join("\n\n"))
-        };
-
-        let anthropic_messages = self.convert_messages_to_anthropic_format(messages);
-        let anthropic_tools = self.convert_tools_to_anthropic_format(tools);
-
-        let mut request = serde_json::json!({
-            "model": self.model,
-            "messages": anthropic_messages,
-            "max_tokens": 4096,
-            "tools": anthropic_tools,
-            "tool_choice": {"type": "auto"}
-        });
-
-        // Add system message if present
-        if let Some(system_content) = combined_system {
-            request["system"] = serde_json::Value::String(system_content);
-        }
+        let request = self.build_chat_request(messages, tools);
 
         // Log request to file for persistent debugging
         let _ = self.log_request_to_file(&self.get_messages_url(), &request);
@@ -196,8 +269,7 @@ impl LlmClient for AnthropicLlmClient {
             .await?;
 
         if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Anthropic API error: {}", error_text));
+            return Err(ApiError::from_response("Anthropic", response).await.into());
         }
 
         let response_text = response.text().await?;
@@ -206,49 +278,19 @@ impl LlmClient for AnthropicLlmClient {
         let message = self.convert_anthropic_response_to_chat_message(&response_json);
 
         let usage = response_json.get("usage").map(|u| {
-            TokenUsage {
-                prompt_tokens: u["input_tokens"].as_u64().unwrap_or(0) as u32,
-                completion_tokens: u["output_tokens"].as_u64().unwrap_or(0) as u32,
-                total_tokens: (u["input_tokens"].as_u64().unwrap_or(0) +
-                               u["output_tokens"].as_u64().unwrap_or(0)) as u32,
-            }
+            usage_from(u, u["output_tokens"].as_u64().unwrap_or(0) as u32)
         });
 
         Ok(LlmResponse {
             message,
             usage,
+            provider: None,
         })
     }
 
     async fn chat_streaming(&self, messages: Vec<ChatMessage>, tools: Vec<ToolDefinition>) -> Result<Box<dyn Stream<Item = Result<StreamingChunk>> + Send + Unpin>> {
-        // Extract system messages and combine them
-        let system_messages: Vec<String> = messages.iter()
-            .filter(|msg| msg.role == "system")
-            .map(|msg| msg.content.clone())
-            .collect();
-
-        let combined_system = if system_messages.is_empty() {
-            None
-        } else {
-            Some(system_messages.join("\n\n"))
-        };
-
-        let anthropic_messages = self.convert_messages_to_anthropic_format(messages);
-        let anthropic_tools = self.convert_tools_to_anthropic_format(tools);
-
-        let mut request = serde_json::json!({
-            "model": self.model,
-            "messages": anthropic_messages,
-            "max_tokens": 4096,
-            "tools": anthropic_tools,
-            "tool_choice": {"type": "auto"},
-            "stream": true
-        });
-
-        // Add system message if present
-        if let Some(system_content) = combined_system {
-            request["system"] = serde_json::Value::String(system_content);
-        }
+        let mut request = self.build_chat_request(messages, tools);
+        request["stream"] = Value::Bool(true);
 
         // Log request to file for persistent debugging
         let _ = self.log_request_to_file(&self.get_messages_url(), &request);
@@ -266,8 +308,7 @@ impl LlmClient for AnthropicLlmClient {
             .await?;
 
         if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Anthropic API streaming error: {}", error_text));
+            return Err(ApiError::from_response("Anthropic", response).await.into());
         }
 
         let byte_stream = response.bytes_stream();
@@ -276,6 +317,7 @@ impl LlmClient for AnthropicLlmClient {
             let mut buffer = String::new();
             let mut byte_stream = byte_stream;
             let mut event_buffer = String::new();
+            let mut state = AnthropicStreamState::default();
 
             while let Some(chunk_result) = byte_stream.next().await {
                 match chunk_result {
@@ -292,7 +334,7 @@ impl LlmClient for AnthropicLlmClient {
                                 let line = std::mem::take(&mut event_buffer);
 
                                 // Parse SSE line immediately and yield if we get content
-                                if let Some(streaming_chunk) = Self::parse_sse_line(&line) {
+                                if let Some(streaming_chunk) = state.parse_sse_line(&line) {
                                     yield Ok(streaming_chunk);
                                 }
                             }
@@ -307,7 +349,7 @@ impl LlmClient for AnthropicLlmClient {
 
             // Process any remaining data in the buffer
             if !event_buffer.is_empty() {
-                if let Some(streaming_chunk) = Self::parse_sse_line(&event_buffer) {
+                if let Some(streaming_chunk) = state.parse_sse_line(&event_buffer) {
                     yield Ok(streaming_chunk);
                 }
             }
@@ -317,7 +359,7 @@ impl LlmClient for AnthropicLlmClient {
     }
 
     async fn chat_completion(&self, messages: &[ChatMessage]) -> Result<String> {
-        let anthropic_messages = self.convert_messages_to_anthropic_format(messages.to_vec());
+        let anthropic_messages = self.convert_messages_to_anthropic_format(messages.to_vec(), false);
 
         let request = serde_json::json!({
             "model": self.model,
@@ -341,10 +383,8 @@ impl LlmClient for AnthropicLlmClient {
             .send()
             .await?;
 
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(anyhow::anyhow!("API request failed: {} - {}", status, error_text));
+        if !response.status().is_success() {
+            return Err(ApiError::from_response("Anthropic", response).await.into());
         }
 
         let response_text = response.text().await?;
@@ -366,77 +406,188 @@ impl LlmClient for AnthropicLlmClient {
     }
 }
 
-impl AnthropicLlmClient {
-    /// Parse a single SSE line and return a streaming chunk if it contains text
-    fn parse_sse_line(line: &str) -> Option<StreamingChunk> {
-        // Only process data lines
-        if !line.starts_with("data: ") {
-            return None;
-        }
+/// Whether the conversation is in the middle of an assistant tool-use turn
+/// that was produced without thinking (e.g. by another model)
+///
+/// With thinking enabled, Anthropic requires such a turn to start with a
+/// thinking block, so thinking has to stay off until the turn is over.
+fn continues_turn_without_thinking(messages: &[ChatMessage]) -> bool {
+    messages.iter()
+        .rev()
+        .find(|msg| msg.role != "tool")
+        .is_some_and(|msg| {
+            msg.role == "assistant" && msg.tool_calls.is_some() && msg.reasoning_details.is_none()
+        })
+}
 
-        let data = &line[6..];
+/// Marker that ends a cacheable prompt prefix
+fn cache_control() -> Value {
+    serde_json::json!({"type": "ephemeral"})
+}
+
+/// Convert an Anthropic usage object to `TokenUsage`
+///
+/// Anthropic's `input_tokens` only counts tokens after the last cache
+/// breakpoint, so cache writes and reads are added back to get the full prompt.
+fn usage_from(usage: &Value, output_tokens: u32) -> TokenUsage {
+    let count = |field: &str| usage[field].as_u64().unwrap_or(0) as u32;
+    let cache_creation_input_tokens = count("cache_creation_input_tokens");
+    let cache_read_input_tokens = count("cache_read_input_tokens");
+    let prompt_tokens = count("input_tokens") + cache_creation_input_tokens + cache_read_input_tokens;
+
+    TokenUsage {
+        prompt_tokens,
+        completion_tokens: output_tokens,
+        total_tokens: prompt_tokens + output_tokens,
+        cache_creation_input_tokens,
+        cache_read_input_tokens,
+    }
+}
+
+/// Per-stream state for assembling Anthropic SSE events into chunks
+#[derive(Default)]
+struct AnthropicStreamState {
+    /// Tool calls being assembled, keyed by content block index
+    tool_blocks: Vec<(usize, ToolCall)>,
+    /// Thinking blocks being assembled, keyed by content block index
+    reasoning_blocks: Vec<(usize, Value)>,
+    /// Usage from `message_start`; output tokens are updated by `message_delta`
+    usage: Value,
+    output_tokens: u32,
+    stop_reason: Option<String>,
+}
+
+impl AnthropicStreamState {
+    /// Parse a single SSE line and return a streaming chunk if it produced any events
+    fn parse_sse_line(&mut self, line: &str) -> Option<StreamingChunk> {
+        // Only process data lines
+        let data = line.strip_prefix("data: ")?;
 
         // Check for stream end
         if data.trim() == "[DONE]" {
-            return Some(StreamingChunk {
-                content: String::new(),
-                delta: String::new(),
-                finish_reason: Some("stop".to_string()),
-            });
+            return Some(self.finish());
         }
 
         // Parse JSON event
-        if let Ok(json) = serde_json::from_str::<Value>(data) {
-            if let Some(content_type) = json["type"].as_str() {
-                match content_type {
-                    "content_block_delta" => {
-                        // This is the main event type for streaming text content
-                        if let Some(delta) = json.get("delta").and_then(|v| v.as_object()) {
-                            if let Some(text) = delta.get("text").and_then(|v| v.as_str()) {
-                                // Return immediately for minimal latency
-                                return Some(StreamingChunk {
-                                    content: String::new(),
-                                    delta: text.to_string(),
-                                    finish_reason: None,
-                                });
-                            }
-                        }
+        let json = serde_json::from_str::<Value>(data).ok()?;
+        let index = json["index"].as_u64().unwrap_or(0) as usize;
+
+        match json["type"].as_str()? {
+            "message_start" => {
+                self.usage = json["message"]["usage"].clone();
+                self.output_tokens = self.usage["output_tokens"].as_u64().unwrap_or(0) as u32;
+                None
+            }
+            "content_block_start" => {
+                let block = &json["content_block"];
+                match block["type"].as_str() {
+                    Some("tool_use") => {
+                        let id = block["id"].as_str().unwrap_or_default().to_string();
+                        let name = block["name"].as_str().unwrap_or_default().to_string();
+                        self.tool_blocks.push((index, ToolCall {
+                            id: id.clone(),
+                            function: FunctionCall { name: name.clone(), arguments: String::new() },
+                        }));
+                        let tool_index = self.tool_blocks.len() - 1;
+                        Some(StreamingChunk::from_events(vec![
+                            StreamEvent::ToolCallStart { index: tool_index, id, name },
+                        ]))
                     }
-                    "content_block_start" => {
-                        // Handle initial content block (less common for text)
-                        if let Some(content_block) = json.get("content_block").and_then(|v| v.as_object()) {
-                            if let Some(text) = content_block.get("text").and_then(|v| v.as_str()) {
-                                return Some(StreamingChunk {
-                                    content: text.to_string(),
-                                    delta: text.to_string(),
-                                    finish_reason: None,
-                                });
-                            }
-                        }
+                    Some("thinking") | Some("redacted_thinking") => {
+                        self.reasoning_blocks.push((index, block.clone()));
+                        None
                     }
-                    "message_stop" => {
-                        return Some(StreamingChunk {
-                            content: String::new(),
-                            delta: String::new(),
-                            finish_reason: Some("stop".to_string()),
-                        });
+                    // Handle initial content block (less common for text)
+                    _ => block["text"].as_str()
+                        .filter(|text| !text.is_empty())
+                        .map(|text| StreamingChunk::from_events(vec![StreamEvent::TextDelta(text.to_string())])),
+                }
+            }
+            "content_block_delta" => {
+                // This is the main event type for streaming content
+                let delta = &json["delta"];
+                match delta["type"].as_str() {
+                    Some("input_json_delta") => {
+                        let fragment = delta["partial_json"].as_str().unwrap_or_default();
+                        let tool_index = self.tool_blocks.iter().position(|(i, _)| *i == index)?;
+                        if fragment.is_empty() {
+                            return None;
+                        }
+                        self.tool_blocks[tool_index].1.function.arguments.push_str(fragment);
+                        Some(StreamingChunk::from_events(vec![StreamEvent::ToolCallArgumentsDelta {
+                            index: tool_index,
+                            arguments: fragment.to_string(),
+                        }]))
                     }
-                    "error" => {
-                        // Handle errors - these will be propagated as Err results
-                        if let Some(error) = json.get("error").and_then(|v| v.as_object()) {
-                            let error_msg = error.get("message").and_then(|v| v.as_str()).unwrap_or("Unknown error");
-                            // Don't return a chunk for errors - let the stream consumer handle them
+                    Some("thinking_delta") => {
+                        let thinking = delta["thinking"].as_str()?;
+                        if let Some((_, block)) = self.reasoning_blocks.iter_mut().find(|(i, _)| *i == index) {
+                            let text = format!("{}{}", block["thinking"].as_str().unwrap_or_default(), thinking);
+                            block["thinking"] = Value::String(text);
                         }
+                        Some(StreamingChunk::from_events(vec![StreamEvent::ReasoningDelta(thinking.to_string())]))
                     }
-                    // Other event types (ping, message_start, content_block_stop, etc.) are ignored
-                    _ => {}
+                    Some("signature_delta") => {
+                        let (_, block) = self.reasoning_blocks.iter_mut().find(|(i, _)| *i == index)?;
+                        block["signature"] = delta["signature"].clone();
+                        None
+                    }
+                    _ => delta["text"].as_str()
+                        .map(|text| StreamingChunk::from_events(vec![StreamEvent::TextDelta(text.to_string())])),
+                }
+            }
+            "content_block_stop" => {
+                let tool_index = self.tool_blocks.iter().position(|(i, _)| *i == index)?;
+                let tool_call = &mut self.tool_blocks[tool_index].1;
+                // Tools without parameters stream no input at all
+                if tool_call.function.arguments.is_empty() {
+                    tool_call.function.arguments = "{}".to_string();
+                }
+                Some(StreamingChunk::from_events(vec![StreamEvent::ToolCallEnd {
+                    index: tool_index,
+                    tool_call: tool_call.clone(),
+                }]))
+            }
+            "message_delta" => {
+                if let Some(output_tokens) = json["usage"]["output_tokens"].as_u64() {
+                    self.output_tokens = output_tokens as u32;
+                }
+                if let Some(stop_reason) = json["delta"]["stop_reason"].as_str() {
+                    self.stop_reason = Some(stop_reason.to_string());
                 }
+                None
             }
+            "message_stop" => Some(self.finish()),
+            // Other event types (ping, error, etc.) are ignored here
+            _ => None,
         }
+    }
+
+    /// Build the final chunk carrying the assembled tool calls and usage
+    fn finish(&mut self) -> StreamingChunk {
+        let tool_calls: Vec<ToolCall> = std::mem::take(&mut self.tool_blocks)
+            .into_iter()
+            .map(|(_, call)| call)
+            .collect();
+        let usage = usage_from(&self.usage, self.output_tokens);
 
-        None
+        let mut chunk = StreamingChunk::from_events(vec![StreamEvent::Usage(usage.clone())]);
+        chunk.finish_reason = Some(match self.stop_reason.take().as_deref() {
+            Some("tool_use") => "tool_calls".to_string(),
+            _ => "stop".to_string(),
+        });
+        chunk.tool_calls = if tool_calls.is_empty() { None } else { Some(tool_calls) };
+        chunk.usage = Some(usage);
+        let reasoning_blocks: Vec<Value> = std::mem::take(&mut self.reasoning_blocks)
+            .into_iter()
+            .map(|(_, block)| block)
+            .collect();
+        chunk.reasoning_details = if reasoning_blocks.is_empty() { None } else { Some(reasoning_blocks) };
+        chunk
     }
+}
 
+impl AnthropicLlmClient {
     fn log_request_to_file(&self, url: &str, request: &serde_json::Value) -> Result<()> {
         // Use centralized logs directory
         let logs_dir: PathBuf = get_logs_dir()?;
@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+/// Error returned by LLM clients when the provider answers with a non-success status
+///
+/// Keeps the HTTP status and any server-provided wait hint so callers such as
+/// `RetryingLlmClient` can decide whether and when to retry.
+#[derive(Debug, thiserror::Error)]
+#[error("{provider} API error ({status}): {body}")]
+pub struct ApiError {
+    pub provider: String,
+    pub status: u16,
+    pub body: String,
+    /// Delay suggested by `retry-after` or rate-limit reset headers
+    pub retry_after: Option<Duration>,
+}
+
+impl ApiError {
+    /// Build an error from a failed response, consuming its body
+    pub async fn from_response(provider: &str, response: reqwest::Response) -> Self {
+        let status = response.status().as_u16();
+        let retry_after = retry_after_from_headers(response.headers());
+        let body = response.text().await.unwrap_or_default();
+
+        Self {
+            provider: provider.to_string(),
+            status,
+            body,
+            retry_after,
+        }
+    }
+
+    /// Whether the status indicates a transient failure worth retrying
+    pub fn is_retryable(&self) -> bool {
+        // 529 is Anthropic's "overloaded" status
+        matches!(self.status, 408 | 409 | 429 | 500 | 502 | 503 | 504 | 529)
+    }
+}
+
+/// Extract how long the server asked us to wait before retrying
+///
+/// Understands `retry-after` (seconds), `retry-after-ms`, and the
+/// `x-ratelimit-reset-*` headers sent by OpenAI and Groq.
+pub(crate) fn retry_after_from_headers(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(str::trim);
+
+    if let Some(ms) = header("retry-after-ms").and_then(|v| v.parse::<f64>().ok()) {
+        return Some(Duration::from_secs_f64(ms.max(0.0) / 1000.0));
+    }
+    if let Some(secs) = header("retry-after").and_then(|v| v.parse::<f64>().ok()) {
+        return Some(Duration::from_secs_f64(secs.max(0.0)));
+    }
+
+    // Only wait for the limits that are actually exhausted, if the server says which
+    let resets = [
+        ("x-ratelimit-remaining-requests", "x-ratelimit-reset-requests"),
+        ("x-ratelimit-remaining-tokens", "x-ratelimit-reset-tokens"),
+    ];
+    let any_remaining_reported = resets.iter().any(|(remaining, _)| header(remaining).is_some());
+
+    resets
+        .iter()
+        .filter(|(remaining, _)| !any_remaining_reported || header(remaining) == Some("0"))
+        .filter_map(|(_, reset)| header(reset).and_then(parse_reset_duration))
+        .max()
+}
+
+/// Parse rate-limit reset values like `"2m59.56s"`, `"7.66s"` or `"120ms"`
+pub(crate) fn parse_reset_duration(value: &str) -> Option<Duration> {
+    let mut total = 0.0;
+    let mut number = String::new();
+    let mut chars = value.trim().chars().peekable();
+    let mut parsed_any = false;
+
+    while let Some(c) = chars.next() {
+        if c.is_ascii_digit() || c == '.' {
+            number.push(c);
+            continue;
+        }
+
+        let unit_secs = match c {
+            'h' => 3600.0,
+            'm' if chars.peek() == Some(&'s') => {
+                chars.next();
+                0.001
+            }
+            'm' => 60.0,
+            's' => 1.0,
+            _ => return None,
+        };
+        total += number.parse::<f64>().ok()? * unit_secs;
+        number.clear();
+        parsed_any = true;
+    }
+
+    // A bare number is treated as seconds
+    if !number.is_empty() {
+        total += number.parse::<f64>().ok()?;
+        parsed_any = true;
+    }
+
+    parsed_any.then(|| Duration::from_secs_f64(total))
+}
@@ -0,0 +1,135 @@
+use crate::client::{ApiError, LlmClient, LlmResponse, ChatMessage, ToolDefinition, StreamingChunk};
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use std::future::Future;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+/// Consecutive 5xx responses from a provider before the chain moves past it
+const DEFAULT_FAILOVER_AFTER: u32 = 2;
+
+/// How a provider failed, as far as the fallback chain is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Failure {
+    /// Connection problems: the provider is unreachable
+    Unreachable,
+    /// A 5xx response: the provider may recover, so it gets a few chances
+    ServerError,
+    /// Client errors such as a bad request or an invalid key would fail the
+    /// same way on every provider, so they are returned directly
+    Other,
+}
+
+fn classify(error: &anyhow::Error) -> Failure {
+    if let Some(api_error) = error.downcast_ref::<ApiError>() {
+        return if api_error.status >= 500 { Failure::ServerError } else { Failure::Other };
+    }
+
+    if let Some(reqwest_error) = error.downcast_ref::<reqwest::Error>() {
+        if reqwest_error.is_connect() || reqwest_error.is_timeout() || reqwest_error.is_request() {
+            return Failure::Unreachable;
+        }
+    }
+
+    Failure::Other
+}
+
+/// LLM client that tries an ordered chain of providers until one answers
+///
+/// A provider that can't be reached is skipped at once. One that answers with
+/// 5xx errors is skipped after `failover_after` of them in a row, counted
+/// across requests; until then the error is returned so the caller can retry
+/// it. Wrap the chain in a `RetryingLlmClient` rather than its providers, so
+/// those retries see the whole chain. The label of the provider that answered
+/// is reported in `LlmResponse::provider` and `StreamingChunk::provider`.
+pub struct FallbackLlmClient {
+    providers: Vec<(String, Arc<dyn LlmClient>)>,
+    /// Consecutive 5xx responses per provider, reset when it answers
+    server_errors: Vec<AtomicU32>,
+    failover_after: u32,
+}
+
+impl FallbackLlmClient {
+    /// Create a fallback chain from `(label, client)` pairs, in order of preference
+    pub fn new(providers: Vec<(String, Arc<dyn LlmClient>)>) -> Self {
+        assert!(!providers.is_empty(), "FallbackLlmClient needs at least one provider");
+        let server_errors = providers.iter().map(|_| AtomicU32::new(0)).collect();
+        Self { providers, server_errors, failover_after: DEFAULT_FAILOVER_AFTER }
+    }
+
+    /// Fail over after this many consecutive 5xx responses from a provider
+    pub fn with_failover_after(mut self, failover_after: u32) -> Self {
+        self.failover_after = failover_after.max(1);
+        self
+    }
+
+    async fn first_available<'a, T, F, Fut>(&'a self, mut operation: F) -> Result<(T, &'a str)>
+    where
+        F: FnMut(&'a Arc<dyn LlmClient>) -> Fut + Send,
+        Fut: Future<Output = Result<T>> + Send + 'a,
+        T: Send,
+    {
+        let mut providers = self.providers.iter().zip(&self.server_errors).peekable();
+
+        loop {
+            let ((label, client), server_errors) = providers.next().expect("provider chain is not empty");
+            match operation(client).await {
+                Ok(value) => {
+                    server_errors.store(0, Ordering::Relaxed);
+                    return Ok((value, label.as_str()));
+                }
+                Err(error) => {
+                    let Some(((next_label, _), _)) = providers.peek() else {
+                        return Err(error);
+                    };
+                    let fail_over = match classify(&error) {
+                        Failure::Unreachable => true,
+                        Failure::ServerError => server_errors.fetch_add(1, Ordering::Relaxed) + 1 >= self.failover_after,
+                        Failure::Other => false,
+                    };
+                    if !fail_over {
+                        return Err(error);
+                    }
+                    let _ = kimichat_logging::log_failover_to_file(label, next_label, &format!("{:#}", error));
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl LlmClient for FallbackLlmClient {
+    async fn chat(&self, messages: Vec<ChatMessage>, tools: Vec<ToolDefinition>) -> Result<LlmResponse> {
+        let (mut response, label) = self
+            .first_available(|client| client.chat(messages.clone(), tools.clone()))
+            .await?;
+        response.provider = Some(label.to_string());
+        Ok(response)
+    }
+
+    async fn chat_completion(&self, messages: &[ChatMessage]) -> Result<String> {
+        let (content, _) = self
+            .first_available(|client| client.chat_completion(messages))
+            .await?;
+        Ok(content)
+    }
+
+    async fn chat_streaming(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Vec<ToolDefinition>,
+    ) -> Result<Box<dyn Stream<Item = Result<StreamingChunk>> + Send + Unpin>> {
+        let (stream, label) = self
+            .first_available(|client| client.chat_streaming(messages.clone(), tools.clone()))
+            .await?;
+
+        let label = label.to_string();
+        Ok(Box::new(stream.map(move |chunk| {
+            chunk.map(|mut chunk| {
+                chunk.provider = Some(label.clone());
+                chunk
+            })
+        })))
+    }
+}
@@ -1,17 +1,24 @@
-use crate::client::{LlmClient, LlmResponse, ChatMessage, ToolDefinition, TokenUsage};
+use crate::client::{ApiError, LlmClient, LlmResponse, ChatMessage, ToolDefinition, TokenUsage, StreamingChunk};
 use anyhow::{Result, Context};
 use async_trait::async_trait;
+use futures::Stream;
 use std::fs;
 use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
 use kimichat_logging::get_logs_dir;
+use kimichat_models::ReasoningConfig;
+use super::openai_stream;
 
 /// Groq LLM client implementation (OpenAI-compatible API)
+///
+/// Also used for OpenAI and keyless OpenAI-compatible servers; the
+/// `Authorization` header is only sent when an API key is set.
 pub struct GroqLlmClient {
     api_key: String,
     model: String,
     api_url: String,
     agent_name: String,
+    reasoning: Option<ReasoningConfig>,
     client: reqwest::Client,
 }
 
@@ -22,9 +29,26 @@ impl GroqLlmClient {
             model,
             api_url,
             agent_name,
+            reasoning: None,
             client: reqwest::Client::new(),
         }
     }
+
+    /// Request a reasoning effort from reasoning models such as gpt-oss or o3
+    pub fn with_reasoning(mut self, reasoning: Option<ReasoningConfig>) -> Self {
+        self.reasoning = reasoning;
+        self
+    }
+
+    /// Start a POST request, authenticating only when an API key is configured
+    fn post(&self, url: &str) -> reqwest::RequestBuilder {
+        let request = self.client.post(url);
+        if self.api_key.is_empty() {
+            request
+        } else {
+            request.header("Authorization", format!("Bearer {}", self.api_key))
+        }
+    }
 }
 
 #[async_trait]
@@ -35,17 +59,14 @@ impl LlmClient for GroqLlmClient {
         // Log request to file for persistent debugging
         let _ = self.log_request_to_file(&self.api_url, &request);
 
-        let response = self.client
-            .post(&self.api_url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
+        let response = self.post(&self.api_url)
             .header("Content-Type", "application/json")
             .json(&request)
             .send()
             .await?;
 
         if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Groq API error: {}", error_text));
+            return Err(ApiError::from_response("Groq", response).await.into());
         }
 
         let response_text = response.text().await?;
@@ -66,7 +87,8 @@ impl LlmClient for GroqLlmClient {
                 }),
                 tool_call_id: choice.message.tool_call_id,
                 name: choice.message.name,
-                reasoning: None,
+                reasoning: choice.message.reasoning,
+                reasoning_details: None,
             }
         } else {
             ChatMessage {
@@ -76,6 +98,7 @@ impl LlmClient for GroqLlmClient {
                 tool_call_id: None,
                 name: None,
                 reasoning: None,
+                reasoning_details: None,
             }
         };
 
@@ -85,7 +108,9 @@ impl LlmClient for GroqLlmClient {
                 prompt_tokens: usage.prompt_tokens as u32,
                 completion_tokens: usage.completion_tokens as u32,
                 total_tokens: usage.total_tokens as u32,
+                ..TokenUsage::default()
             }),
+            provider: None,
         })
     }
 
@@ -101,19 +126,14 @@ impl LlmClient for GroqLlmClient {
         // Log request to file for persistent debugging
         let _ = self.log_request_to_file(&self.api_url, &api_request);
 
-        let client = reqwest::Client::new();
-        let response = client
-            .post(&self.api_url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
+        let response = self.post(&self.api_url)
             .header("Content-Type", "application/json")
             .json(&api_request)
             .send()
             .await?;
 
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(anyhow::anyhow!("API request failed: {} - {}", status, error_text));
+        if !response.status().is_success() {
+            return Err(ApiError::from_response("Groq", response).await.into());
         }
 
         let response_text = response.text().await?;
@@ -125,6 +145,28 @@ impl LlmClient for GroqLlmClient {
             Err(anyhow::anyhow!("No content in response"))
         }
     }
+
+    async fn chat_streaming(&self, messages: Vec<ChatMessage>, tools: Vec<ToolDefinition>) -> Result<Box<dyn Stream<Item = Result<StreamingChunk>> + Send + Unpin>> {
+        let mut request = self.build_chat_request(messages, tools).await?;
+        request["stream"] = serde_json::Value::Bool(true);
+        request["stream_options"] = serde_json::json!({"include_usage": true});
+
+        // Log request to file for persistent debugging
+        let _ = self.log_request_to_file(&self.api_url, &request);
+
+        let response = self.post(&self.api_url)
+            .header("Content-Type", "application/json")
+            .header("Accept", "text/event-stream")
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(ApiError::from_response("Groq", response).await.into());
+        }
+
+        Ok(openai_stream::stream_response(response))
+    }
 }
 
 impl GroqLlmClient {
@@ -204,6 +246,7 @@ impl GroqLlmClient {
                 tool_call_id: msg.tool_call_id,
                 name: msg.name,
                 reasoning: None,
+                reasoning_details: None,
             }
         }).collect();
 
@@ -218,11 +261,16 @@ impl GroqLlmClient {
             }
         }).collect();
 
-        Ok(serde_json::json!({
+        let mut request = serde_json::json!({
             "model": self.model,
             "messages": chat_messages,
             "tools": tool_definitions,
             "tool_choice": "auto"
-        }))
+        });
+        if let Some(reasoning) = &self.reasoning {
+            request["reasoning_effort"] = reasoning.effort().as_str().into();
+        }
+
+        Ok(request)
     }
 }
@@ -1,6 +1,8 @@
-use crate::client::{LlmClient, LlmResponse, ChatMessage, ToolDefinition, TokenUsage};
+use crate::client::{ApiError, LlmClient, LlmResponse, ChatMessage, ToolDefinition, TokenUsage, StreamingChunk};
 use anyhow::Result;
 use async_trait::async_trait;
+use futures::Stream;
+use super::openai_stream;
 
 /// llama.cpp server LLM client implementation with OpenAI-compatible API
 pub struct LlamaCppClient {
@@ -38,8 +40,7 @@ impl LlmClient for LlamaCppClient {
             .await?;
 
         if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("llama.cpp API error: {}", error_text));
+            return Err(ApiError::from_response("llama.cpp", response).await.into());
         }
 
         let response_text = response.text().await?;
@@ -61,6 +62,7 @@ impl LlmClient for LlamaCppClient {
                 tool_call_id: choice.message.tool_call_id,
                 name: choice.message.name,
                 reasoning: None,
+                reasoning_details: None,
             }
         } else {
             ChatMessage {
@@ -70,6 +72,7 @@ impl LlmClient for LlamaCppClient {
                 tool_call_id: None,
                 name: None,
                 reasoning: None,
+                reasoning_details: None,
             }
         };
 
@@ -79,7 +82,9 @@ impl LlmClient for LlamaCppClient {
                 prompt_tokens: usage.prompt_tokens as u32,
                 completion_tokens: usage.completion_tokens as u32,
                 total_tokens: usage.total_tokens as u32,
+                ..TokenUsage::default()
             }),
+            provider: None,
         })
     }
 
@@ -99,10 +104,8 @@ impl LlmClient for LlamaCppClient {
             .send()
             .await?;
 
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(anyhow::anyhow!("API request failed: {} - {}", status, error_text));
+        if !response.status().is_success() {
+            return Err(ApiError::from_response("llama.cpp", response).await.into());
         }
 
         let response_text = response.text().await?;
@@ -114,6 +117,26 @@ impl LlmClient for LlamaCppClient {
             Err(anyhow::anyhow!("No content in response"))
         }
     }
+
+    async fn chat_streaming(&self, messages: Vec<ChatMessage>, tools: Vec<ToolDefinition>) -> Result<Box<dyn Stream<Item = Result<StreamingChunk>> + Send + Unpin>> {
+        let mut request = self.build_chat_request(messages, tools).await?;
+        request["stream"] = serde_json::Value::Bool(true);
+        request["stream_options"] = serde_json::json!({"include_usage": true});
+
+        let response = self.client
+            .post(self.get_chat_completions_url())
+            .header("Content-Type", "application/json")
+            .header("Accept", "text/event-stream")
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(ApiError::from_response("llama.cpp", response).await.into());
+        }
+
+        Ok(openai_stream::stream_response(response))
+    }
 }
 
 impl LlamaCppClient {
@@ -135,6 +158,7 @@ impl LlamaCppClient {
                 tool_call_id: msg.tool_call_id,
                 name: msg.name,
                 reasoning: None,
+                reasoning_details: None,
             }
         }).collect();
 
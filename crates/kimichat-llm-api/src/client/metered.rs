@@ -0,0 +1,76 @@
+use crate::client::{LlmClient, LlmResponse, ChatMessage, ToolDefinition, StreamingChunk, TokenUsage};
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use std::sync::Arc;
+
+/// Receives the token usage of LLM calls, e.g. to account for their cost
+pub trait UsageRecorder: Send + Sync {
+    /// Record the usage of a call made on behalf of `source` (such as an agent name) to `model`
+    fn record_usage(&self, source: &str, model: &str, usage: &TokenUsage);
+
+    /// Fail if no further calls should be made, e.g. because a spending cap was reached
+    fn check_budget(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// LLM client wrapper that reports token usage to a `UsageRecorder`
+///
+/// Usage is recorded for `chat` responses and for the streaming chunk that
+/// carries the server's usage. `chat_completion` returns no usage, so those
+/// calls are only checked against the budget. Every call is refused once the
+/// recorder's budget check fails.
+pub struct MeteredLlmClient {
+    inner: Arc<dyn LlmClient>,
+    recorder: Arc<dyn UsageRecorder>,
+    source: String,
+    model: String,
+}
+
+impl MeteredLlmClient {
+    pub fn new(inner: Arc<dyn LlmClient>, recorder: Arc<dyn UsageRecorder>, source: String, model: String) -> Self {
+        Self {
+            inner,
+            recorder,
+            source,
+            model,
+        }
+    }
+}
+
+#[async_trait]
+impl LlmClient for MeteredLlmClient {
+    async fn chat(&self, messages: Vec<ChatMessage>, tools: Vec<ToolDefinition>) -> Result<LlmResponse> {
+        self.recorder.check_budget()?;
+        let response = self.inner.chat(messages, tools).await?;
+        if let Some(usage) = &response.usage {
+            self.recorder.record_usage(&self.source, &self.model, usage);
+        }
+        Ok(response)
+    }
+
+    async fn chat_completion(&self, messages: &[ChatMessage]) -> Result<String> {
+        self.recorder.check_budget()?;
+        self.inner.chat_completion(messages).await
+    }
+
+    async fn chat_streaming(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Vec<ToolDefinition>,
+    ) -> Result<Box<dyn Stream<Item = Result<StreamingChunk>> + Send + Unpin>> {
+        self.recorder.check_budget()?;
+        let stream = self.inner.chat_streaming(messages, tools).await?;
+
+        let recorder = Arc::clone(&self.recorder);
+        let source = self.source.clone();
+        let model = self.model.clone();
+        Ok(Box::new(stream.map(move |chunk| {
+            if let Ok(StreamingChunk { usage: Some(usage), .. }) = &chunk {
+                recorder.record_usage(&source, &model, usage);
+            }
+            chunk
+        })))
+    }
+}
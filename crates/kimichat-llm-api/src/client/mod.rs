@@ -4,8 +4,19 @@ use futures::Stream;
 use serde::{Deserialize, Serialize};
 
 pub mod anthropic;
+pub mod error;
+pub mod fallback;
 pub mod groq;
 pub mod llama_cpp;
+pub mod metered;
+pub mod ollama;
+pub(crate) mod openai_stream;
+pub mod retry;
+
+pub use error::ApiError;
+pub use fallback::FallbackLlmClient;
+pub use metered::{MeteredLlmClient, UsageRecorder};
+pub use retry::{RetryConfig, RetryingLlmClient};
 
 /// Chat message structure (OpenAI-compatible format)
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,6 +30,10 @@ pub struct ChatMessage {
     pub name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reasoning: Option<String>,
+    /// Provider blocks that must be sent back unchanged with this message,
+    /// such as Anthropic's signed and redacted thinking
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_details: Option<Vec<serde_json::Value>>,
 }
 
 /// Tool call structure
@@ -35,12 +50,60 @@ pub struct FunctionCall {
     pub arguments: String,
 }
 
+/// Typed event within a streamed response
+#[derive(Debug, Clone)]
+pub enum StreamEvent {
+    /// Visible assistant text
+    TextDelta(String),
+    /// Reasoning / thinking text (not part of the final answer)
+    ReasoningDelta(String),
+    /// A new tool call has started forming
+    ToolCallStart { index: usize, id: String, name: String },
+    /// A fragment of a tool call's JSON arguments
+    ToolCallArgumentsDelta { index: usize, arguments: String },
+    /// A tool call is complete and ready to execute
+    ToolCallEnd { index: usize, tool_call: ToolCall },
+    /// Token usage reported by the server
+    Usage(TokenUsage),
+}
+
 /// Streaming chunk for LLM responses
 #[derive(Debug, Clone)]
 pub struct StreamingChunk {
     pub content: String,
     pub delta: String,
     pub finish_reason: Option<String>,
+    /// Fully assembled tool calls (set on the final chunk only)
+    pub tool_calls: Option<Vec<ToolCall>>,
+    /// Token usage reported by the server (set on the final chunk only)
+    pub usage: Option<TokenUsage>,
+    /// Typed events carried by this chunk, in arrival order
+    pub events: Vec<StreamEvent>,
+    /// Provider that produced the stream, when the client chooses between several
+    pub provider: Option<String>,
+    /// Reasoning blocks to send back with the assembled message (set on the final chunk only)
+    pub reasoning_details: Option<Vec<serde_json::Value>>,
+}
+
+impl StreamingChunk {
+    /// Create a chunk from a list of events, filling `delta` from the text events
+    pub fn from_events(events: Vec<StreamEvent>) -> Self {
+        let delta = events.iter().filter_map(|event| match event {
+            StreamEvent::TextDelta(text) => Some(text.as_str()),
+            _ => None,
+        }).collect();
+
+        Self {
+            content: String::new(),
+            delta,
+            finish_reason: None,
+            tool_calls: None,
+            usage: None,
+            events,
+            provider: None,
+            reasoning_details: None,
+        }
+    }
 }
 
 /// LLM client trait - unified interface for all LLM providers
@@ -68,14 +131,24 @@ pub trait LlmClient: Send + Sync {
 pub struct LlmResponse {
     pub message: ChatMessage,
     pub usage: Option<TokenUsage>,
+    /// Provider that answered, when the client chooses between several (e.g. "kimi@groq")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
 }
 
 /// Token usage information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TokenUsage {
+    /// All prompt tokens, including those written to or read from the prompt cache
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
     pub total_tokens: u32,
+    /// Prompt tokens written to the provider's prompt cache
+    #[serde(default)]
+    pub cache_creation_input_tokens: u32,
+    /// Prompt tokens served from the provider's prompt cache
+    #[serde(default)]
+    pub cache_read_input_tokens: u32,
 }
 
 /// Tool definition for function calling
@@ -0,0 +1,384 @@
+use crate::client::{ApiError, LlmClient, LlmResponse, ChatMessage, ToolDefinition, TokenUsage, StreamingChunk, StreamEvent, ToolCall, FunctionCall};
+use anyhow::Result;
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Ollama LLM client using the native /api/chat endpoint
+///
+/// Ollama takes tool call arguments as JSON objects rather than strings, does
+/// not assign tool call ids, and streams newline-delimited JSON instead of SSE,
+/// so it gets its own translation layer instead of reusing the OpenAI one.
+pub struct OllamaClient {
+    base_url: String,
+    model: String,
+    client: reqwest::Client,
+}
+
+/// Response body of /api/chat (also used for each streamed line)
+#[derive(Debug, Deserialize)]
+struct OllamaChatResponse {
+    #[serde(default)]
+    message: Option<OllamaMessage>,
+    #[serde(default)]
+    done: bool,
+    #[serde(default)]
+    done_reason: Option<String>,
+    #[serde(default)]
+    prompt_eval_count: Option<u32>,
+    #[serde(default)]
+    eval_count: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaMessage {
+    #[serde(default)]
+    role: Option<String>,
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    thinking: Option<String>,
+    #[serde(default)]
+    tool_calls: Option<Vec<OllamaToolCall>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaToolCall {
+    #[serde(default)]
+    id: Option<String>,
+    function: OllamaFunction,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaFunction {
+    name: String,
+    #[serde(default)]
+    arguments: serde_json::Value,
+}
+
+impl OllamaClient {
+    pub fn new(base_url: String, model: String) -> Self {
+        // Ensure base_url doesn't end with a slash
+        let base_url = base_url.trim_end_matches('/').to_string();
+        Self {
+            base_url,
+            model,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    fn get_chat_url(&self) -> String {
+        if self.base_url.ends_with("/api/chat") {
+            self.base_url.clone()
+        } else {
+            format!("{}/api/chat", self.base_url)
+        }
+    }
+
+    async fn send(&self, request: &serde_json::Value) -> Result<reqwest::Response> {
+        let response = self.client
+            .post(self.get_chat_url())
+            .header("Content-Type", "application/json")
+            .json(request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(ApiError::from_response("Ollama", response).await.into());
+        }
+        Ok(response)
+    }
+
+    fn build_chat_request(&self, messages: Vec<ChatMessage>, tools: Vec<ToolDefinition>, stream: bool) -> serde_json::Value {
+        let mut request = serde_json::json!({
+            "model": self.model,
+            "messages": to_ollama_messages(messages),
+            "stream": stream,
+        });
+
+        if !tools.is_empty() {
+            request["tools"] = tools.into_iter().map(|tool| serde_json::json!({
+                "type": "function",
+                "function": {
+                    "name": tool.name,
+                    "description": tool.description,
+                    "parameters": tool.parameters,
+                },
+            })).collect();
+        }
+        request
+    }
+}
+
+/// Convert messages to Ollama's format
+///
+/// Tool call arguments become JSON objects, and tool results are tagged with
+/// the name of the tool that produced them since Ollama has no call ids.
+fn to_ollama_messages(messages: Vec<ChatMessage>) -> Vec<serde_json::Value> {
+    let mut tool_names: HashMap<String, String> = HashMap::new();
+
+    messages.into_iter().map(|msg| {
+        let mut value = serde_json::json!({
+            "role": msg.role,
+            "content": msg.content,
+        });
+
+        if let Some(calls) = msg.tool_calls.filter(|calls| !calls.is_empty()) {
+            value["tool_calls"] = calls.into_iter().map(|call| {
+                tool_names.insert(call.id, call.function.name.clone());
+                let arguments = serde_json::from_str::<serde_json::Value>(&call.function.arguments)
+                    .unwrap_or_else(|_| serde_json::json!({}));
+                serde_json::json!({
+                    "function": {"name": call.function.name, "arguments": arguments},
+                })
+            }).collect();
+        }
+
+        if msg.role == "tool" {
+            let tool_name = msg.name.or_else(|| {
+                msg.tool_call_id.as_ref().and_then(|id| tool_names.get(id).cloned())
+            });
+            if let Some(tool_name) = tool_name {
+                value["tool_name"] = serde_json::Value::String(tool_name);
+            }
+        }
+
+        value
+    }).collect()
+}
+
+/// Ollama doesn't assign ids to tool calls, so make ones unique enough to pair results with
+fn tool_call_id(call: &OllamaToolCall, index: usize) -> String {
+    if let Some(id) = call.id.as_ref().filter(|id| !id.is_empty()) {
+        return id.clone();
+    }
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    format!("call_{:x}_{}", nanos, index)
+}
+
+fn to_tool_call(call: OllamaToolCall, index: usize) -> ToolCall {
+    let id = tool_call_id(&call, index);
+    let arguments = match call.function.arguments {
+        serde_json::Value::String(arguments) => arguments,
+        serde_json::Value::Null => "{}".to_string(),
+        arguments => arguments.to_string(),
+    };
+
+    ToolCall {
+        id,
+        function: FunctionCall {
+            name: call.function.name,
+            arguments,
+        },
+    }
+}
+
+fn usage_from(response: &OllamaChatResponse) -> Option<TokenUsage> {
+    if response.prompt_eval_count.is_none() && response.eval_count.is_none() {
+        return None;
+    }
+    let prompt_tokens = response.prompt_eval_count.unwrap_or(0);
+    let completion_tokens = response.eval_count.unwrap_or(0);
+    Some(TokenUsage {
+        prompt_tokens,
+        completion_tokens,
+        total_tokens: prompt_tokens + completion_tokens,
+        ..TokenUsage::default()
+    })
+}
+
+#[async_trait]
+impl LlmClient for OllamaClient {
+    async fn chat(&self, messages: Vec<ChatMessage>, tools: Vec<ToolDefinition>) -> Result<LlmResponse> {
+        let request = self.build_chat_request(messages, tools, false);
+        let response = self.send(&request).await?;
+
+        let response_text = response.text().await?;
+        let chat_response: OllamaChatResponse = serde_json::from_str(&response_text)?;
+        let usage = usage_from(&chat_response);
+
+        let message = match chat_response.message {
+            Some(message) => {
+                let tool_calls: Vec<ToolCall> = message.tool_calls.unwrap_or_default()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, call)| to_tool_call(call, index))
+                    .collect();
+                ChatMessage {
+                    role: message.role.unwrap_or_else(|| "assistant".to_string()),
+                    content: message.content,
+                    tool_calls: if tool_calls.is_empty() { None } else { Some(tool_calls) },
+                    tool_call_id: None,
+                    name: None,
+                    reasoning: message.thinking.filter(|thinking| !thinking.is_empty()),
+                    reasoning_details: None,
+                }
+            }
+            None => ChatMessage {
+                role: "assistant".to_string(),
+                content: "No response generated".to_string(),
+                tool_calls: None,
+                tool_call_id: None,
+                name: None,
+                reasoning: None,
+                reasoning_details: None,
+            },
+        };
+
+        Ok(LlmResponse {
+            message,
+            usage,
+            provider: None,
+        })
+    }
+
+    async fn chat_completion(&self, messages: &[ChatMessage]) -> Result<String> {
+        // For progress evaluation, make a simple API call without tools
+        let mut request = self.build_chat_request(messages.to_vec(), vec![], false);
+        request["options"] = serde_json::json!({"temperature": 0.1, "num_predict": 2000});
+
+        let response = self.send(&request).await?;
+        let chat_response: OllamaChatResponse = serde_json::from_str(&response.text().await?)?;
+
+        chat_response.message
+            .map(|message| message.content)
+            .ok_or_else(|| anyhow::anyhow!("No content in response"))
+    }
+
+    async fn chat_streaming(&self, messages: Vec<ChatMessage>, tools: Vec<ToolDefinition>) -> Result<Box<dyn Stream<Item = Result<StreamingChunk>> + Send + Unpin>> {
+        let request = self.build_chat_request(messages, tools, true);
+        let response = self.send(&request).await?;
+        let mut byte_stream = response.bytes_stream();
+
+        let stream = stream! {
+            let mut parser = OllamaStreamParser::default();
+
+            while let Some(chunk_result) = byte_stream.next().await {
+                match chunk_result {
+                    Ok(bytes) => {
+                        for chunk in parser.push(&bytes) {
+                            yield Ok(chunk);
+                        }
+                        if parser.done {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        yield Err(anyhow::anyhow!("Stream error: {}", e));
+                        return;
+                    }
+                }
+            }
+
+            yield Ok(parser.finish());
+        };
+
+        Ok(Box::new(Box::pin(stream)))
+    }
+}
+
+/// Incremental parser for Ollama's newline-delimited JSON stream
+#[derive(Default)]
+pub(crate) struct OllamaStreamParser {
+    buffer: Vec<u8>,
+    tool_calls: Vec<ToolCall>,
+    usage: Option<TokenUsage>,
+    finish_reason: Option<String>,
+    pub(crate) done: bool,
+}
+
+impl OllamaStreamParser {
+    /// Feed raw bytes and return chunks for every complete line
+    pub(crate) fn push(&mut self, data: &[u8]) -> Vec<StreamingChunk> {
+        self.buffer.extend_from_slice(data);
+
+        let mut chunks = Vec::new();
+        while let Some(line_end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=line_end).collect();
+            if let Some(chunk) = self.parse_line(&String::from_utf8_lossy(&line)) {
+                chunks.push(chunk);
+            }
+        }
+        chunks
+    }
+
+    /// Flush any trailing line and produce the final chunk with tool calls and usage
+    pub(crate) fn finish(&mut self) -> StreamingChunk {
+        let remaining = std::mem::take(&mut self.buffer);
+        let mut events = self.parse_line(&String::from_utf8_lossy(&remaining))
+            .map(|chunk| chunk.events)
+            .unwrap_or_default();
+
+        let tool_calls = std::mem::take(&mut self.tool_calls);
+        for (index, tool_call) in tool_calls.iter().enumerate() {
+            events.push(StreamEvent::ToolCallEnd { index, tool_call: tool_call.clone() });
+        }
+        let usage = self.usage.take();
+        if let Some(ref usage) = usage {
+            events.push(StreamEvent::Usage(usage.clone()));
+        }
+
+        let mut chunk = StreamingChunk::from_events(events);
+        chunk.finish_reason = Some(if tool_calls.is_empty() {
+            self.finish_reason.take().unwrap_or_else(|| "stop".to_string())
+        } else {
+            // Ollama reports "stop" even when the model called tools
+            "tool_calls".to_string()
+        });
+        chunk.tool_calls = if tool_calls.is_empty() { None } else { Some(tool_calls) };
+        chunk.usage = usage;
+        chunk
+    }
+
+    fn parse_line(&mut self, line: &str) -> Option<StreamingChunk> {
+        let line = line.trim();
+        if line.is_empty() {
+            return None;
+        }
+        let response: OllamaChatResponse = serde_json::from_str(line).ok()?;
+
+        if response.done {
+            self.done = true;
+            self.finish_reason = response.done_reason.clone();
+            self.usage = usage_from(&response);
+        }
+
+        let message = response.message?;
+        let mut events = Vec::new();
+
+        if let Some(thinking) = message.thinking.filter(|thinking| !thinking.is_empty()) {
+            events.push(StreamEvent::ReasoningDelta(thinking));
+        }
+        if !message.content.is_empty() {
+            events.push(StreamEvent::TextDelta(message.content));
+        }
+
+        // Tool calls arrive complete, so each one starts and carries its full arguments at once
+        for call in message.tool_calls.unwrap_or_default() {
+            let index = self.tool_calls.len();
+            let tool_call = to_tool_call(call, index);
+            events.push(StreamEvent::ToolCallStart {
+                index,
+                id: tool_call.id.clone(),
+                name: tool_call.function.name.clone(),
+            });
+            events.push(StreamEvent::ToolCallArgumentsDelta {
+                index,
+                arguments: tool_call.function.arguments.clone(),
+            });
+            self.tool_calls.push(tool_call);
+        }
+
+        if events.is_empty() {
+            None
+        } else {
+            Some(StreamingChunk::from_events(events))
+        }
+    }
+}
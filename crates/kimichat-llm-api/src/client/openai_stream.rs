@@ -0,0 +1,197 @@
+use crate::client::{StreamingChunk, StreamEvent, ToolCall, FunctionCall, TokenUsage};
+use anyhow::Result;
+use futures::Stream;
+use futures::StreamExt;
+use async_stream::stream;
+
+/// Incremental parser for OpenAI-compatible SSE streams (Groq, OpenAI, llama.cpp)
+///
+/// Text, reasoning and tool call deltas are emitted as typed events as soon as
+/// they arrive. Tool call deltas are also merged by their `index` (argument
+/// fragments are concatenated) and handed out in the final chunk together with
+/// the usage reported by the server.
+#[derive(Default)]
+pub(crate) struct OpenAiStreamParser {
+    buffer: Vec<u8>,
+    tool_calls: Vec<ToolCall>,
+    usage: Option<TokenUsage>,
+    finish_reason: Option<String>,
+    done: bool,
+}
+
+impl OpenAiStreamParser {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the `[DONE]` marker has been seen
+    pub(crate) fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Feed raw bytes from the response body and return any chunks they complete
+    ///
+    /// Bytes are buffered until a full line is available so multi-byte UTF-8
+    /// characters split across network reads are decoded correctly.
+    pub(crate) fn push(&mut self, data: &[u8]) -> Vec<StreamingChunk> {
+        self.buffer.extend_from_slice(data);
+
+        let mut chunks = Vec::new();
+        while let Some(line_end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=line_end).collect();
+            let line = String::from_utf8_lossy(&line);
+            if let Some(chunk) = self.parse_line(line.trim_end_matches(['\r', '\n'])) {
+                chunks.push(chunk);
+            }
+        }
+        chunks
+    }
+
+    /// Flush any trailing line and produce the final chunk with tool calls and usage
+    pub(crate) fn finish(&mut self) -> StreamingChunk {
+        let remaining = std::mem::take(&mut self.buffer);
+        let remaining = String::from_utf8_lossy(&remaining);
+        let mut events = self.parse_line(remaining.trim_end_matches(['\r', '\n']))
+            .map(|chunk| chunk.events)
+            .unwrap_or_default();
+
+        let tool_calls = std::mem::take(&mut self.tool_calls);
+        for (index, tool_call) in tool_calls.iter().enumerate() {
+            events.push(StreamEvent::ToolCallEnd { index, tool_call: tool_call.clone() });
+        }
+        let usage = self.usage.take();
+        if let Some(ref usage) = usage {
+            events.push(StreamEvent::Usage(usage.clone()));
+        }
+
+        let mut chunk = StreamingChunk::from_events(events);
+        chunk.finish_reason = Some(self.finish_reason.take().unwrap_or_else(|| {
+            if tool_calls.is_empty() { "stop".to_string() } else { "tool_calls".to_string() }
+        }));
+        chunk.tool_calls = if tool_calls.is_empty() { None } else { Some(tool_calls) };
+        chunk.usage = usage;
+        chunk
+    }
+
+    /// Parse a single SSE line, accumulating tool call deltas and usage
+    fn parse_line(&mut self, line: &str) -> Option<StreamingChunk> {
+        let data = line.strip_prefix("data:")?.trim_start();
+
+        if data.trim() == "[DONE]" {
+            self.done = true;
+            return None;
+        }
+
+        let chunk: kimichat_models::StreamChunk = serde_json::from_str(data).ok()?;
+
+        // OpenAI/llama.cpp report usage at the top level, Groq under x_groq
+        if let Some(usage) = chunk.usage.or_else(|| chunk.x_groq.and_then(|x| x.usage)) {
+            self.usage = Some(TokenUsage {
+                prompt_tokens: usage.prompt_tokens as u32,
+                completion_tokens: usage.completion_tokens as u32,
+                total_tokens: usage.total_tokens as u32,
+                ..TokenUsage::default()
+            });
+        }
+
+        let choice = chunk.choices.into_iter().next()?;
+        if choice.finish_reason.is_some() {
+            self.finish_reason = choice.finish_reason;
+        }
+
+        let mut events = Vec::new();
+
+        if let Some(reasoning) = choice.delta.reasoning_content {
+            if !reasoning.is_empty() {
+                events.push(StreamEvent::ReasoningDelta(reasoning));
+            }
+        }
+
+        if let Some(text) = choice.delta.content {
+            if !text.is_empty() {
+                events.push(StreamEvent::TextDelta(text));
+            }
+        }
+
+        if let Some(deltas) = choice.delta.tool_calls {
+            for delta_call in deltas {
+                let index = delta_call.index;
+                let is_new = index >= self.tool_calls.len();
+
+                // Ensure we have enough slots for this index
+                while self.tool_calls.len() <= index {
+                    self.tool_calls.push(ToolCall {
+                        id: String::new(),
+                        function: FunctionCall {
+                            name: String::new(),
+                            arguments: String::new(),
+                        },
+                    });
+                }
+
+                let tool_call = &mut self.tool_calls[index];
+                if let Some(id) = delta_call.id {
+                    tool_call.id = id;
+                }
+                let mut arguments = None;
+                if let Some(function) = delta_call.function {
+                    if let Some(name) = function.name {
+                        tool_call.function.name.push_str(&name);
+                    }
+                    arguments = function.arguments.filter(|a| !a.is_empty());
+                }
+
+                if is_new {
+                    events.push(StreamEvent::ToolCallStart {
+                        index,
+                        id: tool_call.id.clone(),
+                        name: tool_call.function.name.clone(),
+                    });
+                }
+                if let Some(arguments) = arguments {
+                    tool_call.function.arguments.push_str(&arguments);
+                    events.push(StreamEvent::ToolCallArgumentsDelta { index, arguments });
+                }
+            }
+        }
+
+        if events.is_empty() {
+            None
+        } else {
+            Some(StreamingChunk::from_events(events))
+        }
+    }
+}
+
+/// Turn an OpenAI-compatible SSE response into a stream of chunks
+pub(crate) fn stream_response(
+    response: reqwest::Response,
+) -> Box<dyn Stream<Item = Result<StreamingChunk>> + Send + Unpin> {
+    let byte_stream = response.bytes_stream();
+
+    let stream = stream! {
+        let mut parser = OpenAiStreamParser::new();
+        let mut byte_stream = byte_stream;
+
+        while let Some(chunk_result) = byte_stream.next().await {
+            match chunk_result {
+                Ok(bytes) => {
+                    for chunk in parser.push(&bytes) {
+                        yield Ok(chunk);
+                    }
+                    if parser.is_done() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    yield Err(anyhow::anyhow!("Stream error: {}", e));
+                    return;
+                }
+            }
+        }
+
+        yield Ok(parser.finish());
+    };
+
+    Box::new(Box::pin(stream))
+}
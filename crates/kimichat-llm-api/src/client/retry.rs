@@ -0,0 +1,161 @@
+use crate::client::{ApiError, LlmClient, LlmResponse, ChatMessage, ToolDefinition, StreamingChunk};
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::Stream;
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Retry policy for transient LLM API failures
+#[derive(Debug, Clone)]
+pub struct RetryConfig {
+    /// Number of retries after the first attempt
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled on every further attempt
+    pub base_delay: Duration,
+    /// Upper bound for a single backoff delay
+    pub max_delay: Duration,
+    /// Give up once waiting would take the request past this much time in total
+    pub max_total_time: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(60),
+            max_total_time: Duration::from_secs(300),
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Backoff for the given retry (1-based), honouring a server hint when present
+    ///
+    /// Computed delays use "equal jitter": a random value between half and the
+    /// full exponential delay, so concurrent agents don't retry in lockstep.
+    fn delay_for(&self, attempt: u32, server_hint: Option<Duration>) -> Duration {
+        if let Some(hint) = server_hint {
+            return hint;
+        }
+
+        let exponential = self.base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay);
+        let half = exponential / 2;
+        let jitter_range = (exponential - half).as_millis() as u64;
+        let jitter = if jitter_range == 0 { 0 } else { random_u64() % (jitter_range + 1) };
+        half + Duration::from_millis(jitter)
+    }
+}
+
+/// Cheap randomness for jitter without pulling in an RNG crate
+fn random_u64() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// Why a failed request may be retried, or `None` if it should fail immediately
+fn retry_reason(error: &anyhow::Error) -> Option<(String, Option<Duration>)> {
+    if let Some(api_error) = error.downcast_ref::<ApiError>() {
+        return api_error.is_retryable()
+            .then(|| (api_error.to_string(), api_error.retry_after));
+    }
+
+    if let Some(reqwest_error) = error.downcast_ref::<reqwest::Error>() {
+        if reqwest_error.is_connect() || reqwest_error.is_timeout() || reqwest_error.is_request() {
+            return Some((reqwest_error.to_string(), None));
+        }
+    }
+
+    None
+}
+
+/// LLM client wrapper that retries rate-limited and transient failures
+///
+/// Retries HTTP 429, 5xx and connection/timeout errors with jittered
+/// exponential backoff, waiting as long as `retry-after` or rate-limit reset
+/// headers ask when the provider sends them. For streaming only opening the
+/// stream is retried; errors in the middle of a stream are passed through.
+pub struct RetryingLlmClient {
+    inner: Arc<dyn LlmClient>,
+    config: RetryConfig,
+    model: String,
+    agent_name: String,
+}
+
+impl RetryingLlmClient {
+    pub fn new(inner: Arc<dyn LlmClient>, config: RetryConfig, model: String, agent_name: String) -> Self {
+        Self {
+            inner,
+            config,
+            model,
+            agent_name,
+        }
+    }
+
+    async fn with_retries<T, F, Fut>(&self, mut operation: F) -> Result<T>
+    where
+        F: FnMut() -> Fut + Send,
+        Fut: Future<Output = Result<T>> + Send,
+        T: Send,
+    {
+        let started = Instant::now();
+        let mut attempt = 0;
+
+        loop {
+            let error = match operation().await {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+
+            let Some((reason, server_hint)) = retry_reason(&error) else {
+                return Err(error);
+            };
+            if attempt >= self.config.max_retries {
+                return Err(error.context(format!("Giving up after {} retries", attempt)));
+            }
+
+            attempt += 1;
+            let delay = self.config.delay_for(attempt, server_hint);
+            if started.elapsed() + delay > self.config.max_total_time {
+                return Err(error.context(format!(
+                    "Giving up: retrying in {:.1}s would exceed the {:.0}s retry budget",
+                    delay.as_secs_f64(),
+                    self.config.max_total_time.as_secs_f64()
+                )));
+            }
+
+            let _ = kimichat_logging::log_retry_to_file(
+                &self.model,
+                &self.agent_name,
+                attempt,
+                self.config.max_retries,
+                delay,
+                &reason,
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+#[async_trait]
+impl LlmClient for RetryingLlmClient {
+    async fn chat(&self, messages: Vec<ChatMessage>, tools: Vec<ToolDefinition>) -> Result<LlmResponse> {
+        self.with_retries(|| self.inner.chat(messages.clone(), tools.clone())).await
+    }
+
+    async fn chat_completion(&self, messages: &[ChatMessage]) -> Result<String> {
+        self.with_retries(|| self.inner.chat_completion(messages)).await
+    }
+
+    async fn chat_streaming(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Vec<ToolDefinition>,
+    ) -> Result<Box<dyn Stream<Item = Result<StreamingChunk>> + Send + Unpin>> {
+        self.with_retries(|| self.inner.chat_streaming(messages.clone(), tools.clone())).await
+    }
+}
@@ -1,8 +1,9 @@
+use anyhow::{bail, Context, Result};
 use std::env;
 use std::sync::Arc;
 
-use crate::client::{LlmClient, anthropic::AnthropicLlmClient, groq::GroqLlmClient, llama_cpp::LlamaCppClient};
-use crate::config::{BackendType, GROQ_API_URL, ANTHROPIC_API_URL, OPENAI_API_URL};
+use crate::client::{LlmClient, RetryConfig, RetryingLlmClient, anthropic::AnthropicLlmClient, groq::GroqLlmClient, llama_cpp::LlamaCppClient, ollama::OllamaClient};
+use crate::config::{BackendType, GROQ_API_URL, ANTHROPIC_API_URL, OPENAI_API_URL, OLLAMA_API_URL, detect_backend_from_url, normalize_api_url};
 
 /// Client factory for creating LLM clients
 pub struct ClientFactory;
@@ -11,24 +12,54 @@ impl ClientFactory {
     /// Create an LLM client based on the specified backend type
     ///
     /// # Arguments
-    /// * `backend` - The backend type to use (Groq, Anthropic, Llama, OpenAI)
+    /// * `backend` - The backend type to use (Groq, Anthropic, Llama, OpenAI, Ollama, OpenAICompatible)
     /// * `api_key` - API key for authentication (optional for some backends like llama.cpp)
     /// * `model` - Model name to use
     /// * `api_url` - Optional custom API URL (uses default if None)
     /// * `agent_name` - Optional agent name for logging purposes (defaults to "default")
     ///
     /// # Returns
-    /// Arc-wrapped LLM client implementing the LlmClient trait
+    /// Arc-wrapped LLM client implementing the LlmClient trait, retrying
+    /// rate-limited and transient failures with the default `RetryConfig`,
+    /// or an error when the backend is missing required configuration
     pub fn create(
         backend: BackendType,
         api_key: Option<String>,
         model: String,
         api_url: Option<String>,
         agent_name: Option<String>,
-    ) -> Arc<dyn LlmClient> {
+    ) -> Result<Arc<dyn LlmClient>> {
+        Self::create_with_retry(backend, api_key, model, api_url, agent_name, Some(RetryConfig::default()))
+    }
+
+    /// Create an LLM client with an explicit retry policy
+    ///
+    /// Passing `None` for `retry` returns the bare provider client.
+    pub fn create_with_retry(
+        backend: BackendType,
+        api_key: Option<String>,
+        model: String,
+        api_url: Option<String>,
+        agent_name: Option<String>,
+        retry: Option<RetryConfig>,
+    ) -> Result<Arc<dyn LlmClient>> {
         let agent_name = agent_name.unwrap_or_else(|| "default".to_string());
+        let client = Self::create_unwrapped(backend, api_key, model.clone(), api_url, agent_name.clone())?;
 
-        match backend {
+        Ok(match retry {
+            Some(config) => Arc::new(RetryingLlmClient::new(client, config, model, agent_name)),
+            None => client,
+        })
+    }
+
+    fn create_unwrapped(
+        backend: BackendType,
+        api_key: Option<String>,
+        model: String,
+        api_url: Option<String>,
+        agent_name: String,
+    ) -> Result<Arc<dyn LlmClient>> {
+        Ok(match backend {
             BackendType::Anthropic => {
                 let url = api_url.unwrap_or_else(|| ANTHROPIC_API_URL.to_string());
                 let key = api_key
@@ -39,7 +70,7 @@ impl ClientFactory {
                 Arc::new(AnthropicLlmClient::new(key, model, url, agent_name))
             }
             BackendType::Llama => {
-                let url = api_url.expect("llama.cpp backend requires api_url to be specified");
+                let url = api_url.context("llama.cpp backend requires api_url to be specified")?;
                 Arc::new(LlamaCppClient::new(url, model))
             }
             BackendType::Groq => {
@@ -58,19 +89,32 @@ impl ClientFactory {
 
                 // Ensure we don't fall back to GROQ_API_KEY for OpenAI
                 if key.is_empty() {
-                    panic!("OPENAI_API_KEY must be set when using OpenAI backend");
+                    bail!("OPENAI_API_KEY must be set when using OpenAI backend");
                 }
 
                 // OpenAI uses the same client as Groq (OpenAI-compatible)
                 Arc::new(GroqLlmClient::new(key, model, url, agent_name))
             }
-        }
+            BackendType::Ollama => {
+                let url = api_url
+                    .or_else(|| env::var("OLLAMA_HOST").ok())
+                    .unwrap_or_else(|| OLLAMA_API_URL.to_string());
+                Arc::new(OllamaClient::new(url, model))
+            }
+            BackendType::OpenAICompatible => {
+                let url = api_url.context("OpenAI-compatible backend requires api_url to be specified")?;
+                // Local servers usually need no key; send one only if provided
+                let key = api_key.unwrap_or_default();
+                Arc::new(GroqLlmClient::new(key, model, normalize_api_url(&url), agent_name))
+            }
+        })
     }
 
     /// Create an LLM client with automatic backend detection
     ///
     /// Detects the backend based on:
-    /// 1. URL patterns (if URL contains "anthropic", uses Anthropic)
+    /// 1. URL patterns (Anthropic, Groq, OpenAI and Ollama hosts); any other
+    ///    URL is treated as a generic OpenAI-compatible server
     /// 2. Environment variables (if ANTHROPIC_AUTH_TOKEN is set, uses Anthropic)
     /// 3. Falls back to Groq if no specific backend is detected
     ///
@@ -81,21 +125,16 @@ impl ClientFactory {
     /// * `agent_name` - Optional agent name for logging
     ///
     /// # Returns
-    /// Arc-wrapped LLM client implementing the LlmClient trait
+    /// Arc-wrapped LLM client implementing the LlmClient trait, or an error
+    /// when the detected backend is missing required configuration
     pub fn create_with_auto_detect(
         api_key: Option<String>,
         model: String,
         api_url: Option<String>,
         agent_name: Option<String>,
-    ) -> Arc<dyn LlmClient> {
+    ) -> Result<Arc<dyn LlmClient>> {
         let backend = if let Some(ref url) = api_url {
-            if url.contains("anthropic") {
-                BackendType::Anthropic
-            } else if url.contains("openai") {
-                BackendType::OpenAI
-            } else {
-                BackendType::Llama
-            }
+            detect_backend_from_url(url).unwrap_or(BackendType::OpenAICompatible)
         } else if env::var("ANTHROPIC_AUTH_TOKEN").is_ok() ||
                   env::var("ANTHROPIC_API_KEY").is_ok() {
             BackendType::Anthropic
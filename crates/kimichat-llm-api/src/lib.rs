@@ -5,6 +5,8 @@
 //! - Groq
 //! - OpenAI
 //! - llama.cpp (self-hosted)
+//! - Ollama (native API)
+//! - Any other OpenAI-compatible server (vLLM, LM Studio, LocalAI)
 //!
 //! ## Features
 //!
@@ -13,6 +15,8 @@
 //! - **Streaming Support**: Both streaming and non-streaming APIs
 //! - **Flexible Configuration**: Environment variables or programmatic configuration
 //! - **Provider Auto-detection**: Automatically detect backend from URL or environment
+//! - **Retries**: Rate limits and transient errors are retried with backoff
+//! - **Usage Metering**: Token usage can be reported to a `UsageRecorder` for cost accounting
 //!
 //! ## Example
 //!
@@ -29,7 +33,7 @@
 //!         "claude-3-5-sonnet-20241022".to_string(),
 //!         None,
 //!         Some("my-agent".to_string()),
-//!     );
+//!     )?;
 //!
 //!     // Make a chat request
 //!     let messages = vec![
@@ -40,6 +44,7 @@
 //!             tool_call_id: None,
 //!             name: None,
 //!             reasoning: None,
+//!             reasoning_details: None,
 //!         }
 //!     ];
 //!
@@ -66,6 +71,13 @@ pub use client::{
     TokenUsage,
     ToolDefinition,
     StreamingChunk,
+    StreamEvent,
+    ApiError,
+    FallbackLlmClient,
+    MeteredLlmClient,
+    UsageRecorder,
+    RetryConfig,
+    RetryingLlmClient,
 };
 
 pub use config::{
@@ -73,10 +85,12 @@ pub use config::{
     GROQ_API_URL,
     ANTHROPIC_API_URL,
     OPENAI_API_URL,
+    OLLAMA_API_URL,
     normalize_api_url,
+    detect_backend_from_url,
     get_default_url_for_backend,
     get_default_model_for_backend,
 };
 
 // Re-export BackendType from kimichat-models to maintain API compatibility
-pub use kimichat_models::BackendType;
+pub use kimichat_models::{BackendType, ReasoningConfig, ReasoningEffort};
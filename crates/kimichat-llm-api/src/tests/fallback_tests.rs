@@ -0,0 +1,220 @@
+#[cfg(test)]
+mod fallback_tests {
+    use crate::client::llama_cpp::LlamaCppClient;
+    use crate::client::{ApiError, ChatMessage, FallbackLlmClient, LlmClient, RetryConfig, RetryingLlmClient};
+    use futures::StreamExt;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn user_message(content: &str) -> ChatMessage {
+        ChatMessage {
+            role: "user".to_string(),
+            content: content.to_string(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+            reasoning: None,
+            reasoning_details: None,
+        }
+    }
+
+    fn provider(label: &str, url: String) -> (String, Arc<dyn LlmClient>) {
+        (label.to_string(), Arc::new(LlamaCppClient::new(url, "local".to_string())))
+    }
+
+    async fn answering_server(content: &str) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "choices": [{"message": {"role": "assistant", "content": content}}]
+            })))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    /// A provider that answers `status` and must be asked exactly `calls` times
+    async fn failing_server(status: u16, calls: u64) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(status).set_body_string("unavailable"))
+            .expect(calls)
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn test_repeated_server_errors_fail_over_and_report_provider() {
+        let primary = failing_server(502, 3).await;
+        let backup = answering_server("from backup").await;
+
+        let client = FallbackLlmClient::new(vec![
+            provider("kimi@groq", primary.uri()),
+            provider("llama3@llama", backup.uri()),
+        ]);
+        // A single 5xx is returned for the caller to retry
+        let err = client.chat(vec![user_message("hi")], vec![]).await.err().unwrap();
+        assert_eq!(err.downcast_ref::<ApiError>().unwrap().status, 502);
+
+        // The second in a row fails over, and so does every one after it
+        for _ in 0..2 {
+            let response = client.chat(vec![user_message("hi")], vec![]).await.unwrap();
+            assert_eq!(response.message.content, "from backup");
+            assert_eq!(response.provider.as_deref(), Some("llama3@llama"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retried_chain_fails_over_after_repeated_server_errors() {
+        let primary = failing_server(503, 2).await;
+        let backup = answering_server("from backup").await;
+
+        let chain = FallbackLlmClient::new(vec![
+            provider("primary", primary.uri()),
+            provider("backup", backup.uri()),
+        ]);
+        let config = RetryConfig {
+            max_retries: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+            max_total_time: Duration::from_secs(5),
+        };
+        let client = RetryingLlmClient::new(Arc::new(chain), config, "local".to_string(), "fallback_test".to_string());
+
+        let response = client.chat(vec![user_message("hi")], vec![]).await.unwrap();
+        assert_eq!(response.provider.as_deref(), Some("backup"));
+    }
+
+    #[tokio::test]
+    async fn test_retried_chain_skips_unreachable_provider_at_once() {
+        // Nothing listens on this port once the listener is dropped
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let backup = answering_server("from backup").await;
+
+        let chain = FallbackLlmClient::new(vec![
+            provider("down", format!("http://127.0.0.1:{}", port)),
+            provider("backup", backup.uri()),
+        ]);
+        // With the default backoff, a retry before failing over would take far
+        // longer than the backup does to answer
+        let client = RetryingLlmClient::new(Arc::new(chain), RetryConfig::default(), "local".to_string(), "fallback_test".to_string());
+
+        let started = Instant::now();
+        let response = client.chat(vec![user_message("hi")], vec![]).await.unwrap();
+        assert_eq!(response.provider.as_deref(), Some("backup"));
+        assert!(started.elapsed() < Duration::from_millis(400));
+    }
+
+    #[tokio::test]
+    async fn test_connection_error_fails_over() {
+        // Nothing listens on this port once the listener is dropped
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let unreachable = format!("http://127.0.0.1:{}", port);
+        let backup = answering_server("still here").await;
+
+        let client = FallbackLlmClient::new(vec![
+            provider("down@llama", unreachable),
+            provider("up@llama", backup.uri()),
+        ]);
+        let content = client.chat_completion(&[user_message("hi")]).await.unwrap();
+        assert_eq!(content, "still here");
+    }
+
+    #[tokio::test]
+    async fn test_client_error_does_not_fail_over() {
+        let primary = failing_server(401, 1).await;
+        let backup = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&backup)
+            .await;
+
+        let client = FallbackLlmClient::new(vec![
+            provider("primary", primary.uri()),
+            provider("backup", backup.uri()),
+        ]);
+        let err = client.chat(vec![user_message("hi")], vec![]).await.err().unwrap();
+        assert_eq!(err.downcast_ref::<ApiError>().unwrap().status, 401);
+    }
+
+    #[tokio::test]
+    async fn test_success_resets_server_error_count() {
+        let primary = MockServer::start().await;
+        for status in [502, 200, 502] {
+            let template = if status == 200 {
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                    "choices": [{"message": {"role": "assistant", "content": "from primary"}}]
+                }))
+            } else {
+                ResponseTemplate::new(status).set_body_string("unavailable")
+            };
+            Mock::given(method("POST"))
+                .respond_with(template)
+                .up_to_n_times(1)
+                .expect(1)
+                .mount(&primary)
+                .await;
+        }
+        let backup = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&backup)
+            .await;
+
+        let client = FallbackLlmClient::new(vec![
+            provider("primary", primary.uri()),
+            provider("backup", backup.uri()),
+        ]);
+        assert!(client.chat(vec![user_message("hi")], vec![]).await.is_err());
+        let response = client.chat(vec![user_message("hi")], vec![]).await.unwrap();
+        assert_eq!(response.provider.as_deref(), Some("primary"));
+        // The 5xx after a success is the first in a row again
+        let err = client.chat(vec![user_message("hi")], vec![]).await.err().unwrap();
+        assert_eq!(err.downcast_ref::<ApiError>().unwrap().status, 502);
+    }
+
+    #[tokio::test]
+    async fn test_last_provider_error_is_returned() {
+        let primary = failing_server(500, 1).await;
+        let backup = failing_server(503, 1).await;
+
+        let client = FallbackLlmClient::new(vec![
+            provider("primary", primary.uri()),
+            provider("backup", backup.uri()),
+        ])
+        .with_failover_after(1);
+        let err = client.chat(vec![user_message("hi")], vec![]).await.err().unwrap();
+        assert_eq!(err.downcast_ref::<ApiError>().unwrap().status, 503);
+    }
+
+    #[tokio::test]
+    async fn test_streaming_chunks_carry_provider() {
+        let primary = failing_server(503, 1).await;
+        let backup = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                "data: {\"choices\":[{\"delta\":{\"content\":\"hey\"}}]}\n\ndata: [DONE]\n\n",
+                "text/event-stream",
+            ))
+            .mount(&backup)
+            .await;
+
+        let client = FallbackLlmClient::new(vec![
+            provider("primary", primary.uri()),
+            provider("backup", backup.uri()),
+        ])
+        .with_failover_after(1);
+        let chunks: Vec<_> = client.chat_streaming(vec![user_message("hi")], vec![]).await.unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(chunks[0].delta, "hey");
+        assert!(chunks.iter().all(|chunk| chunk.provider.as_deref() == Some("backup")));
+    }
+}
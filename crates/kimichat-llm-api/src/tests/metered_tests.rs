@@ -0,0 +1,116 @@
+#[cfg(test)]
+mod metered_tests {
+    use crate::client::llama_cpp::LlamaCppClient;
+    use crate::client::{ChatMessage, LlmClient, MeteredLlmClient, TokenUsage, UsageRecorder};
+    use futures::StreamExt;
+    use std::sync::{Arc, Mutex};
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// Recorder that keeps every call and refuses calls past a token limit
+    #[derive(Default)]
+    struct TestRecorder {
+        calls: Mutex<Vec<(String, String, u32)>>,
+        token_limit: Option<u32>,
+    }
+
+    impl UsageRecorder for TestRecorder {
+        fn record_usage(&self, source: &str, model: &str, usage: &TokenUsage) {
+            self.calls.lock().unwrap().push((source.to_string(), model.to_string(), usage.total_tokens));
+        }
+
+        fn check_budget(&self) -> anyhow::Result<()> {
+            let used: u32 = self.calls.lock().unwrap().iter().map(|(_, _, tokens)| tokens).sum();
+            match self.token_limit {
+                Some(limit) if used >= limit => anyhow::bail!("token limit of {} reached", limit),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    fn user_message(content: &str) -> ChatMessage {
+        ChatMessage {
+            role: "user".to_string(),
+            content: content.to_string(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+            reasoning: None,
+            reasoning_details: None,
+        }
+    }
+
+    fn metered_client(server: &MockServer, recorder: Arc<TestRecorder>) -> MeteredLlmClient {
+        let inner = Arc::new(LlamaCppClient::new(server.uri(), "local".to_string()));
+        MeteredLlmClient::new(inner, recorder, "planner".to_string(), "blu_model".to_string())
+    }
+
+    async fn answering_server() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "choices": [{"message": {"role": "assistant", "content": "done"}}],
+                "usage": {"prompt_tokens": 8, "completion_tokens": 2, "total_tokens": 10}
+            })))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn test_chat_usage_is_recorded_with_source_and_model() {
+        let server = answering_server().await;
+        let recorder = Arc::new(TestRecorder::default());
+        let client = metered_client(&server, recorder.clone());
+
+        client.chat(vec![user_message("hi")], vec![]).await.unwrap();
+        client.chat(vec![user_message("again")], vec![]).await.unwrap();
+
+        let calls = recorder.calls.lock().unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0], ("planner".to_string(), "blu_model".to_string(), 10));
+    }
+
+    #[tokio::test]
+    async fn test_streaming_usage_is_recorded_once() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                "data: {\"choices\":[{\"delta\":{\"content\":\"hey\"}}]}\n\n\
+                 data: {\"choices\":[],\"usage\":{\"prompt_tokens\":4,\"completion_tokens\":1,\"total_tokens\":5}}\n\n\
+                 data: [DONE]\n\n",
+                "text/event-stream",
+            ))
+            .mount(&server)
+            .await;
+
+        let recorder = Arc::new(TestRecorder::default());
+        let client = metered_client(&server, recorder.clone());
+        let stream = client.chat_streaming(vec![user_message("hi")], vec![]).await.unwrap();
+        let chunks: Vec<_> = stream.collect().await;
+
+        assert!(chunks.iter().all(|chunk| chunk.is_ok()));
+        assert_eq!(*recorder.calls.lock().unwrap(), vec![("planner".to_string(), "blu_model".to_string(), 5)]);
+    }
+
+    #[tokio::test]
+    async fn test_calls_are_refused_once_budget_is_exhausted() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "choices": [{"message": {"role": "assistant", "content": "done"}}],
+                "usage": {"prompt_tokens": 8, "completion_tokens": 2, "total_tokens": 10}
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let recorder = Arc::new(TestRecorder { token_limit: Some(10), ..TestRecorder::default() });
+        let client = metered_client(&server, recorder.clone());
+
+        client.chat(vec![user_message("hi")], vec![]).await.unwrap();
+        let err = client.chat(vec![user_message("more")], vec![]).await.err().unwrap();
+        assert!(err.to_string().contains("token limit of 10 reached"));
+        assert!(client.chat_completion(&[user_message("more")]).await.is_err());
+    }
+}
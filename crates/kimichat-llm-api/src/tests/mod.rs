@@ -1 +1,8 @@
-pub mod model_config_tests;
\ No newline at end of file
+pub mod model_config_tests;
+pub mod streaming_tests;
+pub mod retry_tests;
+pub mod fallback_tests;
+pub mod ollama_tests;
+pub mod metered_tests;
+pub mod prompt_caching_tests;
+pub mod reasoning_tests;
@@ -1,6 +1,6 @@
 #[cfg(test)]
 mod model_config_tests {
-    use crate::config::{parse_model_attings, BackendType, get_default_url_for_backend, get_default_model_for_backend};
+    use crate::config::{parse_model_attings, split_provider_chain, detect_backend_from_url, BackendType, get_default_url_for_backend, get_default_model_for_backend};
 
     #[test]
     fn test_parse_model_full_format() {
@@ -318,4 +318,48 @@ mod model_config_tests {
             assert_eq!(url, None);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_split_provider_chain() {
+        let chain = split_provider_chain("kimi@groq, llama3@llama(http://localhost:8080/v1?a=1,b=2),@anthropic");
+        assert_eq!(chain, vec![
+            "kimi@groq".to_string(),
+            "llama3@llama(http://localhost:8080/v1?a=1,b=2)".to_string(),
+            "@anthropic".to_string(),
+        ]);
+
+        assert_eq!(split_provider_chain("kimi@groq"), vec!["kimi@groq".to_string()]);
+        assert_eq!(split_provider_chain("kimi,,"), vec!["kimi".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_model_ollama_and_openai_compatible() {
+        let (model, backend, url) = parse_model_attings("qwen@ollama(http://localhost:11434)");
+        assert_eq!(model, "qwen");
+        assert_eq!(backend, Some(BackendType::Ollama));
+        assert_eq!(url, Some("http://localhost:11434".to_string()));
+
+        let (model, backend, url) = parse_model_attings("@ollama");
+        assert_eq!(model, "llama3.1");
+        assert_eq!(backend, Some(BackendType::Ollama));
+        assert_eq!(url, Some("http://localhost:11434".to_string()));
+
+        for alias in ["openai-compatible", "vllm", "lmstudio", "localai"] {
+            let (model, backend, url) = parse_model_attings(&format!("mistral@{}(http://localhost:8000)", alias));
+            assert_eq!(model, "mistral");
+            assert_eq!(backend, Some(BackendType::OpenAICompatible));
+            assert_eq!(url, Some("http://localhost:8000".to_string()));
+        }
+        assert_eq!(BackendType::OpenAICompatible.as_str(), "openai-compatible");
+        assert_eq!(get_default_url_for_backend(&BackendType::OpenAICompatible), None);
+    }
+
+    #[test]
+    fn test_detect_backend_from_url() {
+        assert_eq!(detect_backend_from_url("https://api.anthropic.com"), Some(BackendType::Anthropic));
+        assert_eq!(detect_backend_from_url("https://api.groq.com/openai/v1/chat/completions"), Some(BackendType::Groq));
+        assert_eq!(detect_backend_from_url("https://api.openai.com/v1/chat/completions"), Some(BackendType::OpenAI));
+        assert_eq!(detect_backend_from_url("http://localhost:11434"), Some(BackendType::Ollama));
+        assert_eq!(detect_backend_from_url("http://localhost:1234/v1"), None);
+    }
+}
@@ -0,0 +1,149 @@
+#[cfg(test)]
+mod ollama_tests {
+    use crate::client::ollama::OllamaClient;
+    use crate::client::{ChatMessage, FunctionCall, LlmClient, StreamEvent, ToolCall, ToolDefinition};
+    use crate::config::{BackendType, ClientFactory};
+    use futures::StreamExt;
+    use wiremock::matchers::{body_partial_json, header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn message(role: &str, content: &str) -> ChatMessage {
+        ChatMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+            reasoning: None,
+            reasoning_details: None,
+        }
+    }
+
+    fn list_files_tool() -> ToolDefinition {
+        ToolDefinition {
+            name: "list_files".to_string(),
+            description: "List files".to_string(),
+            parameters: serde_json::json!({"type": "object", "properties": {"pattern": {"type": "string"}}}),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ollama_chat_translates_tool_calls() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(body_partial_json(serde_json::json!({
+                "model": "qwen",
+                "stream": false,
+                "tools": [{"type": "function", "function": {"name": "list_files"}}],
+                "messages": [
+                    {"role": "user", "content": "what is here?"},
+                    {"role": "assistant", "tool_calls": [{"function": {"name": "list_files", "arguments": {"pattern": "*"}}}]},
+                    {"role": "tool", "content": "a.rs", "tool_name": "list_files"},
+                ],
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "model": "qwen",
+                "message": {
+                    "role": "assistant",
+                    "content": "",
+                    "tool_calls": [{"function": {"name": "open_file", "arguments": {"file_path": "a.rs"}}}]
+                },
+                "done": true,
+                "done_reason": "stop",
+                "prompt_eval_count": 30,
+                "eval_count": 8
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut assistant = message("assistant", "");
+        assistant.tool_calls = Some(vec![ToolCall {
+            id: "call_1".to_string(),
+            function: FunctionCall { name: "list_files".to_string(), arguments: r#"{"pattern":"*"}"#.to_string() },
+        }]);
+        let mut tool_result = message("tool", "a.rs");
+        tool_result.tool_call_id = Some("call_1".to_string());
+
+        let client = OllamaClient::new(server.uri(), "qwen".to_string());
+        let response = client
+            .chat(vec![message("user", "what is here?"), assistant, tool_result], vec![list_files_tool()])
+            .await
+            .unwrap();
+
+        let calls = response.message.tool_calls.expect("tool calls should be translated");
+        assert_eq!(calls[0].function.name, "open_file");
+        assert_eq!(calls[0].function.arguments, r#"{"file_path":"a.rs"}"#);
+        assert!(!calls[0].id.is_empty());
+
+        let usage = response.usage.unwrap();
+        assert_eq!(usage.prompt_tokens, 30);
+        assert_eq!(usage.total_tokens, 38);
+    }
+
+    #[tokio::test]
+    async fn test_ollama_streaming_ndjson() {
+        let server = MockServer::start().await;
+        let body = [
+            r#"{"message":{"role":"assistant","content":"","thinking":"hmm"},"done":false}"#,
+            r#"{"message":{"role":"assistant","content":"Hel"},"done":false}"#,
+            r#"{"message":{"role":"assistant","content":"lo"},"done":false}"#,
+            r#"{"message":{"role":"assistant","content":"","tool_calls":[{"function":{"name":"list_files","arguments":{"pattern":"*.rs"}}}]},"done":false}"#,
+            r#"{"message":{"role":"assistant","content":""},"done":true,"done_reason":"stop","prompt_eval_count":5,"eval_count":4}"#,
+        ].join("\n") + "\n";
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(body_partial_json(serde_json::json!({"stream": true})))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "application/x-ndjson"))
+            .mount(&server)
+            .await;
+
+        let client = OllamaClient::new(format!("{}/", server.uri()), "qwen".to_string());
+        let chunks: Vec<_> = client.chat_streaming(vec![message("user", "hi")], vec![list_files_tool()]).await.unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+
+        let text: String = chunks.iter().map(|c| c.delta.as_str()).collect();
+        assert_eq!(text, "Hello");
+        let events: Vec<&StreamEvent> = chunks.iter().flat_map(|c| c.events.iter()).collect();
+        assert!(matches!(events[0], StreamEvent::ReasoningDelta(r) if r == "hmm"));
+        assert!(events.iter().any(|e| matches!(e, StreamEvent::ToolCallStart { index: 0, name, .. } if name == "list_files")));
+
+        let last = chunks.last().unwrap();
+        assert_eq!(last.finish_reason.as_deref(), Some("tool_calls"));
+        assert_eq!(last.tool_calls.as_ref().unwrap()[0].function.arguments, r#"{"pattern":"*.rs"}"#);
+        assert_eq!(last.usage.as_ref().unwrap().total_tokens, 9);
+    }
+
+    #[tokio::test]
+    async fn test_openai_compatible_backend_is_keyless() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header_exists("authorization"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "choices": [{"message": {"role": "assistant", "content": "local answer"}}]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = ClientFactory::create_with_retry(
+            BackendType::OpenAICompatible,
+            None,
+            "local-model".to_string(),
+            Some(server.uri()),
+            None,
+            None,
+        )
+        .unwrap();
+        let response = client.chat(vec![message("user", "hi")], vec![]).await.unwrap();
+        assert_eq!(response.message.content, "local answer");
+    }
+}
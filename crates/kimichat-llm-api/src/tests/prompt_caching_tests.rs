@@ -0,0 +1,147 @@
+#[cfg(test)]
+mod prompt_caching_tests {
+    use crate::client::anthropic::AnthropicLlmClient;
+    use crate::client::{ChatMessage, LlmClient, ToolDefinition};
+    use futures::StreamExt;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn message(role: &str, content: &str) -> ChatMessage {
+        ChatMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+            reasoning: None,
+            reasoning_details: None,
+        }
+    }
+
+    fn tool(name: &str) -> ToolDefinition {
+        ToolDefinition {
+            name: name.to_string(),
+            description: format!("The {} tool", name),
+            parameters: serde_json::json!({"type": "object", "properties": {}}),
+        }
+    }
+
+    fn client(server: &MockServer) -> AnthropicLlmClient {
+        AnthropicLlmClient::new("test-key".to_string(), "claude-test".to_string(), server.uri(), "test".to_string())
+    }
+
+    fn cached_response() -> serde_json::Value {
+        serde_json::json!({
+            "role": "assistant",
+            "content": [{"type": "text", "text": "done"}],
+            "usage": {
+                "input_tokens": 20,
+                "cache_creation_input_tokens": 300,
+                "cache_read_input_tokens": 4000,
+                "output_tokens": 7
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_breakpoints_on_system_tools_and_last_message() {
+        let server = MockServer::start().await;
+        let ephemeral = serde_json::json!({"type": "ephemeral"});
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(body_partial_json(serde_json::json!({
+                "system": [{"type": "text", "text": "be brief", "cache_control": ephemeral}],
+                "tools": [{"name": "read"}, {"name": "write", "cache_control": ephemeral}],
+                "messages": [
+                    {"role": "user", "content": [{"type": "text", "text": "first"}]},
+                    {"role": "assistant", "content": [{"type": "text", "text": "ok"}]},
+                    {"role": "user", "content": [{"type": "text", "text": "second", "cache_control": ephemeral}]}
+                ]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(cached_response()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let messages = vec![
+            message("system", "be brief"),
+            message("user", "first"),
+            message("assistant", "ok"),
+            message("user", "second"),
+        ];
+        let response = client(&server).chat(messages, vec![tool("read"), tool("write")]).await.unwrap();
+
+        // Cached tokens count towards the prompt and are reported separately
+        let usage = response.usage.unwrap();
+        assert_eq!(usage.prompt_tokens, 4320);
+        assert_eq!(usage.total_tokens, 4327);
+        assert_eq!(usage.cache_creation_input_tokens, 300);
+        assert_eq!(usage.cache_read_input_tokens, 4000);
+    }
+
+    #[tokio::test]
+    async fn test_only_first_and_last_messages_are_marked() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(cached_response()))
+            .mount(&server)
+            .await;
+
+        let messages = vec![message("user", "first"), message("assistant", "ok"), message("user", "second")];
+        client(&server).chat(messages, vec![tool("read"), tool("write")]).await.unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        let marked: Vec<bool> = body["messages"].as_array().unwrap().iter()
+            .map(|m| m["content"][0].get("cache_control").is_some())
+            .collect();
+        assert_eq!(marked, vec![false, false, true]);
+        assert!(body["tools"][0].get("cache_control").is_none());
+        assert!(body.get("system").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_caching_can_be_disabled() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(cached_response()))
+            .mount(&server)
+            .await;
+
+        let messages = vec![message("system", "be brief"), message("user", "hi")];
+        client(&server)
+            .with_prompt_caching(false)
+            .chat(messages, vec![tool("read")])
+            .await
+            .unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        let body = String::from_utf8(requests[0].body.clone()).unwrap();
+        assert!(!body.contains("cache_control"));
+        assert!(body.contains(r#""system":"be brief""#));
+    }
+
+    #[tokio::test]
+    async fn test_streaming_usage_includes_cache_counts() {
+        let server = MockServer::start().await;
+        let body = [
+            r#"{"type":"message_start","message":{"role":"assistant","usage":{"input_tokens":5,"cache_creation_input_tokens":0,"cache_read_input_tokens":2048,"output_tokens":1}}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"hi"}}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":3}}"#,
+            r#"{"type":"message_stop"}"#,
+        ].iter().map(|e| format!("data: {}\n\n", e)).collect::<String>();
+        Mock::given(method("POST"))
+            .and(body_partial_json(serde_json::json!({"stream": true})))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&server)
+            .await;
+
+        let stream = client(&server).chat_streaming(vec![message("user", "hi")], vec![]).await.unwrap();
+        let chunks: Vec<_> = stream.map(|c| c.unwrap()).collect().await;
+
+        let usage = chunks.last().unwrap().usage.clone().unwrap();
+        assert_eq!(usage.prompt_tokens, 2053);
+        assert_eq!(usage.completion_tokens, 3);
+        assert_eq!(usage.cache_read_input_tokens, 2048);
+    }
+}
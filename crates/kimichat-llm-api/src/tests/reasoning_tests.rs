@@ -0,0 +1,193 @@
+#[cfg(test)]
+mod reasoning_tests {
+    use crate::client::anthropic::AnthropicLlmClient;
+    use crate::client::groq::GroqLlmClient;
+    use crate::client::{ChatMessage, FunctionCall, LlmClient, StreamEvent, ToolCall};
+    use crate::{ReasoningConfig, ReasoningEffort};
+    use futures::StreamExt;
+    use wiremock::matchers::{body_partial_json, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn message(role: &str, content: &str) -> ChatMessage {
+        ChatMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+            reasoning: None,
+            reasoning_details: None,
+        }
+    }
+
+    fn tool_turn(reasoning_details: Option<Vec<serde_json::Value>>) -> Vec<ChatMessage> {
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            function: FunctionCall { name: "read".to_string(), arguments: "{}".to_string() },
+        };
+        let assistant = ChatMessage {
+            tool_calls: Some(vec![call]),
+            reasoning_details,
+            ..message("assistant", "")
+        };
+        let result = ChatMessage {
+            tool_call_id: Some("call_1".to_string()),
+            ..message("tool", "file contents")
+        };
+        vec![message("user", "read it"), assistant, result]
+    }
+
+    fn anthropic(server: &MockServer, reasoning: ReasoningConfig) -> AnthropicLlmClient {
+        AnthropicLlmClient::new("test-key".to_string(), "claude-test".to_string(), server.uri(), "test".to_string())
+            .with_reasoning(Some(reasoning))
+    }
+
+    async fn request_body(server: &MockServer) -> serde_json::Value {
+        let requests = server.received_requests().await.unwrap();
+        serde_json::from_slice(&requests.last().unwrap().body).unwrap()
+    }
+
+    fn text_response() -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "role": "assistant",
+            "content": [{"type": "text", "text": "done"}],
+            "usage": {"input_tokens": 10, "output_tokens": 2}
+        }))
+    }
+
+    #[test]
+    fn test_reasoning_config_parsing() {
+        assert_eq!(ReasoningConfig::parse("High"), Some(ReasoningConfig::Effort(ReasoningEffort::High)));
+        assert_eq!(ReasoningConfig::parse("8000"), Some(ReasoningConfig::BudgetTokens(8000)));
+        assert_eq!(ReasoningConfig::parse("lots"), None);
+
+        // Budgets below the provider minimum are raised, efforts map both ways
+        assert_eq!(ReasoningConfig::BudgetTokens(100).budget_tokens(), 1024);
+        assert_eq!(ReasoningConfig::Effort(ReasoningEffort::Medium).budget_tokens(), 8192);
+        assert_eq!(ReasoningConfig::BudgetTokens(20_000).effort(), ReasoningEffort::High);
+        assert_eq!(ReasoningConfig::BudgetTokens(2048).effort(), ReasoningEffort::Low);
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_requests_thinking_budget() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(serde_json::json!({
+                "thinking": {"type": "enabled", "budget_tokens": 2048},
+                "max_tokens": 4096 + 2048
+            })))
+            .respond_with(text_response())
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        anthropic(&server, ReasoningConfig::Effort(ReasoningEffort::Low))
+            .chat(vec![message("user", "hi")], vec![])
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_thinking_is_returned_and_sent_back() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "role": "assistant",
+                "content": [
+                    {"type": "thinking", "thinking": "I should read the file", "signature": "sig"},
+                    {"type": "redacted_thinking", "data": "opaque"},
+                    {"type": "tool_use", "id": "call_1", "name": "read", "input": {}}
+                ],
+                "usage": {"input_tokens": 10, "output_tokens": 20}
+            })))
+            .mount(&server)
+            .await;
+
+        let client = anthropic(&server, ReasoningConfig::BudgetTokens(4096));
+        let response = client.chat(vec![message("user", "read it")], vec![]).await.unwrap();
+        assert_eq!(response.message.reasoning.as_deref(), Some("I should read the file"));
+        let details = response.message.reasoning_details.clone().unwrap();
+        assert_eq!(details.len(), 2);
+
+        // The signed blocks lead the assistant turn when the tool result is sent
+        client.chat(tool_turn(Some(details)), vec![]).await.unwrap();
+        let body = request_body(&server).await;
+        let content = &body["messages"][1]["content"];
+        assert_eq!(content[0]["type"], "thinking");
+        assert_eq!(content[0]["signature"], "sig");
+        assert_eq!(content[1]["type"], "redacted_thinking");
+        assert_eq!(content[2]["type"], "tool_use");
+        assert!(body.get("thinking").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_skips_thinking_for_tool_turn_without_blocks() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).respond_with(text_response()).mount(&server).await;
+
+        anthropic(&server, ReasoningConfig::BudgetTokens(4096))
+            .chat(tool_turn(None), vec![])
+            .await
+            .unwrap();
+
+        let body = request_body(&server).await;
+        assert!(body.get("thinking").is_none());
+        assert_eq!(body["max_tokens"], 4096);
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_streaming_assembles_signed_thinking() {
+        let server = MockServer::start().await;
+        let body = [
+            r#"{"type":"message_start","message":{"role":"assistant","usage":{"input_tokens":5,"output_tokens":1}}}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"thinking","thinking":""}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"thinking_delta","thinking":"Let me "}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"thinking_delta","thinking":"think"}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"signature_delta","signature":"sig"}}"#,
+            r#"{"type":"content_block_stop","index":0}"#,
+            r#"{"type":"content_block_start","index":1,"content_block":{"type":"text","text":""}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"text_delta","text":"answer"}}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":3}}"#,
+            r#"{"type":"message_stop"}"#,
+        ].iter().map(|e| format!("data: {}\n\n", e)).collect::<String>();
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&server)
+            .await;
+
+        let stream = anthropic(&server, ReasoningConfig::BudgetTokens(4096))
+            .chat_streaming(vec![message("user", "hi")], vec![])
+            .await
+            .unwrap();
+        let chunks: Vec<_> = stream.map(|c| c.unwrap()).collect().await;
+
+        let reasoning: String = chunks.iter().flat_map(|c| &c.events).filter_map(|e| match e {
+            StreamEvent::ReasoningDelta(text) => Some(text.as_str()),
+            _ => None,
+        }).collect();
+        assert_eq!(reasoning, "Let me think");
+
+        let details = chunks.last().unwrap().reasoning_details.clone().unwrap();
+        assert_eq!(details, vec![serde_json::json!({"type": "thinking", "thinking": "Let me think", "signature": "sig"})]);
+    }
+
+    #[tokio::test]
+    async fn test_groq_sends_reasoning_effort() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(serde_json::json!({"reasoning_effort": "medium"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "choices": [{"message": {"role": "assistant", "content": "done", "reasoning": "thought"}}]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let response = GroqLlmClient::new(String::new(), "gpt-oss".to_string(), server.uri(), "test".to_string())
+            .with_reasoning(Some(ReasoningConfig::BudgetTokens(8192)))
+            .chat(vec![message("user", "hi")], vec![])
+            .await
+            .unwrap();
+        assert_eq!(response.message.reasoning.as_deref(), Some("thought"));
+    }
+}
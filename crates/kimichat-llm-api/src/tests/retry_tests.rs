@@ -0,0 +1,136 @@
+#[cfg(test)]
+mod retry_tests {
+    use crate::client::error::{parse_reset_duration, retry_after_from_headers};
+    use crate::client::llama_cpp::LlamaCppClient;
+    use crate::client::{ApiError, ChatMessage, LlmClient, RetryConfig, RetryingLlmClient};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn user_message(content: &str) -> ChatMessage {
+        ChatMessage {
+            role: "user".to_string(),
+            content: content.to_string(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+            reasoning: None,
+            reasoning_details: None,
+        }
+    }
+
+    fn fast_config() -> RetryConfig {
+        RetryConfig {
+            max_retries: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+            max_total_time: Duration::from_secs(5),
+        }
+    }
+
+    fn retrying_client(server: &MockServer, config: RetryConfig) -> RetryingLlmClient {
+        let inner = Arc::new(LlamaCppClient::new(server.uri(), "local".to_string()));
+        RetryingLlmClient::new(inner, config, "local".to_string(), "retry_test".to_string())
+    }
+
+    fn ok_body() -> serde_json::Value {
+        serde_json::json!({
+            "choices": [{"message": {"role": "assistant", "content": "done"}}],
+            "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2}
+        })
+    }
+
+    #[test]
+    fn test_parse_reset_duration_formats() {
+        assert_eq!(parse_reset_duration("7.66s"), Some(Duration::from_millis(7660)));
+        assert_eq!(parse_reset_duration("2m59.5s"), Some(Duration::from_millis(179_500)));
+        assert_eq!(parse_reset_duration("120ms"), Some(Duration::from_millis(120)));
+        assert_eq!(parse_reset_duration("1h"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_reset_duration("3"), Some(Duration::from_secs(3)));
+        assert_eq!(parse_reset_duration("soon"), None);
+        assert_eq!(parse_reset_duration(""), None);
+    }
+
+    #[test]
+    fn test_retry_after_uses_exhausted_rate_limit() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("x-ratelimit-remaining-requests", "10".parse().unwrap());
+        headers.insert("x-ratelimit-reset-requests", "30s".parse().unwrap());
+        headers.insert("x-ratelimit-remaining-tokens", "0".parse().unwrap());
+        headers.insert("x-ratelimit-reset-tokens", "1.5s".parse().unwrap());
+        assert_eq!(retry_after_from_headers(&headers), Some(Duration::from_millis(1500)));
+
+        headers.insert("retry-after", "4".parse().unwrap());
+        assert_eq!(retry_after_from_headers(&headers), Some(Duration::from_secs(4)));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_is_retried_after_server_hint() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "0").set_body_string("slow down"))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(ok_body()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = retrying_client(&server, fast_config());
+        let response = client.chat(vec![user_message("hi")], vec![]).await.unwrap();
+        assert_eq!(response.message.content, "done");
+    }
+
+    #[tokio::test]
+    async fn test_server_errors_give_up_after_max_retries() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503).set_body_string("overloaded"))
+            .expect(4)
+            .mount(&server)
+            .await;
+
+        let client = retrying_client(&server, fast_config());
+        let err = client.chat_completion(&[user_message("hi")]).await.err().unwrap();
+        let api_error = err.downcast_ref::<ApiError>().expect("original error should be preserved");
+        assert_eq!(api_error.status, 503);
+        assert!(err.to_string().contains("Giving up after 3 retries"));
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_are_not_retried() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(400).set_body_string("bad request"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = retrying_client(&server, fast_config());
+        let err = client.chat(vec![user_message("hi")], vec![]).await.err().unwrap();
+        assert_eq!(err.downcast_ref::<ApiError>().unwrap().status, 400);
+        assert!(err.to_string().contains("bad request"));
+    }
+
+    #[tokio::test]
+    async fn test_server_hint_beyond_total_budget_fails_fast() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "60"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let config = RetryConfig { max_total_time: Duration::from_secs(1), ..fast_config() };
+        let client = retrying_client(&server, config);
+
+        let started = Instant::now();
+        let err = client.chat_streaming(vec![user_message("hi")], vec![]).await.err().unwrap();
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(err.to_string().contains("retry budget"));
+    }
+}
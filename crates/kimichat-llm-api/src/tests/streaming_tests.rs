@@ -0,0 +1,217 @@
+#[cfg(test)]
+mod streaming_tests {
+    use crate::client::{ChatMessage, LlmClient, StreamEvent, StreamingChunk};
+    use crate::client::anthropic::AnthropicLlmClient;
+    use crate::client::llama_cpp::LlamaCppClient;
+    use crate::client::openai_stream::OpenAiStreamParser;
+    use futures::StreamExt;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn user_message(content: &str) -> ChatMessage {
+        ChatMessage {
+            role: "user".to_string(),
+            content: content.to_string(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+            reasoning: None,
+            reasoning_details: None,
+        }
+    }
+
+    fn sse_body(events: &[&str]) -> String {
+        events.iter().map(|e| format!("data: {}\n\n", e)).collect()
+    }
+
+    async fn collect(stream: Box<dyn futures::Stream<Item = anyhow::Result<StreamingChunk>> + Send + Unpin>) -> Vec<StreamingChunk> {
+        stream.map(|c| c.expect("chunk should parse")).collect().await
+    }
+
+    #[test]
+    fn test_parser_joins_tool_call_arguments_by_index() {
+        let mut parser = OpenAiStreamParser::new();
+        let body = sse_body(&[
+            r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"id":"call_a","type":"function","function":{"name":"open_file","arguments":""}}]}}]}"#,
+            r#"{"choices":[{"delta":{"tool_calls":[{"index":1,"id":"call_b","type":"function","function":{"name":"list_files","arguments":"{\"pat"}}]}}]}"#,
+            r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"file_path\":"}}]}}]}"#,
+            r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"\"a.rs\"}"}}]}}]}"#,
+            r#"{"choices":[{"delta":{"tool_calls":[{"index":1,"function":{"arguments":"tern\":\"*\"}"}}]}}]}"#,
+            r#"{"choices":[{"delta":{},"finish_reason":"tool_calls"}]}"#,
+            "[DONE]",
+        ]);
+
+        let chunks = parser.push(body.as_bytes());
+        assert!(chunks.iter().all(|c| c.delta.is_empty()));
+        assert!(parser.is_done());
+
+        let last = parser.finish();
+        assert_eq!(last.finish_reason.as_deref(), Some("tool_calls"));
+        let calls = last.tool_calls.expect("tool calls should be assembled");
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].id, "call_a");
+        assert_eq!(calls[0].function.name, "open_file");
+        assert_eq!(calls[0].function.arguments, r#"{"file_path":"a.rs"}"#);
+        assert_eq!(calls[1].id, "call_b");
+        assert_eq!(calls[1].function.arguments, r#"{"pattern":"*"}"#);
+    }
+
+    #[test]
+    fn test_parser_handles_split_utf8_and_lines() {
+        let mut parser = OpenAiStreamParser::new();
+        let body = sse_body(&[r#"{"choices":[{"delta":{"content":"héllo"}}]}"#]);
+        let bytes = body.as_bytes();
+        // Split inside the two-byte 'é'
+        let split = body.find('é').unwrap() + 1;
+
+        assert!(parser.push(&bytes[..split]).is_empty());
+        let chunks = parser.push(&bytes[split..]);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].delta, "héllo");
+    }
+
+    #[tokio::test]
+    async fn test_groq_streaming_text_and_groq_usage() {
+        let server = MockServer::start().await;
+        let body = sse_body(&[
+            r#"{"choices":[{"delta":{"role":"assistant","content":""}}]}"#,
+            r#"{"choices":[{"delta":{"content":"Hello"}}]}"#,
+            r#"{"choices":[{"delta":{"content":", world"}}]}"#,
+            r#"{"choices":[{"delta":{},"finish_reason":"stop"}],"x_groq":{"usage":{"prompt_tokens":7,"completion_tokens":3,"total_tokens":10}}}"#,
+            "[DONE]",
+        ]);
+        Mock::given(method("POST"))
+            .and(path("/openai/v1/chat/completions"))
+            .and(body_partial_json(serde_json::json!({"stream": true})))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&server)
+            .await;
+
+        let client = crate::client::groq::GroqLlmClient::new(
+            "test-key".to_string(),
+            "test-model".to_string(),
+            format!("{}/openai/v1/chat/completions", server.uri()),
+            "test".to_string(),
+        );
+        let chunks = collect(client.chat_streaming(vec![user_message("hi")], vec![]).await.unwrap()).await;
+
+        let text: String = chunks.iter().map(|c| c.delta.as_str()).collect();
+        assert_eq!(text, "Hello, world");
+
+        let last = chunks.last().unwrap();
+        assert_eq!(last.finish_reason.as_deref(), Some("stop"));
+        assert!(last.tool_calls.is_none());
+        let usage = last.usage.as_ref().expect("usage should be reported");
+        assert_eq!(usage.prompt_tokens, 7);
+        assert_eq!(usage.total_tokens, 10);
+    }
+
+    #[tokio::test]
+    async fn test_llama_cpp_streaming_tool_calls_and_usage() {
+        let server = MockServer::start().await;
+        let body = sse_body(&[
+            r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"run_command","arguments":"{\"command\":"}}]}}]}"#,
+            r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"\"ls\"}"}}]}}]}"#,
+            r#"{"choices":[{"delta":{},"finish_reason":"tool_calls"}]}"#,
+            r#"{"choices":[],"usage":{"prompt_tokens":20,"completion_tokens":5,"total_tokens":25}}"#,
+            "[DONE]",
+        ]);
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&server)
+            .await;
+
+        let client = LlamaCppClient::new(server.uri(), "local".to_string());
+        let chunks = collect(client.chat_streaming(vec![user_message("list")], vec![]).await.unwrap()).await;
+
+        let last = chunks.last().unwrap();
+        assert_eq!(last.finish_reason.as_deref(), Some("tool_calls"));
+        let calls = last.tool_calls.as_ref().unwrap();
+        assert_eq!(calls[0].function.name, "run_command");
+        assert_eq!(calls[0].function.arguments, r#"{"command":"ls"}"#);
+        assert_eq!(last.usage.as_ref().unwrap().completion_tokens, 5);
+    }
+
+    #[tokio::test]
+    async fn test_streaming_http_error_is_reported() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("boom"))
+            .mount(&server)
+            .await;
+
+        let client = LlamaCppClient::new(server.uri(), "local".to_string());
+        let err = client.chat_streaming(vec![user_message("hi")], vec![]).await.err().unwrap();
+        assert!(err.to_string().contains("boom"));
+    }
+
+    #[test]
+    fn test_parser_emits_typed_events() {
+        let mut parser = OpenAiStreamParser::new();
+        let body = sse_body(&[
+            r#"{"choices":[{"delta":{"reasoning":"thinking..."}}]}"#,
+            r#"{"choices":[{"delta":{"content":"Let me look."}}]}"#,
+            r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"id":"call_a","type":"function","function":{"name":"open_file","arguments":"{\"file_path\":"}}]}}]}"#,
+            r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"\"a.rs\"}"}}]}}]}"#,
+            r#"{"choices":[{"delta":{},"finish_reason":"tool_calls"}],"usage":{"prompt_tokens":1,"completion_tokens":2,"total_tokens":3}}"#,
+        ]);
+
+        let mut events: Vec<StreamEvent> = parser.push(body.as_bytes())
+            .into_iter()
+            .flat_map(|chunk| chunk.events)
+            .collect();
+        events.extend(parser.finish().events);
+
+        assert!(matches!(&events[0], StreamEvent::ReasoningDelta(r) if r == "thinking..."));
+        assert!(matches!(&events[1], StreamEvent::TextDelta(t) if t == "Let me look."));
+        assert!(matches!(&events[2], StreamEvent::ToolCallStart { index: 0, id, name } if id == "call_a" && name == "open_file"));
+        assert!(matches!(&events[3], StreamEvent::ToolCallArgumentsDelta { index: 0, arguments } if arguments == "{\"file_path\":"));
+        assert!(matches!(&events[4], StreamEvent::ToolCallArgumentsDelta { index: 0, .. }));
+        assert!(matches!(&events[5], StreamEvent::ToolCallEnd { index: 0, tool_call } if tool_call.function.arguments == r#"{"file_path":"a.rs"}"#));
+        assert!(matches!(&events[6], StreamEvent::Usage(u) if u.total_tokens == 3));
+        assert_eq!(events.len(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_streaming_tool_use_events() {
+        let server = MockServer::start().await;
+        let body = sse_body(&[
+            r#"{"type":"message_start","message":{"role":"assistant","usage":{"input_tokens":12,"output_tokens":1}}}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Checking"}}"#,
+            r#"{"type":"content_block_stop","index":0}"#,
+            r#"{"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"toolu_1","name":"list_files","input":{}}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{\"pattern\":"}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"\"*.rs\"}"}}"#,
+            r#"{"type":"content_block_stop","index":1}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"tool_use"},"usage":{"output_tokens":9}}"#,
+            r#"{"type":"message_stop"}"#,
+        ]);
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&server)
+            .await;
+
+        let client = AnthropicLlmClient::new(
+            "test-key".to_string(),
+            "claude-test".to_string(),
+            server.uri(),
+            "test".to_string(),
+        );
+        let chunks = collect(client.chat_streaming(vec![user_message("list")], vec![]).await.unwrap()).await;
+        let events: Vec<&StreamEvent> = chunks.iter().flat_map(|c| c.events.iter()).collect();
+
+        assert!(matches!(events[0], StreamEvent::TextDelta(t) if t == "Checking"));
+        assert!(matches!(events[1], StreamEvent::ToolCallStart { index: 0, id, name } if id == "toolu_1" && name == "list_files"));
+        assert!(matches!(events[4], StreamEvent::ToolCallEnd { index: 0, tool_call } if tool_call.function.arguments == r#"{"pattern":"*.rs"}"#));
+
+        let last = chunks.last().unwrap();
+        assert_eq!(last.finish_reason.as_deref(), Some("tool_calls"));
+        assert_eq!(last.tool_calls.as_ref().unwrap()[0].id, "toolu_1");
+        let usage = last.usage.as_ref().unwrap();
+        assert_eq!(usage.prompt_tokens, 12);
+        assert_eq!(usage.completion_tokens, 9);
+    }
+}
@@ -36,11 +36,14 @@ struct LogEntry {
     task_depth: Option<usize>,
     #[serde(skip_serializing_if = "Option::is_none")]
     agent_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    provider: Option<String>,
 }
 
 pub struct ConversationLogger {
     file_path: PathBuf,
     file: Option<tokio::fs::File>,
+    provider: Option<String>,
 }
 
 impl ConversationLogger {
@@ -60,7 +63,7 @@ impl ConversationLogger {
             .append(true)
             .open(&file_path)
             .await?;
-        Ok(Self { file_path, file: Some(file) })
+        Ok(Self { file_path, file: Some(file), provider: None })
     }
 
     /// Create a new logger for task mode; generates the file name with "-task" suffix.
@@ -79,7 +82,20 @@ impl ConversationLogger {
             .append(true)
             .open(&file_path)
             .await?;
-        Ok(Self { file_path, file: Some(file) })
+        Ok(Self { file_path, file: Some(file), provider: None })
+    }
+
+    /// Record the provider that answered the latest LLM call.
+    ///
+    /// Assistant entries logged afterwards carry it, so a fallback provider
+    /// answering is visible in the conversation log.
+    pub fn set_provider(&mut self, provider: Option<String>) {
+        self.provider = provider;
+    }
+
+    /// Provider to record for an entry with the given role
+    fn provider_for(&self, role: &str) -> Option<String> {
+        if role == "assistant" { self.provider.clone() } else { None }
     }
 
     /// Append a single log entry.
@@ -112,6 +128,7 @@ impl ConversationLogger {
             parent_task_id: parent_task_id.map(|s| s.to_string()),
             task_depth,
             agent_name: agent_name.map(|s| s.to_string()),
+            provider: self.provider_for(role),
         };
         if let Some(file) = &mut self.file {
             if let Ok(json) = serde_json::to_string(&entry) {
@@ -166,6 +183,7 @@ impl ConversationLogger {
             parent_task_id: parent_task_id.map(|s| s.to_string()),
             task_depth,
             agent_name: agent_name.map(|s| s.to_string()),
+            provider: self.provider_for(role),
         };
         if let Some(file) = &mut self.file {
             if let Ok(json) = serde_json::to_string(&entry) {
@@ -218,6 +236,7 @@ impl ConversationLogger {
             parent_task_id: parent_task_id.map(|s| s.to_string()),
             task_depth,
             agent_name: agent_name.map(|s| s.to_string()),
+            provider: None,
         };
         if let Some(file) = &mut self.file {
             if let Ok(json) = serde_json::to_string(&entry) {
@@ -16,6 +16,8 @@ pub use request_logger::{
     log_response_to_file,
     log_raw_response_to_file,
     log_stream_chunk,
+    log_retry_to_file,
+    log_failover_to_file,
 };
 
 /// Safely truncate a string to a maximum number of characters
@@ -1,7 +1,7 @@
 use anyhow::{Context, Result};
 use colored::Colorize;
 use std::fs;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use kimichat_models::{ChatRequest, ModelColor};
 use crate::{safe_truncate, get_logs_dir};
@@ -112,6 +112,84 @@ pub fn log_request_to_file(url: &str, request: &ChatRequest, model: &ModelColor,
     Ok(())
 }
 
+/// Log a retry of a failed LLM request next to the request logs
+///
+/// Each retry gets its own file so a request log can be matched with the
+/// attempts that followed it by timestamp, model and agent name.
+pub fn log_retry_to_file(
+    model: &str,
+    agent_name: &str,
+    attempt: u32,
+    max_retries: u32,
+    delay: Duration,
+    reason: &str,
+) -> Result<()> {
+    let logs_dir = get_logs_dir()?;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let model_name = model.replace('/', "-");
+    let filename = format!("retry-{}-{}-agent-{}-attempt-{}.txt", timestamp, model_name, agent_name, attempt);
+    let file_path = logs_dir.join(filename);
+
+    let mut log_content = String::new();
+    log_content.push_str("LLM REQUEST RETRY LOG\n");
+    log_content.push_str("=====================\n\n");
+    log_content.push_str(&format!("Timestamp: {}\n", timestamp));
+    log_content.push_str(&format!("Model: {}\n", model));
+    log_content.push_str(&format!("Agent: {}\n", agent_name));
+    log_content.push_str(&format!("Attempt: {}/{}\n", attempt, max_retries));
+    log_content.push_str(&format!("Delay: {} ms\n\n", delay.as_millis()));
+    log_content.push_str("Reason:\n");
+    log_content.push_str(reason);
+    log_content.push('\n');
+
+    fs::write(&file_path, log_content)
+        .with_context(|| format!("Failed to write retry log to {}", file_path.display()))?;
+
+    println!("{}", format!("⏳ Retry {}/{} for {} in {:.1}s: {}",
+        attempt, max_retries, model, delay.as_secs_f64(), safe_truncate(reason, 200)).yellow());
+
+    Ok(())
+}
+
+/// Log a provider fallback chain moving on to its next provider
+///
+/// Written next to the retry logs, so a failed request can be followed from
+/// its retries to the provider that took over.
+pub fn log_failover_to_file(from: &str, to: &str, reason: &str) -> Result<()> {
+    let logs_dir = get_logs_dir()?;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let filename = format!("failover-{}-{}.txt", timestamp, from.replace('/', "-"));
+    let file_path = logs_dir.join(filename);
+
+    let mut log_content = String::new();
+    log_content.push_str("LLM PROVIDER FAILOVER LOG\n");
+    log_content.push_str("=========================\n\n");
+    log_content.push_str(&format!("Timestamp: {}\n", timestamp));
+    log_content.push_str(&format!("From: {}\n", from));
+    log_content.push_str(&format!("To: {}\n\n", to));
+    log_content.push_str("Reason:\n");
+    log_content.push_str(reason);
+    log_content.push('\n');
+
+    fs::write(&file_path, log_content)
+        .with_context(|| format!("Failed to write failover log to {}", file_path.display()))?;
+
+    println!("{}", format!("🔀 Provider {} failed, falling back to {}: {}",
+        from, to, safe_truncate(reason, 200)).yellow());
+
+    Ok(())
+}
+
 /// Log HTTP response to file for persistent debugging
 pub fn log_response_to_file(
     status: &reqwest::StatusCode,
@@ -0,0 +1,136 @@
+//! A connection to one MCP server
+
+use crate::config::{McpServerConfig, TransportKind, DEFAULT_TIMEOUT_SECS};
+use crate::protocol::{self, CallToolResult, InitializeResult, ListToolsResult, RemoteTool};
+use crate::transport::{HttpTransport, StdioTransport, Transport};
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// An initialized session with an MCP server
+pub struct McpClient {
+    name: String,
+    transport: Transport,
+    next_id: AtomicU64,
+    timeout: Duration,
+    server: InitializeResult,
+}
+
+impl std::fmt::Debug for McpClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("McpClient")
+            .field("name", &self.name)
+            .field("server", &self.server.server_info.name)
+            .field("protocol_version", &self.server.protocol_version)
+            .finish()
+    }
+}
+
+impl McpClient {
+    /// Launch or reach the server and run the `initialize` handshake.
+    /// Commands run in `work_dir` unless the config names a directory.
+    pub async fn connect(name: &str, config: &McpServerConfig, work_dir: &Path) -> Result<Self> {
+        let transport = match config.transport()? {
+            TransportKind::Stdio => Transport::Stdio(StdioTransport::spawn(config, work_dir)?),
+            TransportKind::Http => Transport::Http(HttpTransport::new(config)?),
+        };
+        Self::initialize(name, transport, config.timeout()).await
+    }
+
+    /// Run the handshake with a server on the other end of a pair of byte
+    /// streams, speaking newline-delimited JSON like a stdio server
+    pub async fn connect_streams<R, W>(name: &str, reader: R, writer: W) -> Result<Self>
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        let transport = Transport::Stdio(StdioTransport::from_streams(reader, writer));
+        Self::initialize(name, transport, Duration::from_secs(DEFAULT_TIMEOUT_SECS)).await
+    }
+
+    async fn initialize(name: &str, transport: Transport, timeout: Duration) -> Result<Self> {
+        let mut client = Self {
+            name: name.to_string(),
+            transport,
+            next_id: AtomicU64::new(1),
+            timeout,
+            server: InitializeResult::default(),
+        };
+
+        let result = client.request("initialize", protocol::initialize_params()).await?;
+        client.server = serde_json::from_value(result)
+            .with_context(|| format!("MCP server '{}' sent an invalid initialize result", name))?;
+        client.transport.set_protocol_version(&client.server.protocol_version);
+        client.notify("notifications/initialized", json!({})).await?;
+        Ok(client)
+    }
+
+    /// The name the server is configured under
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// What the server said about itself when the session started
+    pub fn server(&self) -> &InitializeResult {
+        &self.server
+    }
+
+    /// Every tool the server offers, following `tools/list` pagination
+    pub async fn list_tools(&self) -> Result<Vec<RemoteTool>> {
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let page: ListToolsResult = serde_json::from_value(self.request("tools/list", params).await?)
+                .with_context(|| format!("MCP server '{}' sent an invalid tool list", self.name))?;
+            tools.extend(page.tools);
+            match page.next_cursor {
+                Some(next) if Some(&next) != cursor.as_ref() => cursor = Some(next),
+                _ => return Ok(tools),
+            }
+        }
+    }
+
+    /// Call one of the server's tools by its own name
+    pub async fn call_tool(&self, tool: &str, arguments: Value) -> Result<CallToolResult> {
+        let result = self.request("tools/call", json!({ "name": tool, "arguments": arguments })).await?;
+        serde_json::from_value(result)
+            .with_context(|| format!("MCP server '{}' sent an invalid result for '{}'", self.name, tool))
+    }
+
+    /// Send a request and wait for its result. On timeout the server is told
+    /// to cancel the request.
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let message = protocol::request(id, method, params);
+        let response = match tokio::time::timeout(self.timeout, self.transport.request(id, message)).await {
+            Ok(response) => response.with_context(|| format!("MCP server '{}' failed on {}", self.name, method))?,
+            Err(_) => {
+                self.transport.forget(id);
+                let _ = self
+                    .notify("notifications/cancelled", json!({ "requestId": id, "reason": "timed out" }))
+                    .await;
+                return Err(anyhow!(
+                    "MCP server '{}' did not answer {} within {}s",
+                    self.name,
+                    method,
+                    self.timeout.as_secs()
+                ));
+            }
+        };
+        protocol::into_result(response).map_err(|e| anyhow!("MCP server '{}' returned an error for {}: {}", self.name, method, e))
+    }
+
+    pub async fn notify(&self, method: &str, params: Value) -> Result<()> {
+        self.transport
+            .notify(protocol::notification(method, params))
+            .await
+            .with_context(|| format!("MCP server '{}' failed on {}", self.name, method))
+    }
+}
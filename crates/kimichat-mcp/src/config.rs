@@ -0,0 +1,175 @@
+//! MCP server configuration, read from `mcp.toml`
+//!
+//! ```toml
+//! [servers.github]
+//! command = "npx"
+//! args = ["-y", "@modelcontextprotocol/server-github"]
+//! env = { GITHUB_PERSONAL_ACCESS_TOKEN = "..." }
+//!
+//! [servers.docs]
+//! url = "http://127.0.0.1:8931/mcp"
+//! ```
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// Seconds to wait for a server's answer when the config doesn't say
+pub const DEFAULT_TIMEOUT_SECS: u64 = 60;
+
+/// The MCP servers to start, by name
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct McpConfig {
+    #[serde(default)]
+    pub servers: BTreeMap<String, McpServerConfig>,
+    /// Servers whose definition comes from the project file. A checkout can't
+    /// be trusted to pick the programs it launches, so these need approval.
+    #[serde(skip)]
+    pub project_servers: BTreeSet<String>,
+}
+
+/// One MCP server: a command speaking MCP on stdin/stdout, or the URL of a
+/// streamable HTTP endpoint
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct McpServerConfig {
+    /// Program to launch
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Variables added to the program's environment
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Working directory of the program (default: the workspace)
+    #[serde(default)]
+    pub cwd: Option<PathBuf>,
+    /// Streamable HTTP endpoint
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Headers sent with every HTTP request, e.g. `Authorization`
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Seconds to wait for each answer from the server
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Set to false to keep the server configured but not started
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// How to reach a server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportKind {
+    Stdio,
+    Http,
+}
+
+impl Default for McpServerConfig {
+    fn default() -> Self {
+        Self {
+            command: None,
+            args: Vec::new(),
+            env: BTreeMap::new(),
+            cwd: None,
+            url: None,
+            headers: BTreeMap::new(),
+            timeout_secs: None,
+            enabled: true,
+        }
+    }
+}
+
+impl McpServerConfig {
+    /// A server launched as `command args...`
+    pub fn stdio(command: impl Into<String>, args: Vec<String>) -> Self {
+        Self { command: Some(command.into()), args, ..Self::default() }
+    }
+
+    /// A server at a streamable HTTP endpoint
+    pub fn http(url: impl Into<String>) -> Self {
+        Self { url: Some(url.into()), ..Self::default() }
+    }
+
+    /// Which transport the config describes; exactly one of `command` and `url` must be set
+    pub fn transport(&self) -> Result<TransportKind> {
+        match (&self.command, &self.url) {
+            (Some(_), None) => Ok(TransportKind::Stdio),
+            (None, Some(_)) => Ok(TransportKind::Http),
+            (Some(_), Some(_)) => bail!("set either `command` or `url`, not both"),
+            (None, None) => bail!("set `command` (stdio) or `url` (streamable HTTP)"),
+        }
+    }
+
+    pub fn timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS))
+    }
+
+    /// `command args...` as one line, quoting arguments the shell would split,
+    /// for `mcp_server_launch` policy rules. `None` for HTTP servers.
+    pub fn command_line(&self) -> Option<String> {
+        let command = self.command.as_ref()?;
+        let words = std::iter::once(command).chain(&self.args).map(|word| {
+            if !word.is_empty() && word.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:@%+,".contains(c)) {
+                word.clone()
+            } else {
+                format!("'{}'", word.replace('\'', "'\\''"))
+            }
+        });
+        Some(words.collect::<Vec<_>>().join(" "))
+    }
+}
+
+impl McpConfig {
+    /// Load a config file; a missing file configures no servers
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read MCP config {}", path.display()))?;
+        let config: McpConfig = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse MCP config {}", path.display()))?;
+        config.validate().with_context(|| format!("Invalid MCP config {}", path.display()))?;
+        Ok(config)
+    }
+
+    /// Merge the user file (if any) under the project file: a server defined
+    /// in both takes the project's definition and counts as a project server
+    pub fn layered(user_file: Option<PathBuf>, project_file: PathBuf) -> Result<Self> {
+        let mut config = match user_file {
+            Some(user_file) => Self::load(&user_file)?,
+            None => Self::default(),
+        };
+        let project = Self::load(&project_file)?;
+        config.project_servers.extend(project.servers.keys().cloned());
+        config.servers.extend(project.servers);
+        Ok(config)
+    }
+
+    /// The server's definition comes from the project file
+    pub fn is_project_server(&self, name: &str) -> bool {
+        self.project_servers.contains(name)
+    }
+
+    /// Reject servers whose transport is unclear or whose name can't be part of a tool name
+    pub fn validate(&self) -> Result<()> {
+        for (name, server) in &self.servers {
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') || name.contains("__") {
+                bail!("Invalid MCP server name '{}': use letters, digits, '-' and single '_'", name);
+            }
+            server.transport().with_context(|| format!("MCP server '{}'", name))?;
+        }
+        Ok(())
+    }
+
+    /// The servers to start
+    pub fn enabled_servers(&self) -> impl Iterator<Item = (&String, &McpServerConfig)> {
+        self.servers.iter().filter(|(_, server)| server.enabled)
+    }
+}
@@ -0,0 +1,25 @@
+//! Model Context Protocol client and server
+//!
+//! Starts the MCP servers configured in `mcp.toml`, over stdio or streamable
+//! HTTP, and mounts each tool they offer as a registry `Tool` named
+//! `mcp__<server>__<tool>`. Launching a stdio server is checked against the
+//! `mcp_server_launch` policy action, and calls against `mcp_tool` before
+//! they are forwarded to the server.
+//!
+//! `McpServer` is the other direction: it serves a `ToolRegistry`, with
+//! prompts and resources alongside, to an MCP client on stdio.
+
+pub mod client;
+pub mod config;
+pub mod protocol;
+pub mod server;
+pub mod servers;
+pub mod tool;
+mod transport;
+
+pub use client::McpClient;
+pub use config::{McpConfig, McpServerConfig, TransportKind};
+pub use protocol::{CallToolResult, ContentBlock, RemoteTool};
+pub use server::{McpServer, Prompt, PromptArgument, Resource, ResourceContents, ServerContent};
+pub use servers::McpServers;
+pub use tool::McpTool;
@@ -0,0 +1,225 @@
+//! The parts of the Model Context Protocol kimichat speaks: JSON-RPC framing,
+//! the tool listing and tool call results
+
+use kimichat_toolcore::ToolResult;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// Protocol revision the client asks for in `initialize`
+pub const PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// Revisions the server can speak, newest first
+pub const SUPPORTED_PROTOCOL_VERSIONS: [&str; 3] = [PROTOCOL_VERSION, "2025-03-26", "2024-11-05"];
+
+/// JSON-RPC error codes
+pub const PARSE_ERROR: i64 = -32700;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const RESOURCE_NOT_FOUND: i64 = -32002;
+
+pub fn request(id: u64, method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+pub fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+pub fn success_response(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+pub fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// Id of a JSON-RPC response (a message with a result or an error)
+pub fn response_id(message: &Value) -> Option<u64> {
+    if message.get("result").is_none() && message.get("error").is_none() {
+        return None;
+    }
+    message.get("id").and_then(Value::as_u64)
+}
+
+/// The result of a response, or its error as text
+pub fn into_result(message: Value) -> Result<Value, String> {
+    match message.get("error") {
+        Some(error) => {
+            let code = error.get("code").and_then(Value::as_i64).unwrap_or(0);
+            let text = error.get("message").and_then(Value::as_str).unwrap_or("unknown error");
+            Err(format!("{} (code {})", text, code))
+        }
+        None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+    }
+}
+
+/// Reply to a request the server sent the client. The client offers no
+/// capabilities, so only `ping` gets an answer.
+pub fn reply_to_server_request(message: &Value) -> Option<Value> {
+    let id = message.get("id")?.clone();
+    let method = message.get("method")?.as_str()?;
+    Some(match method {
+        "ping" => success_response(id, json!({})),
+        _ => error_response(id, METHOD_NOT_FOUND, &format!("Method not supported by client: {}", method)),
+    })
+}
+
+pub fn initialize_params() -> Value {
+    json!({
+        "protocolVersion": PROTOCOL_VERSION,
+        "capabilities": {},
+        "clientInfo": { "name": "kimichat", "version": env!("CARGO_PKG_VERSION") }
+    })
+}
+
+/// What the server said about itself in `initialize`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InitializeResult {
+    #[serde(default)]
+    pub protocol_version: String,
+    #[serde(default)]
+    pub server_info: ServerInfo,
+    #[serde(default)]
+    pub instructions: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ServerInfo {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+}
+
+/// A page of `tools/list`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListToolsResult {
+    pub tools: Vec<RemoteTool>,
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+}
+
+/// A tool as the server describes it
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteTool {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub input_schema: Value,
+    #[serde(default)]
+    pub annotations: ToolAnnotations,
+}
+
+/// Hints about a tool's behaviour; only read-only-ness is used
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolAnnotations {
+    #[serde(default)]
+    pub read_only_hint: Option<bool>,
+}
+
+/// Result of `tools/call`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallToolResult {
+    #[serde(default)]
+    pub content: Vec<ContentBlock>,
+    #[serde(default)]
+    pub structured_content: Option<Value>,
+    #[serde(default)]
+    pub is_error: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentBlock {
+    Text {
+        text: String,
+    },
+    Image {
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+    Audio {
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+    Resource {
+        resource: EmbeddedResource,
+    },
+    ResourceLink {
+        uri: String,
+        #[serde(default)]
+        name: Option<String>,
+    },
+    #[serde(other)]
+    Unsupported,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddedResource {
+    pub uri: String,
+    #[serde(default)]
+    pub mime_type: Option<String>,
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub blob: Option<String>,
+}
+
+/// Size in bytes of base64-encoded data
+fn decoded_len(data: &str) -> usize {
+    data.trim_end_matches('=').len() * 3 / 4
+}
+
+impl ContentBlock {
+    /// The block as text for the model. Binary content can't be shown in a
+    /// tool result, so it is described instead.
+    pub fn to_text(&self) -> String {
+        match self {
+            ContentBlock::Text { text } => text.clone(),
+            ContentBlock::Image { data, mime_type } => format!("[image: {}, {} bytes]", mime_type, decoded_len(data)),
+            ContentBlock::Audio { data, mime_type } => format!("[audio: {}, {} bytes]", mime_type, decoded_len(data)),
+            ContentBlock::Resource { resource } => match (&resource.text, &resource.blob) {
+                (Some(text), _) => format!("[resource: {}]\n{}", resource.uri, text),
+                (None, Some(blob)) => format!(
+                    "[resource: {}, {}, {} bytes]",
+                    resource.uri,
+                    resource.mime_type.as_deref().unwrap_or("binary"),
+                    decoded_len(blob)
+                ),
+                (None, None) => format!("[resource: {}]", resource.uri),
+            },
+            ContentBlock::ResourceLink { uri, name } => match name {
+                Some(name) => format!("[resource link: {} ({})]", uri, name),
+                None => format!("[resource link: {}]", uri),
+            },
+            ContentBlock::Unsupported => "[unsupported content]".to_string(),
+        }
+    }
+}
+
+impl CallToolResult {
+    /// The content blocks joined into one tool result. Structured content is
+    /// used when the server sent no blocks; `isError` makes it a failure.
+    pub fn into_tool_result(self) -> ToolResult {
+        let mut text = self.content.iter().map(ContentBlock::to_text).collect::<Vec<_>>().join("\n");
+        if self.content.is_empty() {
+            if let Some(structured) = &self.structured_content {
+                text = serde_json::to_string_pretty(structured).unwrap_or_default();
+            }
+        }
+
+        if self.is_error {
+            ToolResult::error(if text.is_empty() { "The tool reported an error".to_string() } else { text })
+        } else {
+            ToolResult::success(text)
+        }
+    }
+}
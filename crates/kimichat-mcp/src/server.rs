@@ -0,0 +1,363 @@
+//! Serving a `ToolRegistry` over MCP: the registry's tools, plus prompts and
+//! resources from a `ServerContent`, on newline-delimited JSON over stdio
+
+use crate::protocol::{
+    self, error_response, success_response, INVALID_PARAMS, METHOD_NOT_FOUND, PARSE_ERROR, RESOURCE_NOT_FOUND,
+    SUPPORTED_PROTOCOL_VERSIONS,
+};
+use crate::transport::{write_message, Writer};
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use kimichat_toolcore::{ToolContext, ToolParameters, ToolRegistry};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, BufReader};
+use tokio::task::JoinSet;
+
+/// How often subscribed resources are checked for changes
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A prompt template offered through `prompts/list`
+#[derive(Debug, Clone, Serialize)]
+pub struct Prompt {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub arguments: Vec<PromptArgument>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptArgument {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub required: bool,
+}
+
+/// A resource offered through `resources/list`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Resource {
+    pub uri: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+/// The text of a resource, as returned by `resources/read`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceContents {
+    pub uri: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    pub text: String,
+}
+
+/// Prompts and resources the server offers besides its tools. Both lists are
+/// asked for again on every request, so they can change while serving.
+#[async_trait]
+pub trait ServerContent: Send + Sync {
+    async fn prompts(&self) -> Vec<Prompt> {
+        Vec::new()
+    }
+
+    /// The prompt's text with its arguments filled in
+    async fn get_prompt(&self, name: &str, _arguments: &HashMap<String, String>) -> Result<String> {
+        bail!("Unknown prompt '{}'", name)
+    }
+
+    async fn resources(&self) -> Vec<Resource> {
+        Vec::new()
+    }
+
+    async fn read_resource(&self, uri: &str) -> Result<ResourceContents> {
+        bail!("Unknown resource '{}'", uri)
+    }
+}
+
+struct NoContent;
+
+impl ServerContent for NoContent {}
+
+/// An MCP server for one client
+pub struct McpServer {
+    registry: Arc<ToolRegistry>,
+    context: Arc<ToolContext>,
+    content: Arc<dyn ServerContent>,
+    poll_interval: Duration,
+}
+
+impl McpServer {
+    /// Serve the registry's tools, run with `context`
+    pub fn new(registry: ToolRegistry, context: ToolContext) -> Self {
+        Self {
+            registry: Arc::new(registry),
+            context: Arc::new(context),
+            content: Arc::new(NoContent),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        }
+    }
+
+    pub fn with_content(mut self, content: Arc<dyn ServerContent>) -> Self {
+        self.content = content;
+        self
+    }
+
+    /// How often subscribed resources and the resource list are checked for changes
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Answer requests from `reader` on `writer` until the client closes its
+    /// end. Requests are handled concurrently, so a long tool call doesn't
+    /// hold up a `ping` or a resource read.
+    pub async fn serve<R, W>(self, reader: R, writer: W) -> Result<()>
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        let writer: Writer = Arc::new(tokio::sync::Mutex::new(Box::new(writer)));
+        let session = Arc::new(Session {
+            registry: self.registry,
+            context: self.context,
+            content: self.content,
+            initialized: AtomicBool::new(false),
+            subscriptions: Mutex::new(HashMap::new()),
+        });
+
+        let mut tasks = JoinSet::new();
+        tasks.spawn(watch_resources(session.clone(), writer.clone(), self.poll_interval));
+
+        let mut lines = BufReader::new(reader).lines();
+        while let Some(line) = lines.next_line().await? {
+            while tasks.try_join_next().is_some() {}
+            if line.trim().is_empty() {
+                continue;
+            }
+            let message: Value = match serde_json::from_str(&line) {
+                Ok(message) => message,
+                Err(e) => {
+                    let reply = error_response(Value::Null, PARSE_ERROR, &format!("Invalid JSON: {}", e));
+                    write_message(&writer, &reply).await?;
+                    continue;
+                }
+            };
+            // Notifications (initialized, cancelled) and responses need no answer
+            let (Some(id), Some(method)) = (message.get("id").cloned(), message.get("method").and_then(Value::as_str))
+            else {
+                continue;
+            };
+
+            let method = method.to_string();
+            let params = message.get("params").cloned().unwrap_or_else(|| json!({}));
+            let session = session.clone();
+            let writer = writer.clone();
+            tasks.spawn(async move {
+                let reply = match session.handle(&method, params).await {
+                    Ok(result) => success_response(id, result),
+                    Err((code, text)) => error_response(id, code, &text),
+                };
+                let _ = write_message(&writer, &reply).await;
+            });
+        }
+        Ok(())
+    }
+}
+
+type RpcResult = std::result::Result<Value, (i64, String)>;
+
+struct Session {
+    registry: Arc<ToolRegistry>,
+    context: Arc<ToolContext>,
+    content: Arc<dyn ServerContent>,
+    initialized: AtomicBool,
+    /// Subscribed URIs and a hash of the text last seen for each
+    subscriptions: Mutex<HashMap<String, u64>>,
+}
+
+impl Session {
+    async fn handle(&self, method: &str, params: Value) -> RpcResult {
+        match method {
+            "initialize" => Ok(self.initialize(&params)),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(self.list_tools()),
+            "tools/call" => self.call_tool(&params).await,
+            "prompts/list" => Ok(json!({ "prompts": self.content.prompts().await })),
+            "prompts/get" => self.get_prompt(&params).await,
+            "resources/list" => Ok(json!({ "resources": self.content.resources().await })),
+            "resources/templates/list" => Ok(json!({ "resourceTemplates": [] })),
+            "resources/read" => {
+                let contents = self.read_resource(&params).await?;
+                Ok(json!({ "contents": [contents] }))
+            }
+            "resources/subscribe" => {
+                let contents = self.read_resource(&params).await?;
+                self.subscriptions.lock().unwrap().insert(contents.uri, text_hash(&contents.text));
+                Ok(json!({}))
+            }
+            "resources/unsubscribe" => {
+                self.subscriptions.lock().unwrap().remove(required_str(&params, "uri")?);
+                Ok(json!({}))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("Method not supported: {}", method))),
+        }
+    }
+
+    /// Agree on the client's protocol revision if it's one we speak, else offer ours
+    fn initialize(&self, params: &Value) -> Value {
+        let requested = params.get("protocolVersion").and_then(Value::as_str).unwrap_or_default();
+        let version = SUPPORTED_PROTOCOL_VERSIONS
+            .iter()
+            .find(|version| **version == requested)
+            .copied()
+            .unwrap_or(protocol::PROTOCOL_VERSION);
+        self.initialized.store(true, Ordering::Relaxed);
+        json!({
+            "protocolVersion": version,
+            "capabilities": {
+                "tools": { "listChanged": false },
+                "prompts": { "listChanged": false },
+                "resources": { "subscribe": true, "listChanged": true }
+            },
+            "serverInfo": { "name": "kimichat", "version": env!("CARGO_PKG_VERSION") }
+        })
+    }
+
+    fn list_tools(&self) -> Value {
+        let mut tools = self.registry.get_all_tools();
+        tools.sort_by(|a, b| a.name().cmp(b.name()));
+        let tools: Vec<Value> = tools
+            .iter()
+            .map(|tool| {
+                json!({
+                    "name": tool.name(),
+                    "description": tool.description(),
+                    "inputSchema": tool.parameters_schema(),
+                    "annotations": { "readOnlyHint": tool.read_only() }
+                })
+            })
+            .collect();
+        json!({ "tools": tools })
+    }
+
+    /// Run a tool through the registry. A failing tool is a successful call
+    /// with `isError` set, so the client's model sees why it failed.
+    async fn call_tool(&self, params: &Value) -> RpcResult {
+        let name = required_str(params, "name")?;
+        if !self.registry.has_tool(name) {
+            return Err((INVALID_PARAMS, format!("Unknown tool: {}", name)));
+        }
+        let data = match params.get("arguments") {
+            None | Some(Value::Null) => HashMap::new(),
+            Some(Value::Object(arguments)) => arguments.clone().into_iter().collect(),
+            Some(_) => return Err((INVALID_PARAMS, "Tool arguments must be an object".to_string())),
+        };
+
+        let result = self.registry.execute_tool(name, ToolParameters { data }, &self.context).await;
+        let text = match result.error {
+            Some(error) if !result.success => error,
+            _ => result.content,
+        };
+        Ok(json!({
+            "content": [{ "type": "text", "text": text }],
+            "isError": !result.success
+        }))
+    }
+
+    async fn get_prompt(&self, params: &Value) -> RpcResult {
+        let name = required_str(params, "name")?;
+        let mut arguments = HashMap::new();
+        if let Some(Value::Object(given)) = params.get("arguments") {
+            for (key, value) in given {
+                let value = match value {
+                    Value::String(text) => text.clone(),
+                    other => other.to_string(),
+                };
+                arguments.insert(key.clone(), value);
+            }
+        }
+
+        let description = self.content.prompts().await.into_iter().find(|prompt| prompt.name == name).and_then(|p| p.description);
+        let text = self.content.get_prompt(name, &arguments).await.map_err(|e| (INVALID_PARAMS, format!("{:#}", e)))?;
+        let mut result = json!({
+            "messages": [{ "role": "user", "content": { "type": "text", "text": text } }]
+        });
+        if let Some(description) = description {
+            result["description"] = Value::String(description);
+        }
+        Ok(result)
+    }
+
+    async fn read_resource(&self, params: &Value) -> std::result::Result<ResourceContents, (i64, String)> {
+        let uri = required_str(params, "uri")?;
+        self.content.read_resource(uri).await.map_err(|e| (RESOURCE_NOT_FOUND, format!("{:#}", e)))
+    }
+}
+
+fn required_str<'a>(params: &'a Value, key: &str) -> std::result::Result<&'a str, (i64, String)> {
+    params
+        .get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| (INVALID_PARAMS, format!("Missing '{}'", key)))
+}
+
+fn text_hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Tell the client when the resource list changes, and when a subscribed
+/// resource's text does. Resources like terminal screens change without the
+/// server being asked anything, so they are polled.
+async fn watch_resources(session: Arc<Session>, writer: Writer, interval: Duration) {
+    let uris = |resources: Vec<Resource>| resources.into_iter().map(|r| r.uri).collect::<HashSet<_>>();
+    let mut listed = uris(session.content.resources().await);
+    loop {
+        tokio::time::sleep(interval).await;
+        if !session.initialized.load(Ordering::Relaxed) {
+            continue;
+        }
+
+        let current = uris(session.content.resources().await);
+        if current != listed {
+            listed = current;
+            let message = protocol::notification("notifications/resources/list_changed", json!({}));
+            if write_message(&writer, &message).await.is_err() {
+                return;
+            }
+        }
+
+        let subscribed: Vec<(String, u64)> =
+            session.subscriptions.lock().unwrap().iter().map(|(uri, hash)| (uri.clone(), *hash)).collect();
+        for (uri, last) in subscribed {
+            // A resource that went away (a closed terminal) keeps its last state
+            let Ok(contents) = session.content.read_resource(&uri).await else {
+                continue;
+            };
+            let hash = text_hash(&contents.text);
+            if hash == last {
+                continue;
+            }
+            // Skip the update if the client unsubscribed meanwhile
+            match session.subscriptions.lock().unwrap().get_mut(&uri) {
+                Some(seen) => *seen = hash,
+                None => continue,
+            }
+            let message = protocol::notification("notifications/resources/updated", json!({ "uri": uri }));
+            if write_message(&writer, &message).await.is_err() {
+                return;
+            }
+        }
+    }
+}
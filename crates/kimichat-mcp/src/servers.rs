@@ -0,0 +1,128 @@
+//! Starting the configured servers and mounting their tools
+
+use crate::client::McpClient;
+use crate::config::{McpConfig, McpServerConfig};
+use crate::protocol::RemoteTool;
+use crate::tool::McpTool;
+use anyhow::{bail, Result};
+use kimichat_policy::{ActionType, Decision, PolicyScope};
+use kimichat_toolcore::{Tool, ToolContext, ToolRegistry};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// The servers that started, and their tools
+#[derive(Debug, Default)]
+pub struct McpServers {
+    clients: Vec<Arc<McpClient>>,
+    tools: Vec<McpTool>,
+    errors: Vec<String>,
+}
+
+impl McpServers {
+    /// Start every enabled server the policy lets launch, all at once, in the
+    /// context's work directory, and list its tools. A server that is refused,
+    /// fails to start or fails to list its tools is left out and its error kept.
+    pub async fn connect(config: &McpConfig, context: &ToolContext) -> Self {
+        let mut servers = Self::default();
+
+        // One at a time, as approving may prompt the user
+        let mut approved = Vec::new();
+        for (name, server) in config.enabled_servers() {
+            match approve_launch(name, server, config.is_project_server(name), context).await {
+                Ok(()) => approved.push((name, server)),
+                Err(e) => servers.errors.push(format!("MCP server '{}' not started: {:#}", name, e)),
+            }
+        }
+
+        let work_dir = context.work_dir.as_path();
+        let connections = approved.iter().map(|&(name, server)| async move {
+            let client = McpClient::connect(name, server, work_dir).await?;
+            let tools = client.list_tools().await?;
+            anyhow::Ok((client, tools))
+        });
+        let results = futures_util::future::join_all(connections).await;
+
+        for ((name, _), result) in approved.into_iter().zip(results) {
+            match result {
+                Ok((client, tools)) => servers.add(Arc::new(client), tools),
+                Err(e) => servers.errors.push(format!("MCP server '{}' not started: {:#}", name, e)),
+            }
+        }
+        servers
+    }
+
+    /// Mount a connected server's tools. Tools whose name collides with one
+    /// already mounted are left out.
+    pub fn add(&mut self, client: Arc<McpClient>, tools: Vec<RemoteTool>) {
+        let mut names: HashSet<String> = self.tools.iter().map(|tool| tool.name().to_string()).collect();
+        for tool in tools {
+            let tool = McpTool::new(client.clone(), tool);
+            if names.insert(tool.name().to_string()) {
+                self.tools.push(tool);
+            } else {
+                self.errors.push(format!(
+                    "MCP tool '{}' on server '{}' skipped: another tool is already named {}",
+                    tool.remote_name(),
+                    client.name(),
+                    tool.name()
+                ));
+            }
+        }
+        self.clients.push(client);
+    }
+
+    /// Register every mounted tool, under the `mcp` category
+    pub fn register_tools(&self, registry: &mut ToolRegistry) {
+        for tool in &self.tools {
+            registry.register(tool.clone());
+        }
+    }
+
+    pub fn clients(&self) -> &[Arc<McpClient>] {
+        &self.clients
+    }
+
+    pub fn tools(&self) -> &[McpTool] {
+        &self.tools
+    }
+
+    /// Why servers or tools were left out
+    pub fn errors(&self) -> &[String] {
+        &self.errors
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+}
+
+/// Check a stdio server's command line against the `mcp_server_launch` policy
+/// action, asking when the policy says so. The project's policy file can't
+/// allow the project's own servers, as both come with the checkout: those need
+/// a user or session rule, or the user's answer.
+async fn approve_launch(name: &str, server: &McpServerConfig, project: bool, context: &ToolContext) -> Result<()> {
+    // HTTP servers run nothing here
+    let Some(command_line) = server.command_line() else {
+        return Ok(());
+    };
+
+    let mut explanation =
+        context.policy_manager.evaluate_explained(&ActionType::McpServerLaunch, &command_line, &context.policy_context);
+    if project && explanation.scope == PolicyScope::Project && explanation.decision == Decision::Allow {
+        explanation.decision = Decision::Ask;
+    }
+
+    match explanation.decision {
+        Decision::Allow => Ok(()),
+        Decision::Deny => bail!("launching `{}` is denied by policy", command_line),
+        Decision::Ask => {
+            let source = if project { "the project's .kimichat/mcp.toml" } else { "your mcp.toml" };
+            let prompt = format!("Start MCP server '{}' from {}: `{}`? [Y/n]", name, source, command_line);
+            match context.ask(&explanation, &prompt).await? {
+                (true, _) => Ok(()),
+                (false, Some(reason)) => bail!("launching `{}` was not approved: {}", command_line, reason),
+                (false, None) => bail!("launching `{}` was not approved", command_line),
+            }
+        }
+    }
+}
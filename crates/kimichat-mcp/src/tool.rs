@@ -0,0 +1,125 @@
+//! Remote MCP tools as registry `Tool`s
+
+use crate::client::McpClient;
+use crate::protocol::RemoteTool;
+use async_trait::async_trait;
+use kimichat_policy::ActionType;
+use kimichat_toolcore::{parameters_from_schema, sorted_keys, ParameterDefinition, Tool, ToolContext, ToolParameters, ToolResult};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Longest tool name the model APIs accept
+const MAX_TOOL_NAME_LEN: usize = 64;
+
+/// A tool on an MCP server; calls are forwarded as `tools/call`
+#[derive(Debug, Clone)]
+pub struct McpTool {
+    name: String,
+    remote_name: String,
+    description: String,
+    schema: Value,
+    read_only: bool,
+    client: Arc<McpClient>,
+}
+
+impl McpTool {
+    pub fn new(client: Arc<McpClient>, tool: RemoteTool) -> Self {
+        let description = match tool.description.as_deref().map(str::trim) {
+            Some(description) if !description.is_empty() => description.to_string(),
+            _ => format!("{} (from MCP server '{}')", tool.name, client.name()),
+        };
+        Self {
+            name: Self::qualified_name(client.name(), &tool.name),
+            schema: normalize_schema(tool.input_schema),
+            read_only: tool.annotations.read_only_hint.unwrap_or(false),
+            remote_name: tool.name,
+            description,
+            client,
+        }
+    }
+
+    /// `mcp__<server>__<tool>`, with characters the model APIs reject in tool
+    /// names replaced by `_`, cut to 64 characters
+    pub fn qualified_name(server: &str, tool: &str) -> String {
+        format!("mcp__{}__{}", server, tool)
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+            .take(MAX_TOOL_NAME_LEN)
+            .collect()
+    }
+
+    /// The tool's name on its server
+    pub fn remote_name(&self) -> &str {
+        &self.remote_name
+    }
+
+    /// The name of the server the tool is on
+    pub fn server(&self) -> &str {
+        self.client.name()
+    }
+}
+
+/// The server's input schema as an object schema with sorted keys. `$schema`
+/// is dropped since some model APIs refuse it.
+fn normalize_schema(schema: Value) -> Value {
+    let mut schema = match sorted_keys(&schema) {
+        Value::Object(schema) => schema,
+        _ => serde_json::Map::new(),
+    };
+    schema.remove("$schema");
+    schema.insert("type".to_string(), Value::String("object".to_string()));
+    schema
+        .entry("properties")
+        .or_insert_with(|| Value::Object(serde_json::Map::new()));
+    Value::Object(schema)
+}
+
+#[async_trait]
+impl Tool for McpTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn parameters(&self) -> HashMap<String, ParameterDefinition> {
+        parameters_from_schema(&self.schema)
+    }
+
+    fn parameters_schema(&self) -> Value {
+        self.schema.clone()
+    }
+
+    fn categories(&self) -> Vec<String> {
+        vec!["mcp".to_string()]
+    }
+
+    fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    async fn execute(&self, params: ToolParameters, context: &ToolContext) -> ToolResult {
+        match context
+            .check_permission(
+                ActionType::McpTool,
+                &self.name,
+                &format!("Call {} on MCP server '{}'? [Y/n]", self.remote_name, self.server()),
+            )
+            .await
+        {
+            Ok((true, _)) => {}
+            Ok((false, Some(reason))) => return ToolResult::error(format!("MCP tool call cancelled by user: {}", reason)),
+            Ok((false, None)) => return ToolResult::error("MCP tool call cancelled by user or policy".to_string()),
+            Err(e) => return ToolResult::error(format!("Permission check failed: {}", e)),
+        }
+
+        let arguments = Value::Object(params.data.into_iter().collect());
+        match self.client.call_tool(&self.remote_name, arguments).await {
+            Ok(result) => result.into_tool_result(),
+            Err(e) => ToolResult::error(format!("{:#}", e)),
+        }
+    }
+}
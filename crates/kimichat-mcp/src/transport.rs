@@ -0,0 +1,323 @@
+//! Moving JSON-RPC messages to and from a server: newline-delimited JSON over
+//! a child process's stdin/stdout, or streamable HTTP
+
+use crate::config::McpServerConfig;
+use crate::protocol::{reply_to_server_request, response_id};
+use anyhow::{anyhow, bail, Context, Result};
+use futures_util::StreamExt;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, CONTENT_TYPE};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+/// Lines of a server's stderr kept for error messages
+const STDERR_LINES: usize = 20;
+
+const SESSION_ID_HEADER: &str = "mcp-session-id";
+const PROTOCOL_VERSION_HEADER: &str = "mcp-protocol-version";
+
+pub(crate) enum Transport {
+    Stdio(StdioTransport),
+    Http(HttpTransport),
+}
+
+impl Transport {
+    /// Send a request and wait for the response with the same id
+    pub(crate) async fn request(&self, id: u64, message: Value) -> Result<Value> {
+        match self {
+            Transport::Stdio(stdio) => stdio.request(id, message).await,
+            Transport::Http(http) => http.request(id, message).await,
+        }
+    }
+
+    pub(crate) async fn notify(&self, message: Value) -> Result<()> {
+        match self {
+            Transport::Stdio(stdio) => stdio.send(&message).await,
+            Transport::Http(http) => http.post(&message).await.map(drop),
+        }
+    }
+
+    /// Stop waiting for a request's response
+    pub(crate) fn forget(&self, id: u64) {
+        if let Transport::Stdio(stdio) = self {
+            stdio.pending.lock().unwrap().waiting.remove(&id);
+        }
+    }
+
+    /// Remember the protocol version agreed in `initialize`
+    pub(crate) fn set_protocol_version(&self, version: &str) {
+        if let Transport::Http(http) = self {
+            *http.protocol_version.lock().unwrap() = Some(version.to_string());
+        }
+    }
+}
+
+pub(crate) type Writer = Arc<tokio::sync::Mutex<Box<dyn AsyncWrite + Send + Unpin>>>;
+
+#[derive(Default)]
+struct Pending {
+    /// Why the connection is gone, once it is
+    closed: Option<String>,
+    waiting: HashMap<u64, oneshot::Sender<Value>>,
+}
+
+pub(crate) struct StdioTransport {
+    writer: Writer,
+    pending: Arc<Mutex<Pending>>,
+    stderr: Arc<Mutex<VecDeque<String>>>,
+    tasks: Vec<JoinHandle<()>>,
+    // Killed when the transport is dropped
+    _child: Option<Child>,
+}
+
+impl StdioTransport {
+    /// Launch the server's command, in `work_dir` unless the config names a directory
+    pub(crate) fn spawn(config: &McpServerConfig, work_dir: &Path) -> Result<Self> {
+        let program = config.command.as_deref().ok_or_else(|| anyhow!("no command to launch"))?;
+        let mut command = Command::new(program);
+        command
+            .args(&config.args)
+            .envs(&config.env)
+            .current_dir(config.cwd.as_ref().map_or_else(|| work_dir.to_path_buf(), |cwd| work_dir.join(cwd)))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        // Keep the server out of the terminal's process group, so Ctrl-C stops
+        // the current request without taking the server down with it
+        #[cfg(unix)]
+        command.process_group(0);
+
+        let mut child = command.spawn().with_context(|| format!("Failed to launch `{}`", program))?;
+        let stdin = child.stdin.take().ok_or_else(|| anyhow!("server stdin is not piped"))?;
+        let stdout = child.stdout.take().ok_or_else(|| anyhow!("server stdout is not piped"))?;
+        let stderr = child.stderr.take();
+
+        let mut transport = Self::from_streams(stdout, stdin);
+        if let Some(stderr) = stderr {
+            transport.tasks.push(tokio::spawn(keep_stderr_tail(stderr, transport.stderr.clone())));
+        }
+        transport._child = Some(child);
+        Ok(transport)
+    }
+
+    /// Talk to a server over any pair of byte streams
+    pub(crate) fn from_streams<R, W>(reader: R, writer: W) -> Self
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        let writer: Writer = Arc::new(tokio::sync::Mutex::new(Box::new(writer)));
+        let pending = Arc::new(Mutex::new(Pending::default()));
+        let reader = tokio::spawn(read_messages(reader, writer.clone(), pending.clone()));
+        Self {
+            writer,
+            pending,
+            stderr: Arc::new(Mutex::new(VecDeque::new())),
+            tasks: vec![reader],
+            _child: None,
+        }
+    }
+
+    async fn request(&self, id: u64, message: Value) -> Result<Value> {
+        let (sender, receiver) = oneshot::channel();
+        {
+            let mut pending = self.pending.lock().unwrap();
+            if let Some(reason) = &pending.closed {
+                bail!("{}{}", reason, self.stderr_tail());
+            }
+            pending.waiting.insert(id, sender);
+        }
+        if let Err(e) = self.send(&message).await {
+            self.pending.lock().unwrap().waiting.remove(&id);
+            return Err(e);
+        }
+        match receiver.await {
+            Ok(response) => Ok(response),
+            Err(_) => {
+                let reason = self.pending.lock().unwrap().closed.clone().unwrap_or_default();
+                bail!("{}{}", reason, self.stderr_tail())
+            }
+        }
+    }
+
+    async fn send(&self, message: &Value) -> Result<()> {
+        write_message(&self.writer, message)
+            .await
+            .with_context(|| format!("Failed to write to the server{}", self.stderr_tail()))
+    }
+
+    fn stderr_tail(&self) -> String {
+        let lines = self.stderr.lock().unwrap();
+        if lines.is_empty() {
+            return String::new();
+        }
+        format!("\nServer stderr:\n{}", lines.iter().cloned().collect::<Vec<_>>().join("\n"))
+    }
+}
+
+impl Drop for StdioTransport {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+pub(crate) async fn write_message(writer: &Writer, message: &Value) -> Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    let mut writer = writer.lock().await;
+    writer.write_all(&line).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Hand each response to its waiting request and answer the server's own
+/// requests, until the server's output ends
+async fn read_messages<R: AsyncRead + Unpin>(reader: R, writer: Writer, pending: Arc<Mutex<Pending>>) {
+    let mut lines = BufReader::new(reader).lines();
+    let reason = loop {
+        match lines.next_line().await {
+            Ok(Some(line)) => {
+                // Anything that isn't JSON is a server writing logs to stdout
+                let Ok(message) = serde_json::from_str::<Value>(&line) else {
+                    continue;
+                };
+                if let Some(id) = response_id(&message) {
+                    let sender = pending.lock().unwrap().waiting.remove(&id);
+                    if let Some(sender) = sender {
+                        let _ = sender.send(message);
+                    }
+                } else if let Some(reply) = reply_to_server_request(&message) {
+                    let _ = write_message(&writer, &reply).await;
+                }
+                // Notifications (progress, logging, list changes) are not used
+            }
+            Ok(None) => break "The server closed its output".to_string(),
+            Err(e) => break format!("Reading from the server failed: {}", e),
+        }
+    };
+
+    let mut pending = pending.lock().unwrap();
+    pending.closed = Some(reason);
+    // Dropping the senders wakes every waiting request
+    pending.waiting.clear();
+}
+
+async fn keep_stderr_tail<R: AsyncRead + Unpin>(stderr: R, tail: Arc<Mutex<VecDeque<String>>>) {
+    let mut lines = BufReader::new(stderr).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let mut tail = tail.lock().unwrap();
+        if tail.len() == STDERR_LINES {
+            tail.pop_front();
+        }
+        tail.push_back(line);
+    }
+}
+
+pub(crate) struct HttpTransport {
+    client: reqwest::Client,
+    url: String,
+    headers: HeaderMap,
+    session_id: Mutex<Option<String>>,
+    protocol_version: Mutex<Option<String>>,
+}
+
+impl HttpTransport {
+    pub(crate) fn new(config: &McpServerConfig) -> Result<Self> {
+        let url = config.url.clone().ok_or_else(|| anyhow!("no URL to connect to"))?;
+        let mut headers = HeaderMap::new();
+        for (name, value) in &config.headers {
+            let name = HeaderName::from_bytes(name.as_bytes()).with_context(|| format!("Invalid header name '{}'", name))?;
+            let value = HeaderValue::from_str(value).with_context(|| format!("Invalid value for header '{}'", name))?;
+            headers.insert(name, value);
+        }
+        Ok(Self {
+            client: reqwest::Client::new(),
+            url,
+            headers,
+            session_id: Mutex::new(None),
+            protocol_version: Mutex::new(None),
+        })
+    }
+
+    /// POST one message, keeping the session id the server assigns
+    async fn post(&self, message: &Value) -> Result<reqwest::Response> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .headers(self.headers.clone())
+            .header(ACCEPT, "application/json, text/event-stream")
+            .json(message);
+        if let Some(session_id) = self.session_id.lock().unwrap().clone() {
+            request = request.header(SESSION_ID_HEADER, session_id);
+        }
+        if let Some(version) = self.protocol_version.lock().unwrap().clone() {
+            request = request.header(PROTOCOL_VERSION_HEADER, version);
+        }
+
+        let response = request.send().await.with_context(|| format!("Failed to reach {}", self.url))?;
+        if let Some(session_id) = response.headers().get(SESSION_ID_HEADER).and_then(|v| v.to_str().ok()) {
+            *self.session_id.lock().unwrap() = Some(session_id.to_string());
+        }
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            bail!("{} answered HTTP {}: {}", self.url, status, body.trim());
+        }
+        Ok(response)
+    }
+
+    /// The response comes back as the JSON body, or as an event on an SSE stream
+    async fn request(&self, id: u64, message: Value) -> Result<Value> {
+        let response = self.post(&message).await?;
+        let is_event_stream = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|content_type| content_type.starts_with("text/event-stream"));
+        if !is_event_stream {
+            return response.json().await.context("The server's response is not JSON");
+        }
+
+        let mut stream = response.bytes_stream();
+        let mut buffer: Vec<u8> = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            buffer.extend(chunk?.iter().filter(|&&b| b != b'\r'));
+            while let Some(end) = buffer.windows(2).position(|w| w == b"\n\n") {
+                let event: Vec<u8> = buffer.drain(..end + 2).collect();
+                let Some(message) = event_data(&event) else {
+                    continue;
+                };
+                if response_id(&message) == Some(id) {
+                    return Ok(message);
+                }
+                if let Some(reply) = reply_to_server_request(&message) {
+                    let _ = self.post(&reply).await;
+                }
+            }
+        }
+        bail!("The server ended the event stream without answering")
+    }
+}
+
+/// The JSON message in an SSE event's `data:` lines
+fn event_data(event: &[u8]) -> Option<Value> {
+    let event = std::str::from_utf8(event).ok()?;
+    let data: Vec<&str> = event
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(|data| data.strip_prefix(' ').unwrap_or(data))
+        .collect();
+    if data.is_empty() {
+        return None;
+    }
+    serde_json::from_str(&data.join("\n")).ok()
+}
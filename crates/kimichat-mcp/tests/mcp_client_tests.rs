@@ -0,0 +1,411 @@
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use kimichat_mcp::{McpClient, McpConfig, McpServerConfig, McpServers, McpTool};
+use kimichat_policy::PolicyManager;
+use kimichat_toolcore::{
+    Confirmation, DenyAllConfirmation, ScriptedConfirmation, Tool, ToolContext, ToolParameters, ToolRegistry,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+/// An MCP server speaking newline-delimited JSON on the other end of a pair
+/// of in-memory pipes. Every message it receives is recorded.
+fn spawn_mock_server() -> (McpStreams, Arc<Mutex<Vec<Value>>>) {
+    let (client_reader, mut server_writer) = tokio::io::duplex(64 * 1024);
+    let (server_reader, client_writer) = tokio::io::duplex(64 * 1024);
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let log = received.clone();
+
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(server_reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let message: Value = serde_json::from_str(&line).unwrap();
+            log.lock().unwrap().push(message.clone());
+            let Some(id) = message.get("id").cloned() else {
+                continue;
+            };
+            let Some(method) = message["method"].as_str() else {
+                // The client's answer to our ping
+                continue;
+            };
+
+            let result = match method {
+                "initialize" => {
+                    // Servers may log to stdout and send requests of their own
+                    server_writer.write_all(b"starting up...\n").await.unwrap();
+                    let ping = json!({ "jsonrpc": "2.0", "id": "ping-1", "method": "ping" });
+                    server_writer.write_all(format!("{}\n", ping).as_bytes()).await.unwrap();
+                    json!({
+                        "protocolVersion": "2025-06-18",
+                        "capabilities": { "tools": {} },
+                        "serverInfo": { "name": "mock", "version": "1.2.3" }
+                    })
+                }
+                "tools/list" if message["params"]["cursor"].is_null() => json!({
+                    "tools": [{
+                        "name": "echo",
+                        "description": "Echo the arguments",
+                        "inputSchema": {
+                            "$schema": "http://json-schema.org/draft-07/schema#",
+                            "type": "object",
+                            "properties": { "text": { "type": "string", "description": "What to echo" } },
+                            "required": ["text"]
+                        },
+                        "annotations": { "readOnlyHint": true }
+                    }],
+                    "nextCursor": "page-2"
+                }),
+                "tools/list" => json!({
+                    "tools": [
+                        { "name": "fail", "inputSchema": { "type": "object" } },
+                        { "name": "files.snapshot", "description": "Files as content blocks", "inputSchema": { "type": "object" } }
+                    ]
+                }),
+                "tools/call" => match message["params"]["name"].as_str().unwrap() {
+                    "echo" => json!({
+                        "content": [{ "type": "text", "text": message["params"]["arguments"]["text"] }]
+                    }),
+                    "fail" => json!({
+                        "content": [{ "type": "text", "text": "no such file" }],
+                        "isError": true
+                    }),
+                    _ => json!({
+                        "content": [
+                            { "type": "image", "data": "aGVsbG8=", "mimeType": "image/png" },
+                            { "type": "resource", "resource": { "uri": "file:///a.txt", "text": "alpha" } },
+                            { "type": "resource_link", "uri": "file:///b.txt", "name": "b.txt" },
+                            { "type": "hologram" }
+                        ]
+                    }),
+                },
+                _ => {
+                    let error = json!({ "jsonrpc": "2.0", "id": id, "error": { "code": -32601, "message": "Method not found" } });
+                    server_writer.write_all(format!("{}\n", error).as_bytes()).await.unwrap();
+                    continue;
+                }
+            };
+            let response = json!({ "jsonrpc": "2.0", "id": id, "result": result });
+            server_writer.write_all(format!("{}\n", response).as_bytes()).await.unwrap();
+        }
+    });
+
+    ((client_reader, client_writer), received)
+}
+
+type McpStreams = (tokio::io::DuplexStream, tokio::io::DuplexStream);
+
+async fn connect_mock(name: &str) -> (McpServers, Arc<Mutex<Vec<Value>>>) {
+    let ((reader, writer), received) = spawn_mock_server();
+    let client = McpClient::connect_streams(name, reader, writer).await.unwrap();
+    let tools = client.list_tools().await.unwrap();
+    let mut servers = McpServers::default();
+    servers.add(Arc::new(client), tools);
+    (servers, received)
+}
+
+fn create_test_context(policy_manager: PolicyManager) -> (TempDir, ToolContext) {
+    let temp_dir = TempDir::new().unwrap();
+    let context = ToolContext::new(temp_dir.path().to_path_buf(), "test_session".to_string(), policy_manager);
+    (temp_dir, context)
+}
+
+/// A stdio server answering initialize with $SERVER_NAME and listing no tools
+#[cfg(unix)]
+fn script_server() -> McpServerConfig {
+    let script = r#"
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+  case "$line" in
+    *'"method":"initialize"'*) printf '{"jsonrpc":"2.0","id":%s,"result":{"protocolVersion":"2025-06-18","capabilities":{},"serverInfo":{"name":"%s","version":"0"}}}\n' "$id" "$SERVER_NAME" ;;
+    *'"method":"tools/list"'*) printf '{"jsonrpc":"2.0","id":%s,"result":{"tools":[]}}\n' "$id" ;;
+  esac
+done
+"#;
+    McpServerConfig::stdio("sh", vec!["-c".to_string(), script.to_string()])
+}
+
+fn params(value: Value) -> ToolParameters {
+    let data: HashMap<String, Value> = serde_json::from_value(value).unwrap();
+    ToolParameters { data }
+}
+
+fn methods(received: &Mutex<Vec<Value>>) -> Vec<String> {
+    received
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|message| message["method"].as_str().unwrap_or("(response)").to_string())
+        .collect()
+}
+
+/// A message the HTTP endpoint received, with the session id it came with
+type SessionMessage = (Option<String>, Value);
+
+#[derive(Clone, Default)]
+struct HttpServerState {
+    received: Arc<Mutex<Vec<SessionMessage>>>,
+}
+
+/// A streamable HTTP endpoint: a JSON answer to `initialize`, which assigns
+/// the session, and SSE streams for everything after it
+async fn http_endpoint(
+    axum::extract::State(state): axum::extract::State<HttpServerState>,
+    headers: HeaderMap,
+    Json(message): Json<Value>,
+) -> Response {
+    let session = headers.get("mcp-session-id").and_then(|v| v.to_str().ok()).map(str::to_string);
+    state.received.lock().unwrap().push((session.clone(), message.clone()));
+
+    let method = message["method"].as_str().unwrap_or_default().to_string();
+    if method == "initialize" {
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": message["id"],
+            "result": { "protocolVersion": "2025-06-18", "capabilities": {}, "serverInfo": { "name": "http-mock", "version": "0.1" } }
+        });
+        return ([("mcp-session-id", "session-42")], Json(body)).into_response();
+    }
+    if session.as_deref() != Some("session-42") {
+        return (StatusCode::BAD_REQUEST, "missing session").into_response();
+    }
+    if message.get("id").is_none() {
+        return StatusCode::ACCEPTED.into_response();
+    }
+
+    let result = match method.as_str() {
+        "tools/list" => json!({ "tools": [{ "name": "add", "inputSchema": {
+            "type": "object",
+            "properties": { "a": { "type": "integer" }, "b": { "type": "integer" } },
+            "required": ["a", "b"]
+        } }] }),
+        _ => {
+            let sum = message["params"]["arguments"]["a"].as_i64().unwrap() + message["params"]["arguments"]["b"].as_i64().unwrap();
+            json!({ "content": [], "structuredContent": { "sum": sum } })
+        }
+    };
+    let progress = json!({ "jsonrpc": "2.0", "method": "notifications/progress", "params": { "progress": 1 } });
+    let response = json!({ "jsonrpc": "2.0", "id": message["id"], "result": result });
+    let body = format!("event: message\r\ndata: {}\r\n\r\nevent: message\ndata: {}\n\n", progress, response);
+    ([("content-type", "text/event-stream")], body).into_response()
+}
+
+async fn spawn_http_server() -> (String, HttpServerState) {
+    let state = HttpServerState::default();
+    let app = Router::new().route("/mcp", post(http_endpoint)).with_state(state.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (url, state)
+}
+
+#[cfg(test)]
+mod mcp_client_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_handshake_and_paginated_tool_list() {
+        let (servers, received) = connect_mock("files").await;
+
+        let client = &servers.clients()[0];
+        assert_eq!(client.server().server_info.name, "mock");
+        assert_eq!(client.server().protocol_version, "2025-06-18");
+        // The server's ping was answered, and the initialized notification sent before listing tools
+        assert_eq!(
+            methods(&received),
+            vec!["initialize", "(response)", "notifications/initialized", "tools/list", "tools/list"]
+        );
+        assert_eq!(received.lock().unwrap()[1]["id"], "ping-1");
+        assert_eq!(received.lock().unwrap()[4]["params"]["cursor"], "page-2");
+
+        let names: Vec<&str> = servers.tools().iter().map(|tool| tool.name()).collect();
+        assert_eq!(names, vec!["mcp__files__echo", "mcp__files__fail", "mcp__files__files_snapshot"]);
+        assert!(servers.errors().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_remote_schema_becomes_the_tool_definition() {
+        let (servers, _) = connect_mock("files").await;
+        let echo = &servers.tools()[0];
+
+        assert_eq!(echo.remote_name(), "echo");
+        assert_eq!(echo.server(), "files");
+        assert_eq!(echo.description(), "Echo the arguments");
+        assert!(echo.read_only());
+        assert_eq!(echo.categories(), vec!["mcp"]);
+        let schema = echo.parameters_schema();
+        assert!(schema.get("$schema").is_none());
+        assert_eq!(schema["required"], json!(["text"]));
+        assert_eq!(echo.parameters()["text"].description, "What to echo");
+        assert!(echo.parameters()["text"].required);
+
+        let fail = &servers.tools()[1];
+        assert_eq!(fail.description(), "fail (from MCP server 'files')");
+        assert_eq!(fail.parameters_schema(), json!({ "type": "object", "properties": {} }));
+        assert!(!fail.read_only());
+    }
+
+    #[tokio::test]
+    async fn test_calls_are_forwarded_and_content_mapped() {
+        let (servers, received) = connect_mock("files").await;
+        let mut registry = ToolRegistry::new();
+        servers.register_tools(&mut registry);
+        assert_eq!(registry.get_tools_by_category("mcp").len(), 3);
+        let (_dir, context) = create_test_context(PolicyManager::allow_all());
+
+        let result = registry.execute_tool("mcp__files__echo", params(json!({ "text": "hello" })), &context).await;
+        assert!(result.success);
+        assert_eq!(result.content, "hello");
+        let call = received.lock().unwrap().last().unwrap().clone();
+        assert_eq!(call["params"], json!({ "name": "echo", "arguments": { "text": "hello" } }));
+
+        let result = registry.execute_tool("mcp__files__fail", params(json!({})), &context).await;
+        assert!(!result.success);
+        assert_eq!(result.error.unwrap(), "no such file");
+
+        let result = registry.execute_tool("mcp__files__files_snapshot", params(json!({})), &context).await;
+        assert_eq!(
+            result.content,
+            "[image: image/png, 5 bytes]\n[resource: file:///a.txt]\nalpha\n[resource link: file:///b.txt (b.txt)]\n[unsupported content]"
+        );
+
+        // Arguments are validated against the server's schema before anything is sent
+        let calls = received.lock().unwrap().len();
+        let result = registry.execute_tool("mcp__files__echo", params(json!({ "text": 5 })), &context).await;
+        assert!(result.error.unwrap().contains("Invalid arguments for tool 'mcp__files__echo'"));
+        assert_eq!(received.lock().unwrap().len(), calls);
+    }
+
+    #[tokio::test]
+    async fn test_policy_is_checked_before_calling_the_server() {
+        let (servers, received) = connect_mock("files").await;
+        let (_dir, context) = create_test_context(PolicyManager::new());
+        let context = context.with_confirmation_provider(Arc::new(DenyAllConfirmation));
+        let calls = received.lock().unwrap().len();
+
+        let result = servers.tools()[0].execute(params(json!({ "text": "hello" })), &context).await;
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("cancelled"));
+        assert_eq!(received.lock().unwrap().len(), calls);
+    }
+
+    #[tokio::test]
+    async fn test_streamable_http_keeps_the_session() {
+        let (url, state) = spawn_http_server().await;
+        let mut config = McpConfig::default();
+        config.servers.insert("calc".to_string(), McpServerConfig::http(url));
+
+        // Nothing is launched, so nothing needs approving
+        let (_dir, context) = create_test_context(PolicyManager::new());
+        let servers = McpServers::connect(&config, &context.with_confirmation_provider(Arc::new(DenyAllConfirmation))).await;
+        assert!(servers.errors().is_empty(), "{:?}", servers.errors());
+        let (_dir, context) = create_test_context(PolicyManager::allow_all());
+        let result = servers.tools()[0].execute(params(json!({ "a": 2, "b": 3 })), &context).await;
+        assert!(result.success);
+        assert_eq!(serde_json::from_str::<Value>(&result.content).unwrap(), json!({ "sum": 5 }));
+
+        let received = state.received.lock().unwrap();
+        let sessions: Vec<Option<&str>> = received.iter().map(|(session, _)| session.as_deref()).collect();
+        assert_eq!(
+            sessions,
+            vec![None, Some("session-42"), Some("session-42"), Some("session-42")]
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stdio_server_process() {
+        let mut config = McpConfig::default();
+        let mut server = script_server();
+        server.env.insert("SERVER_NAME".to_string(), "from-env".to_string());
+        config.servers.insert("script".to_string(), server);
+        config.servers.insert("missing".to_string(), McpServerConfig::stdio("/nonexistent/mcp-server", Vec::new()));
+
+        let (_dir, context) = create_test_context(PolicyManager::allow_all());
+        let servers = McpServers::connect(&config, &context).await;
+        assert_eq!(servers.clients().len(), 1);
+        assert_eq!(servers.clients()[0].server().server_info.name, "from-env");
+        assert!(servers.tools().is_empty());
+        assert_eq!(servers.errors().len(), 1);
+        assert!(servers.errors()[0].starts_with("MCP server 'missing' not started: Failed to launch"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_launches_are_checked_against_the_policy() {
+        let dir = TempDir::new().unwrap();
+        let user_policy = dir.path().join("user.toml");
+        let project_policy = dir.path().join("project.toml");
+        std::fs::write(&user_policy, "[[rules]]\naction = \"mcp_server_launch\"\npattern = \"rm *\"\ndecision = \"deny\"\n").unwrap();
+        std::fs::write(&project_policy, "[[rules]]\naction = \"mcp_server_launch\"\npattern = \"sh *\"\ndecision = \"allow\"\n").unwrap();
+        let policy = || PolicyManager::layered(Some(user_policy.clone()), project_policy.clone(), true, false);
+
+        let mut config = McpConfig::default();
+        config.servers.insert("blocked".to_string(), McpServerConfig::stdio("rm", vec!["-rf".to_string(), "/".to_string()]));
+        config.servers.insert("project".to_string(), script_server());
+        config.servers.insert("user".to_string(), script_server());
+        config.project_servers.insert("project".to_string());
+
+        // A trusted project policy allows `sh`, but not for the project's own server
+        let confirmation = Arc::new(ScriptedConfirmation::new([Confirmation::Rejected { reason: Some("untrusted".to_string()) }]));
+        let (_work_dir, context) = create_test_context(policy());
+        let context = context.with_confirmation_provider(confirmation.clone());
+        let servers = McpServers::connect(&config, &context).await;
+        let started: Vec<&str> = servers.clients().iter().map(|client| client.name()).collect();
+        assert_eq!(started, vec!["user"]);
+        assert_eq!(servers.errors().len(), 2);
+        assert_eq!(servers.errors()[0], "MCP server 'blocked' not started: launching `rm -rf /` is denied by policy");
+        assert!(servers.errors()[1].starts_with("MCP server 'project' not started: launching `sh -c '"));
+        assert!(servers.errors()[1].ends_with("` was not approved: untrusted"), "{}", servers.errors()[1]);
+        let requests = confirmation.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].prompt.starts_with("Start MCP server 'project' from the project's .kimichat/mcp.toml: `sh -c"));
+
+        // Once approved, it starts
+        let confirmation = Arc::new(ScriptedConfirmation::new([Confirmation::Approved]));
+        let (_work_dir, context) = create_test_context(policy());
+        let context = context.with_confirmation_provider(confirmation);
+        config.servers.remove("blocked");
+        let servers = McpServers::connect(&config, &context).await;
+        assert!(servers.errors().is_empty(), "{:?}", servers.errors());
+        assert_eq!(servers.clients().len(), 2);
+    }
+
+    #[test]
+    fn test_config_layers_and_validation() {
+        let dir = TempDir::new().unwrap();
+        let user_file = dir.path().join("user.toml");
+        let project_file = dir.path().join("project.toml");
+        std::fs::write(
+            &user_file,
+            "[servers.github]\ncommand = \"github-mcp\"\n\n[servers.docs]\nurl = \"http://127.0.0.1:1/mcp\"\n",
+        )
+        .unwrap();
+        std::fs::write(&project_file, "[servers.github]\ncommand = \"npx\"\nargs = [\"github-mcp\"]\nenabled = false\n").unwrap();
+
+        let config = McpConfig::layered(Some(user_file), project_file).unwrap();
+        assert_eq!(config.servers["github"].command.as_deref(), Some("npx"));
+        assert!(config.is_project_server("github") && !config.is_project_server("docs"));
+        assert_eq!(config.servers["github"].command_line().as_deref(), Some("npx github-mcp"));
+        assert_eq!(config.servers["docs"].command_line(), None);
+        let quoted = McpServerConfig::stdio("sh", vec!["-c".to_string(), "echo 'hi' there".to_string()]);
+        assert_eq!(quoted.command_line().as_deref(), Some("sh -c 'echo '\\''hi'\\'' there'"));
+        let enabled: Vec<&String> = config.enabled_servers().map(|(name, _)| name).collect();
+        assert_eq!(enabled, vec!["docs"]);
+        assert!(McpConfig::load(&dir.path().join("none.toml")).unwrap().servers.is_empty());
+
+        let both = dir.path().join("both.toml");
+        std::fs::write(&both, "[servers.x]\ncommand = \"a\"\nurl = \"http://b\"\n").unwrap();
+        assert!(format!("{:#}", McpConfig::load(&both).unwrap_err()).contains("not both"));
+        let bad_name = dir.path().join("bad_name.toml");
+        std::fs::write(&bad_name, "[servers.\"a__b\"]\ncommand = \"a\"\n").unwrap();
+        assert!(McpConfig::load(&bad_name).is_err());
+
+        assert_eq!(McpTool::qualified_name("gh", "repo/list issues"), "mcp__gh__repo_list_issues");
+        assert_eq!(McpTool::qualified_name("gh", &"x".repeat(100)).len(), 64);
+    }
+}
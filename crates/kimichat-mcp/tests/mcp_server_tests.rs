@@ -0,0 +1,244 @@
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use kimichat_mcp::{
+    McpClient, McpServer, McpServers, Prompt, PromptArgument, Resource, ResourceContents, ServerContent,
+};
+use kimichat_policy::PolicyManager;
+use kimichat_toolcore::{ParameterDefinition, Tool, ToolContext, ToolParameters, ToolRegistry, ToolResult};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+
+/// Shouts its text back, or fails when asked to
+struct ShoutTool;
+
+#[async_trait]
+impl Tool for ShoutTool {
+    fn name(&self) -> &str {
+        "shout"
+    }
+
+    fn description(&self) -> &str {
+        "Repeat text in capitals"
+    }
+
+    fn parameters(&self) -> HashMap<String, ParameterDefinition> {
+        let mut parameters = HashMap::new();
+        parameters.insert(
+            "text".to_string(),
+            ParameterDefinition { param_type: "string".to_string(), description: "What to shout".to_string(), required: true, ..Default::default() },
+        );
+        parameters
+    }
+
+    fn read_only(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, params: ToolParameters, _context: &ToolContext) -> ToolResult {
+        match params.get_required::<String>("text") {
+            Ok(text) if text == "fail" => ToolResult::error("Refusing to shout".to_string()),
+            Ok(text) => ToolResult::success(text.to_uppercase()),
+            Err(e) => ToolResult::error(e.to_string()),
+        }
+    }
+}
+
+/// One prompt, and a screen resource whose text and existence tests control
+#[derive(Default)]
+struct TestContent {
+    screen: Mutex<Option<String>>,
+}
+
+#[async_trait]
+impl ServerContent for TestContent {
+    async fn prompts(&self) -> Vec<Prompt> {
+        vec![Prompt {
+            name: "review".to_string(),
+            description: Some("Review a change".to_string()),
+            arguments: vec![PromptArgument { name: "task".to_string(), description: None, required: false }],
+        }]
+    }
+
+    async fn get_prompt(&self, name: &str, arguments: &HashMap<String, String>) -> Result<String> {
+        if name != "review" {
+            bail!("Unknown prompt '{}'", name);
+        }
+        Ok(format!("Review carefully.\n\nTask: {}", arguments.get("task").map_or("none", String::as_str)))
+    }
+
+    async fn resources(&self) -> Vec<Resource> {
+        match &*self.screen.lock().unwrap() {
+            Some(_) => vec![Resource {
+                uri: "pty://1/screen".to_string(),
+                name: "Terminal 1".to_string(),
+                description: None,
+                mime_type: Some("text/plain".to_string()),
+            }],
+            None => Vec::new(),
+        }
+    }
+
+    async fn read_resource(&self, uri: &str) -> Result<ResourceContents> {
+        match (uri, &*self.screen.lock().unwrap()) {
+            ("pty://1/screen", Some(text)) => Ok(ResourceContents {
+                uri: uri.to_string(),
+                mime_type: Some("text/plain".to_string()),
+                text: text.clone(),
+            }),
+            _ => bail!("Unknown resource '{}'", uri),
+        }
+    }
+}
+
+/// Serve a registry with `ShoutTool` on one end of a pair of in-memory pipes,
+/// returning the other end
+fn spawn_server(content: Arc<TestContent>) -> (TempDir, McpStreams) {
+    let temp_dir = TempDir::new().unwrap();
+    let context = ToolContext::new(temp_dir.path().to_path_buf(), "test_session".to_string(), PolicyManager::allow_all());
+    let mut registry = ToolRegistry::new();
+    registry.register(ShoutTool);
+    let server = McpServer::new(registry, context).with_content(content).with_poll_interval(Duration::from_millis(10));
+
+    let (client_reader, server_writer) = tokio::io::duplex(64 * 1024);
+    let (server_reader, client_writer) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(server.serve(server_reader, server_writer));
+    (temp_dir, (client_reader, client_writer))
+}
+
+type McpStreams = (tokio::io::DuplexStream, tokio::io::DuplexStream);
+
+async fn send(writer: &mut tokio::io::DuplexStream, message: Value) {
+    writer.write_all(format!("{}\n", message).as_bytes()).await.unwrap();
+}
+
+/// The next message with the given method, skipping others
+async fn next_with_method(lines: &mut Lines<BufReader<tokio::io::DuplexStream>>, method: &str) -> Value {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let line = lines.next_line().await.unwrap().unwrap();
+            let message: Value = serde_json::from_str(&line).unwrap();
+            if message["method"] == method {
+                return message;
+            }
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("no {} notification", method))
+}
+
+#[cfg(test)]
+mod mcp_server_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tools_round_trip_through_the_client() {
+        let (_dir, (reader, writer)) = spawn_server(Arc::new(TestContent::default()));
+        let client = McpClient::connect_streams("kimichat", reader, writer).await.unwrap();
+        assert_eq!(client.server().server_info.name, "kimichat");
+        assert_eq!(client.server().protocol_version, "2025-06-18");
+
+        let tools = client.list_tools().await.unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].description.as_deref(), Some("Repeat text in capitals"));
+        assert_eq!(tools[0].input_schema["required"], json!(["text"]));
+        assert_eq!(tools[0].annotations.read_only_hint, Some(true));
+
+        // Mounted as a client would mount any other server's tools
+        let mut servers = McpServers::default();
+        servers.add(Arc::new(client), tools);
+        let tool = &servers.tools()[0];
+        assert_eq!(tool.name(), "mcp__kimichat__shout");
+        assert!(tool.read_only());
+        let dir = TempDir::new().unwrap();
+        let context = ToolContext::new(dir.path().to_path_buf(), "client".to_string(), PolicyManager::allow_all());
+        let mut params = ToolParameters::new();
+        params.set("text", "hello");
+        let result = tool.execute(params, &context).await;
+        assert!(result.success);
+        assert_eq!(result.content, "HELLO");
+
+        let client = &servers.clients()[0];
+        let result = client.call_tool("shout", json!({ "text": "fail" })).await.unwrap();
+        assert!(result.is_error);
+        assert_eq!(result.into_tool_result().error.unwrap(), "Refusing to shout");
+
+        // Arguments are validated by the registry before the tool runs
+        let result = client.call_tool("shout", json!({ "text": 5 })).await.unwrap();
+        assert!(result.is_error);
+        assert!(result.into_tool_result().error.unwrap().contains("Invalid arguments for tool 'shout'"));
+
+        let error = client.call_tool("whisper", json!({})).await.unwrap_err();
+        assert!(format!("{:#}", error).contains("Unknown tool: whisper (code -32602)"));
+    }
+
+    #[tokio::test]
+    async fn test_prompts_and_resources() {
+        let content = Arc::new(TestContent::default());
+        *content.screen.lock().unwrap() = Some("$ ls\nCargo.toml".to_string());
+        let (_dir, (reader, writer)) = spawn_server(content);
+        let client = McpClient::connect_streams("kimichat", reader, writer).await.unwrap();
+
+        let prompts = client.request("prompts/list", json!({})).await.unwrap();
+        assert_eq!(
+            prompts["prompts"],
+            json!([{ "name": "review", "description": "Review a change", "arguments": [{ "name": "task", "required": false }] }])
+        );
+        let prompt = client.request("prompts/get", json!({ "name": "review", "arguments": { "task": "fix the build" } })).await.unwrap();
+        assert_eq!(prompt["description"], "Review a change");
+        assert_eq!(
+            prompt["messages"],
+            json!([{ "role": "user", "content": { "type": "text", "text": "Review carefully.\n\nTask: fix the build" } }])
+        );
+        assert!(client.request("prompts/get", json!({ "name": "other" })).await.is_err());
+
+        let resources = client.request("resources/list", json!({})).await.unwrap();
+        assert_eq!(
+            resources["resources"],
+            json!([{ "uri": "pty://1/screen", "name": "Terminal 1", "mimeType": "text/plain" }])
+        );
+        let read = client.request("resources/read", json!({ "uri": "pty://1/screen" })).await.unwrap();
+        assert_eq!(read["contents"], json!([{ "uri": "pty://1/screen", "mimeType": "text/plain", "text": "$ ls\nCargo.toml" }]));
+        let error = client.request("resources/read", json!({ "uri": "pty://9/screen" })).await.unwrap_err();
+        assert!(format!("{:#}", error).contains("code -32002"));
+
+        let error = client.request("sampling/createMessage", json!({})).await.unwrap_err();
+        assert!(format!("{:#}", error).contains("code -32601"));
+    }
+
+    #[tokio::test]
+    async fn test_subscribed_resources_send_updates() {
+        let content = Arc::new(TestContent::default());
+        let (_dir, (reader, mut writer)) = spawn_server(content.clone());
+        let mut lines = BufReader::new(reader).lines();
+
+        send(&mut writer, json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": { "protocolVersion": "2024-11-05" } })).await;
+        let initialized = serde_json::from_str::<Value>(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(initialized["result"]["protocolVersion"], "2024-11-05");
+        assert_eq!(initialized["result"]["capabilities"]["resources"]["subscribe"], true);
+
+        // A terminal opens
+        *content.screen.lock().unwrap() = Some("$ ".to_string());
+        next_with_method(&mut lines, "notifications/resources/list_changed").await;
+
+        send(&mut writer, json!({ "jsonrpc": "2.0", "id": 2, "method": "resources/subscribe", "params": { "uri": "pty://1/screen" } })).await;
+        let subscribed = serde_json::from_str::<Value>(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(subscribed, json!({ "jsonrpc": "2.0", "id": 2, "result": {} }));
+
+        *content.screen.lock().unwrap() = Some("$ make\nok".to_string());
+        let updated = next_with_method(&mut lines, "notifications/resources/updated").await;
+        assert_eq!(updated["params"]["uri"], "pty://1/screen");
+
+        // Lines that aren't JSON get a parse error, and the session carries on
+        writer.write_all(b"not json\n").await.unwrap();
+        let error = serde_json::from_str::<Value>(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(error["error"]["code"], -32700);
+        assert_eq!(error["id"], Value::Null);
+        send(&mut writer, json!({ "jsonrpc": "2.0", "id": 3, "method": "ping" })).await;
+        let pong = serde_json::from_str::<Value>(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(pong, json!({ "jsonrpc": "2.0", "id": 3, "result": {} }));
+    }
+}
@@ -0,0 +1,323 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::tokenizer::{BpeTokenizer, HeuristicTokenizer, Tokenizer};
+
+/// Embedded default registry, shipped with the binary
+const DEFAULT_MODELS_TOML: &str = include_str!("../models.toml");
+
+/// Where the tiktoken rank files are published
+const ENCODINGS_URL: &str = "https://openaipublic.blob.core.windows.net/encodings";
+
+/// A rank file that was read, or why it could not be
+type LoadedTokenizer = Result<Arc<BpeTokenizer>, String>;
+
+/// What a model can do and what it costs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelCapabilities {
+    /// Total context window in tokens (prompt + completion)
+    pub context_window: usize,
+    /// Maximum number of tokens the model may generate in one response
+    pub max_output_tokens: usize,
+    /// Whether the model can be given tools
+    pub supports_tools: bool,
+    /// Whether the model's API can stream responses
+    pub supports_streaming: bool,
+    /// Price in USD per million prompt tokens
+    pub input_price_per_mtok: f64,
+    /// Price in USD per million completion tokens
+    pub output_price_per_mtok: f64,
+    /// Price in USD per million prompt tokens written to the prompt cache
+    /// (the input price when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_write_price_per_mtok: Option<f64>,
+    /// Price in USD per million prompt tokens read from the prompt cache
+    /// (the input price when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_price_per_mtok: Option<f64>,
+    /// BPE encoding the model uses (e.g. "cl100k_base"); token counts fall
+    /// back to a character heuristic when unset or when the rank file is missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokenizer: Option<String>,
+}
+
+impl Default for ModelCapabilities {
+    /// Conservative values for models the registry knows nothing about
+    fn default() -> Self {
+        Self {
+            context_window: 32_768,
+            max_output_tokens: 4_096,
+            supports_tools: true,
+            supports_streaming: true,
+            input_price_per_mtok: 0.0,
+            output_price_per_mtok: 0.0,
+            cache_write_price_per_mtok: None,
+            cache_read_price_per_mtok: None,
+            tokenizer: None,
+        }
+    }
+}
+
+impl ModelCapabilities {
+    /// Tokens available for the prompt once room for a full response is reserved
+    pub fn input_token_budget(&self) -> usize {
+        self.context_window.saturating_sub(self.max_output_tokens)
+    }
+
+    /// Whether any pricing is known for this model
+    pub fn has_pricing(&self) -> bool {
+        self.input_price_per_mtok > 0.0 || self.output_price_per_mtok > 0.0
+    }
+
+    /// Cost in USD of a request with the given token counts
+    pub fn cost(&self, prompt_tokens: usize, completion_tokens: usize) -> f64 {
+        self.cost_with_cache(prompt_tokens, completion_tokens, 0, 0)
+    }
+
+    /// Cost in USD of a request whose prompt was partly written to or read
+    /// from the prompt cache; `prompt_tokens` includes the cached tokens
+    pub fn cost_with_cache(
+        &self,
+        prompt_tokens: usize,
+        completion_tokens: usize,
+        cache_write_tokens: usize,
+        cache_read_tokens: usize,
+    ) -> f64 {
+        let uncached = prompt_tokens.saturating_sub(cache_write_tokens + cache_read_tokens);
+        let write_price = self.cache_write_price_per_mtok.unwrap_or(self.input_price_per_mtok);
+        let read_price = self.cache_read_price_per_mtok.unwrap_or(self.input_price_per_mtok);
+
+        (uncached as f64 * self.input_price_per_mtok
+            + cache_write_tokens as f64 * write_price
+            + cache_read_tokens as f64 * read_price
+            + completion_tokens as f64 * self.output_price_per_mtok)
+            / 1_000_000.0
+    }
+}
+
+/// Entry as written in a TOML file, where every field is optional so that
+/// user files can override a single value of a built-in entry
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CapabilitiesOverride {
+    context_window: Option<usize>,
+    max_output_tokens: Option<usize>,
+    supports_tools: Option<bool>,
+    supports_streaming: Option<bool>,
+    input_price_per_mtok: Option<f64>,
+    output_price_per_mtok: Option<f64>,
+    cache_write_price_per_mtok: Option<f64>,
+    cache_read_price_per_mtok: Option<f64>,
+    tokenizer: Option<String>,
+}
+
+impl CapabilitiesOverride {
+    fn apply_to(self, mut base: ModelCapabilities) -> ModelCapabilities {
+        if let Some(v) = self.context_window { base.context_window = v; }
+        if let Some(v) = self.max_output_tokens { base.max_output_tokens = v; }
+        if let Some(v) = self.supports_tools { base.supports_tools = v; }
+        if let Some(v) = self.supports_streaming { base.supports_streaming = v; }
+        if let Some(v) = self.input_price_per_mtok { base.input_price_per_mtok = v; }
+        if let Some(v) = self.output_price_per_mtok { base.output_price_per_mtok = v; }
+        if let Some(v) = self.cache_write_price_per_mtok { base.cache_write_price_per_mtok = Some(v); }
+        if let Some(v) = self.cache_read_price_per_mtok { base.cache_read_price_per_mtok = Some(v); }
+        if let Some(v) = self.tokenizer { base.tokenizer = Some(v); }
+        base
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RegistryFile {
+    #[serde(default)]
+    default: Option<CapabilitiesOverride>,
+    #[serde(default)]
+    models: HashMap<String, CapabilitiesOverride>,
+}
+
+/// Registry of model capabilities keyed by model name
+///
+/// Names containing `*` are wildcard patterns (e.g. `claude-sonnet-4*`). A
+/// lookup tries the exact name first, then the pattern with the most literal
+/// characters that matches, and finally the registry's default entry.
+#[derive(Debug, Clone, Default)]
+pub struct ModelRegistry {
+    models: HashMap<String, ModelCapabilities>,
+    default: ModelCapabilities,
+    /// Directory holding `<encoding>.tiktoken` rank files
+    tokenizer_dir: Option<PathBuf>,
+    /// Rank files loaded so far (or why loading failed), shared between clones
+    tokenizers: Arc<Mutex<HashMap<String, LoadedTokenizer>>>,
+    /// (model, tokenizer) pairs already reported, shared between clones
+    announced: Arc<Mutex<HashSet<(String, String)>>>,
+    /// Reports not yet collected with `take_tokenizer_notices`
+    notices: Arc<Mutex<Vec<String>>>,
+}
+
+impl ModelRegistry {
+    /// Create an empty registry where every model gets the conservative defaults
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a registry with the built-in entries
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry
+            .merge_toml(DEFAULT_MODELS_TOML)
+            .expect("embedded models.toml is valid");
+        registry
+    }
+
+    /// Parse a registry from TOML, on top of the conservative defaults
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let mut registry = Self::new();
+        registry.merge_toml(content)?;
+        Ok(registry)
+    }
+
+    /// Overlay entries from TOML onto this registry
+    ///
+    /// An entry for a name the registry already resolves (exactly or via a
+    /// pattern) only replaces the fields it sets.
+    pub fn merge_toml(&mut self, content: &str) -> Result<()> {
+        let file: RegistryFile = toml::from_str(content).context("Failed to parse model registry")?;
+
+        if let Some(default) = file.default {
+            self.default = default.apply_to(self.default.clone());
+        }
+
+        // Exact names resolve against patterns, so apply patterns first
+        let (patterns, names): (Vec<_>, Vec<_>) = file.models
+            .into_iter()
+            .partition(|(name, _)| name.contains('*'));
+        for (name, entry) in patterns.into_iter().chain(names) {
+            let base = self.get(&name).clone();
+            self.models.insert(name, entry.apply_to(base));
+        }
+        Ok(())
+    }
+
+    /// Overlay entries from a TOML file onto this registry
+    pub fn load_file(&mut self, path: &Path) -> Result<()> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read model registry {}", path.display()))?;
+        self.merge_toml(&content)
+            .with_context(|| format!("Invalid model registry {}", path.display()))
+    }
+
+    /// Add or replace an entry
+    pub fn insert(&mut self, name: impl Into<String>, capabilities: ModelCapabilities) {
+        self.models.insert(name.into(), capabilities);
+    }
+
+    /// Whether the registry has an entry (exact or pattern) for this model
+    pub fn is_known(&self, model: &str) -> bool {
+        self.lookup(model).is_some()
+    }
+
+    /// Capabilities for a model, falling back to the default entry
+    pub fn get(&self, model: &str) -> &ModelCapabilities {
+        self.lookup(model).unwrap_or(&self.default)
+    }
+
+    /// Look for tokenizer rank files in this directory
+    pub fn set_tokenizer_dir(&mut self, dir: impl Into<PathBuf>) {
+        self.tokenizer_dir = Some(dir.into());
+    }
+
+    /// Tokenizer for counting a model's tokens
+    ///
+    /// Returns the model's BPE encoding when its rank file is available, and
+    /// the character heuristic otherwise. The first time a model gets a
+    /// tokenizer, which one and why is queued for `take_tokenizer_notices`.
+    pub fn tokenizer(&self, model: &str) -> Arc<dyn Tokenizer> {
+        let (tokenizer, description) = self.resolve_tokenizer(model);
+        let key = (model.to_string(), tokenizer.name().to_string());
+        if self.announced.lock().unwrap().insert(key) {
+            self.notices.lock().unwrap().push(description);
+        }
+        tokenizer
+    }
+
+    /// Which tokenizer counts a model's tokens, and why
+    pub fn describe_tokenizer(&self, model: &str) -> String {
+        self.resolve_tokenizer(model).1
+    }
+
+    /// Reports of the tokenizer chosen for each model since the last call
+    pub fn take_tokenizer_notices(&self) -> Vec<String> {
+        std::mem::take(&mut *self.notices.lock().unwrap())
+    }
+
+    fn resolve_tokenizer(&self, model: &str) -> (Arc<dyn Tokenizer>, String) {
+        let heuristic = |reason: String| {
+            let tokenizer = Arc::new(HeuristicTokenizer::default()) as Arc<dyn Tokenizer>;
+            (tokenizer, format!("Estimating tokens for {} from characters: {}", model, reason))
+        };
+        let Some(encoding) = &self.get(model).tokenizer else {
+            return heuristic("no BPE encoding is known for this model".to_string());
+        };
+        let Some(dir) = &self.tokenizer_dir else {
+            return heuristic("no tokenizer directory is set".to_string());
+        };
+        let path = dir.join(format!("{}.tiktoken", encoding));
+
+        let mut tokenizers = self.tokenizers.lock().unwrap();
+        let loaded = match tokenizers.get(encoding) {
+            Some(loaded) => loaded.clone(),
+            // Not cached, so the file can be added during the session
+            None if !path.exists() => {
+                return heuristic(format!(
+                    "{} not found; for exact counts download it from {}/{}.tiktoken",
+                    path.display(),
+                    ENCODINGS_URL,
+                    encoding
+                ));
+            }
+            None => {
+                let loaded = BpeTokenizer::load(&path).map(Arc::new).map_err(|e| format!("{:#}", e));
+                tokenizers.insert(encoding.clone(), loaded.clone());
+                loaded
+            }
+        };
+        match loaded {
+            Ok(tokenizer) => (tokenizer, format!("Counting tokens for {} with the {} encoding", model, encoding)),
+            Err(e) => heuristic(e),
+        }
+    }
+
+    fn lookup(&self, model: &str) -> Option<&ModelCapabilities> {
+        if let Some(capabilities) = self.models.get(model) {
+            return Some(capabilities);
+        }
+
+        self.models
+            .iter()
+            .filter(|(pattern, _)| pattern.contains('*') && wildcard_match(pattern, model))
+            .max_by_key(|(pattern, _)| (pattern.len() - pattern.matches('*').count(), pattern.as_str()))
+            .map(|(_, capabilities)| capabilities)
+    }
+}
+
+/// Match `text` against a pattern where `*` stands for any run of characters
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+
+    if text.len() < first.len() + last.len() || !text.starts_with(first) || !text.ends_with(last) {
+        return false;
+    }
+
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
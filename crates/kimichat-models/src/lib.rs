@@ -1,12 +1,16 @@
 // Models module - data structures for API communication
+pub mod capabilities;
 pub mod requests;
 pub mod responses;
+pub mod tokenizer;
 pub mod types;
 
 #[cfg(test)]
 mod tests;
 
 // Re-export commonly used types
+pub use capabilities::{ModelCapabilities, ModelRegistry};
 pub use requests::{ChatRequest, FunctionDef, Tool};
+pub use tokenizer::{BpeTokenizer, HeuristicTokenizer, Tokenizer};
 pub use responses::{ChatResponse, StreamChunk, Usage};
-pub use types::{FunctionCall, Message, ModelColor, ModelProvider, BackendType, SwitchModelArgs, ToolCall, ModelConfig};
+pub use types::{FunctionCall, Message, ModelColor, ModelProvider, BackendType, SwitchModelArgs, ToolCall, ModelConfig, ReasoningConfig, ReasoningEffort};
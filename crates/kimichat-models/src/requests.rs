@@ -1,4 +1,4 @@
-use super::types::Message;
+use super::types::{Message, ReasoningEffort};
 use serde::{Deserialize, Serialize};
 
 /// Tool definition for chat API
@@ -23,6 +23,12 @@ pub struct ChatRequest {
     pub model: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
+    /// Completion token limit, sized so prompt plus completion fit the context window
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<usize>,
+    /// Reasoning effort for reasoning models such as gpt-oss
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<ReasoningEffort>,
     pub tool_choice: String,
     pub tools: Vec<Tool>,
     pub messages: Vec<Message>,
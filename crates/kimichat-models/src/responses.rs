@@ -2,11 +2,18 @@ use super::types::Message;
 use serde::{Deserialize, Serialize};
 
 /// Token usage information from API response
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct Usage {
+    /// All prompt tokens, including those written to or read from the prompt cache
     pub prompt_tokens: usize,
     pub completion_tokens: usize,
     pub total_tokens: usize,
+    /// Prompt tokens written to the provider's prompt cache
+    #[serde(default)]
+    pub cache_creation_input_tokens: usize,
+    /// Prompt tokens served from the provider's prompt cache
+    #[serde(default)]
+    pub cache_read_input_tokens: usize,
 }
 
 /// Chat API response structure
@@ -55,6 +62,16 @@ pub struct StreamChunk {
     pub model: Option<String>,
     #[serde(default)]
     pub usage: Option<Usage>,
+    /// Groq-specific extensions (Groq reports streaming usage here)
+    #[serde(default)]
+    pub x_groq: Option<GroqStreamExtensions>,
+}
+
+/// Groq-specific fields attached to streaming chunks
+#[derive(Debug, Deserialize)]
+pub struct GroqStreamExtensions {
+    #[serde(default)]
+    pub usage: Option<Usage>,
 }
 
 /// Choice structure within streaming chunk
@@ -74,7 +91,8 @@ pub struct StreamDelta {
     pub role: Option<String>,
     #[serde(default)]
     pub content: Option<String>,
-    #[serde(default)]
+    /// Reasoning text (`reasoning_content`, or `reasoning` on Groq)
+    #[serde(default, alias = "reasoning")]
     pub reasoning_content: Option<String>,
     #[serde(default)]
     pub tool_calls: Option<Vec<StreamToolCallDelta>>,
@@ -0,0 +1,97 @@
+#[cfg(test)]
+mod tests {
+    use crate::{ModelCapabilities, ModelRegistry};
+
+    #[test]
+    fn test_embedded_defaults_cover_color_defaults() {
+        let registry = ModelRegistry::with_defaults();
+
+        for model in ["moonshotai/kimi-k2-instruct-0905", "openai/gpt-oss-120b", "meta-llama/llama-3.1-70b-versatile"] {
+            assert!(registry.is_known(model), "{} should be in the embedded registry", model);
+            assert!(registry.get(model).has_pricing());
+        }
+        assert_eq!(registry.get("openai/gpt-oss-120b").context_window, 131_072);
+    }
+
+    #[test]
+    fn test_lookup_prefers_exact_then_most_specific_pattern() {
+        let registry = ModelRegistry::from_toml(r#"
+            [models."claude-*"]
+            context_window = 100000
+            [models."claude-sonnet-4*"]
+            context_window = 200000
+            [models."claude-sonnet-4-special"]
+            context_window = 50000
+        "#).unwrap();
+
+        assert_eq!(registry.get("claude-3-opus").context_window, 100_000);
+        assert_eq!(registry.get("claude-sonnet-4-20250514").context_window, 200_000);
+        assert_eq!(registry.get("claude-sonnet-4-special").context_window, 50_000);
+
+        assert!(!registry.is_known("mystery-model"));
+        assert_eq!(registry.get("mystery-model"), &ModelCapabilities::default());
+    }
+
+    #[test]
+    fn test_overrides_only_replace_fields_they_set() {
+        let mut registry = ModelRegistry::with_defaults();
+        let before = registry.get("openai/gpt-oss-120b").clone();
+
+        registry.merge_toml(r#"
+            [default]
+            context_window = 16384
+
+            [models."openai/gpt-oss-120b"]
+            input_price_per_mtok = 0.5
+
+            [models."my-finetune"]
+            supports_tools = false
+        "#).unwrap();
+
+        let after = registry.get("openai/gpt-oss-120b");
+        assert_eq!(after.input_price_per_mtok, 0.5);
+        assert_eq!(after.context_window, before.context_window);
+        assert_eq!(after.output_price_per_mtok, before.output_price_per_mtok);
+
+        // New entries start from the default entry
+        let finetune = registry.get("my-finetune");
+        assert!(!finetune.supports_tools);
+        assert_eq!(finetune.context_window, 16_384);
+    }
+
+    #[test]
+    fn test_unknown_fields_are_rejected() {
+        let err = ModelRegistry::from_toml("[models.\"x\"]\ncontext_windw = 5\n").err().unwrap();
+        assert!(format!("{:#}", err).contains("context_windw"));
+    }
+
+    #[test]
+    fn test_cost_and_input_budget() {
+        let capabilities = ModelCapabilities {
+            context_window: 128_000,
+            max_output_tokens: 16_000,
+            input_price_per_mtok: 2.0,
+            output_price_per_mtok: 8.0,
+            ..ModelCapabilities::default()
+        };
+
+        assert_eq!(capabilities.input_token_budget(), 112_000);
+        assert!((capabilities.cost(500_000, 250_000) - 3.0).abs() < 1e-9);
+        assert!(!ModelCapabilities::default().has_pricing());
+    }
+
+    #[test]
+    fn test_cache_prices_default_to_input_price() {
+        let mut capabilities = ModelCapabilities {
+            input_price_per_mtok: 3.0,
+            output_price_per_mtok: 15.0,
+            ..ModelCapabilities::default()
+        };
+        assert!((capabilities.cost_with_cache(1_000_000, 0, 200_000, 600_000) - 3.0).abs() < 1e-9);
+
+        capabilities.cache_write_price_per_mtok = Some(3.75);
+        capabilities.cache_read_price_per_mtok = Some(0.3);
+        // 200k uncached at $3, 200k written at $3.75, 600k read at $0.30
+        assert!((capabilities.cost_with_cache(1_000_000, 0, 200_000, 600_000) - 1.53).abs() < 1e-9);
+    }
+}
@@ -1,2 +1,4 @@
 pub mod model_resolution_tests;
 pub mod model_provider_tests;
+pub mod capabilities_tests;
+pub mod tokenizer_tests;
@@ -63,4 +63,27 @@ mod tests {
         assert_eq!(provider.api_url, None);
         assert_eq!(provider.api_key, None);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_model_provider_fallback_chain() {
+        let mut provider = ModelProvider::with_config(
+            "kimi".to_string(),
+            Some(BackendType::Groq),
+            None,
+            None,
+        );
+        assert!(!provider.has_fallbacks());
+        assert_eq!(provider.chain().count(), 1);
+
+        provider.fallbacks.push(ModelProvider::with_config(
+            "llama3".to_string(),
+            None,
+            Some("http://localhost:8080".to_string()),
+            None,
+        ));
+        provider.fallbacks.push(ModelProvider::new("claude".to_string()));
+
+        let labels: Vec<String> = provider.chain().map(|p| p.label()).collect();
+        assert_eq!(labels, vec!["kimi@groq", "llama3@http://localhost:8080", "claude"]);
+    }
+}
@@ -0,0 +1,121 @@
+#[cfg(test)]
+mod tests {
+    use crate::tokenizer::pre_tokenize;
+    use crate::{BpeTokenizer, HeuristicTokenizer, Message, ModelRegistry, Tokenizer};
+    use base64::Engine;
+
+    /// Rank file with every single byte plus a few merges that spell "hello"
+    fn tiny_ranks() -> String {
+        let engine = base64::engine::general_purpose::STANDARD;
+        let merges: [&[u8]; 4] = [b"he", b"ll", b"llo", b"hello"];
+
+        (0u8..=255).map(|b| vec![b])
+            .chain(merges.iter().map(|m| m.to_vec()))
+            .enumerate()
+            .map(|(rank, token)| format!("{} {}", engine.encode(token), rank))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn message(role: &str, content: &str) -> Message {
+        Message {
+            role: role.to_string(),
+            content: content.to_string(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+            reasoning: None,
+            reasoning_details: None,
+        }
+    }
+
+    #[test]
+    fn test_pre_tokenize_follows_cl100k_rules() {
+        assert_eq!(
+            pre_tokenize("Hello world, it's 12345!\n\n  done"),
+            vec!["Hello", " world", ",", " it", "'s", " ", "123", "45", "!\n\n", " ", " done"]
+        );
+        assert_eq!(pre_tokenize("a  \n\tb"), vec!["a", "  \n", "\tb"]);
+        assert_eq!(pre_tokenize("").len(), 0);
+    }
+
+    #[test]
+    fn test_bpe_merges_by_rank() {
+        let tokenizer = BpeTokenizer::from_tiktoken("tiny", &tiny_ranks()).unwrap();
+
+        assert_eq!(tokenizer.count_tokens("hello"), 1);
+        assert_eq!(tokenizer.count_tokens("hel"), 2);
+        // " hello" has no merge for the leading space
+        assert_eq!(tokenizer.count_tokens("hello hello"), 3);
+        assert_eq!(tokenizer.count_tokens("xyz"), 3);
+    }
+
+    #[test]
+    fn test_bpe_rejects_malformed_rank_file() {
+        assert!(BpeTokenizer::from_tiktoken("bad", "aGk= notanumber").is_err());
+        assert!(BpeTokenizer::from_tiktoken("bad", "no-rank-here").is_err());
+    }
+
+    #[test]
+    fn test_message_tokens_include_overhead() {
+        let tokenizer = HeuristicTokenizer::default();
+        assert_eq!(tokenizer.count_tokens("12345678"), 2);
+        assert_eq!(tokenizer.count_tokens("123456789"), 3);
+
+        // 3 per message + content, plus 3 to prime the reply
+        let messages = [message("system", "12345678"), message("user", "1234")];
+        assert_eq!(tokenizer.count_message_tokens(&messages), 3 + 2 + 3 + 1 + 3);
+    }
+
+    #[test]
+    fn test_registry_loads_tokenizer_rank_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("tiny.tiktoken"), tiny_ranks()).unwrap();
+
+        let mut registry = ModelRegistry::from_toml(r#"
+            [models."bpe-model"]
+            tokenizer = "tiny"
+            [models."missing-model"]
+            tokenizer = "not_downloaded"
+        "#).unwrap();
+
+        // Without a tokenizer directory everything is estimated
+        assert_eq!(registry.tokenizer("bpe-model").name(), "heuristic");
+        assert_eq!(
+            registry.describe_tokenizer("bpe-model"),
+            "Estimating tokens for bpe-model from characters: no tokenizer directory is set"
+        );
+
+        registry.set_tokenizer_dir(dir.path());
+        assert_eq!(registry.tokenizer("bpe-model").name(), "tiny");
+        assert_eq!(registry.tokenizer("bpe-model").count_tokens("hello"), 1);
+        assert_eq!(registry.describe_tokenizer("bpe-model"), "Counting tokens for bpe-model with the tiny encoding");
+        assert_eq!(registry.tokenizer("missing-model").name(), "heuristic");
+        assert_eq!(
+            registry.describe_tokenizer("missing-model"),
+            format!(
+                "Estimating tokens for missing-model from characters: {} not found; for exact counts download it \
+                 from https://openaipublic.blob.core.windows.net/encodings/not_downloaded.tiktoken",
+                dir.path().join("not_downloaded.tiktoken").display()
+            )
+        );
+        assert_eq!(registry.tokenizer("unknown").name(), "heuristic");
+        assert!(registry.describe_tokenizer("unknown").ends_with("no BPE encoding is known for this model"));
+
+        // Each model's tokenizer is reported once, and again when it changes
+        let notices = registry.take_tokenizer_notices();
+        assert_eq!(notices.len(), 4, "{:?}", notices);
+        assert!(notices[1].starts_with("Counting tokens for bpe-model"));
+        registry.tokenizer("bpe-model");
+        assert!(registry.take_tokenizer_notices().is_empty());
+
+        // A broken rank file is reported with the reason
+        std::fs::write(dir.path().join("broken.tiktoken"), "not base64 1\n").unwrap();
+        let mut registry = ModelRegistry::from_toml("[models.\"broken-model\"]\ntokenizer = \"broken\"").unwrap();
+        registry.set_tokenizer_dir(dir.path());
+        assert_eq!(registry.tokenizer("broken-model").name(), "heuristic");
+        let notices = registry.take_tokenizer_notices();
+        assert!(notices[0].starts_with("Estimating tokens for broken-model from characters: "), "{:?}", notices);
+        assert!(notices[0].contains("broken.tiktoken"), "{:?}", notices);
+    }
+}
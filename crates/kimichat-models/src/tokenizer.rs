@@ -0,0 +1,242 @@
+use anyhow::{Context, Result};
+use base64::Engine;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::types::Message;
+
+/// Tokens every chat message costs on top of its content (role and separators)
+const TOKENS_PER_MESSAGE: usize = 3;
+/// Tokens the server adds to prime the assistant's reply
+const TOKENS_PER_REPLY: usize = 3;
+
+/// Counts how many tokens a model would see for a piece of text
+pub trait Tokenizer: Send + Sync {
+    /// Name of the encoding, e.g. "cl100k_base" or "heuristic"
+    fn name(&self) -> &str;
+
+    /// Number of tokens `text` encodes to
+    fn count_tokens(&self, text: &str) -> usize;
+
+    /// Number of tokens a list of chat messages takes up in a prompt
+    fn count_message_tokens(&self, messages: &[Message]) -> usize {
+        let content: usize = messages.iter().map(|message| {
+            let mut tokens = TOKENS_PER_MESSAGE + self.count_tokens(&message.content);
+            if let Some(name) = &message.name {
+                tokens += self.count_tokens(name);
+            }
+            for call in message.tool_calls.iter().flatten() {
+                tokens += TOKENS_PER_MESSAGE
+                    + self.count_tokens(&call.function.name)
+                    + self.count_tokens(&call.function.arguments);
+            }
+            tokens
+        }).sum();
+
+        content + TOKENS_PER_REPLY
+    }
+}
+
+/// Character-count estimate for models without a known encoding
+#[derive(Debug, Clone)]
+pub struct HeuristicTokenizer {
+    chars_per_token: f64,
+}
+
+impl HeuristicTokenizer {
+    pub fn new(chars_per_token: f64) -> Self {
+        Self { chars_per_token }
+    }
+}
+
+impl Default for HeuristicTokenizer {
+    /// About four characters per token, which holds for English text and code
+    fn default() -> Self {
+        Self::new(4.0)
+    }
+}
+
+impl Tokenizer for HeuristicTokenizer {
+    fn name(&self) -> &str {
+        "heuristic"
+    }
+
+    fn count_tokens(&self, text: &str) -> usize {
+        (text.chars().count() as f64 / self.chars_per_token).ceil() as usize
+    }
+}
+
+/// Byte-pair encoding tokenizer using a tiktoken rank file
+///
+/// Rank files (e.g. `cl100k_base.tiktoken`) have one base64-encoded token and
+/// its merge rank per line. Text is split with the cl100k pre-tokenization
+/// rules and each piece is merged by rank, the same way tiktoken does.
+pub struct BpeTokenizer {
+    name: String,
+    ranks: HashMap<Vec<u8>, u32>,
+}
+
+impl std::fmt::Debug for BpeTokenizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BpeTokenizer")
+            .field("name", &self.name)
+            .field("vocab_size", &self.ranks.len())
+            .finish()
+    }
+}
+
+impl BpeTokenizer {
+    /// Parse a tiktoken rank file
+    pub fn from_tiktoken(name: impl Into<String>, content: &str) -> Result<Self> {
+        let engine = base64::engine::general_purpose::STANDARD;
+        let mut ranks = HashMap::new();
+
+        for (line_number, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (token, rank) = line.split_once(' ')
+                .with_context(|| format!("Malformed rank on line {}", line_number + 1))?;
+            let token = engine.decode(token)
+                .with_context(|| format!("Invalid base64 token on line {}", line_number + 1))?;
+            let rank: u32 = rank.trim().parse()
+                .with_context(|| format!("Invalid rank on line {}", line_number + 1))?;
+            ranks.insert(token, rank);
+        }
+
+        Ok(Self { name: name.into(), ranks })
+    }
+
+    /// Load a tiktoken rank file, named after the file stem
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read tokenizer {}", path.display()))?;
+        let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("bpe");
+        Self::from_tiktoken(name, &content)
+            .with_context(|| format!("Invalid tokenizer {}", path.display()))
+    }
+
+    /// Number of tokens a single pre-tokenized piece merges into
+    fn count_piece(&self, piece: &[u8]) -> usize {
+        if piece.len() < 2 || self.ranks.contains_key(piece) {
+            return 1;
+        }
+
+        let rank_of = |bytes: &[u8]| self.ranks.get(bytes).copied().unwrap_or(u32::MAX);
+
+        // (start offset, rank of merging this part with the next one)
+        let mut parts: Vec<(usize, u32)> = (0..piece.len() - 1)
+            .map(|i| (i, rank_of(&piece[i..i + 2])))
+            .collect();
+        parts.push((piece.len() - 1, u32::MAX));
+        parts.push((piece.len(), u32::MAX));
+
+        let merged_rank = |parts: &[(usize, u32)], i: usize| {
+            if i + 3 < parts.len() {
+                rank_of(&piece[parts[i].0..parts[i + 3].0])
+            } else {
+                u32::MAX
+            }
+        };
+
+        while let Some((i, _)) = parts[..parts.len() - 1]
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, rank))| *rank != u32::MAX)
+            .min_by_key(|(_, (_, rank))| *rank)
+        {
+            if i > 0 {
+                parts[i - 1].1 = merged_rank(&parts, i - 1);
+            }
+            parts[i].1 = merged_rank(&parts, i);
+            parts.remove(i + 1);
+        }
+
+        parts.len() - 1
+    }
+}
+
+impl Tokenizer for BpeTokenizer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn count_tokens(&self, text: &str) -> usize {
+        pre_tokenize(text)
+            .into_iter()
+            .map(|piece| self.count_piece(piece.as_bytes()))
+            .sum()
+    }
+}
+
+/// Split text into the pieces BPE merges within, following the cl100k pattern:
+/// contractions, words with one leading non-letter, numbers of up to three
+/// digits, punctuation runs, and whitespace (with the last space of a run
+/// left to lead the next word)
+pub(crate) fn pre_tokenize(text: &str) -> Vec<&str> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let offset = |i: usize| chars.get(i).map_or(text.len(), |(offset, _)| *offset);
+    let char_at = |i: usize| chars.get(i).map(|(_, c)| *c);
+    let is_newline = |c: char| c == '\r' || c == '\n';
+    let is_punctuation = |c: char| !c.is_whitespace() && !c.is_alphanumeric();
+
+    let mut pieces = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i].1;
+        let next = char_at(i + 1);
+
+        let end = if let Some(len) = contraction_len(&text[offset(i)..]) {
+            i + len
+        } else if c.is_alphabetic() || (!is_newline(c) && !c.is_numeric() && next.is_some_and(char::is_alphabetic)) {
+            let mut j = i + 1;
+            while char_at(j).is_some_and(char::is_alphabetic) {
+                j += 1;
+            }
+            j
+        } else if c.is_numeric() {
+            let mut j = i + 1;
+            while j < i + 3 && char_at(j).is_some_and(char::is_numeric) {
+                j += 1;
+            }
+            j
+        } else if is_punctuation(c) || (c == ' ' && next.is_some_and(is_punctuation)) {
+            let mut j = if c == ' ' { i + 1 } else { i };
+            while char_at(j).is_some_and(is_punctuation) {
+                j += 1;
+            }
+            while char_at(j).is_some_and(is_newline) {
+                j += 1;
+            }
+            j
+        } else {
+            let mut run_end = i;
+            while char_at(run_end).is_some_and(char::is_whitespace) {
+                run_end += 1;
+            }
+            let last_newline = (i..run_end).rev().find(|&j| is_newline(chars[j].1));
+            match last_newline {
+                Some(j) => j + 1,
+                None if run_end == chars.len() || run_end - i == 1 => run_end,
+                None => run_end - 1,
+            }
+        };
+
+        pieces.push(&text[offset(i)..offset(end)]);
+        i = end;
+    }
+    pieces
+}
+
+/// Length in chars of an English contraction suffix ('s, 're, ...) at the start of `text`
+fn contraction_len(text: &str) -> Option<usize> {
+    let rest = text.strip_prefix('\'')?;
+    let lower: String = rest.chars().take(2).collect::<String>().to_lowercase();
+    if ["re", "ve", "ll"].iter().any(|suffix| lower.starts_with(suffix)) {
+        Some(3)
+    } else if ["s", "t", "m", "d"].iter().any(|suffix| lower.starts_with(suffix)) {
+        Some(2)
+    } else {
+        None
+    }
+}
@@ -7,6 +7,10 @@ pub enum BackendType {
     Anthropic,
     Llama,
     OpenAI,
+    /// Ollama's native /api/chat endpoint
+    Ollama,
+    /// Any OpenAI-compatible server that needs no API key (vLLM, LM Studio, LocalAI)
+    OpenAICompatible,
 }
 
 impl BackendType {
@@ -17,6 +21,9 @@ impl BackendType {
             "anthropic" | "claude" => Some(Self::Anthropic),
             "llama" | "llamacpp" | "llama.cpp" | "llama-cpp" => Some(Self::Llama),
             "openai" => Some(Self::OpenAI),
+            "ollama" => Some(Self::Ollama),
+            "openai-compatible" | "openai_compatible" | "openai-compat" | "compatible"
+            | "vllm" | "lmstudio" | "lm-studio" | "localai" => Some(Self::OpenAICompatible),
             _ => None,
         }
     }
@@ -28,6 +35,73 @@ impl BackendType {
             Self::Anthropic => "anthropic",
             Self::Llama => "llama",
             Self::OpenAI => "openai",
+            Self::Ollama => "ollama",
+            Self::OpenAICompatible => "openai-compatible",
+        }
+    }
+}
+
+/// Effort level for OpenAI-style reasoning models (`reasoning_effort`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningEffort {
+    Low,
+    Medium,
+    High,
+}
+
+impl ReasoningEffort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        }
+    }
+}
+
+/// How much a provider's model should reason before answering
+///
+/// Anthropic takes a thinking budget in tokens and OpenAI-style APIs take an
+/// effort level; either form is translated for the other kind of provider.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReasoningConfig {
+    BudgetTokens(u32),
+    Effort(ReasoningEffort),
+}
+
+impl ReasoningConfig {
+    /// Smallest thinking budget Anthropic accepts
+    pub const MIN_BUDGET_TOKENS: u32 = 1024;
+
+    /// Parse an effort level ("low", "medium", "high") or a token budget ("16000")
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "low" => Some(Self::Effort(ReasoningEffort::Low)),
+            "medium" => Some(Self::Effort(ReasoningEffort::Medium)),
+            "high" => Some(Self::Effort(ReasoningEffort::High)),
+            other => other.parse().ok().map(Self::BudgetTokens),
+        }
+    }
+
+    /// Thinking budget in tokens, for providers that take one
+    pub fn budget_tokens(&self) -> u32 {
+        match self {
+            Self::BudgetTokens(tokens) => (*tokens).max(Self::MIN_BUDGET_TOKENS),
+            Self::Effort(ReasoningEffort::Low) => 2048,
+            Self::Effort(ReasoningEffort::Medium) => 8192,
+            Self::Effort(ReasoningEffort::High) => 24576,
+        }
+    }
+
+    /// Effort level, for providers that take one
+    pub fn effort(&self) -> ReasoningEffort {
+        match self {
+            Self::Effort(effort) => *effort,
+            Self::BudgetTokens(tokens) if *tokens < 4096 => ReasoningEffort::Low,
+            Self::BudgetTokens(tokens) if *tokens < 16384 => ReasoningEffort::Medium,
+            Self::BudgetTokens(_) => ReasoningEffort::High,
         }
     }
 }
@@ -51,6 +125,12 @@ pub struct ModelProvider {
     pub api_url: Option<String>,
     /// API key for the provider
     pub api_key: Option<String>,
+    /// Providers to try, in order, when this one is unavailable
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallbacks: Vec<ModelProvider>,
+    /// Reasoning budget or effort to request, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<ReasoningConfig>,
 }
 
 impl std::fmt::Debug for ModelProvider {
@@ -66,6 +146,8 @@ impl std::fmt::Debug for ModelProvider {
             .field("backend", &self.backend)
             .field("api_url", &self.api_url)
             .field("api_key", &masked_key)
+            .field("fallbacks", &self.fallbacks)
+            .field("reasoning", &self.reasoning)
             .finish()
     }
 }
@@ -78,6 +160,8 @@ impl ModelProvider {
             backend: None,
             api_url: None,
             api_key: None,
+            fallbacks: Vec::new(),
+            reasoning: None,
         }
     }
     
@@ -88,6 +172,27 @@ impl ModelProvider {
             backend,
             api_url,
             api_key,
+            fallbacks: Vec::new(),
+            reasoning: None,
+        }
+    }
+
+    /// Whether any fallback providers are configured
+    pub fn has_fallbacks(&self) -> bool {
+        !self.fallbacks.is_empty()
+    }
+
+    /// This provider followed by its fallbacks, in the order they should be tried
+    pub fn chain(&self) -> impl Iterator<Item = &ModelProvider> {
+        std::iter::once(self).chain(self.fallbacks.iter())
+    }
+
+    /// Short human-readable name such as "kimi@groq" or "llama3@http://localhost:8080"
+    pub fn label(&self) -> String {
+        match (&self.backend, &self.api_url) {
+            (Some(backend), _) => format!("{}@{}", self.model_name, backend.as_str()),
+            (None, Some(url)) => format!("{}@{}", self.model_name, url),
+            (None, None) => self.model_name.clone(),
         }
     }
 }
@@ -103,6 +208,8 @@ pub struct ModelConfig {
     pub api_key: Option<String>,
     /// Model name override for this model
     pub model: Option<String>,
+    /// Reasoning effort ("low", "medium", "high") or thinking budget in tokens
+    pub reasoning: Option<String>,
 }
 
 impl ModelColor {
@@ -226,6 +333,10 @@ pub struct Message {
     pub name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub reasoning: Option<String>,
+    /// Provider blocks that must be sent back unchanged with this message,
+    /// such as Anthropic's signed and redacted thinking
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub reasoning_details: Option<Vec<serde_json::Value>>,
 }
 
 /// Tool call structure
@@ -0,0 +1,108 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::{ActionType, Decision, PolicyContext, PolicyRule, PolicyScope, SessionType};
+
+/// One line of the audit log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: String,
+    pub workspace: Option<String>,
+    pub action: ActionType,
+    pub target: String,
+    pub decision: Decision,
+    /// Layer the deciding rule (or default) came from
+    pub scope: PolicyScope,
+    /// The matching rule, absent when the default decided
+    pub rule: Option<PolicyRule>,
+    pub agent: Option<String>,
+    pub session_type: Option<SessionType>,
+    /// `policy` for rule and default decisions, otherwise whoever answered the confirmation
+    pub answered_by: String,
+    pub reason: Option<String>,
+}
+
+/// Append-only JSONL log of policy decisions
+#[derive(Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+    workspace: Option<String>,
+    // Serializes appends so concurrent tool calls do not interleave lines
+    lock: Mutex<()>,
+}
+
+impl AuditLog {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            workspace: None,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Record the workspace in every entry, since one log covers all projects
+    pub fn with_workspace(mut self, workspace: &Path) -> Self {
+        self.workspace = Some(workspace.display().to_string());
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn entry(
+        &self,
+        action: &ActionType,
+        target: &str,
+        decision: Decision,
+        scope: PolicyScope,
+        rule: Option<PolicyRule>,
+        context: &PolicyContext,
+        answered_by: &str,
+        reason: Option<String>,
+    ) -> AuditEntry {
+        AuditEntry {
+            timestamp: chrono::Local::now().to_rfc3339(),
+            workspace: self.workspace.clone(),
+            action: action.clone(),
+            target: target.to_string(),
+            decision,
+            scope,
+            rule,
+            agent: context.agent.clone(),
+            session_type: context.session_type,
+            answered_by: answered_by.to_string(),
+            reason,
+        }
+    }
+
+    pub fn append(&self, entry: &AuditEntry) -> Result<()> {
+        let line = serde_json::to_string(entry)?;
+        let _guard = self.lock.lock().unwrap();
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open policy audit log: {}", self.path.display()))?;
+        writeln!(file, "{}", line)?;
+        Ok(())
+    }
+
+    /// Read every entry back, for tests and tooling
+    pub fn read_entries(&self) -> Result<Vec<AuditEntry>> {
+        let content = std::fs::read_to_string(&self.path)?;
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(Into::into))
+            .collect()
+    }
+}
@@ -0,0 +1,172 @@
+/// A command line split into the simple commands the shell would run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ShellCommand {
+    /// argv of every simple command, in order
+    pub segments: Vec<Vec<String>>,
+    /// The line chains commands, uses subshells or substitutions, or redirects
+    pub chained: bool,
+    /// Part of the line cannot be inspected (substitution inside double quotes,
+    /// unbalanced quotes). Such commands never match an allow rule.
+    pub opaque: bool,
+}
+
+/// Split `command` at unquoted control operators (`;`, `&`, `|`, newlines,
+/// parentheses, backticks and `$(`) and parse each piece into argv
+pub(crate) fn parse(command: &str) -> ShellCommand {
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    let mut chained = false;
+    let mut opaque = false;
+    let mut in_single = false;
+    let mut in_double = false;
+    let mut chars = command.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_single {
+            current.push(c);
+            if c == '\'' {
+                in_single = false;
+            }
+            continue;
+        }
+        if in_double {
+            current.push(c);
+            match c {
+                '\\' => {
+                    if let Some(next) = chars.next() {
+                        current.push(next);
+                    }
+                }
+                '"' => in_double = false,
+                // Still expanded inside double quotes, and we cannot split there
+                '`' => opaque = true,
+                '$' if chars.peek() == Some(&'(') => opaque = true,
+                _ => {}
+            }
+            continue;
+        }
+
+        match c {
+            '\\' => {
+                current.push(c);
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            '\'' => {
+                in_single = true;
+                current.push(c);
+            }
+            '"' => {
+                in_double = true;
+                current.push(c);
+            }
+            ';' | '&' | '|' | '\n' | '(' | ')' | '`' => {
+                if c == '&' && chars.peek() == Some(&'>') {
+                    // `&>` redirects both streams
+                    chained = true;
+                    current.push(c);
+                    continue;
+                }
+                chained |= matches!(c, '(' | ')' | '`');
+                pieces.push(std::mem::take(&mut current));
+            }
+            '$' if chars.peek() == Some(&'(') => {
+                chained = true;
+                pieces.push(std::mem::take(&mut current));
+            }
+            '>' | '<' => {
+                chained = true;
+                current.push(c);
+                // `2>&1` and friends stay part of the redirection
+                if chars.peek() == Some(&'&') {
+                    current.push(chars.next().unwrap_or('&'));
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+    pieces.push(current);
+    if in_single || in_double {
+        opaque = true;
+    }
+
+    let mut segments = Vec::new();
+    for piece in pieces {
+        match shell_words::split(&piece) {
+            Ok(argv) if argv.is_empty() => {}
+            Ok(argv) => segments.push(argv),
+            Err(_) => opaque = true,
+        }
+    }
+    chained |= segments.len() > 1;
+
+    ShellCommand { segments, chained, opaque }
+}
+
+/// The command an argv runs, past leading `NAME=value` assignments and an
+/// `env` prefix carrying them
+pub(crate) fn command_words(argv: &[String]) -> &[String] {
+    let mut rest = argv;
+    if rest.first().is_some_and(|word| word == "env") {
+        rest = &rest[1..];
+    }
+    while rest.first().is_some_and(|word| is_assignment(word)) {
+        rest = &rest[1..];
+    }
+    if rest.len() == argv.len() {
+        argv
+    } else {
+        rest
+    }
+}
+
+/// Whether a word is a variable assignment such as `PATH=/tmp`
+fn is_assignment(word: &str) -> bool {
+    let Some((name, _)) = word.split_once('=') else {
+        return false;
+    };
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Match a pattern argv against a command argv. A `*` argument matches any
+/// number of arguments; other arguments may use `*` as a wildcard within them.
+pub(crate) fn argv_match(pattern: &[String], argv: &[String]) -> bool {
+    match pattern.split_first() {
+        None => argv.is_empty(),
+        Some((first, rest)) if first == "*" => {
+            (0..=argv.len()).any(|skip| argv_match(rest, &argv[skip..]))
+        }
+        Some((first, rest)) => match argv.split_first() {
+            Some((arg, argv_rest)) => wildcard_match(first, arg) && argv_match(rest, argv_rest),
+            None => false,
+        },
+    }
+}
+
+/// `*` matches any run of characters, everything else matches literally
+pub(crate) fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
@@ -0,0 +1,216 @@
+//! Procedural macros for kimichat-toolcore
+//!
+//! Use them through `kimichat_toolcore`, which re-exports them along with the
+//! support code the generated impls call.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::meta::ParseNestedMeta;
+use syn::{parse_macro_input, Attribute, Expr, ExprArray, ExprLit, FnArg, ItemFn, Lit, LitStr, Meta};
+
+/// Declare a tool as an async function over a typed argument struct
+///
+/// ```ignore
+/// use kimichat_toolcore::{tool, ToolContext, ToolResult};
+/// use schemars::JsonSchema;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, JsonSchema)]
+/// struct GreetArgs {
+///     /// Who to greet
+///     name: String,
+///     /// How many times
+///     times: Option<u32>,
+/// }
+///
+/// /// Greet someone by name
+/// #[tool(categories = ["examples"])]
+/// async fn greet(args: GreetArgs, context: &ToolContext) -> ToolResult {
+///     ToolResult::success(format!("Hello, {}!", args.name).repeat(args.times.unwrap_or(1) as usize))
+/// }
+///
+/// registry.register(GreetTool);
+/// ```
+///
+/// This keeps the function and adds a unit struct named after it (`GreetTool`)
+/// that implements `Tool`:
+/// - the tool name is the function name, or `name = "..."`
+/// - the description is the function's doc comment, or `description = "..."`
+/// - the parameters come from the argument struct's JSON Schema, so field
+///   types, doc comments, enums and `Option`s are described to the model
+/// - the arguments are deserialized into the struct before the function is
+///   called; if that fails the model gets serde's error and the function
+///   does not run
+/// - `ToolRegistry::register` files the tool under `categories = [...]`
+/// - `read_only` marks a tool that changes nothing, so its calls may run
+///   concurrently
+///
+/// The `&ToolContext` parameter can be left out if the tool doesn't need it.
+/// The crate using the macro needs `serde` and `schemars` for the derives.
+#[proc_macro_attribute]
+pub fn tool(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut options = ToolOptions::default();
+    let parser = syn::meta::parser(|meta| options.parse(meta));
+    parse_macro_input!(attr with parser);
+    let function = parse_macro_input!(item as ItemFn);
+
+    match expand(options, function) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+#[derive(Default)]
+struct ToolOptions {
+    name: Option<LitStr>,
+    description: Option<LitStr>,
+    categories: Vec<LitStr>,
+    read_only: bool,
+}
+
+impl ToolOptions {
+    fn parse(&mut self, meta: ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("name") {
+            self.name = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("description") {
+            self.description = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("read_only") {
+            self.read_only = true;
+        } else if meta.path.is_ident("categories") {
+            let categories: ExprArray = meta.value()?.parse()?;
+            for category in categories.elems {
+                match category {
+                    Expr::Lit(ExprLit { lit: Lit::Str(category), .. }) => self.categories.push(category),
+                    other => return Err(syn::Error::new_spanned(other, "expected a string literal")),
+                }
+            }
+        } else {
+            return Err(meta.error("unknown #[tool] option, expected `name`, `description`, `categories` or `read_only`"));
+        }
+        Ok(())
+    }
+}
+
+fn expand(options: ToolOptions, function: ItemFn) -> syn::Result<TokenStream2> {
+    let sig = &function.sig;
+    let fn_ident = &sig.ident;
+    if sig.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(sig.fn_token, "a #[tool] function must be async"));
+    }
+
+    let args_type = match sig.inputs.first() {
+        Some(FnArg::Typed(args)) => &args.ty,
+        Some(receiver @ FnArg::Receiver(_)) => {
+            return Err(syn::Error::new_spanned(receiver, "a #[tool] function cannot take `self`"));
+        }
+        None => {
+            return Err(syn::Error::new_spanned(
+                &sig.ident,
+                "a #[tool] function takes its argument struct, and optionally `&ToolContext`",
+            ));
+        }
+    };
+    let call = match sig.inputs.len() {
+        1 => quote! { #fn_ident(args).await },
+        2 => quote! { #fn_ident(args, context).await },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &sig.inputs,
+                "a #[tool] function takes its argument struct, and optionally `&ToolContext`",
+            ));
+        }
+    };
+
+    let name = options.name.map(|name| name.value()).unwrap_or_else(|| fn_ident.to_string());
+    let description = match options.description {
+        Some(description) => description.value(),
+        None => doc_comment(&function.attrs).ok_or_else(|| {
+            syn::Error::new_spanned(
+                &sig.ident,
+                "a #[tool] function needs a doc comment or `description = \"...\"` to describe it to the model",
+            )
+        })?,
+    };
+    let categories = options.categories.iter().map(LitStr::value);
+    let read_only = options.read_only;
+
+    let vis = &function.vis;
+    let struct_ident = format_ident!("{}Tool", pascal_case(&fn_ident.to_string()));
+    let struct_doc = format!("Tool generated by `#[tool]` from [`{}`]", fn_ident);
+
+    Ok(quote! {
+        #function
+
+        #[doc = #struct_doc]
+        #[derive(Debug, Clone, Copy, Default)]
+        #vis struct #struct_ident;
+
+        #[::kimichat_toolcore::__private::async_trait]
+        impl ::kimichat_toolcore::Tool for #struct_ident {
+            fn name(&self) -> &str {
+                #name
+            }
+
+            fn description(&self) -> &str {
+                #description
+            }
+
+            fn parameters(&self) -> ::std::collections::HashMap<::std::string::String, ::kimichat_toolcore::ParameterDefinition> {
+                ::kimichat_toolcore::typed::parameters_for::<#args_type>()
+            }
+
+            fn categories(&self) -> ::std::vec::Vec<::std::string::String> {
+                ::std::vec![#(::std::string::String::from(#categories)),*]
+            }
+
+            fn read_only(&self) -> bool {
+                #read_only
+            }
+
+            async fn execute(
+                &self,
+                params: ::kimichat_toolcore::ToolParameters,
+                context: &::kimichat_toolcore::ToolContext,
+            ) -> ::kimichat_toolcore::ToolResult {
+                let _ = context;
+                let args = match ::kimichat_toolcore::typed::parse_arguments::<#args_type>(#name, params) {
+                    Ok(args) => args,
+                    Err(e) => return ::kimichat_toolcore::ToolResult::error(e),
+                };
+                #call
+            }
+        }
+    })
+}
+
+/// The doc comment's text, with the space after each `///` removed
+fn doc_comment(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(doc) => match &doc.value {
+                Expr::Lit(ExprLit { lit: Lit::Str(line), .. }) => Some(line.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .map(|line| line.strip_prefix(' ').map(str::to_string).unwrap_or(line))
+        .collect();
+    let text = lines.join("\n").trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+fn pascal_case(snake: &str) -> String {
+    snake
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
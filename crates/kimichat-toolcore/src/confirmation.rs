@@ -0,0 +1,160 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use colored::Colorize;
+use kimichat_policy::ActionType;
+use rustyline::DefaultEditor;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// An action the policy wants the user to approve
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfirmationRequest {
+    pub action: ActionType,
+    /// What the policy matched: a workspace-relative file path or a command line
+    pub target: String,
+    /// Question to show the user
+    pub prompt: String,
+}
+
+/// The user's answer to a confirmation request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Confirmation {
+    Approved,
+    /// Approved, and the same action on the same target is allowed for the rest of the session
+    ApprovedForSession,
+    Rejected { reason: Option<String> },
+}
+
+/// Asks the user to approve actions the policy marks as "ask"
+///
+/// Implementations approve only on an explicit answer. When nobody can answer
+/// (closed input, timeout, unattended run) they reject.
+#[async_trait]
+pub trait ConfirmationProvider: Send + Sync + std::fmt::Debug {
+    async fn confirm(&self, request: &ConfirmationRequest) -> Result<Confirmation>;
+
+    /// Who answers, as recorded in the policy audit log
+    fn name(&self) -> &str;
+}
+
+/// Prompts on the terminal
+#[derive(Debug, Default)]
+pub struct TerminalConfirmation;
+
+#[async_trait]
+impl ConfirmationProvider for TerminalConfirmation {
+    async fn confirm(&self, request: &ConfirmationRequest) -> Result<Confirmation> {
+        println!("\n{}", request.prompt.bright_green().bold());
+        println!("{}", "(y)es, (n)o, or (s)ession to allow this for the rest of the session".dimmed());
+
+        let mut rl = DefaultEditor::new()?;
+        let answer = match rl.readline(">>> ") {
+            Ok(answer) => answer.trim().to_lowercase(),
+            // Ctrl-C or closed input
+            Err(_) => return Ok(Confirmation::Rejected { reason: None }),
+        };
+
+        if answer.is_empty() || answer == "y" || answer == "yes" {
+            return Ok(Confirmation::Approved);
+        }
+        if answer == "s" || answer == "session" {
+            return Ok(Confirmation::ApprovedForSession);
+        }
+
+        // Ask for reason if rejected
+        println!("{}", "Why not? (optional - helps the AI understand):".bright_yellow());
+        let reason = rl
+            .readline(">>> ")
+            .ok()
+            .map(|reason| reason.trim().to_string())
+            .filter(|reason| !reason.is_empty());
+        Ok(Confirmation::Rejected { reason })
+    }
+
+    fn name(&self) -> &str {
+        "terminal"
+    }
+}
+
+/// Rejects every request, for unattended runs such as CI
+#[derive(Debug, Default)]
+pub struct DenyAllConfirmation;
+
+#[async_trait]
+impl ConfirmationProvider for DenyAllConfirmation {
+    async fn confirm(&self, request: &ConfirmationRequest) -> Result<Confirmation> {
+        Ok(Confirmation::Rejected {
+            reason: Some(format!(
+                "{} on '{}' needs confirmation and nobody is available to confirm it",
+                request.action, request.target
+            )),
+        })
+    }
+
+    fn name(&self) -> &str {
+        "deny_all"
+    }
+}
+
+/// Passes requests on to another provider one at a time, so tools running
+/// concurrently don't prompt the user over each other
+#[derive(Debug)]
+pub struct SerialConfirmation {
+    inner: Arc<dyn ConfirmationProvider>,
+    turn: tokio::sync::Mutex<()>,
+}
+
+impl SerialConfirmation {
+    pub fn new(inner: Arc<dyn ConfirmationProvider>) -> Self {
+        Self { inner, turn: tokio::sync::Mutex::new(()) }
+    }
+}
+
+#[async_trait]
+impl ConfirmationProvider for SerialConfirmation {
+    async fn confirm(&self, request: &ConfirmationRequest) -> Result<Confirmation> {
+        let _turn = self.turn.lock().await;
+        self.inner.confirm(request).await
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+}
+
+/// Answers from a fixed script, for tests
+///
+/// Every request is recorded. Once the script runs out, requests are rejected.
+#[derive(Debug, Default)]
+pub struct ScriptedConfirmation {
+    answers: Mutex<VecDeque<Confirmation>>,
+    requests: Mutex<Vec<ConfirmationRequest>>,
+}
+
+impl ScriptedConfirmation {
+    pub fn new(answers: impl IntoIterator<Item = Confirmation>) -> Self {
+        Self {
+            answers: Mutex::new(answers.into_iter().collect()),
+            requests: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Requests received so far, in order
+    pub fn requests(&self) -> Vec<ConfirmationRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl ConfirmationProvider for ScriptedConfirmation {
+    async fn confirm(&self, request: &ConfirmationRequest) -> Result<Confirmation> {
+        self.requests.lock().unwrap().push(request.clone());
+        Ok(self.answers.lock().unwrap().pop_front().unwrap_or(Confirmation::Rejected {
+            reason: Some("No scripted answer left".to_string()),
+        }))
+    }
+
+    fn name(&self) -> &str {
+        "scripted"
+    }
+}
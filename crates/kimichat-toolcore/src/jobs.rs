@@ -0,0 +1,396 @@
+//! Running shell commands with a time limit and live output, in the
+//! foreground or as background jobs
+
+use crate::output::{DiscardOutput, OutputSink, OutputStream};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::process::{ExitStatus, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncReadExt;
+use tokio::process::{Child, Command as AsyncCommand};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+/// How long to keep reading after the command exits, for output still held
+/// by processes it left running
+const READER_GRACE: Duration = Duration::from_secs(1);
+
+/// Most output a background job keeps per stream; older output is dropped
+pub const DEFAULT_JOB_OUTPUT_LIMIT: usize = 1024 * 1024;
+
+/// Finished background jobs kept for the job tools; older ones are forgotten
+/// when new jobs start
+pub const DEFAULT_FINISHED_JOBS_KEPT: usize = 20;
+
+/// What a finished command printed and how it ended
+#[derive(Debug, Clone, Default)]
+pub struct CommandOutput {
+    pub exit_code: Option<i32>,
+    /// Signal that ended the command, on Unix
+    pub signal: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    /// Killed at the time limit
+    pub timed_out: bool,
+    /// Killed on request
+    pub killed: bool,
+}
+
+impl CommandOutput {
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0) && !self.timed_out && !self.killed
+    }
+}
+
+#[derive(Debug, Default)]
+struct Captured {
+    stdout: String,
+    stderr: String,
+    /// Most bytes kept per stream, when bounded; the newest output is kept
+    limit: Option<usize>,
+    /// Bytes dropped from the front of the streams to stay within `limit`
+    dropped: usize,
+}
+
+/// A started command whose output is being read
+struct Running {
+    child: Child,
+    captured: Arc<Mutex<Captured>>,
+    readers: Vec<JoinHandle<()>>,
+}
+
+impl Running {
+    fn start(mut cmd: AsyncCommand, sink: Arc<dyn OutputSink>, limit: Option<usize>) -> Result<Self> {
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        // Lead a process group so a kill also reaches what the command started
+        #[cfg(unix)]
+        cmd.process_group(0);
+
+        let mut child = cmd.spawn()?;
+        let captured = Arc::new(Mutex::new(Captured { limit, ..Captured::default() }));
+        let readers = vec![
+            read_stream(child.stdout.take(), OutputStream::Stdout, sink.clone(), captured.clone()),
+            read_stream(child.stderr.take(), OutputStream::Stderr, sink, captured.clone()),
+        ];
+        Ok(Self { child, captured, readers })
+    }
+
+    async fn finish(mut self, timeout: Duration, kill: Arc<Notify>) -> Result<CommandOutput> {
+        let mut timed_out = false;
+        let mut killed = false;
+        let status = tokio::select! {
+            status = self.child.wait() => status?,
+            _ = tokio::time::sleep(timeout) => {
+                timed_out = true;
+                self.stop().await?
+            }
+            _ = kill.notified() => {
+                killed = true;
+                self.stop().await?
+            }
+        };
+
+        let deadline = tokio::time::sleep(READER_GRACE);
+        tokio::pin!(deadline);
+        for reader in &mut self.readers {
+            tokio::select! {
+                _ = &mut *reader => {}
+                _ = &mut deadline => reader.abort(),
+            }
+        }
+
+        let captured = self.captured.lock().unwrap();
+        Ok(CommandOutput {
+            exit_code: status.code(),
+            signal: signal(&status),
+            stdout: captured.stdout.clone(),
+            stderr: captured.stderr.clone(),
+            timed_out,
+            killed,
+        })
+    }
+
+    async fn stop(&mut self) -> Result<ExitStatus> {
+        kill_process_group(self.child.id());
+        self.child.kill().await.ok();
+        Ok(self.child.wait().await?)
+    }
+}
+
+/// Run a command to completion, sending its output to `sink` as it arrives.
+/// The command and everything it started are killed after `timeout`.
+pub async fn run_command(cmd: AsyncCommand, timeout: Duration, sink: Arc<dyn OutputSink>) -> Result<CommandOutput> {
+    Running::start(cmd, sink, None)?.finish(timeout, Arc::new(Notify::new())).await
+}
+
+fn read_stream<R>(
+    pipe: Option<R>,
+    stream: OutputStream,
+    sink: Arc<dyn OutputSink>,
+    captured: Arc<Mutex<Captured>>,
+) -> JoinHandle<()>
+where
+    R: tokio::io::AsyncRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let Some(mut pipe) = pipe else { return };
+        let mut buffer = [0u8; 8192];
+        let mut pending = Vec::new();
+        loop {
+            let read = match pipe.read(&mut buffer).await {
+                Ok(0) | Err(_) => break,
+                Ok(read) => read,
+            };
+            pending.extend_from_slice(&buffer[..read]);
+            let chunk = take_utf8(&mut pending);
+            if chunk.is_empty() {
+                continue;
+            }
+            append(&captured, stream, &chunk);
+            sink.output(stream, &chunk).await;
+        }
+        if !pending.is_empty() {
+            let chunk = String::from_utf8_lossy(&pending).into_owned();
+            append(&captured, stream, &chunk);
+            sink.output(stream, &chunk).await;
+        }
+    })
+}
+
+fn append(captured: &Mutex<Captured>, stream: OutputStream, chunk: &str) {
+    let mut captured = captured.lock().unwrap();
+    let Captured { stdout, stderr, limit, dropped } = &mut *captured;
+    let text = match stream {
+        OutputStream::Stdout => stdout,
+        OutputStream::Stderr => stderr,
+    };
+    text.push_str(chunk);
+    if let Some(limit) = *limit {
+        if text.len() > limit {
+            let mut cut = text.len() - limit;
+            while !text.is_char_boundary(cut) {
+                cut += 1;
+            }
+            text.drain(..cut);
+            *dropped += cut;
+        }
+    }
+}
+
+/// Decode what is complete in `pending`, leaving a character split across
+/// reads for the next one
+fn take_utf8(pending: &mut Vec<u8>) -> String {
+    let complete = match std::str::from_utf8(pending) {
+        Ok(_) => pending.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => pending.len(),
+    };
+    let rest = pending.split_off(complete);
+    let chunk = String::from_utf8_lossy(pending).into_owned();
+    *pending = rest;
+    chunk
+}
+
+#[cfg(unix)]
+fn signal(status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn signal(_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
+#[cfg(unix)]
+fn kill_process_group(pid: Option<u32>) {
+    if let Some(pid) = pid {
+        // SAFETY: kill only sends a signal
+        unsafe {
+            libc::kill(-(pid as i32), libc::SIGKILL);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_process_group(_pid: Option<u32>) {}
+
+/// Where a background job is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobState {
+    Running,
+    Exited { exit_code: Option<i32>, signal: Option<i32> },
+    Killed,
+    TimedOut,
+    Failed(String),
+}
+
+impl std::fmt::Display for JobState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JobState::Running => write!(f, "running"),
+            JobState::Exited { exit_code: Some(code), .. } => write!(f, "exited with code {}", code),
+            JobState::Exited { signal: Some(signal), .. } => write!(f, "ended by signal {}", signal),
+            JobState::Exited { .. } => write!(f, "exited"),
+            JobState::Killed => write!(f, "killed"),
+            JobState::TimedOut => write!(f, "killed at its time limit"),
+            JobState::Failed(error) => write!(f, "failed: {}", error),
+        }
+    }
+}
+
+/// A background job as seen at one moment
+#[derive(Debug, Clone)]
+pub struct JobSnapshot {
+    pub id: String,
+    pub command: String,
+    pub state: JobState,
+    pub elapsed: Duration,
+    pub stdout: String,
+    pub stderr: String,
+    /// Bytes of early output dropped to keep the job's buffers bounded
+    pub dropped_bytes: usize,
+}
+
+#[derive(Debug)]
+struct Job {
+    command: String,
+    started: Instant,
+    finished: Mutex<Option<Instant>>,
+    state: Mutex<JobState>,
+    captured: Arc<Mutex<Captured>>,
+    kill: Arc<Notify>,
+}
+
+/// Commands started with `background: true`, kept for the session so the
+/// job tools can look at them. Each job keeps only the latest output, and
+/// only the most recent finished jobs are kept.
+#[derive(Debug)]
+pub struct JobManager {
+    next_id: AtomicU64,
+    jobs: Mutex<HashMap<String, Arc<Job>>>,
+    output_limit: usize,
+    finished_kept: usize,
+}
+
+impl Default for JobManager {
+    fn default() -> Self {
+        Self {
+            next_id: AtomicU64::new(0),
+            jobs: Mutex::new(HashMap::new()),
+            output_limit: DEFAULT_JOB_OUTPUT_LIMIT,
+            finished_kept: DEFAULT_FINISHED_JOBS_KEPT,
+        }
+    }
+}
+
+impl JobManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep at most this many bytes of each job's stdout and stderr
+    pub fn with_output_limit(mut self, bytes: usize) -> Self {
+        self.output_limit = bytes;
+        self
+    }
+
+    /// Keep at most this many finished jobs
+    pub fn with_finished_kept(mut self, jobs: usize) -> Self {
+        self.finished_kept = jobs;
+        self
+    }
+
+    /// Start a command in the background and return its job id
+    pub fn spawn(&self, command_line: &str, cmd: AsyncCommand, timeout: Duration) -> Result<String> {
+        let running = Running::start(cmd, Arc::new(DiscardOutput), Some(self.output_limit))?;
+        self.prune();
+        let id = format!("job-{}", self.next_id.fetch_add(1, Ordering::Relaxed) + 1);
+        let job = Arc::new(Job {
+            command: command_line.to_string(),
+            started: Instant::now(),
+            finished: Mutex::new(None),
+            state: Mutex::new(JobState::Running),
+            captured: running.captured.clone(),
+            kill: Arc::new(Notify::new()),
+        });
+        self.jobs.lock().unwrap().insert(id.clone(), job.clone());
+
+        tokio::spawn(async move {
+            let result = running.finish(timeout, job.kill.clone()).await;
+            let state = match result {
+                Ok(output) if output.killed => JobState::Killed,
+                Ok(output) if output.timed_out => JobState::TimedOut,
+                Ok(output) => JobState::Exited { exit_code: output.exit_code, signal: output.signal },
+                Err(e) => JobState::Failed(e.to_string()),
+            };
+            *job.finished.lock().unwrap() = Some(Instant::now());
+            *job.state.lock().unwrap() = state;
+        });
+        Ok(id)
+    }
+
+    /// The job's state and its output so far
+    pub fn get(&self, id: &str) -> Option<JobSnapshot> {
+        let job = self.jobs.lock().unwrap().get(id).cloned()?;
+        Some(Self::snapshot(id, &job))
+    }
+
+    /// Every job of the session, oldest first
+    pub fn list(&self) -> Vec<JobSnapshot> {
+        let jobs = self.jobs.lock().unwrap();
+        let mut snapshots: Vec<JobSnapshot> = jobs.iter().map(|(id, job)| Self::snapshot(id, job)).collect();
+        snapshots.sort_by_key(|snapshot| {
+            snapshot.id.trim_start_matches("job-").parse::<u64>().unwrap_or(u64::MAX)
+        });
+        snapshots
+    }
+
+    /// Ask a running job to stop. Returns false for unknown or finished jobs.
+    pub fn kill(&self, id: &str) -> bool {
+        let Some(job) = self.jobs.lock().unwrap().get(id).cloned() else {
+            return false;
+        };
+        if *job.state.lock().unwrap() != JobState::Running {
+            return false;
+        }
+        job.kill.notify_one();
+        true
+    }
+
+    /// Forget the oldest finished jobs beyond the number kept
+    fn prune(&self) {
+        let mut jobs = self.jobs.lock().unwrap();
+        let mut finished: Vec<u64> = jobs
+            .iter()
+            .filter(|(_, job)| *job.state.lock().unwrap() != JobState::Running)
+            .filter_map(|(id, _)| id.trim_start_matches("job-").parse().ok())
+            .collect();
+        if finished.len() <= self.finished_kept {
+            return;
+        }
+        finished.sort_unstable();
+        for number in &finished[..finished.len() - self.finished_kept] {
+            jobs.remove(&format!("job-{}", number));
+        }
+    }
+
+    fn snapshot(id: &str, job: &Job) -> JobSnapshot {
+        let captured = job.captured.lock().unwrap();
+        let end = job.finished.lock().unwrap().unwrap_or_else(Instant::now);
+        JobSnapshot {
+            id: id.to_string(),
+            command: job.command.clone(),
+            state: job.state.lock().unwrap().clone(),
+            elapsed: end.duration_since(job.started),
+            stdout: captured.stdout.clone(),
+            stderr: captured.stderr.clone(),
+            dropped_bytes: captured.dropped,
+        }
+    }
+}
@@ -7,8 +7,29 @@ pub mod tool;
 pub mod tool_registry;
 pub mod tool_context;
 pub mod tool_parsing;
+pub mod path_resolver;
+pub mod confirmation;
+pub mod output;
+pub mod jobs;
+pub mod read_tracker;
+pub mod schema;
+pub mod typed;
 
 pub use tool::*;
 pub use tool_registry::*;
 pub use tool_context::*;
 pub use tool_parsing::*;
+pub use path_resolver::*;
+pub use confirmation::*;
+pub use output::*;
+pub use jobs::*;
+pub use read_tracker::*;
+pub use schema::*;
+
+pub use kimichat_toolcore_macros::tool;
+
+/// Paths used by code that `#[tool]` generates
+#[doc(hidden)]
+pub mod __private {
+    pub use async_trait::async_trait;
+}
@@ -0,0 +1,82 @@
+use async_trait::async_trait;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::sync::Mutex;
+
+/// Which stream of a command a chunk of output came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Receives a tool's output while it is still running
+///
+/// Tools also return the whole output in their result; a sink is only for
+/// showing progress live.
+#[async_trait]
+pub trait OutputSink: Send + Sync + std::fmt::Debug {
+    async fn output(&self, stream: OutputStream, chunk: &str);
+}
+
+/// Prints output to the terminal as it arrives
+#[derive(Debug, Default)]
+pub struct TerminalOutput;
+
+#[async_trait]
+impl OutputSink for TerminalOutput {
+    async fn output(&self, stream: OutputStream, chunk: &str) {
+        match stream {
+            OutputStream::Stdout => {
+                print!("{}", chunk.bright_black());
+                std::io::stdout().flush().ok();
+            }
+            OutputStream::Stderr => {
+                eprint!("{}", chunk.bright_black());
+                std::io::stderr().flush().ok();
+            }
+        }
+    }
+}
+
+/// Drops all output, for runs nobody watches
+#[derive(Debug, Default)]
+pub struct DiscardOutput;
+
+#[async_trait]
+impl OutputSink for DiscardOutput {
+    async fn output(&self, _stream: OutputStream, _chunk: &str) {}
+}
+
+/// Keeps every chunk, for tests
+#[derive(Debug, Default)]
+pub struct RecordedOutput {
+    chunks: Mutex<Vec<(OutputStream, String)>>,
+}
+
+impl RecordedOutput {
+    /// Chunks received so far, in order
+    pub fn chunks(&self) -> Vec<(OutputStream, String)> {
+        self.chunks.lock().unwrap().clone()
+    }
+
+    /// Everything received on one stream
+    pub fn text(&self, stream: OutputStream) -> String {
+        self.chunks
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(s, _)| *s == stream)
+            .map(|(_, chunk)| chunk.as_str())
+            .collect()
+    }
+}
+
+#[async_trait]
+impl OutputSink for RecordedOutput {
+    async fn output(&self, stream: OutputStream, chunk: &str) {
+        self.chunks.lock().unwrap().push((stream, chunk.to_string()));
+    }
+}
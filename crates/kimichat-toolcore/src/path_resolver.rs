@@ -0,0 +1,113 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// A tool path resolved against the workspace jail
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedPath {
+    /// Absolute path with `.`, `..` and symlinks resolved
+    pub absolute: PathBuf,
+    /// Path relative to the workspace with `/` separators (`.` for the workspace itself),
+    /// or the absolute path when it lies in an extra root. This is what policies match against.
+    pub relative: String,
+}
+
+/// Resolves tool paths and keeps them inside the workspace
+///
+/// Relative paths are joined to the workspace, then `..` and symlinks are resolved
+/// against the real filesystem. Paths that do not exist yet (files about to be
+/// written) are allowed as long as their existing part stays inside. Anything that
+/// ends up outside the workspace and every extra root is rejected.
+#[derive(Debug, Clone)]
+pub struct PathResolver {
+    work_dir: PathBuf,
+    extra_roots: Vec<PathBuf>,
+}
+
+impl PathResolver {
+    pub fn new(work_dir: PathBuf) -> Self {
+        Self {
+            work_dir,
+            extra_roots: Vec::new(),
+        }
+    }
+
+    /// Also allow paths under these directories
+    pub fn with_extra_roots(mut self, extra_roots: Vec<PathBuf>) -> Self {
+        self.extra_roots = extra_roots;
+        self
+    }
+
+    pub fn resolve(&self, path: impl AsRef<Path>) -> Result<ResolvedPath> {
+        let path = path.as_ref();
+        let work_dir = canonicalize_root(&self.work_dir)?;
+        let absolute = resolve_components(&work_dir.join(path))
+            .with_context(|| format!("Cannot resolve path '{}'", path.display()))?;
+
+        if let Ok(relative) = absolute.strip_prefix(&work_dir) {
+            return Ok(ResolvedPath {
+                relative: to_slash_string(relative),
+                absolute,
+            });
+        }
+
+        for root in &self.extra_roots {
+            let Ok(root) = canonicalize_root(root) else { continue };
+            if absolute.starts_with(&root) {
+                return Ok(ResolvedPath {
+                    relative: absolute.to_string_lossy().to_string(),
+                    absolute,
+                });
+            }
+        }
+
+        anyhow::bail!(
+            "Path '{}' resolves to {}, which is outside the workspace {}",
+            path.display(),
+            absolute.display(),
+            work_dir.display()
+        )
+    }
+}
+
+fn canonicalize_root(root: &Path) -> Result<PathBuf> {
+    root.canonicalize()
+        .with_context(|| format!("Failed to canonicalize workspace root: {}", root.display()))
+}
+
+/// Resolve `path` one component at a time, following each existing symlink
+///
+/// Every existing prefix is canonicalized before the next component is applied, so
+/// `..` always means the real parent. A dangling symlink fails to canonicalize and is
+/// rejected instead of being written through.
+fn resolve_components(path: &Path) -> Result<PathBuf> {
+    let mut resolved = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => resolved.push(component),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::Normal(name) => {
+                resolved.push(name);
+                if fs::symlink_metadata(&resolved).is_ok() {
+                    resolved = resolved.canonicalize()?;
+                }
+            }
+        }
+    }
+    Ok(resolved)
+}
+
+fn to_slash_string(relative: &Path) -> String {
+    let parts: Vec<String> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect();
+    if parts.is_empty() {
+        ".".to_string()
+    } else {
+        parts.join("/")
+    }
+}
@@ -0,0 +1,119 @@
+//! Per-session record of the file contents a model has seen
+//!
+//! Read tools record what they showed, and tools that write record what they
+//! wrote. Before changing a file, edit tools ask whether the model has seen
+//! it and whether it has changed on disk since (edited by the user, a command
+//! or another agent), so they can refuse stale edits and show what changed.
+
+use similar::TextDiff;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Files larger than this are tracked by fingerprint only, without a diff
+const MAX_TRACKED_BYTES: usize = 1024 * 1024;
+/// Longest diff of external changes handed back to the model
+const MAX_DIFF_LINES: usize = 60;
+
+/// What a file looked like when the model last saw it
+#[derive(Debug, Clone)]
+struct Fingerprint {
+    modified: Option<SystemTime>,
+    len: u64,
+    hash: u64,
+    /// The content itself, kept for diffs unless the file is large
+    content: Option<String>,
+}
+
+/// Whether the model's view of a file is current
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Freshness {
+    /// No file at the path
+    Missing,
+    /// The file exists but the model has not seen it in this session
+    Unread,
+    /// Unchanged since the model last saw it
+    Fresh,
+    /// Changed on disk since the model last saw it. `diff` goes from what the
+    /// model saw to what is there now; `complete` is false when it was cut
+    /// short or could not be produced.
+    Changed { diff: String, complete: bool },
+}
+
+/// Fingerprints of the files seen in one session, keyed by absolute path
+#[derive(Debug, Default)]
+pub struct ReadTracker {
+    seen: Mutex<HashMap<PathBuf, Fingerprint>>,
+}
+
+impl ReadTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember that the model has seen `content` as the file at `path`
+    pub fn record(&self, path: &Path, content: &str) {
+        let metadata = fs::metadata(path).ok();
+        let fingerprint = Fingerprint {
+            modified: metadata.as_ref().and_then(|m| m.modified().ok()),
+            len: metadata.map(|m| m.len()).unwrap_or(content.len() as u64),
+            hash: hash_bytes(content.as_bytes()),
+            content: (content.len() <= MAX_TRACKED_BYTES).then(|| content.to_string()),
+        };
+        self.seen.lock().unwrap().insert(path.to_path_buf(), fingerprint);
+    }
+
+    /// Compare the file at `path` with what the model last saw of it
+    pub fn check(&self, path: &Path) -> Freshness {
+        let Ok(metadata) = fs::metadata(path) else {
+            return Freshness::Missing;
+        };
+        let mut seen = self.seen.lock().unwrap();
+        let Some(fingerprint) = seen.get_mut(path) else {
+            return Freshness::Unread;
+        };
+        let modified = metadata.modified().ok();
+        if modified.is_some() && modified == fingerprint.modified && metadata.len() == fingerprint.len {
+            return Freshness::Fresh;
+        }
+
+        let Ok(bytes) = fs::read(path) else {
+            return Freshness::Changed { diff: "(the file can no longer be read)".to_string(), complete: false };
+        };
+        if hash_bytes(&bytes) == fingerprint.hash {
+            // Touched but not changed
+            fingerprint.modified = modified;
+            fingerprint.len = metadata.len();
+            return Freshness::Fresh;
+        }
+        match (&fingerprint.content, String::from_utf8(bytes)) {
+            (Some(before), Ok(now)) => external_diff(before, &now),
+            _ => Freshness::Changed { diff: "(the content changed; too large or not text to diff)".to_string(), complete: false },
+        }
+    }
+}
+
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn external_diff(before: &str, now: &str) -> Freshness {
+    let diff = TextDiff::from_lines(before, now)
+        .unified_diff()
+        .context_radius(1)
+        .header("as last seen", "on disk now")
+        .to_string();
+    let lines: Vec<&str> = diff.lines().collect();
+    if lines.len() <= MAX_DIFF_LINES {
+        return Freshness::Changed { diff: lines.join("\n"), complete: true };
+    }
+    let mut diff = lines[..MAX_DIFF_LINES].join("\n");
+    diff.push_str(&format!("\n[... {} more diff lines ...]", lines.len() - MAX_DIFF_LINES));
+    Freshness::Changed { diff, complete: false }
+}
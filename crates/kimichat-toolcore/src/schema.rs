@@ -0,0 +1,131 @@
+//! JSON Schema for tool parameters, and argument validation before a tool runs
+
+use crate::tool::{ParameterDefinition, Tool, ToolParameters};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+
+/// Copy of a JSON value with every object's keys in sorted order, so tool
+/// definitions serialize identically on every request (for prompt caching)
+/// whatever map ordering serde_json was built with
+pub fn sorted_keys(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            let mut sorted = Map::new();
+            for key in keys {
+                sorted.insert(key.clone(), sorted_keys(&map[key]));
+            }
+            Value::Object(sorted)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(sorted_keys).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Parameter definitions for the properties of an object schema, such as a
+/// typed tool's argument struct or a tool's schema from elsewhere
+pub fn parameters_from_schema(schema: &Value) -> HashMap<String, ParameterDefinition> {
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return HashMap::new();
+    };
+
+    properties
+        .iter()
+        .map(|(name, property)| {
+            let definition = parameter_from_schema(property, required.contains(&name.as_str()));
+            (name.clone(), definition)
+        })
+        .collect()
+}
+
+fn parameter_from_schema(property: &Value, required: bool) -> ParameterDefinition {
+    let mut schema = property.as_object().cloned().unwrap_or_default();
+    let description = match schema.remove("description") {
+        Some(Value::String(description)) => description,
+        _ => String::new(),
+    };
+    let default = schema.remove("default");
+
+    // Optional fields often come out as `["T", "null"]`; leaving the argument
+    // out is how the model skips them, so a plain `T` reads better
+    if let Some(Value::Array(types)) = schema.get_mut("type") {
+        types.retain(|t| t != "null");
+        if types.len() == 1 {
+            let single = types.remove(0);
+            schema.insert("type".to_string(), single);
+        }
+    }
+    // Anything but a single type name stays in the schema, with no `param_type`
+    let param_type = match schema.remove("type") {
+        Some(Value::String(param_type)) => param_type,
+        Some(other) => {
+            schema.insert("type".to_string(), other);
+            String::new()
+        }
+        None => String::new(),
+    };
+
+    ParameterDefinition { param_type, description, required, default, schema }
+}
+
+/// A tool's parameter schema compiled once, to check the arguments of each
+/// call before the tool runs
+pub struct ArgumentValidator {
+    tool_name: String,
+    required: HashSet<String>,
+    validator: Result<jsonschema::Validator, String>,
+}
+
+impl ArgumentValidator {
+    /// Compile the parameter schema of `tool`; an invalid schema is reported
+    /// on every call rather than here
+    pub fn new(tool: &dyn Tool) -> Self {
+        let required = tool
+            .parameters()
+            .into_iter()
+            .filter(|(_, definition)| definition.required)
+            .map(|(name, _)| name)
+            .collect();
+        let validator = jsonschema::validator_for(&tool.parameters_schema())
+            .map_err(|e| format!("Tool '{}' has an invalid parameter schema: {}", tool.name(), e));
+        Self { tool_name: tool.name().to_string(), required, validator }
+    }
+
+    /// Check the arguments of one call
+    ///
+    /// Optional arguments given as `null` count as omitted and are removed, since
+    /// models often send them that way. On failure the error lists every problem,
+    /// each with the path of the offending value, for the model to fix and retry.
+    pub fn validate(&self, params: &mut ToolParameters) -> Result<(), String> {
+        params.data.retain(|name, value| !value.is_null() || self.required.contains(name));
+
+        let validator = self.validator.as_ref().map_err(Clone::clone)?;
+        let instance = Value::Object(params.data.iter().map(|(k, v)| (k.clone(), v.clone())).collect());
+
+        let mut problems: Vec<String> = validator
+            .iter_errors(&instance)
+            .map(|error| {
+                let path = error.instance_path.to_string();
+                match path.trim_start_matches('/') {
+                    "" => format!("- {}", error),
+                    path => format!("- {}: {}", path, error),
+                }
+            })
+            .collect();
+        if problems.is_empty() {
+            return Ok(());
+        }
+        problems.sort();
+        Err(format!(
+            "Invalid arguments for tool '{}' (the tool was not run):\n{}",
+            self.tool_name,
+            problems.join("\n")
+        ))
+    }
+}
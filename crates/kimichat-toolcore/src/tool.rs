@@ -81,12 +81,43 @@ impl ToolResult {
 }
 
 /// Tool parameter definition
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// `param_type`, `description` and `default` are the common JSON Schema keywords;
+/// `schema` holds any others the parameter needs (`enum`, `items`, `properties`,
+/// `minimum`, `pattern`, ...), so arrays and nested objects can be described fully.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ParameterDefinition {
     pub param_type: String,
     pub description: String,
     pub required: bool,
     pub default: Option<Value>,
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub schema: serde_json::Map<String, Value>,
+}
+
+impl ParameterDefinition {
+    /// Add JSON Schema keywords from a JSON object
+    pub fn with_schema(mut self, keywords: Value) -> Self {
+        if let Value::Object(keywords) = keywords {
+            self.schema.extend(keywords);
+        }
+        self
+    }
+
+    /// The parameter's complete JSON Schema. Keywords in `schema` win over the
+    /// plain fields, so `"type": ["string", "null"]` can widen `param_type`.
+    /// An empty `param_type` leaves the type to `schema` (e.g. an `anyOf`).
+    pub fn to_schema(&self) -> Value {
+        let mut schema = self.schema.clone();
+        if !self.param_type.is_empty() {
+            schema.entry("type").or_insert_with(|| Value::String(self.param_type.clone()));
+        }
+        schema.entry("description").or_insert_with(|| Value::String(self.description.clone()));
+        if let Some(default) = &self.default {
+            schema.entry("default").or_insert_with(|| default.clone());
+        }
+        crate::schema::sorted_keys(&Value::Object(schema))
+    }
 }
 
 /// Tool trait that all tools must implement
@@ -101,24 +132,28 @@ pub trait Tool: Send + Sync {
     /// Parameter definitions
     fn parameters(&self) -> HashMap<String, ParameterDefinition>;
 
+    /// Categories `ToolRegistry::register` files the tool under
+    fn categories(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Whether the tool only looks at things (files, jobs, terminals, skills)
+    /// and changes nothing, so calls to it can run alongside each other
+    fn read_only(&self) -> bool {
+        false
+    }
+
     /// Execute the tool
     async fn execute(&self, params: ToolParameters, context: &crate::tool_context::ToolContext) -> ToolResult;
 
-    /// Get OpenAI-compatible tool definition
-    fn to_openai_definition(&self) -> serde_json::Value {
+    /// JSON Schema for the tool's arguments object, as sent to the model and
+    /// used to validate its calls
+    fn parameters_schema(&self) -> serde_json::Value {
         let mut properties = serde_json::Map::new();
         let mut required = Vec::new();
 
         for (name, param_def) in self.parameters() {
-            // Build parameter definition with sorted keys
-            let mut param_obj = serde_json::Map::new();
-            if let Some(default) = &param_def.default {
-                param_obj.insert("default".to_string(), default.clone());
-            }
-            param_obj.insert("description".to_string(), serde_json::Value::String(param_def.description.clone()));
-            param_obj.insert("type".to_string(), serde_json::Value::String(param_def.param_type.clone()));
-
-            properties.insert(name.clone(), serde_json::Value::Object(param_obj));
+            properties.insert(name.clone(), param_def.to_schema());
 
             if param_def.required {
                 required.push(name);
@@ -141,12 +176,16 @@ pub trait Tool: Send + Sync {
         parameters.insert("properties".to_string(), serde_json::Value::Object(sorted_properties));
         parameters.insert("required".to_string(), serde_json::Value::Array(required.into_iter().map(serde_json::Value::String).collect()));
         parameters.insert("type".to_string(), serde_json::Value::String("object".to_string()));
+        serde_json::Value::Object(parameters)
+    }
 
+    /// Get OpenAI-compatible tool definition
+    fn to_openai_definition(&self) -> serde_json::Value {
         // Build function object with sorted keys
         let mut function = serde_json::Map::new();
         function.insert("description".to_string(), serde_json::Value::String(self.description().to_string()));
         function.insert("name".to_string(), serde_json::Value::String(self.name().to_string()));
-        function.insert("parameters".to_string(), serde_json::Value::Object(parameters));
+        function.insert("parameters".to_string(), self.parameters_schema());
 
         // Build top-level object with sorted keys
         let mut result = serde_json::Map::new();
@@ -160,6 +199,18 @@ pub trait Tool: Send + Sync {
 /// Helper macro for creating parameter definitions
 #[macro_export]
 macro_rules! param {
+    ($name:expr, $type:expr, $desc:expr, required, schema: $schema:expr) => {{
+        let (name, definition) = $crate::param!($name, $type, $desc, required);
+        (name, definition.with_schema($schema))
+    }};
+    ($name:expr, $type:expr, $desc:expr, optional, schema: $schema:expr) => {{
+        let (name, definition) = $crate::param!($name, $type, $desc, optional);
+        (name, definition.with_schema($schema))
+    }};
+    ($name:expr, $type:expr, $desc:expr, optional, $default:expr, schema: $schema:expr) => {{
+        let (name, definition) = $crate::param!($name, $type, $desc, optional, $default);
+        (name, definition.with_schema($schema))
+    }};
     ($name:expr, $type:expr, $desc:expr, required) => {
         (
             $name.to_string(),
@@ -168,6 +219,7 @@ macro_rules! param {
                 description: $desc.to_string(),
                 required: true,
                 default: None,
+                schema: serde_json::Map::new(),
             }
         )
     };
@@ -179,6 +231,7 @@ macro_rules! param {
                 description: $desc.to_string(),
                 required: false,
                 default: Some(serde_json::Value::from($default)),
+                schema: serde_json::Map::new(),
             }
         )
     };
@@ -190,6 +243,7 @@ macro_rules! param {
                 description: $desc.to_string(),
                 required: false,
                 default: None,
+                schema: serde_json::Map::new(),
             }
         )
     };
@@ -2,50 +2,104 @@ use std::path::PathBuf;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use kimichat_policy::PolicyManager;
+use kimichat_policy::{PolicyContext, PolicyManager, SandboxConfig};
 use kimichat_terminal::TerminalManager;
 use kimichat_skills::SkillRegistry;
 use kimichat_todo::TodoManager;
+use kimichat_checkpoints::CheckpointStore;
+use crate::path_resolver::{PathResolver, ResolvedPath};
+use crate::confirmation::{Confirmation, ConfirmationProvider, ConfirmationRequest, TerminalConfirmation};
+use crate::jobs::JobManager;
+use crate::output::{OutputSink, TerminalOutput};
+use crate::read_tracker::{Freshness, ReadTracker};
 
 /// Tool execution context
 ///
 /// This struct provides the execution context for tools, including:
-/// - Working directory for file operations
+/// - Working directory for file operations, and extra roots file tools may reach
 /// - Session identifier for tracking operations
 /// - Environment variables for configuration
-/// - Policy manager for permission checking
+/// - Policy manager for permission checking, and the agent and session type its rules see
 /// - Terminal manager for PTY session management
 /// - Skill registry for accessing skills
 /// - Todo manager for task tracking
-/// - Non-interactive flag for web/API mode
+/// - Checkpoint store that keeps files as they were before the tools changed them
+/// - Read tracker that lets edit tools refuse to change files the model has not seen as they are
+/// - Confirmation provider for actions the policy marks as "ask"
+/// - Sandbox limits for commands, when the caller (such as an agent) wants them sandboxed
+/// - Output sink that shows command output live, and the session's background jobs
 #[derive(Debug, Clone)]
 pub struct ToolContext {
     pub work_dir: PathBuf,
+    pub extra_roots: Vec<PathBuf>,
     pub session_id: String,
     pub environment: HashMap<String, String>,
     pub policy_manager: PolicyManager,
+    pub policy_context: PolicyContext,
     pub terminal_manager: Option<Arc<Mutex<TerminalManager>>>,
     pub skill_registry: Option<Arc<SkillRegistry>>,
     pub todo_manager: Option<Arc<TodoManager>>,
-    pub non_interactive: bool,
+    pub checkpoint_store: Option<Arc<CheckpointStore>>,
+    pub read_tracker: Option<Arc<ReadTracker>>,
+    pub confirmation_provider: Arc<dyn ConfirmationProvider>,
+    pub sandbox: Option<SandboxConfig>,
+    pub output_sink: Arc<dyn OutputSink>,
+    pub job_manager: Option<Arc<JobManager>>,
 }
 
 impl ToolContext {
     pub fn new(work_dir: PathBuf, session_id: String, policy_manager: PolicyManager) -> Self {
         Self {
             work_dir,
+            extra_roots: Vec::new(),
             session_id,
             environment: HashMap::new(),
             policy_manager,
+            policy_context: PolicyContext::default(),
             terminal_manager: None,
             skill_registry: None,
             todo_manager: None,
-            non_interactive: false,
+            checkpoint_store: None,
+            read_tracker: None,
+            confirmation_provider: Arc::new(TerminalConfirmation),
+            sandbox: None,
+            output_sink: Arc::new(TerminalOutput),
+            job_manager: None,
         }
     }
 
-    pub fn with_non_interactive(mut self, non_interactive: bool) -> Self {
-        self.non_interactive = non_interactive;
+    /// Route "ask" decisions to this provider instead of the terminal
+    pub fn with_confirmation_provider(mut self, confirmation_provider: Arc<dyn ConfirmationProvider>) -> Self {
+        self.confirmation_provider = confirmation_provider;
+        self
+    }
+
+    /// Allow file tools to reach these directories besides the work directory
+    pub fn with_extra_roots(mut self, extra_roots: Vec<PathBuf>) -> Self {
+        self.extra_roots = extra_roots;
+        self
+    }
+
+    /// Set the agent and session type that conditional policy rules match on
+    pub fn with_policy_context(mut self, policy_context: PolicyContext) -> Self {
+        self.policy_context = policy_context;
+        self
+    }
+
+    /// Run every command in the sandbox with these limits
+    pub fn with_sandbox(mut self, sandbox: SandboxConfig) -> Self {
+        self.sandbox = Some(sandbox);
+        self
+    }
+
+    /// Stream command output to this sink instead of the terminal
+    pub fn with_output_sink(mut self, output_sink: Arc<dyn OutputSink>) -> Self {
+        self.output_sink = output_sink;
+        self
+    }
+
+    pub fn with_job_manager(mut self, job_manager: Arc<JobManager>) -> Self {
+        self.job_manager = Some(job_manager);
         self
     }
 
@@ -69,75 +123,158 @@ impl ToolContext {
         self
     }
 
-    /// Check if an action is permitted by the policy
+    pub fn with_checkpoint_store(mut self, checkpoint_store: Arc<CheckpointStore>) -> Self {
+        self.checkpoint_store = Some(checkpoint_store);
+        self
+    }
+
+    /// Save a file to the current checkpoint before a tool changes it.
+    /// Without a checkpoint store this does nothing.
+    pub fn save_checkpoint(&self, resolved: &ResolvedPath) -> anyhow::Result<()> {
+        match &self.checkpoint_store {
+            Some(store) => store.save_file(&resolved.absolute, &resolved.relative),
+            None => Ok(()),
+        }
+    }
+
+    pub fn with_read_tracker(mut self, read_tracker: Arc<ReadTracker>) -> Self {
+        self.read_tracker = Some(read_tracker);
+        self
+    }
+
+    /// Record that the model has seen this content of a file, by reading or writing it.
+    /// Without a read tracker this does nothing.
+    pub fn record_read(&self, resolved: &ResolvedPath, content: &str) {
+        if let Some(tracker) = &self.read_tracker {
+            tracker.record(&resolved.absolute, content);
+        }
+    }
+
+    /// Check that the model's view of a file is current before a tool changes it.
+    ///
+    /// Returns the refusal when the file changed on disk since the model saw it,
+    /// with a diff of the changes; if that diff is complete the model now knows the
+    /// current content, so a corrected retry goes through. A file the model never
+    /// saw is refused when `require_read` is set and gives a warning otherwise.
+    /// Files that do not exist yet, and contexts without a tracker, always pass.
+    pub fn check_stale(&self, resolved: &ResolvedPath, require_read: bool) -> Result<Option<String>, String> {
+        let Some(tracker) = &self.read_tracker else {
+            return Ok(None);
+        };
+        match tracker.check(&resolved.absolute) {
+            Freshness::Missing | Freshness::Fresh => Ok(None),
+            Freshness::Unread if require_read => Err(format!(
+                "Not changing {}: it has not been read in this session. Read it with read_file or open_file first",
+                resolved.relative
+            )),
+            Freshness::Unread => Ok(Some(format!(
+                "⚠️  {} was changed without being read first in this session",
+                resolved.relative
+            ))),
+            Freshness::Changed { diff, complete } => {
+                let next_step = if complete {
+                    if let Ok(current) = std::fs::read_to_string(&resolved.absolute) {
+                        tracker.record(&resolved.absolute, &current);
+                    }
+                    "Redo the change against the current content"
+                } else {
+                    "Re-read the file and redo the change against its current content"
+                };
+                Err(format!(
+                    "Not changing {}: it changed on disk since it was last read. Changes since then:\n{}\n{}.",
+                    resolved.relative, diff, next_step
+                ))
+            }
+        }
+    }
+
+    /// Resolve a tool's file path, rejecting paths that escape the work directory
+    /// and the extra roots. Policy checks should use the returned `relative` path.
+    pub fn resolve_path(&self, path: &str) -> anyhow::Result<ResolvedPath> {
+        PathResolver::new(self.work_dir.clone())
+            .with_extra_roots(self.extra_roots.clone())
+            .resolve(path)
+    }
+
+    /// Sandbox limits for a command: this context's, tightened by those of the
+    /// policy rule that decides it. `None` when neither asks for a sandbox.
+    pub fn sandbox_for(&self, action: &kimichat_policy::ActionType, target: &str) -> Option<SandboxConfig> {
+        let explanation = self.policy_manager.explain(action, target, &self.policy_context);
+        let rule_sandbox = explanation.rule.and_then(|winner| winner.rule.sandbox);
+        match (&self.sandbox, rule_sandbox) {
+            (Some(own), Some(rule)) => Some(own.tightest(&rule)),
+            (own, rule) => rule.or_else(|| own.clone()),
+        }
+    }
+
+    /// Check an action against the agent's permissions only, for tools the shared
+    /// policy does not gate (such as reads). Returns the denial message if refused.
+    pub fn check_agent_permission(&self, action: kimichat_policy::ActionType, target: &str) -> Result<(), String> {
+        match self.policy_manager.check_agent_layer(&action, target, &self.policy_context) {
+            Some(violation) => Err(format!("{} on '{}' denied: {}", violation.action, violation.target, violation.reason)),
+            None => Ok(()),
+        }
+    }
+
+    /// Check if an action is permitted by the policy, asking the confirmation
+    /// provider when the policy says "ask". Decisions and answers go to the
+    /// policy audit log.
     /// Returns (approved: bool, rejection_reason: Option<String>)
-    pub fn check_permission(
+    pub async fn check_permission(
         &self,
         action: kimichat_policy::ActionType,
         target: &str,
         prompt_message: &str,
     ) -> anyhow::Result<(bool, Option<String>)> {
         use kimichat_policy::Decision;
-        use colored::Colorize;
-        use std::io::{self, BufRead, Write};
 
-        let decision = self.policy_manager.evaluate(&action, target);
+        let explanation = self.policy_manager.evaluate_explained(&action, target, &self.policy_context);
 
-        match decision {
+        match explanation.decision {
             Decision::Allow => Ok((true, None)),
             Decision::Deny => Ok((false, Some("Denied by policy".to_string()))),
-            Decision::Ask => {
-                // In non-interactive mode (web/API), auto-approve since confirmation
-                // was already handled via web UI
-                if self.non_interactive {
-                    println!("{} {}", "✓".green(), "Auto-confirmed (web UI)".bright_black());
-                    return Ok((true, None));
-                }
-
-                // Ask the user for confirmation in interactive mode
-                println!("\n{}", prompt_message.bright_green().bold());
-                print!(">>> ");
-                io::stdout().flush()?;
-
-                let stdin = io::stdin();
-                let mut handle = stdin.lock();
-                let mut response = String::new();
-                handle.read_line(&mut response)?;
-
-                let response = response.trim();
-                let response_lower = response.to_lowercase();
-                let approved = response_lower.is_empty() || response_lower == "y" || response_lower == "yes";
-
-                let rejection_reason = if !approved {
-                    // Ask for reason if rejected
-                    println!("{}", "Why not? (optional - helps the AI understand):".bright_yellow());
-                    print!(">>> ");
-                    io::stdout().flush()?;
-
-                    let mut reason = String::new();
-                    match handle.read_line(&mut reason) {
-                        Ok(_) => {
-                            let reason = reason.trim();
-                            if reason.is_empty() {
-                                None
-                            } else {
-                                Some(reason.to_string())
-                            }
-                        }
-                        Err(_) => None,
-                    }
-                } else {
-                    None
-                };
+            Decision::Ask => self.ask(&explanation, prompt_message).await,
+        }
+    }
 
-                // Learn from the user's decision if learning is enabled
-                if self.policy_manager.is_learning() {
-                    let decision = if approved { Decision::Allow } else { Decision::Deny };
-                    let _ = self.policy_manager.learn(action, target.to_string(), decision, rejection_reason.clone());
-                }
+    /// Put an action the policy left open to the confirmation provider,
+    /// recording the answer in the audit log and learning from it.
+    /// Returns (approved: bool, rejection_reason: Option<String>)
+    pub async fn ask(
+        &self,
+        explanation: &kimichat_policy::Explanation,
+        prompt_message: &str,
+    ) -> anyhow::Result<(bool, Option<String>)> {
+        use kimichat_policy::Decision;
 
-                Ok((approved, rejection_reason))
+        let action = explanation.action.clone();
+        let target = explanation.target.as_str();
+        let request = ConfirmationRequest {
+            action: action.clone(),
+            target: target.to_string(),
+            prompt: prompt_message.to_string(),
+        };
+        let (approved, rejection_reason) = match self.confirmation_provider.confirm(&request).await? {
+            Confirmation::Approved => (true, None),
+            Confirmation::ApprovedForSession => {
+                self.policy_manager.grant_for_session(action.clone(), target);
+                (true, None)
             }
+            Confirmation::Rejected { reason } => (false, reason),
+        };
+        self.policy_manager.record_answer(
+            explanation,
+            approved,
+            self.confirmation_provider.name(),
+            rejection_reason.clone(),
+        );
+
+        // Learn from the user's decision if learning is enabled
+        if self.policy_manager.is_learning() {
+            let decision = if approved { Decision::Allow } else { Decision::Deny };
+            let _ = self.policy_manager.learn(action, target.to_string(), decision, rejection_reason.clone());
         }
+
+        Ok((approved, rejection_reason))
     }
-}
\ No newline at end of file
+}
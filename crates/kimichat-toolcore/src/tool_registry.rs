@@ -2,11 +2,14 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use super::tool::{Tool, ToolParameters, ToolResult};
 use super::tool_context::ToolContext;
+use super::schema::ArgumentValidator;
 
 /// Registry for managing and discovering tools
 #[derive(Clone)]
 pub struct ToolRegistry {
     tools: HashMap<String, Arc<dyn Tool>>,
+    /// Each tool's compiled parameter schema, built when it is registered
+    validators: HashMap<String, Arc<ArgumentValidator>>,
     categories: HashMap<String, Vec<String>>,
 }
 
@@ -23,24 +26,29 @@ impl ToolRegistry {
     pub fn new() -> Self {
         Self {
             tools: HashMap::new(),
+            validators: HashMap::new(),
             categories: HashMap::new(),
         }
     }
 
-    /// Register a new tool
+    /// Register a new tool, under the categories it declares
     pub fn register<T: Tool + 'static>(&mut self, tool: T) {
-        let name = tool.name().to_string();
-        let tool_arc = Arc::new(tool);
-        self.tools.insert(name.clone(), tool_arc);
+        let categories = tool.categories();
+        self.register_with_categories(tool, categories);
     }
 
-    /// Register a tool with categories
-    pub fn register_with_categories<T: Tool + 'static>(&mut self, tool: T, categories: Vec<String>) {
+    /// Register a tool with categories, in addition to those it declares
+    pub fn register_with_categories<T: Tool + 'static>(&mut self, tool: T, mut categories: Vec<String>) {
         let name = tool.name().to_string();
-        self.register(tool);
+        categories.extend(tool.categories());
+        self.validators.insert(name.clone(), Arc::new(ArgumentValidator::new(&tool)));
+        self.tools.insert(name.clone(), Arc::new(tool));
 
         for category in categories {
-            self.categories.entry(category).or_insert_with(Vec::new).push(name.clone());
+            let names = self.categories.entry(category).or_default();
+            if !names.contains(&name) {
+                names.push(name.clone());
+            }
         }
     }
 
@@ -76,15 +84,26 @@ impl ToolRegistry {
         self.tools.keys().cloned().collect()
     }
 
-    /// Execute a tool by name
+    /// Check arguments for a tool against its parameter schema
+    pub fn validate_arguments(&self, name: &str, params: &mut ToolParameters) -> Result<(), String> {
+        match self.validators.get(name) {
+            Some(validator) => validator.validate(params),
+            None => Err(format!("Tool '{}' not found", name)),
+        }
+    }
+
+    /// Execute a tool by name, after checking the arguments against its schema
     pub async fn execute_tool(
         &self,
         name: &str,
-        params: ToolParameters,
+        mut params: ToolParameters,
         context: &ToolContext,
     ) -> ToolResult {
         match self.get_tool(name) {
-            Some(tool) => tool.execute(params, context).await,
+            Some(tool) => match self.validate_arguments(name, &mut params) {
+                Ok(()) => tool.execute(params, context).await,
+                Err(problems) => ToolResult::error(problems),
+            },
             None => ToolResult::error(format!("Tool '{}' not found", name)),
         }
     }
@@ -0,0 +1,40 @@
+//! Support code for tools declared with `#[tool]` over a typed argument struct
+//!
+//! The macro generates the `Tool` impl; the functions here turn the argument
+//! struct's JSON Schema into parameter definitions and the model's arguments
+//! into the struct.
+
+use crate::schema::parameters_from_schema;
+use crate::tool::{ParameterDefinition, ToolParameters};
+use schemars::generate::SchemaSettings;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// JSON Schema of an argument struct. Nested types are inlined rather than
+/// referenced, because each parameter's schema has to stand on its own.
+pub fn args_schema<T: JsonSchema>() -> Value {
+    SchemaSettings::draft07()
+        .with(|settings| {
+            settings.inline_subschemas = true;
+            settings.meta_schema = None;
+        })
+        .into_generator()
+        .into_root_schema_for::<T>()
+        .to_value()
+}
+
+/// Parameter definitions for the fields of an argument struct, with the
+/// fields' doc comments as descriptions
+pub fn parameters_for<T: JsonSchema>() -> HashMap<String, ParameterDefinition> {
+    parameters_from_schema(&args_schema::<T>())
+}
+
+/// Deserialize a tool's arguments into its argument struct. The error is
+/// meant for the model, naming the tool and what serde objected to.
+pub fn parse_arguments<T: DeserializeOwned>(tool_name: &str, params: ToolParameters) -> Result<T, String> {
+    let arguments = Value::Object(params.data.into_iter().collect());
+    serde_json::from_value(arguments)
+        .map_err(|e| format!("Invalid arguments for tool '{}' (the tool was not run): {}", tool_name, e))
+}
@@ -0,0 +1,123 @@
+use kimichat_toolcore::jobs::{run_command, JobManager, JobState};
+use kimichat_toolcore::output::{OutputStream, RecordedOutput};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+#[cfg(test)]
+mod jobs_tests {
+    use super::*;
+
+    fn bash(script: &str) -> Command {
+        let mut cmd = Command::new("bash");
+        cmd.args(["-c", script]);
+        cmd
+    }
+
+    #[tokio::test]
+    async fn test_run_command_streams_output() {
+        let sink = Arc::new(RecordedOutput::default());
+        let output = run_command(bash("echo out; echo err >&2; exit 3"), Duration::from_secs(10), sink.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(output.exit_code, Some(3));
+        assert_eq!(output.stdout, "out\n");
+        assert_eq!(output.stderr, "err\n");
+        assert!(!output.timed_out);
+        assert_eq!(sink.text(OutputStream::Stdout), "out\n");
+        assert_eq!(sink.text(OutputStream::Stderr), "err\n");
+    }
+
+    #[tokio::test]
+    async fn test_run_command_timeout_kills_process_group() {
+        let start = Instant::now();
+        let sink = Arc::new(RecordedOutput::default());
+        // The background sleep keeps the pipes open unless the whole group is killed
+        let output = run_command(bash("echo started; sleep 30 & sleep 30"), Duration::from_millis(500), sink)
+            .await
+            .unwrap();
+
+        assert!(output.timed_out);
+        assert!(!output.success());
+        assert_eq!(output.stdout, "started\n");
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_background_job_lifecycle() {
+        let jobs = JobManager::new();
+
+        let done = jobs.spawn("echo hello", bash("echo hello"), Duration::from_secs(10)).unwrap();
+        let slow = jobs.spawn("sleep 30", bash("sleep 30"), Duration::from_secs(60)).unwrap();
+        assert_eq!(done, "job-1");
+        assert_eq!(slow, "job-2");
+
+        for _ in 0..50 {
+            if jobs.get(&done).unwrap().state != JobState::Running {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        let finished = jobs.get(&done).unwrap();
+        assert_eq!(finished.state, JobState::Exited { exit_code: Some(0), signal: None });
+        assert_eq!(finished.stdout, "hello\n");
+
+        assert_eq!(jobs.get(&slow).unwrap().state, JobState::Running);
+        assert!(jobs.kill(&slow));
+        for _ in 0..50 {
+            if jobs.get(&slow).unwrap().state != JobState::Running {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(jobs.get(&slow).unwrap().state, JobState::Killed);
+        assert!(!jobs.kill(&slow));
+        assert!(!jobs.kill("job-99"));
+
+        let ids: Vec<String> = jobs.list().into_iter().map(|job| job.id).collect();
+        assert_eq!(ids, vec!["job-1", "job-2"]);
+    }
+
+    async fn wait_until_finished(jobs: &JobManager, id: &str) {
+        for _ in 0..50 {
+            if jobs.get(id).unwrap().state != JobState::Running {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("{} did not finish", id);
+    }
+
+    #[tokio::test]
+    async fn test_background_output_keeps_only_the_latest() {
+        let jobs = JobManager::new().with_output_limit(1000);
+        // 20 000 bytes on stdout, ending with a marker
+        let id = jobs
+            .spawn("yes", bash("yes 0123456789abcdefghi | head -n 1000; echo END"), Duration::from_secs(10))
+            .unwrap();
+        wait_until_finished(&jobs, &id).await;
+
+        let job = jobs.get(&id).unwrap();
+        assert_eq!(job.stdout.len(), 1000);
+        assert!(job.stdout.ends_with("END\n"));
+        assert_eq!(job.dropped_bytes, 20_004 - 1000);
+        assert_eq!(job.stderr, "");
+    }
+
+    #[tokio::test]
+    async fn test_finished_jobs_are_pruned() {
+        let jobs = JobManager::new().with_finished_kept(2);
+        for _ in 0..3 {
+            let id = jobs.spawn("true", bash("true"), Duration::from_secs(10)).unwrap();
+            wait_until_finished(&jobs, &id).await;
+        }
+        let running = jobs.spawn("sleep 30", bash("sleep 30"), Duration::from_secs(60)).unwrap();
+
+        // The oldest finished job is forgotten; running jobs never are
+        let ids: Vec<String> = jobs.list().into_iter().map(|job| job.id).collect();
+        assert_eq!(ids, vec!["job-2", "job-3", "job-4"]);
+        assert!(jobs.get("job-1").is_none());
+        assert!(jobs.kill(&running));
+    }
+}
@@ -0,0 +1,112 @@
+use kimichat_toolcore::path_resolver::PathResolver;
+use kimichat_toolcore::tool_context::ToolContext;
+use kimichat_policy::PolicyManager;
+use std::fs;
+use tempfile::TempDir;
+
+#[cfg(test)]
+mod path_resolver_tests {
+    use super::*;
+
+    /// A workspace with `src/main.rs`, next to an `outside` directory holding `secret.txt`
+    fn create_workspace() -> (TempDir, PathResolver) {
+        let temp_dir = TempDir::new().unwrap();
+        let work_dir = temp_dir.path().join("workspace");
+        fs::create_dir_all(work_dir.join("src")).unwrap();
+        fs::write(work_dir.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::create_dir_all(temp_dir.path().join("outside")).unwrap();
+        fs::write(temp_dir.path().join("outside/secret.txt"), "secret").unwrap();
+        (temp_dir, PathResolver::new(work_dir))
+    }
+
+    #[test]
+    fn test_relative_paths_are_normalized() {
+        let (temp_dir, resolver) = create_workspace();
+
+        let resolved = resolver.resolve("./src/../src/main.rs").unwrap();
+        assert_eq!(resolved.relative, "src/main.rs");
+        assert_eq!(resolved.absolute, temp_dir.path().join("workspace/src/main.rs").canonicalize().unwrap());
+
+        assert_eq!(resolver.resolve(".").unwrap().relative, ".");
+    }
+
+    #[test]
+    fn test_absolute_path_inside_workspace() {
+        let (temp_dir, resolver) = create_workspace();
+        let absolute = temp_dir.path().join("workspace/src/main.rs");
+
+        let resolved = resolver.resolve(&absolute).unwrap();
+        assert_eq!(resolved.relative, "src/main.rs");
+    }
+
+    #[test]
+    fn test_escapes_are_rejected() {
+        let (temp_dir, resolver) = create_workspace();
+
+        let error = resolver.resolve("../outside/secret.txt").unwrap_err();
+        assert!(error.to_string().contains("outside the workspace"));
+        assert!(resolver.resolve(temp_dir.path().join("outside/secret.txt")).is_err());
+        assert!(resolver.resolve("src/../../outside/secret.txt").is_err());
+    }
+
+    #[test]
+    fn test_new_files_resolve_through_missing_directories() {
+        let (_temp_dir, resolver) = create_workspace();
+
+        let resolved = resolver.resolve("docs/guide/intro.md").unwrap();
+        assert_eq!(resolved.relative, "docs/guide/intro.md");
+
+        // `..` through directories that do not exist cannot climb out either
+        assert!(resolver.resolve("missing/../../outside/new.txt").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_are_followed() {
+        let (temp_dir, resolver) = create_workspace();
+        let work_dir = temp_dir.path().join("workspace");
+        std::os::unix::fs::symlink(temp_dir.path().join("outside"), work_dir.join("escape")).unwrap();
+        std::os::unix::fs::symlink(work_dir.join("src"), work_dir.join("code")).unwrap();
+
+        assert!(resolver.resolve("escape/secret.txt").is_err());
+        assert!(resolver.resolve("escape/new.txt").is_err());
+        assert_eq!(resolver.resolve("code/main.rs").unwrap().relative, "src/main.rs");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_dangling_symlink_is_rejected() {
+        let (temp_dir, resolver) = create_workspace();
+        let work_dir = temp_dir.path().join("workspace");
+        std::os::unix::fs::symlink(temp_dir.path().join("outside/missing.txt"), work_dir.join("link.txt")).unwrap();
+
+        // Writing through the link would create a file outside the workspace
+        assert!(resolver.resolve("link.txt").is_err());
+    }
+
+    #[test]
+    fn test_extra_roots_are_allowed() {
+        let (temp_dir, resolver) = create_workspace();
+        let outside = temp_dir.path().join("outside");
+        let resolver = resolver.with_extra_roots(vec![outside.clone()]);
+
+        let resolved = resolver.resolve("../outside/secret.txt").unwrap();
+        assert_eq!(resolved.absolute, outside.canonicalize().unwrap().join("secret.txt"));
+        // Paths in extra roots are matched by policies in absolute form
+        assert_eq!(resolved.relative, resolved.absolute.to_string_lossy());
+    }
+
+    #[test]
+    fn test_tool_context_uses_extra_roots() {
+        let (temp_dir, _) = create_workspace();
+        let context = ToolContext::new(
+            temp_dir.path().join("workspace"),
+            "test_session".to_string(),
+            PolicyManager::new(),
+        );
+
+        assert!(context.resolve_path("../outside/secret.txt").is_err());
+        let context = context.with_extra_roots(vec![temp_dir.path().join("outside")]);
+        assert!(context.resolve_path("../outside/secret.txt").is_ok());
+    }
+}
@@ -0,0 +1,47 @@
+use kimichat_toolcore::read_tracker::{Freshness, ReadTracker};
+use std::fs;
+use tempfile::TempDir;
+
+#[cfg(test)]
+mod read_tracker_tests {
+    use super::*;
+
+    #[test]
+    fn test_touched_file_stays_fresh() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("main.rs");
+        fs::write(&file, "fn main() {}\n").unwrap();
+
+        let tracker = ReadTracker::new();
+        assert_eq!(tracker.check(&file), Freshness::Unread);
+        assert_eq!(tracker.check(&temp_dir.path().join("missing.rs")), Freshness::Missing);
+
+        tracker.record(&file, "fn main() {}\n");
+        // Rewriting the same bytes changes the mtime but not the content
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        fs::write(&file, "fn main() {}\n").unwrap();
+        assert_eq!(tracker.check(&file), Freshness::Fresh);
+    }
+
+    #[test]
+    fn test_long_external_diff_is_cut_short() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("data.txt");
+        let before: String = (0..200).map(|n| format!("line {}\n", n)).collect();
+        let after: String = (0..200).map(|n| format!("line {} changed\n", n)).collect();
+        fs::write(&file, &before).unwrap();
+
+        let tracker = ReadTracker::new();
+        tracker.record(&file, &before);
+        fs::write(&file, &after).unwrap();
+
+        match tracker.check(&file) {
+            Freshness::Changed { diff, complete } => {
+                assert!(!complete);
+                assert!(diff.starts_with("--- as last seen\n+++ on disk now\n"));
+                assert!(diff.ends_with("more diff lines ...]"));
+            }
+            other => panic!("expected a change, got {:?}", other),
+        }
+    }
+}
@@ -1,5 +1,9 @@
 use kimichat_toolcore::tool_context::ToolContext;
-use kimichat_policy::PolicyManager;
+use kimichat_toolcore::confirmation::{Confirmation, DenyAllConfirmation, ScriptedConfirmation};
+use kimichat_policy::{ActionType, Decision, PolicyContext, PolicyManager, PolicyRule, SandboxConfig, SessionType};
+use kimichat_checkpoints::CheckpointStore;
+use kimichat_toolcore::ReadTracker;
+use std::sync::Arc;
 use tempfile::TempDir;
 
 #[cfg(test)]
@@ -28,18 +32,15 @@ mod tool_context_tests {
         assert!(context.terminal_manager.is_none());
         assert!(context.skill_registry.is_none());
         assert!(context.todo_manager.is_none());
-        assert!(!context.non_interactive);
+        assert!(format!("{:?}", context.confirmation_provider).contains("TerminalConfirmation"));
     }
 
     #[test]
-    fn test_context_with_non_interactive() {
+    fn test_context_with_confirmation_provider() {
         let (context, _) = create_test_context();
         
-        let non_interactive_context = context.clone().with_non_interactive(true);
-        assert!(non_interactive_context.non_interactive);
-        
-        let interactive_context = context.with_non_interactive(false);
-        assert!(!interactive_context.non_interactive);
+        let unattended_context = context.with_confirmation_provider(Arc::new(DenyAllConfirmation));
+        assert!(format!("{:?}", unattended_context.confirmation_provider).contains("DenyAllConfirmation"));
     }
 
     #[test]
@@ -60,11 +61,11 @@ mod tool_context_tests {
         let (context, _) = create_test_context();
         
         let built_context = context
-            .with_non_interactive(true)
+            .with_confirmation_provider(Arc::new(DenyAllConfirmation))
             .with_env("TEST_VAR".to_string(), "test_value".to_string())
             .with_env("ANOTHER_VAR".to_string(), "another_value".to_string());
         
-        assert!(built_context.non_interactive);
+        assert!(format!("{:?}", built_context.confirmation_provider).contains("DenyAllConfirmation"));
         assert_eq!(built_context.environment.len(), 2);
         assert!(built_context.environment.contains_key("TEST_VAR"));
         assert!(built_context.environment.contains_key("ANOTHER_VAR"));
@@ -85,16 +86,16 @@ mod tool_context_tests {
         let work_dir = temp_dir.path().to_path_buf();
         let policy_manager = PolicyManager::new();
         
-        // Create context in non-interactive mode to avoid user prompts
+        // Deny confirmations to avoid user prompts
         let context = ToolContext::new(work_dir, "test_session".to_string(), policy_manager)
-            .with_non_interactive(true);
+            .with_confirmation_provider(Arc::new(DenyAllConfirmation));
         
         // Test with a typically allowed action
         let action = kimichat_policy::ActionType::FileRead;
         let target = "/tmp/test_file.txt";
         let prompt = "Allow reading test file?";
         
-        let result = context.check_permission(action, target, prompt);
+        let result = context.check_permission(action, target, prompt).await;
         assert!(result.is_ok());
         
         let (_approved, reason) = result.unwrap();
@@ -109,16 +110,16 @@ mod tool_context_tests {
         let work_dir = temp_dir.path().to_path_buf();
         let policy_manager = PolicyManager::new();
         
-        // Create context in non-interactive mode to avoid user prompts
+        // Deny confirmations to avoid user prompts
         let context = ToolContext::new(work_dir, "test_session".to_string(), policy_manager)
-            .with_non_interactive(true);
+            .with_confirmation_provider(Arc::new(DenyAllConfirmation));
         
         // Test with a typically sensitive action
         let action = kimichat_policy::ActionType::CommandExecution;
         let target = "rm -rf /";
         let prompt = "Allow destructive system command?";
         
-        let result = context.check_permission(action, target, prompt);
+        let result = context.check_permission(action, target, prompt).await;
         assert!(result.is_ok());
         
         let (_approved, reason) = result.unwrap();
@@ -133,19 +134,25 @@ mod tool_context_tests {
         let work_dir = temp_dir.path().to_path_buf();
         let policy_manager = PolicyManager::new();
         
+        let confirmation = Arc::new(ScriptedConfirmation::new([Confirmation::Approved]));
         let context = ToolContext::new(work_dir, "test_session".to_string(), policy_manager)
-            .with_non_interactive(true);
+            .with_confirmation_provider(confirmation.clone());
         
         let action = kimichat_policy::ActionType::FileWrite;
         let target = "/tmp/test.txt";
         let prompt = "Allow file write?";
         
-        let result = context.check_permission(action, target, prompt);
+        let result = context.check_permission(action, target, prompt).await;
         assert!(result.is_ok());
         
-        let (_approved, reason) = result.unwrap();
-        // In non-interactive mode, decisions should be deterministic
+        let (approved, reason) = result.unwrap();
+        // The default "ask" decision goes to the provider, which answered yes
+        assert!(approved);
         assert!(reason.is_none());
+        let requests = confirmation.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].target, target);
+        assert_eq!(requests[0].prompt, prompt);
     }
 
     #[test]
@@ -199,16 +206,16 @@ mod tool_context_tests {
         let work_dir = temp_dir.path().to_path_buf();
         let policy_manager = PolicyManager::new();
         
-        // Create context in non-interactive mode to avoid user prompts
+        // Deny confirmations to avoid user prompts
         let context = ToolContext::new(work_dir, "test_session".to_string(), policy_manager)
-            .with_non_interactive(true);
+            .with_confirmation_provider(Arc::new(DenyAllConfirmation));
         
         // Test with empty target
         let action = kimichat_policy::ActionType::FileRead;
         let target = "";
         let prompt = "Test prompt";
         
-        let result = context.check_permission(action, target, prompt);
+        let result = context.check_permission(action, target, prompt).await;
         assert!(result.is_ok());
     }
 
@@ -220,14 +227,14 @@ mod tool_context_tests {
         // Create a policy manager with learning enabled if possible
         let policy_manager = PolicyManager::new();
         let context = ToolContext::new(work_dir, "test_session".to_string(), policy_manager)
-            .with_non_interactive(true);
+            .with_confirmation_provider(Arc::new(DenyAllConfirmation));
         
         let action = kimichat_policy::ActionType::FileRead;
         let target = "/tmp/test.txt";
         let prompt = "Test prompt";
         
         // This should not panic even if learning is enabled
-        let result = context.check_permission(action, target, prompt);
+        let result = context.check_permission(action, target, prompt).await;
         assert!(result.is_ok());
     }
 
@@ -277,7 +284,7 @@ mod tool_context_tests {
         assert_eq!(context.work_dir, cloned_context.work_dir);
         assert_eq!(context.session_id, cloned_context.session_id);
         assert_eq!(context.environment, cloned_context.environment);
-        assert_eq!(context.non_interactive, cloned_context.non_interactive);
+        assert!(Arc::ptr_eq(&context.confirmation_provider, &cloned_context.confirmation_provider));
     }
 
     #[tokio::test]
@@ -286,9 +293,9 @@ mod tool_context_tests {
         let work_dir = temp_dir.path().to_path_buf();
         let policy_manager = PolicyManager::new();
         
-        // Create context in non-interactive mode to avoid user prompts
+        // Deny confirmations to avoid user prompts
         let context = ToolContext::new(work_dir, "test_session".to_string(), policy_manager)
-            .with_non_interactive(true);
+            .with_confirmation_provider(Arc::new(DenyAllConfirmation));
         
         let actions = vec![
             kimichat_policy::ActionType::FileRead,
@@ -300,7 +307,7 @@ mod tool_context_tests {
             let target = format!("/tmp/test_{}.txt", i);
             let prompt = format!("Test prompt {}", i);
             
-            let result = context.check_permission(action, &target, &prompt);
+            let result = context.check_permission(action, &target, &prompt).await;
             assert!(result.is_ok(), "Permission check {} failed", i);
             
             let (_approved, reason) = result.unwrap();
@@ -330,7 +337,7 @@ mod tool_context_tests {
                 assert_eq!(context_clone.session_id, "test_session");
                 assert!(context_clone.work_dir.exists());
                 assert_eq!(context_clone.environment.len(), 0);
-                assert!(!context_clone.non_interactive);
+                assert!(context_clone.extra_roots.is_empty());
                 format!("thread_{}_completed", i)
             });
             handles.push(handle);
@@ -342,4 +349,222 @@ mod tool_context_tests {
             assert!(result.contains("_completed"));
         }
     }
-}
\ No newline at end of file
+    #[tokio::test]
+    async fn test_ask_is_never_silently_allowed() {
+        let (context, _temp_dir) = create_test_context();
+        let context = context.with_confirmation_provider(Arc::new(DenyAllConfirmation));
+
+        let (approved, reason) = context
+            .check_permission(ActionType::CommandExecution, "cargo test", "Execute?")
+            .await
+            .unwrap();
+        assert!(!approved);
+        assert!(reason.unwrap().contains("cargo test"));
+
+        // A scripted provider that runs out of answers rejects as well
+        let context = context.with_confirmation_provider(Arc::new(ScriptedConfirmation::default()));
+        let (approved, _) = context
+            .check_permission(ActionType::FileEdit, "src/main.rs", "Apply?")
+            .await
+            .unwrap();
+        assert!(!approved);
+    }
+
+    #[tokio::test]
+    async fn test_scripted_rejection_reason_is_returned() {
+        let (context, _temp_dir) = create_test_context();
+        let context = context.with_confirmation_provider(Arc::new(ScriptedConfirmation::new([
+            Confirmation::Rejected { reason: Some("wrong file".to_string()) },
+        ])));
+
+        let (approved, reason) = context
+            .check_permission(ActionType::FileEdit, "src/main.rs", "Apply?")
+            .await
+            .unwrap();
+        assert!(!approved);
+        assert_eq!(reason.as_deref(), Some("wrong file"));
+    }
+
+    #[tokio::test]
+    async fn test_policy_decisions_do_not_ask() {
+        let temp_dir = TempDir::new().unwrap();
+        let confirmation = Arc::new(ScriptedConfirmation::default());
+
+        let context = ToolContext::new(temp_dir.path().to_path_buf(), "test_session".to_string(), PolicyManager::allow_all())
+            .with_confirmation_provider(confirmation.clone());
+        let (approved, _) = context.check_permission(ActionType::FileWrite, "a.txt", "Write?").await.unwrap();
+        assert!(approved);
+
+        let policy_path = temp_dir.path().join("policies.toml");
+        let mut config = kimichat_policy::PolicyConfig::default();
+        config.rules.push(PolicyRule::new(ActionType::FileWrite, "*.lock".to_string(), Decision::Deny));
+        config.save_to_file(&policy_path).unwrap();
+        let context = ToolContext::new(
+            temp_dir.path().to_path_buf(),
+            "test_session".to_string(),
+            PolicyManager::from_file(&policy_path, false).unwrap(),
+        )
+        .with_confirmation_provider(confirmation.clone());
+        let (approved, reason) = context.check_permission(ActionType::FileWrite, "Cargo.lock", "Write?").await.unwrap();
+        assert!(!approved);
+        assert_eq!(reason.as_deref(), Some("Denied by policy"));
+
+        assert!(confirmation.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_policy_context_reaches_conditional_rules() {
+        let temp_dir = TempDir::new().unwrap();
+        let policy_path = temp_dir.path().join("policies.toml");
+        let mut config = kimichat_policy::PolicyConfig::default();
+        config.rules.push(
+            PolicyRule::new(ActionType::TerminalLaunch, "*".to_string(), Decision::Deny)
+                .for_session_type(SessionType::Subagent),
+        );
+        config.save_to_file(&policy_path).unwrap();
+
+        let confirmation = Arc::new(ScriptedConfirmation::default());
+        let context = ToolContext::new(
+            temp_dir.path().to_path_buf(),
+            "test_session".to_string(),
+            PolicyManager::from_file(&policy_path, false).unwrap(),
+        )
+        .with_confirmation_provider(confirmation.clone())
+        .with_policy_context(PolicyContext::new(Some("main".to_string()), Some(SessionType::Subagent)));
+
+        let (approved, reason) = context.check_permission(ActionType::TerminalLaunch, "htop", "Launch?").await.unwrap();
+        assert!(!approved);
+        assert_eq!(reason.as_deref(), Some("Denied by policy"));
+        assert!(confirmation.requests().is_empty());
+
+        // The same rule does not apply in a REPL session, so the user is asked
+        let context = context.with_policy_context(PolicyContext::new(Some("main".to_string()), Some(SessionType::Repl)));
+        let (approved, _) = context.check_permission(ActionType::TerminalLaunch, "htop", "Launch?").await.unwrap();
+        assert!(!approved);
+        assert_eq!(confirmation.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_session_approval_is_remembered_and_audited() {
+        let temp_dir = TempDir::new().unwrap();
+        let audit_path = temp_dir.path().join("audit.jsonl");
+        let confirmation = Arc::new(ScriptedConfirmation::new([Confirmation::ApprovedForSession]));
+        let context = ToolContext::new(
+            temp_dir.path().to_path_buf(),
+            "test_session".to_string(),
+            PolicyManager::new().with_audit_log(kimichat_policy::AuditLog::new(audit_path)),
+        )
+        .with_confirmation_provider(confirmation.clone());
+
+        for _ in 0..2 {
+            let (approved, _) = context.check_permission(ActionType::CommandExecution, "make", "Run?").await.unwrap();
+            assert!(approved);
+        }
+        assert_eq!(confirmation.requests().len(), 1);
+
+        let entries = context.policy_manager.audit_log().unwrap().read_entries().unwrap();
+        let answered_by: Vec<&str> = entries.iter().map(|e| e.answered_by.as_str()).collect();
+        assert_eq!(answered_by, vec!["policy", "scripted", "policy"]);
+        assert_eq!(entries[2].scope, kimichat_policy::PolicyScope::Session);
+    }
+
+    #[tokio::test]
+    async fn test_agent_permissions_deny_without_asking() {
+        let temp_dir = TempDir::new().unwrap();
+        let confirmation = Arc::new(ScriptedConfirmation::new([Confirmation::Approved]));
+        let policy_manager = PolicyManager::new().with_agent_layer(vec![
+            PolicyRule::new(ActionType::FileWrite, "*".to_string(), Decision::Deny)
+                .with_description("Agent has read-only file access".to_string()),
+        ]);
+        let context = ToolContext::new(temp_dir.path().to_path_buf(), "test_session".to_string(), policy_manager)
+            .with_confirmation_provider(confirmation.clone())
+            .with_policy_context(PolicyContext::new(Some("code_analyzer".to_string()), None));
+
+        let (approved, _) = context.check_permission(ActionType::FileWrite, "src/lib.rs", "Write?").await.unwrap();
+        assert!(!approved);
+        assert!(confirmation.requests().is_empty());
+
+        let denial = context.check_agent_permission(ActionType::FileWrite, "notes.md").unwrap_err();
+        assert!(denial.contains("read-only"));
+        assert!(context.check_agent_permission(ActionType::FileRead, "notes.md").is_ok());
+
+        let violations = context.policy_manager.take_violations();
+        assert_eq!(violations.len(), 2);
+        assert_eq!(violations[0].agent.as_deref(), Some("code_analyzer"));
+    }
+
+    #[test]
+    fn test_sandbox_from_context_and_rules() {
+        let temp_dir = TempDir::new().unwrap();
+        let policy_file = temp_dir.path().join("policy.toml");
+        let mut config = kimichat_policy::PolicyConfig::default();
+        config.add_rule(
+            PolicyRule::new(ActionType::CommandExecution, "make *".to_string(), Decision::Allow)
+                .with_sandbox(SandboxConfig { cpu_secs: 5, ..SandboxConfig::default() }),
+        );
+        config.save_to_file(&policy_file).unwrap();
+        let policy_manager = PolicyManager::from_file(&policy_file, false).unwrap();
+        let context = ToolContext::new(temp_dir.path().to_path_buf(), "test_session".to_string(), policy_manager);
+
+        assert!(context.sandbox_for(&ActionType::CommandExecution, "cargo build").is_none());
+        assert_eq!(context.sandbox_for(&ActionType::CommandExecution, "make test").unwrap().cpu_secs, 5);
+
+        // The context's own limits apply everywhere, tightened by the rule's
+        let context = context.with_sandbox(SandboxConfig { memory_mb: 256, ..SandboxConfig::default() });
+        assert_eq!(context.sandbox_for(&ActionType::CommandExecution, "cargo build").unwrap().memory_mb, 256);
+        let sandbox = context.sandbox_for(&ActionType::CommandExecution, "make test").unwrap();
+        assert_eq!((sandbox.memory_mb, sandbox.cpu_secs), (256, 5));
+    }
+
+    #[test]
+    fn test_save_checkpoint() {
+        let (context, temp_dir) = create_test_context();
+        let file = temp_dir.path().join("notes.txt");
+        std::fs::write(&file, "before").unwrap();
+        let resolved = context.resolve_path("notes.txt").unwrap();
+
+        // Without a store there is nothing to save
+        assert!(context.save_checkpoint(&resolved).is_ok());
+
+        let store = Arc::new(CheckpointStore::new(&temp_dir.path().join(".checkpoints"), "test"));
+        let context = context.with_checkpoint_store(store.clone());
+        store.begin_turn("edit notes");
+        context.save_checkpoint(&resolved).unwrap();
+        std::fs::write(&file, "after").unwrap();
+
+        let checkpoints = store.list().unwrap();
+        assert_eq!(checkpoints.len(), 1);
+        assert_eq!(checkpoints[0].files[0].display_path, "notes.txt");
+        store.undo().unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "before");
+    }
+
+    #[test]
+    fn test_check_stale() {
+        let (context, temp_dir) = create_test_context();
+        let file = temp_dir.path().join("notes.txt");
+        std::fs::write(&file, "one\ntwo\n").unwrap();
+        let resolved = context.resolve_path("notes.txt").unwrap();
+
+        // Without a tracker nothing is checked
+        assert_eq!(context.check_stale(&resolved, true), Ok(None));
+
+        let context = context.with_read_tracker(Arc::new(ReadTracker::new()));
+        assert!(context.check_stale(&resolved, true).unwrap_err().contains("has not been read"));
+        assert!(context.check_stale(&resolved, false).unwrap().unwrap().contains("without being read"));
+
+        context.record_read(&resolved, "one\ntwo\n");
+        assert_eq!(context.check_stale(&resolved, true), Ok(None));
+
+        std::fs::write(&file, "one\nTWO\nthree\n").unwrap();
+        let refusal = context.check_stale(&resolved, false).unwrap_err();
+        assert!(refusal.contains("changed on disk"));
+        assert!(refusal.contains("-two\n+TWO\n+three"));
+        // The refusal showed the whole change, so a retry goes through
+        assert_eq!(context.check_stale(&resolved, false), Ok(None));
+
+        // Files that do not exist yet can always be written
+        let new_file = context.resolve_path("new.txt").unwrap();
+        assert_eq!(context.check_stale(&new_file, true), Ok(None));
+    }
+}
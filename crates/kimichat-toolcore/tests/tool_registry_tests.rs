@@ -75,6 +75,7 @@ fn create_parameter_definition(name: &str, param_type: &str, required: bool) ->
         description: format!("Test parameter {}", name),
         required,
         default: None,
+        schema: serde_json::Map::new(),
     }
 }
 
@@ -199,6 +200,127 @@ async fn test_tool_execution_not_found() {
     assert!(result.error.unwrap().contains("not found"));
 }
 
+fn create_validated_tool() -> TestTool {
+    let mut parameters = HashMap::new();
+    parameters.insert(
+        "mode".to_string(),
+        create_parameter_definition("mode", "string", true)
+            .with_schema(serde_json::json!({ "enum": ["fast", "slow"] })),
+    );
+    parameters.insert(
+        "count".to_string(),
+        create_parameter_definition("count", "integer", false)
+            .with_schema(serde_json::json!({ "minimum": 1, "maximum": 10 })),
+    );
+    parameters.insert(
+        "edits".to_string(),
+        create_parameter_definition("edits", "array", false).with_schema(serde_json::json!({
+            "items": {
+                "type": "object",
+                "properties": { "file_path": { "type": "string" } },
+                "required": ["file_path"]
+            }
+        })),
+    );
+    TestTool::new("validated_tool", "Tool with a parameter schema").with_parameters(parameters)
+}
+
+#[tokio::test]
+async fn test_tool_execution_validates_arguments() {
+    let mut registry = ToolRegistry::new();
+    registry.register(create_validated_tool());
+    let context = create_test_context();
+
+    let mut params_data = HashMap::new();
+    params_data.insert("mode".to_string(), serde_json::json!("medium"));
+    params_data.insert("count".to_string(), serde_json::json!("3"));
+    params_data.insert("edits".to_string(), serde_json::json!([{ "file_path": "a.rs" }, { "path": "b.rs" }]));
+    let result = registry.execute_tool("validated_tool", ToolParameters { data: params_data }, &context).await;
+
+    assert!(!result.success);
+    let error = result.error.unwrap();
+    assert!(error.contains("the tool was not run"));
+    assert!(error.contains("- mode: "));
+    assert!(error.contains("- count: "));
+    assert!(error.contains("- edits/1: "));
+    assert!(!error.contains("edits/0"));
+
+    let result = registry.execute_tool("validated_tool", ToolParameters { data: HashMap::new() }, &context).await;
+    assert!(!result.success);
+    assert!(result.error.unwrap().contains("\"mode\" is a required property"));
+}
+
+#[tokio::test]
+async fn test_tool_execution_drops_null_optional_arguments() {
+    let mut registry = ToolRegistry::new();
+    registry.register(create_validated_tool());
+    let context = create_test_context();
+
+    let mut params_data = HashMap::new();
+    params_data.insert("mode".to_string(), serde_json::json!("fast"));
+    params_data.insert("count".to_string(), serde_json::Value::Null);
+    let result = registry.execute_tool("validated_tool", ToolParameters { data: params_data }, &context).await;
+
+    assert!(result.success);
+    assert_eq!(result.content, "Executed validated_tool with 1 parameters");
+}
+
+/// Counts how often its parameter schema is read
+struct SchemaCountingTool {
+    inner: TestTool,
+    reads: Arc<std::sync::atomic::AtomicUsize>,
+}
+
+#[async_trait::async_trait]
+impl Tool for SchemaCountingTool {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn description(&self) -> &str {
+        self.inner.description()
+    }
+
+    fn parameters(&self) -> HashMap<String, ParameterDefinition> {
+        self.reads.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        self.inner.parameters()
+    }
+
+    async fn execute(&self, params: ToolParameters, context: &ToolContext) -> ToolResult {
+        self.inner.execute(params, context).await
+    }
+}
+
+#[tokio::test]
+async fn test_schema_is_compiled_once_at_registration() {
+    let reads = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let mut registry = ToolRegistry::new();
+    registry.register(SchemaCountingTool { inner: create_validated_tool(), reads: reads.clone() });
+    let after_registration = reads.load(std::sync::atomic::Ordering::SeqCst);
+    let context = create_test_context();
+
+    for mode in ["fast", "medium", "slow"] {
+        let mut params_data = HashMap::new();
+        params_data.insert("mode".to_string(), serde_json::json!(mode));
+        let result = registry.execute_tool("validated_tool", ToolParameters { data: params_data }, &context).await;
+        assert_eq!(result.success, mode != "medium");
+    }
+    assert_eq!(reads.load(std::sync::atomic::Ordering::SeqCst), after_registration);
+}
+
+#[test]
+fn test_openai_definition_includes_schema() {
+    let definition = create_validated_tool().to_openai_definition();
+    let parameters = &definition["function"]["parameters"];
+
+    assert_eq!(parameters["type"], "object");
+    assert_eq!(parameters["required"], serde_json::json!(["mode"]));
+    assert_eq!(parameters["properties"]["mode"]["enum"], serde_json::json!(["fast", "slow"]));
+    assert_eq!(parameters["properties"]["count"]["maximum"], 10);
+    assert_eq!(parameters["properties"]["edits"]["type"], "array");
+    assert_eq!(parameters["properties"]["edits"]["items"]["required"], serde_json::json!(["file_path"]));
+}
+
 #[tokio::test]
 async fn test_tool_execution_with_parameters() {
     let mut registry = ToolRegistry::new();
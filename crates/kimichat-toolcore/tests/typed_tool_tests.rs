@@ -0,0 +1,169 @@
+use kimichat_toolcore::tool_context::ToolContext;
+use kimichat_toolcore::tool_registry::ToolRegistry;
+use kimichat_toolcore::{tool, Tool, ToolParameters, ToolResult};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::HashMap;
+use tempfile::TempDir;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum Tone {
+    Polite,
+    Cheerful,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct Recipient {
+    /// Name to greet
+    name: String,
+    /// Title to put before the name
+    title: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct GreetArgs {
+    /// Who to greet
+    recipients: Vec<Recipient>,
+    /// How to greet them
+    tone: Tone,
+    /// How many times to repeat the greeting
+    times: Option<u32>,
+}
+
+/// Greet people by name
+///
+/// Say hello to each recipient.
+#[tool(categories = ["examples", "greetings"])]
+async fn greet(args: GreetArgs, context: &ToolContext) -> ToolResult {
+    let greeting = match args.tone {
+        Tone::Polite => "Good day",
+        Tone::Cheerful => "Hi",
+    };
+    let names: Vec<String> = args
+        .recipients
+        .iter()
+        .map(|r| match &r.title {
+            Some(title) => format!("{} {}", title, r.name),
+            None => r.name.clone(),
+        })
+        .collect();
+    let line = format!("{} {} from {}\n", greeting, names.join(", "), context.session_id);
+    ToolResult::success(line.repeat(args.times.unwrap_or(1) as usize))
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct EchoArgs {
+    text: String,
+}
+
+#[tool(name = "echo_text", description = "Echo the text back", read_only)]
+async fn echo(args: EchoArgs) -> ToolResult {
+    ToolResult::success(args.text)
+}
+
+fn create_test_context() -> ToolContext {
+    let temp_dir = TempDir::new().unwrap();
+    ToolContext::new(
+        temp_dir.path().to_path_buf(),
+        "test_session".to_string(),
+        kimichat_policy::PolicyManager::new(),
+    )
+}
+
+fn params(value: serde_json::Value) -> ToolParameters {
+    let data: HashMap<String, serde_json::Value> = serde_json::from_value(value).unwrap();
+    ToolParameters { data }
+}
+
+#[cfg(test)]
+mod typed_tool_tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_definition() {
+        assert_eq!(GreetTool.name(), "greet");
+        assert_eq!(GreetTool.description(), "Greet people by name\n\nSay hello to each recipient.");
+        assert_eq!(GreetTool.categories(), vec!["examples", "greetings"]);
+        assert_eq!(EchoTool.name(), "echo_text");
+        assert_eq!(EchoTool.description(), "Echo the text back");
+        assert!(EchoTool.categories().is_empty());
+        assert!(EchoTool.read_only());
+        assert!(!GreetTool.read_only());
+
+        let parameters = GreetTool.parameters();
+        assert!(parameters["recipients"].required);
+        assert!(parameters["tone"].required);
+        assert!(!parameters["times"].required);
+        assert_eq!(parameters["recipients"].param_type, "array");
+        assert_eq!(parameters["times"].param_type, "integer");
+        assert_eq!(parameters["times"].description, "How many times to repeat the greeting");
+
+        let schema = GreetTool.parameters_schema();
+        assert_eq!(schema["required"], serde_json::json!(["recipients", "tone"]));
+        assert_eq!(schema["properties"]["tone"]["enum"], serde_json::json!(["polite", "cheerful"]));
+        let recipient = &schema["properties"]["recipients"]["items"];
+        assert_eq!(recipient["required"], serde_json::json!(["name"]));
+        assert_eq!(recipient["properties"]["name"]["description"], "Name to greet");
+        assert!(!schema.to_string().contains("$ref"));
+    }
+
+    #[test]
+    fn test_register_uses_declared_categories() {
+        let mut registry = ToolRegistry::new();
+        registry.register(GreetTool);
+        registry.register_with_categories(EchoTool, vec!["examples".to_string()]);
+
+        assert_eq!(registry.get_tools_by_category("greetings").len(), 1);
+        let mut examples: Vec<String> = registry
+            .get_tools_by_category("examples")
+            .iter()
+            .map(|t| t.name().to_string())
+            .collect();
+        examples.sort();
+        assert_eq!(examples, vec!["echo_text", "greet"]);
+    }
+
+    #[tokio::test]
+    async fn test_execute_with_typed_arguments() {
+        let context = create_test_context();
+        let result = GreetTool
+            .execute(
+                params(serde_json::json!({
+                    "recipients": [{ "name": "Ada", "title": "Dr." }, { "name": "Alan" }],
+                    "tone": "polite",
+                    "times": 2
+                })),
+                &context,
+            )
+            .await;
+        assert!(result.success);
+        assert_eq!(result.content, "Good day Dr. Ada, Alan from test_session\n".repeat(2));
+
+        let result = EchoTool.execute(params(serde_json::json!({ "text": "hello" })), &context).await;
+        assert_eq!(result.content, "hello");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_arguments_do_not_run_the_function() {
+        let context = create_test_context();
+
+        // Called directly, deserialization catches it
+        let result = GreetTool
+            .execute(params(serde_json::json!({ "recipients": [], "tone": "grumpy" })), &context)
+            .await;
+        assert!(!result.success);
+        let error = result.error.unwrap();
+        assert!(error.starts_with("Invalid arguments for tool 'greet'"));
+        assert!(error.contains("grumpy"));
+
+        // Through the registry, schema validation catches it first
+        let mut registry = ToolRegistry::new();
+        registry.register(GreetTool);
+        let result = registry
+            .execute_tool("greet", params(serde_json::json!({ "recipients": [{ "title": "Dr." }], "tone": "polite" })), &context)
+            .await;
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("- recipients/0: \"name\" is a required property"));
+    }
+}
@@ -1,10 +1,14 @@
 use kimichat_toolcore::{param, Tool, ToolParameters, ToolResult, ParameterDefinition};
 use kimichat_toolcore::tool_context::ToolContext;
 use kimichat_logging::get_logs_dir;
+use kimichat_toolcore::ResolvedPath;
+use kimichat_policy::ActionType;
 use crate::open_file;
+use crate::patch::{self, FilePatch, HunkReport, PatchKind};
 use async_trait::async_trait;
 use std::collections::HashMap;
 use std::fs;
+use std::path::PathBuf;
 use colored::Colorize;
 use chrono;
 
@@ -24,11 +28,15 @@ impl Tool for OpenFileTool {
     fn parameters(&self) -> HashMap<String, ParameterDefinition> {
         HashMap::from([
             param!("file_path", "string", "Path to the file relative to the work directory", required),
-            param!("start_line", "integer", "Starting line number (1-based)", optional),
-            param!("end_line", "integer", "Ending line number (1-based)", optional),
+            param!("start_line", "integer", "Starting line number (1-based)", optional, schema: serde_json::json!({ "minimum": 1 })),
+            param!("end_line", "integer", "Ending line number (1-based)", optional, schema: serde_json::json!({ "minimum": 1 })),
         ])
     }
 
+    fn read_only(&self) -> bool {
+        true
+    }
+
     async fn execute(&self, params: ToolParameters, context: &ToolContext) -> ToolResult {
         let file_path = match params.get_required::<String>("file_path") {
             Ok(path) => path,
@@ -48,8 +56,21 @@ impl Tool for OpenFileTool {
             None
         };
 
-        match open_file::open_file(&context.work_dir, &file_path, line_range).await {
-            Ok(content) => ToolResult::success(content),
+        let resolved = match context.resolve_path(&file_path) {
+            Ok(resolved) => resolved,
+            Err(e) => return ToolResult::error(format!("{:#}", e)),
+        };
+        if let Err(denial) = context.check_agent_permission(kimichat_policy::ActionType::FileRead, &resolved.relative) {
+            return ToolResult::error(denial);
+        }
+
+        match open_file::open_file(&resolved.absolute, line_range).await {
+            Ok(content) => {
+                if let Ok(full_content) = fs::read_to_string(&resolved.absolute) {
+                    context.record_read(&resolved, &full_content);
+                }
+                ToolResult::success(content)
+            }
             Err(e) => ToolResult::error(format!("Failed to open file: {}", e)),
         }
     }
@@ -74,14 +95,24 @@ impl Tool for ReadFileTool {
         ])
     }
 
+    fn read_only(&self) -> bool {
+        true
+    }
+
     async fn execute(&self, params: ToolParameters, context: &ToolContext) -> ToolResult {
         let file_path = match params.get_required::<String>("file_path") {
             Ok(path) => path,
             Err(e) => return ToolResult::error(e.to_string()),
         };
 
-        // Use the same logic as the original read_file method
-        let full_path = context.work_dir.join(&file_path);
+        let resolved = match context.resolve_path(&file_path) {
+            Ok(resolved) => resolved,
+            Err(e) => return ToolResult::error(format!("{:#}", e)),
+        };
+        if let Err(denial) = context.check_agent_permission(kimichat_policy::ActionType::FileRead, &resolved.relative) {
+            return ToolResult::error(denial);
+        }
+        let full_path = resolved.absolute.clone();
         if !full_path.exists() {
             // Check for directory with similar name
             if let Some(stem) = full_path.file_stem().and_then(|s| s.to_str()) {
@@ -109,6 +140,7 @@ impl Tool for ReadFileTool {
 
         match fs::read_to_string(&full_path) {
             Ok(content) => {
+                context.record_read(&resolved, &content);
                 let lines: Vec<&str> = content.lines().collect();
                 let total_lines = lines.len();
 
@@ -157,7 +189,20 @@ impl Tool for WriteFileTool {
             Err(e) => return ToolResult::error(e.to_string()),
         };
 
-        let full_path = context.work_dir.join(&file_path);
+        let resolved = match context.resolve_path(&file_path) {
+            Ok(resolved) => resolved,
+            Err(e) => return ToolResult::error(format!("{:#}", e)),
+        };
+        if let Err(denial) = context.check_agent_permission(kimichat_policy::ActionType::FileWrite, &resolved.relative) {
+            return ToolResult::error(denial);
+        }
+        if let Err(refusal) = context.check_stale(&resolved, true) {
+            return ToolResult::error(refusal);
+        }
+        if let Err(e) = context.save_checkpoint(&resolved) {
+            return ToolResult::error(format!("Failed to checkpoint {}, file not written: {:#}", file_path, e));
+        }
+        let full_path = resolved.absolute.clone();
 
         // Create parent directories if they don't exist
         if let Some(parent) = full_path.parent() {
@@ -166,8 +211,11 @@ impl Tool for WriteFileTool {
             }
         }
 
-        match fs::write(&full_path, content) {
-            Ok(_) => ToolResult::success(format!("Successfully wrote to file: {}", file_path)),
+        match fs::write(&full_path, &content) {
+            Ok(_) => {
+                context.record_read(&resolved, &content);
+                ToolResult::success(format!("Successfully wrote to file: {}", file_path))
+            }
             Err(e) => ToolResult::error(format!("Failed to write file: {}", e)),
         }
     }
@@ -214,12 +262,21 @@ impl Tool for EditFileTool {
             return ToolResult::error("old_content must not be empty".to_string());
         }
 
-        let full_path = context.work_dir.join(&file_path);
+        let resolved = match context.resolve_path(&file_path) {
+            Ok(resolved) => resolved,
+            Err(e) => return ToolResult::error(format!("{:#}", e)),
+        };
+        let full_path = resolved.absolute.clone();
 
         if !full_path.exists() {
             return ToolResult::error(format!("File not found: {}", file_path));
         }
 
+        let unread_warning = match context.check_stale(&resolved, false) {
+            Ok(warning) => warning,
+            Err(refusal) => return ToolResult::error(refusal),
+        };
+
         // Read current content
         let current_content = match fs::read_to_string(&full_path) {
             Ok(content) => content,
@@ -297,17 +354,27 @@ impl Tool for EditFileTool {
         // Check permission using policy system
         let (approved, rejection_reason) = match context.check_permission(
             kimichat_policy::ActionType::FileEdit,
-            &file_path,
+            &resolved.relative,
             "Apply these changes? [Y/n]"
-        ) {
+        ).await {
             Ok((approved, reason)) => (approved, reason),
             Err(e) => return ToolResult::error(format!("Permission check failed: {}", e)),
         };
 
         if approved {
+            if let Err(e) = context.save_checkpoint(&resolved) {
+                return ToolResult::error(format!("Failed to checkpoint {}, file not edited: {:#}", file_path, e));
+            }
             // Write back to file
-            match fs::write(&full_path, new_content_full) {
-                Ok(_) => ToolResult::success(format!("✅ Successfully edited {} ({} replacement(s))", file_path, occurrences)),
+            match fs::write(&full_path, &new_content_full) {
+                Ok(_) => {
+                    context.record_read(&resolved, &new_content_full);
+                    let mut message = format!("✅ Successfully edited {} ({} replacement(s))", file_path, occurrences);
+                    if let Some(warning) = unread_warning {
+                        message.push_str(&format!("\n{}", warning));
+                    }
+                    ToolResult::success(message)
+                }
                 Err(e) => ToolResult::error(format!("Failed to write file: {}", e)),
             }
         } else {
@@ -321,6 +388,267 @@ impl Tool for EditFileTool {
     }
 }
 
+/// One file's part of a patch, checked and ready to write
+struct StagedFile {
+    patch: FilePatch,
+    /// The file read from (absent when creating)
+    source: Option<ResolvedPath>,
+    /// The file written (absent when deleting)
+    target: Option<ResolvedPath>,
+    old_content: String,
+    new_content: String,
+    hunks: Vec<HunkReport>,
+    problem: Option<String>,
+    /// Set when the source file was not read before being patched
+    warning: Option<String>,
+}
+
+impl StagedFile {
+    fn ok(&self) -> bool {
+        self.problem.is_none() && self.hunks.iter().all(HunkReport::applied)
+    }
+
+    /// Policy actions this change needs, one per path it touches
+    fn actions(&self) -> Vec<(ActionType, &ResolvedPath)> {
+        match (self.patch.kind(), &self.source, &self.target) {
+            (PatchKind::Create, _, Some(target)) => vec![(ActionType::FileWrite, target)],
+            (PatchKind::Delete, Some(source), _) => vec![(ActionType::FileDelete, source)],
+            (PatchKind::Rename, Some(source), Some(target)) => {
+                vec![(ActionType::FileDelete, source), (ActionType::FileWrite, target)]
+            }
+            (_, _, Some(target)) => vec![(ActionType::FileEdit, target)],
+            _ => Vec::new(),
+        }
+    }
+
+    fn report(&self) -> String {
+        let mut report = format!("{} ({})", self.patch.display_path(), self.patch.kind());
+        if let Some(problem) = &self.problem {
+            report.push_str(&format!("\n  FAILED: {}", problem));
+        }
+        for hunk in &self.hunks {
+            report.push_str(&format!("\n  {}", hunk));
+        }
+        if let Some(warning) = &self.warning {
+            report.push_str(&format!("\n  {}", warning));
+        }
+        report
+    }
+}
+
+/// Tool for applying unified diffs to one or more files
+pub struct ApplyPatchTool;
+
+impl ApplyPatchTool {
+    /// Resolve, read and patch one file in memory. `staged` holds the contents
+    /// earlier parts of the same patch leave behind (`None` once removed).
+    fn stage(
+        patch: FilePatch,
+        context: &ToolContext,
+        staged: &mut HashMap<PathBuf, Option<String>>,
+    ) -> Result<StagedFile, String> {
+        let resolve = |path: &Option<String>| -> Result<Option<ResolvedPath>, String> {
+            path.as_deref().map(|path| context.resolve_path(path).map_err(|e| format!("{:#}", e))).transpose()
+        };
+        let source = resolve(&patch.old_path)?;
+        let target = resolve(&patch.new_path)?;
+        let mut file = StagedFile {
+            patch,
+            source,
+            target,
+            old_content: String::new(),
+            new_content: String::new(),
+            hunks: Vec::new(),
+            problem: None,
+            warning: None,
+        };
+        for (action, path) in file.actions() {
+            context.check_agent_permission(action, &path.relative)?;
+        }
+        if let Some(source) = &file.source {
+            if !staged.contains_key(&source.absolute) {
+                match context.check_stale(source, false) {
+                    Ok(warning) => file.warning = warning,
+                    Err(refusal) => {
+                        file.problem = Some(refusal);
+                        return Ok(file);
+                    }
+                }
+            }
+        }
+
+        let current = |path: &ResolvedPath| -> Option<Option<String>> {
+            staged.get(&path.absolute).cloned().or_else(|| {
+                path.absolute.is_file().then(|| fs::read_to_string(&path.absolute).ok())
+            })
+        };
+        if let Some(source) = &file.source {
+            match current(source) {
+                Some(Some(content)) => file.old_content = content,
+                Some(None) => {
+                    file.problem = Some(format!("{} cannot be read as UTF-8 text", source.relative));
+                    return Ok(file);
+                }
+                None => {
+                    file.problem = Some(format!("file not found: {}", source.relative));
+                    return Ok(file);
+                }
+            }
+        }
+        if let Some(target) = &file.target {
+            let creates = matches!(file.patch.kind(), PatchKind::Create | PatchKind::Rename);
+            if creates && current(target).is_some() {
+                file.problem = Some(format!("{} already exists", target.relative));
+                return Ok(file);
+            }
+        }
+
+        let (new_content, hunks) = patch::apply_hunks(&file.old_content, &file.patch.hunks);
+        file.new_content = new_content;
+        file.hunks = hunks;
+        if file.patch.kind() == PatchKind::Delete && file.ok() && !file.new_content.is_empty() {
+            let remaining = file.new_content.lines().count();
+            file.problem = Some(format!("the patch deletes the file but {} line(s) of it are not in the patch", remaining));
+        }
+
+        if file.ok() {
+            if let Some(source) = &file.source {
+                staged.insert(source.absolute.clone(), None);
+            }
+            if let Some(target) = &file.target {
+                staged.insert(target.absolute.clone(), Some(file.new_content.clone()));
+            }
+        }
+        Ok(file)
+    }
+
+    fn write(file: &StagedFile, context: &ToolContext) -> Result<(), String> {
+        for path in file.source.iter().chain(&file.target) {
+            context.save_checkpoint(path).map_err(|e| format!("Failed to checkpoint {}: {:#}", path.relative, e))?;
+        }
+        if let Some(target) = &file.target {
+            if let Some(parent) = target.absolute.parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("Failed to create directories for {}: {}", target.relative, e))?;
+            }
+            fs::write(&target.absolute, &file.new_content).map_err(|e| format!("Failed to write {}: {}", target.relative, e))?;
+            context.record_read(target, &file.new_content);
+        }
+        if let Some(source) = &file.source {
+            if file.target.as_ref().is_none_or(|target| target.absolute != source.absolute) {
+                fs::remove_file(&source.absolute).map_err(|e| format!("Failed to remove {}: {}", source.relative, e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Tool for ApplyPatchTool {
+    fn name(&self) -> &str {
+        "apply_patch"
+    }
+
+    fn description(&self) -> &str {
+        "Apply a unified diff (as from `diff -u` or `git diff`) to one or more files, creating, deleting or renaming files as it says. \
+        Hunks are located by their context, so shifted line numbers and whitespace differences are tolerated. \
+        Reports the result of every hunk; nothing is written unless all of them apply."
+    }
+
+    fn parameters(&self) -> HashMap<String, ParameterDefinition> {
+        HashMap::from([
+            param!("patch", "string", "Unified diff with ---/+++ file headers and @@ hunks. Use /dev/null as the old path to create a file or as the new path to delete one; git 'rename from'/'rename to' lines rename a file. Include about 3 lines of unchanged context around each change.", required),
+        ])
+    }
+
+    async fn execute(&self, params: ToolParameters, context: &ToolContext) -> ToolResult {
+        let patch_text = match params.get_required::<String>("patch") {
+            Ok(patch) => patch,
+            Err(e) => return ToolResult::error(e.to_string()),
+        };
+
+        let file_patches = match patch::parse_patch(&patch_text) {
+            Ok(patches) => patches,
+            Err(e) => return ToolResult::error(format!("Failed to parse patch: {:#}", e)),
+        };
+
+        let mut staged = HashMap::new();
+        let mut files = Vec::new();
+        for file_patch in file_patches {
+            match Self::stage(file_patch, context, &mut staged) {
+                Ok(file) => files.push(file),
+                Err(denial) => return ToolResult::error(denial),
+            }
+        }
+
+        let reports: Vec<String> = files.iter().map(StagedFile::report).collect();
+        if files.iter().any(|file| !file.ok()) {
+            let failed_hunks = files.iter().flat_map(|file| &file.hunks).filter(|hunk| !hunk.applied()).count();
+            let total_hunks: usize = files.iter().map(|file| file.hunks.len()).sum();
+            let failed_files = files.iter().filter(|file| !file.ok()).count();
+            return ToolResult::error(format!(
+                "Patch not applied ({} of {} file(s) failed, {} of {} hunk(s) failed); no files were changed. \
+                Re-read the affected lines and send a corrected patch.\n{}",
+                failed_files, files.len(), failed_hunks, total_hunks, reports.join("\n")
+            ));
+        }
+
+        // Show the resulting changes and ask for confirmation
+        println!("{}", "═".repeat(60).bright_blue());
+        println!("{} {} file(s)", "🩹 Patching:".bright_cyan().bold(), files.len());
+        for file in &files {
+            println!("{}", "═".repeat(60).bright_black());
+            println!("{} {}", format!("{}:", file.patch.kind()).bright_cyan(), file.patch.display_path().bright_white());
+            for hunk in &file.hunks {
+                println!("  {}", hunk.to_string().bright_black());
+            }
+            let diff = similar::TextDiff::from_lines(&file.old_content, &file.new_content);
+            for line in diff.unified_diff().context_radius(3).to_string().lines() {
+                if line.starts_with('+') {
+                    println!("{}", line.green());
+                } else if line.starts_with('-') {
+                    println!("{}", line.red());
+                } else if line.starts_with("@@") {
+                    println!("{}", line.cyan());
+                } else {
+                    println!("{}", line);
+                }
+            }
+        }
+        println!("{}", "═".repeat(60).bright_black());
+
+        // Every path the patch touches goes through the policy on its own
+        for file in &files {
+            for (action, path) in file.actions() {
+                let prompt = format!("Apply patch ({} {})? [Y/n]", file.patch.kind(), path.relative);
+                match context.check_permission(action, &path.relative, &prompt).await {
+                    Ok((true, _)) => {}
+                    Ok((false, reason)) => {
+                        let feedback = reason.map(|reason| format!(": {}", reason)).unwrap_or_default();
+                        return ToolResult::error(format!(
+                            "Patch cancelled at {}{}; no files were changed", path.relative, feedback
+                        ));
+                    }
+                    Err(e) => return ToolResult::error(format!("Permission check failed: {}", e)),
+                }
+            }
+        }
+
+        for (idx, file) in files.iter().enumerate() {
+            if let Err(e) = Self::write(file, context) {
+                let done: Vec<String> = files[..idx].iter().map(|file| file.patch.display_path()).collect();
+                let done = if done.is_empty() { "none".to_string() } else { done.join(", ") };
+                return ToolResult::error(format!("{}. Files already patched: {}", e, done));
+            }
+        }
+
+        ToolResult::success(format!(
+            "✅ Successfully applied patch to {} file(s):\n{}",
+            files.len(),
+            reports.join("\n")
+        ))
+    }
+}
+
 /// Tool for listing files with glob patterns
 pub struct ListFilesTool;
 
@@ -340,6 +668,10 @@ impl Tool for ListFilesTool {
         ])
     }
 
+    fn read_only(&self) -> bool {
+        true
+    }
+
     async fn execute(&self, params: ToolParameters, context: &ToolContext) -> ToolResult {
         let pattern = params.get_optional::<String>("pattern")
             .unwrap_or(Some("*".to_string()))
@@ -347,6 +679,10 @@ impl Tool for ListFilesTool {
 
         eprintln!("[DEBUG] list_files with pattern: '{}' in work_dir: {:?}", pattern, context.work_dir);
 
+        if let Err(denial) = context.check_agent_permission(kimichat_policy::ActionType::FileRead, &pattern) {
+            return ToolResult::error(denial);
+        }
+
         const MAX_FILES: usize = 1000;
 
         // Use ignore crate's WalkBuilder which respects .gitignore
@@ -1,79 +1,57 @@
-use kimichat_toolcore::{param, Tool, ToolParameters, ToolResult, ParameterDefinition};
-use kimichat_toolcore::tool_context::ToolContext;
-use async_trait::async_trait;
-use std::collections::HashMap;
+use kimichat_toolcore::{tool, ToolResult};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Arguments of [`request_more_iterations`]
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RequestMoreIterationsArgs {
+    /// Number of additional iterations requested (1-10)
+    #[schemars(range(min = 1, max = 10))]
+    pub additional_iterations: i32,
+    /// Detailed explanation of why more iterations are needed and what will be accomplished
+    pub justification: String,
+    /// Summary of progress made so far and current findings
+    pub progress_summary: String,
+}
 
-/// Tool for agents to request additional iterations when needed
-pub struct RequestMoreIterationsTool;
+/// Request additional iterations when the current limit is insufficient for completing the task.
+/// Requires strong justification and evidence of productive progress.
+#[tool(categories = ["agent_control"])]
+pub async fn request_more_iterations(args: RequestMoreIterationsArgs) -> ToolResult {
+    let RequestMoreIterationsArgs { additional_iterations: additional, justification, progress_summary } = args;
 
-#[async_trait]
-impl Tool for RequestMoreIterationsTool {
-    fn name(&self) -> &str {
-        "request_more_iterations"
+    // Validate request
+    if !(1..=10).contains(&additional) {
+        return ToolResult::error("Additional iterations must be between 1 and 10".to_string());
     }
 
-    fn description(&self) -> &str {
-        "Request additional iterations when the current limit is insufficient for completing the task. \
-        Requires strong justification and evidence of productive progress."
+    if justification.len() < 50 {
+        return ToolResult::error("Justification must be at least 50 characters - provide detailed reasoning".to_string());
     }
 
-    fn parameters(&self) -> HashMap<String, ParameterDefinition> {
-        HashMap::from([
-            param!("additional_iterations", "integer", "Number of additional iterations requested (1-10)", required),
-            param!("justification", "string", "Detailed explanation of why more iterations are needed and what will be accomplished", required),
-            param!("progress_summary", "string", "Summary of progress made so far and current findings", required),
-        ])
+    if progress_summary.len() < 30 {
+        return ToolResult::error("Progress summary must be at least 30 characters".to_string());
     }
 
-    async fn execute(&self, params: ToolParameters, _context: &ToolContext) -> ToolResult {
-        let additional = match params.get_required::<i32>("additional_iterations") {
-            Ok(val) => val,
-            Err(e) => return ToolResult::error(e.to_string()),
-        };
-
-        let justification = match params.get_required::<String>("justification") {
-            Ok(val) => val,
-            Err(e) => return ToolResult::error(e.to_string()),
-        };
-
-        let progress_summary = match params.get_required::<String>("progress_summary") {
-            Ok(val) => val,
-            Err(e) => return ToolResult::error(e.to_string()),
-        };
-
-        // Validate request
-        if additional < 1 || additional > 10 {
-            return ToolResult::error("Additional iterations must be between 1 and 10".to_string());
-        }
-
-        if justification.len() < 50 {
-            return ToolResult::error("Justification must be at least 50 characters - provide detailed reasoning".to_string());
-        }
-
-        if progress_summary.len() < 30 {
-            return ToolResult::error("Progress summary must be at least 30 characters".to_string());
-        }
-
-        // Simple heuristic evaluation (in real implementation, this would use ProgressEvaluator)
-        // For now, approve reasonable requests
-        let approved = additional <= 5 &&
-                      justification.len() >= 100 &&
-                      !justification.to_lowercase().contains("just in case");
-
-        if approved {
-            ToolResult::success(format!(
-                "✅ APPROVED: {} additional iteration(s) granted.\n\nJustification: {}\n\nProgress: {}\n\n\
-                Use these iterations wisely to complete your task.",
-                additional, justification, progress_summary
-            ))
-        } else {
-            ToolResult::error(format!(
-                "❌ DENIED: Request for {} additional iterations was not approved.\n\n\
-                Reason: Insufficient justification or excessive request.\n\n\
-                Please provide your response based on the information already gathered, \
-                or improve your justification with specific details about what remains to be done.",
-                additional
-            ))
-        }
+    // Simple heuristic evaluation (in real implementation, this would use ProgressEvaluator)
+    // For now, approve reasonable requests
+    let approved = additional <= 5 &&
+                  justification.len() >= 100 &&
+                  !justification.to_lowercase().contains("just in case");
+
+    if approved {
+        ToolResult::success(format!(
+            "✅ APPROVED: {} additional iteration(s) granted.\n\nJustification: {}\n\nProgress: {}\n\n\
+            Use these iterations wisely to complete your task.",
+            additional, justification, progress_summary
+        ))
+    } else {
+        ToolResult::error(format!(
+            "❌ DENIED: Request for {} additional iterations was not approved.\n\n\
+            Reason: Insufficient justification or excessive request.\n\n\
+            Please provide your response based on the information already gathered, \
+            or improve your justification with specific details about what remains to be done.",
+            additional
+        ))
     }
 }
@@ -0,0 +1,168 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+use kimichat_toolcore::{param, CommandOutput, JobManager, JobSnapshot, JobState, Tool, ToolParameters, ToolResult, ParameterDefinition};
+use kimichat_toolcore::tool_context::ToolContext;
+
+use crate::system::{format_output, limit_output, DEFAULT_MAX_OUTPUT_BYTES};
+
+fn job_manager(context: &ToolContext) -> Result<&JobManager, ToolResult> {
+    context
+        .job_manager
+        .as_deref()
+        .ok_or_else(|| ToolResult::error("Background jobs are not available in this session".to_string()))
+}
+
+fn job_id(params: &ToolParameters) -> Result<String, ToolResult> {
+    params.get_required::<String>("job_id").map_err(|e| ToolResult::error(e.to_string()))
+}
+
+fn status_line(job: &JobSnapshot) -> String {
+    format!("{}: {} ({}s) - {}", job.id, job.state, job.elapsed.as_secs(), job.command)
+}
+
+/// Tool for checking on background jobs
+pub struct JobStatusTool;
+
+#[async_trait]
+impl Tool for JobStatusTool {
+    fn name(&self) -> &str {
+        "job_status"
+    }
+
+    fn description(&self) -> &str {
+        "Show whether a background job started by run_command is still running, or list all jobs of the session"
+    }
+
+    fn parameters(&self) -> HashMap<String, ParameterDefinition> {
+        HashMap::from([
+            param!("job_id", "string", "Job to check (default: list all jobs)", optional),
+        ])
+    }
+
+    fn read_only(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, params: ToolParameters, context: &ToolContext) -> ToolResult {
+        let jobs = match job_manager(context) {
+            Ok(jobs) => jobs,
+            Err(result) => return result,
+        };
+
+        match params.get_optional::<String>("job_id") {
+            Ok(Some(id)) => match jobs.get(&id) {
+                Some(job) => ToolResult::success(status_line(&job)),
+                None => ToolResult::error(format!("No job with id '{}'", id)),
+            },
+            Ok(None) => {
+                let list = jobs.list();
+                if list.is_empty() {
+                    return ToolResult::success("No background jobs".to_string());
+                }
+                ToolResult::success(list.iter().map(status_line).collect::<Vec<_>>().join("\n"))
+            }
+            Err(e) => ToolResult::error(e.to_string()),
+        }
+    }
+}
+
+/// Tool for reading a background job's output
+pub struct JobOutputTool;
+
+#[async_trait]
+impl Tool for JobOutputTool {
+    fn name(&self) -> &str {
+        "job_output"
+    }
+
+    fn description(&self) -> &str {
+        "Get the output of a background job so far, with its status"
+    }
+
+    fn parameters(&self) -> HashMap<String, ParameterDefinition> {
+        HashMap::from([
+            param!("job_id", "string", "Job id returned by run_command", required),
+            param!("max_output_bytes", "integer", "Return at most this much output; the rest is saved to a file", optional, DEFAULT_MAX_OUTPUT_BYTES),
+        ])
+    }
+
+    fn read_only(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, params: ToolParameters, context: &ToolContext) -> ToolResult {
+        let jobs = match job_manager(context) {
+            Ok(jobs) => jobs,
+            Err(result) => return result,
+        };
+        let id = match job_id(&params) {
+            Ok(id) => id,
+            Err(result) => return result,
+        };
+        let max_output_bytes = match params.get_optional::<usize>("max_output_bytes") {
+            Ok(max) => max.unwrap_or(DEFAULT_MAX_OUTPUT_BYTES),
+            Err(e) => return ToolResult::error(e.to_string()),
+        };
+
+        let Some(job) = jobs.get(&id) else {
+            return ToolResult::error(format!("No job with id '{}'", id));
+        };
+        let output = CommandOutput {
+            exit_code: match job.state {
+                JobState::Exited { exit_code, .. } => exit_code,
+                _ => None,
+            },
+            stdout: job.stdout.clone(),
+            stderr: job.stderr.clone(),
+            ..Default::default()
+        };
+        let dropped = match job.dropped_bytes {
+            0 => String::new(),
+            bytes => format!("[{} bytes of earlier output were dropped; only the latest is kept]\n", bytes),
+        };
+        ToolResult::success(format!(
+            "{}\n{}{}",
+            status_line(&job),
+            dropped,
+            limit_output(&format_output(&job.command, &output), max_output_bytes, context)
+        ))
+    }
+}
+
+/// Tool for stopping a background job
+pub struct JobKillTool;
+
+#[async_trait]
+impl Tool for JobKillTool {
+    fn name(&self) -> &str {
+        "job_kill"
+    }
+
+    fn description(&self) -> &str {
+        "Stop a running background job and everything it started"
+    }
+
+    fn parameters(&self) -> HashMap<String, ParameterDefinition> {
+        HashMap::from([
+            param!("job_id", "string", "Job id returned by run_command", required),
+        ])
+    }
+
+    async fn execute(&self, params: ToolParameters, context: &ToolContext) -> ToolResult {
+        let jobs = match job_manager(context) {
+            Ok(jobs) => jobs,
+            Err(result) => return result,
+        };
+        let id = match job_id(&params) {
+            Ok(id) => id,
+            Err(result) => return result,
+        };
+
+        match jobs.get(&id) {
+            None => ToolResult::error(format!("No job with id '{}'", id)),
+            Some(job) if !jobs.kill(&id) => ToolResult::error(format!("Job {} is not running: {}", id, job.state)),
+            Some(_) => ToolResult::success(format!("Sent kill to job {}", id)),
+        }
+    }
+}
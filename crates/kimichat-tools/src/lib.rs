@@ -15,6 +15,9 @@ pub mod todo_tools;
 pub mod terminal_tools;
 pub mod open_file;
 pub mod subagent_tools;
+pub mod sandbox;
+pub mod job_tools;
+pub mod patch;
 
 pub use file_ops::*;
 pub use search::*;
@@ -25,3 +28,4 @@ pub use skill_tools::*;
 pub use todo_tools::*;
 pub use terminal_tools::*;
 pub use subagent_tools::*;
+pub use job_tools::*;
@@ -7,7 +7,6 @@ use std::path::PathBuf;
 use colored::Colorize;
 use serde::{Deserialize, Serialize};
 use similar::{ChangeTag, TextDiff};
-use rustyline::DefaultEditor;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct EditOperation {
@@ -145,27 +144,29 @@ impl Tool for PlanEditsTool {
 
     fn parameters(&self) -> HashMap<String, ParameterDefinition> {
         HashMap::from([
-            param!("edits", "string", "JSON array of edit operations with file_path, old_content, new_content, and description fields", required),
+            param!("edits", "array", "Edit operations to apply in order", required,
+                schema: serde_json::json!({
+                    "minItems": 1,
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "file_path": { "type": "string", "description": "Path to the file relative to the work directory" },
+                            "old_content": { "type": "string", "minLength": 1, "description": "Exact content to replace" },
+                            "new_content": { "type": "string", "description": "Content to replace it with" },
+                            "description": { "type": "string", "description": "What the edit does" }
+                        },
+                        "required": ["file_path", "old_content", "new_content", "description"]
+                    }
+                })),
         ])
     }
 
     async fn execute(&self, params: ToolParameters, context: &ToolContext) -> ToolResult {
-        // Parse edits from parameters
-        let edits_str = match params.get_required::<String>("edits") {
+        let edits: Vec<EditOperation> = match params.get_required("edits") {
             Ok(edits) => edits,
             Err(e) => return ToolResult::error(e.to_string()),
         };
 
-        let edits_value: serde_json::Value = match serde_json::from_str(&edits_str) {
-            Ok(value) => value,
-            Err(e) => return ToolResult::error(format!("Failed to parse edits JSON: {}", e)),
-        };
-
-        let edits: Vec<EditOperation> = match serde_json::from_value(edits_value) {
-            Ok(edits) => edits,
-            Err(e) => return ToolResult::error(format!("Failed to parse edits: {}", e)),
-        };
-
         if edits.is_empty() {
             return ToolResult::error("Cannot create empty edit plan. Provide at least one edit operation.".to_string());
         }
@@ -183,7 +184,10 @@ impl Tool for PlanEditsTool {
             );
 
             // Read current file to validate old_content exists
-            let full_path = context.work_dir.join(&edit.file_path);
+            let full_path = match context.resolve_path(&edit.file_path) {
+                Ok(resolved) => resolved.absolute,
+                Err(e) => return ToolResult::error(format!("Edit #{}: {:#}", idx + 1, e)),
+            };
             let current_content = match fs::read_to_string(&full_path) {
                 Ok(content) => content,
                 Err(_) => return ToolResult::error(format!("Edit #{}: File not found: {}", idx + 1, edit.file_path)),
@@ -278,50 +282,43 @@ impl Tool for ApplyEditPlanTool {
 
         println!("{}", "═".repeat(60).bright_black());
 
-        // Check if we need to ask for confirmation
-        // In non-interactive mode (web/API), skip prompt - already confirmed via web UI
-        if !context.non_interactive {
-            // Ask for confirmation in interactive mode
-            println!("\n{}", "Apply all these changes? [Y/n]".bright_green().bold());
-
-            let mut rl = match DefaultEditor::new() {
-                Ok(rl) => rl,
-                Err(e) => return ToolResult::error(format!("Failed to create readline editor: {}", e)),
+        // Refuse before anything is written if a planned file changed on disk since it was read
+        let mut warnings = Vec::new();
+        let mut checked = Vec::new();
+        for edit in &plan {
+            let Ok(resolved) = context.resolve_path(&edit.file_path) else {
+                continue;
             };
-
-            let response = match rl.readline(">>> ") {
-                Ok(resp) => resp,
-                Err(_) => {
+            if checked.contains(&resolved.absolute) {
+                continue;
+            }
+            match context.check_stale(&resolved, false) {
+                Ok(warning) => warnings.extend(warning),
+                Err(refusal) => {
                     clear_edit_plan(&context.work_dir);
-                    return ToolResult::error("Edit plan application cancelled by user".to_string());
+                    return ToolResult::error(format!("{} Edit plan aborted and cleared.", refusal));
                 }
-            };
-
-            let response = response.trim().to_lowercase();
-
-            match response.as_str() {
-                "" | "y" | "yes" => {
-                    // Continue with applying edits
-                    println!("\n{}", "Applying edits...".green());
-                }
-                _ => {
-                    // Cancelled - ask for optional feedback
-                    println!("{}", "Would you like to provide feedback to the model about why you rejected this? (optional)".bright_yellow());
-                    println!("{}", "Press Enter to skip, or type your feedback:".bright_black());
+            }
+            checked.push(resolved.absolute);
+        }
 
-                    let feedback = match rl.readline("") {
-                        Ok(fb) if !fb.trim().is_empty() => format!(" - {}", fb.trim()),
-                        _ => String::new(),
-                    };
+        // Check permission using policy system
+        let files: Vec<&str> = plan.iter().map(|edit| edit.file_path.as_str()).collect();
+        let (approved, rejection_reason) = match context.check_permission(
+            kimichat_policy::ActionType::ApplyEditPlan,
+            &files.join(", "),
+            "Apply all these changes? [Y/n]"
+        ).await {
+            Ok((approved, reason)) => (approved, reason),
+            Err(e) => return ToolResult::error(format!("Permission check failed: {}", e)),
+        };
 
-                    clear_edit_plan(&context.work_dir);
-                    return ToolResult::error(format!("Edit plan application cancelled by user{}", feedback));
-                }
-            }
-        } else {
-            // Non-interactive mode - auto-confirm (already confirmed via web UI)
-            println!("\n{}", "✓ Confirmed via web UI - Applying edits...".green());
+        if !approved {
+            clear_edit_plan(&context.work_dir);
+            let feedback = rejection_reason.map(|reason| format!(" - {}", reason)).unwrap_or_default();
+            return ToolResult::error(format!("Edit plan application cancelled by user{}", feedback));
         }
+        println!("\n{}", "Applying edits...".green());
 
         // Apply all edits sequentially
         let mut results = Vec::new();
@@ -329,7 +326,14 @@ impl Tool for ApplyEditPlanTool {
             println!("\n{} {}", format!("Applying edit #{}", idx + 1).yellow(), edit.file_path.cyan());
 
             // Re-read file to get current state (in case previous edits affected it)
-            let full_path = context.work_dir.join(&edit.file_path);
+            let resolved = match context.resolve_path(&edit.file_path) {
+                Ok(resolved) => resolved,
+                Err(e) => {
+                    clear_edit_plan(&context.work_dir);
+                    return ToolResult::error(format!("Edit #{} failed: {:#}. Edit plan aborted and cleared.", idx + 1, e));
+                }
+            };
+            let full_path = resolved.absolute.clone();
             let current_content = match fs::read_to_string(&full_path) {
                 Ok(content) => content,
                 Err(_) => {
@@ -355,6 +359,14 @@ impl Tool for ApplyEditPlanTool {
             // Apply the edit
             let updated_content = current_content.replace(&edit.old_content, &edit.new_content);
 
+            if let Err(e) = context.save_checkpoint(&resolved) {
+                clear_edit_plan(&context.work_dir);
+                return ToolResult::error(format!(
+                    "Edit #{} failed: Failed to checkpoint {}: {:#}. Edit plan aborted and cleared.",
+                    idx + 1, edit.file_path, e
+                ));
+            }
+
             // Write the updated content
             if let Err(e) = fs::write(&full_path, &updated_content) {
                 clear_edit_plan(&context.work_dir);
@@ -363,6 +375,7 @@ impl Tool for ApplyEditPlanTool {
                     idx + 1, edit.file_path, e
                 ));
             }
+            context.record_read(&resolved, &updated_content);
 
             results.push(format!("✓ {}", edit.file_path));
             println!("  {} {}", "✓".green(), edit.description);
@@ -374,6 +387,7 @@ impl Tool for ApplyEditPlanTool {
         // Clear the plan after successful application
         clear_edit_plan(&context.work_dir);
 
+        results.extend(warnings);
         ToolResult::success(format!(
             "Successfully applied {} edit(s):\n{}",
             plan.len(),
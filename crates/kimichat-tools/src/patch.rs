@@ -0,0 +1,551 @@
+//! Unified diff parsing and fuzzy hunk placement for the apply_patch tool
+//!
+//! Parsing is lenient because models rarely produce perfect diffs: hunk line
+//! counts are only used to tell where a hunk ends, a bare `@@` header is
+//! placed by its context alone, and blank lines inside a hunk are read as
+//! empty context lines. Hunks are placed like `patch` does, searching outward
+//! from the line the header names, first for an exact match, then ignoring
+//! trailing whitespace, then ignoring all whitespace, and finally with up to
+//! [`MAX_FUZZ`] lines of leading and trailing context dropped.
+
+use anyhow::{bail, Result};
+use std::fmt;
+
+/// Most context lines dropped from each end of a hunk when it does not match as a whole
+pub const MAX_FUZZ: usize = 2;
+
+/// One line of a hunk body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HunkLine {
+    Context(String),
+    Remove(String),
+    Add(String),
+}
+
+/// A single `@@` section of a file patch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    /// 1-based line the hunk starts at in the old file, `None` for a bare `@@`
+    pub old_start: Option<usize>,
+    pub lines: Vec<HunkLine>,
+    /// `\ No newline at end of file` followed the last old line
+    pub old_missing_newline: bool,
+    /// `\ No newline at end of file` followed the last new line
+    pub new_missing_newline: bool,
+}
+
+impl Hunk {
+    fn old_lines(&self) -> Vec<&str> {
+        self.lines.iter().filter_map(|line| match line {
+            HunkLine::Context(text) | HunkLine::Remove(text) => Some(text.as_str()),
+            HunkLine::Add(_) => None,
+        }).collect()
+    }
+
+    fn new_lines(&self) -> Vec<&str> {
+        self.lines.iter().filter_map(|line| match line {
+            HunkLine::Context(text) | HunkLine::Add(text) => Some(text.as_str()),
+            HunkLine::Remove(_) => None,
+        }).collect()
+    }
+
+    /// Context lines before the first change and after the last one
+    fn context_around(&self) -> (usize, usize) {
+        let is_context = |line: &&HunkLine| matches!(line, HunkLine::Context(_));
+        let leading = self.lines.iter().take_while(is_context).count();
+        if leading == self.lines.len() {
+            return (leading, 0);
+        }
+        (leading, self.lines.iter().rev().take_while(is_context).count())
+    }
+}
+
+/// The changes a patch makes to one file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilePatch {
+    /// Path before the change, `None` when the patch creates the file
+    pub old_path: Option<String>,
+    /// Path after the change, `None` when the patch deletes the file
+    pub new_path: Option<String>,
+    pub hunks: Vec<Hunk>,
+}
+
+/// What a file patch does to its file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchKind {
+    Create,
+    Delete,
+    Modify,
+    Rename,
+}
+
+impl fmt::Display for PatchKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatchKind::Create => write!(f, "create"),
+            PatchKind::Delete => write!(f, "delete"),
+            PatchKind::Modify => write!(f, "modify"),
+            PatchKind::Rename => write!(f, "rename"),
+        }
+    }
+}
+
+impl FilePatch {
+    pub fn kind(&self) -> PatchKind {
+        match (&self.old_path, &self.new_path) {
+            (None, _) => PatchKind::Create,
+            (_, None) => PatchKind::Delete,
+            (Some(old), Some(new)) if old != new => PatchKind::Rename,
+            _ => PatchKind::Modify,
+        }
+    }
+
+    /// The path shown to the user: `old -> new` for renames
+    pub fn display_path(&self) -> String {
+        match (&self.old_path, &self.new_path) {
+            (Some(old), Some(new)) if old != new => format!("{} -> {}", old, new),
+            (Some(path), _) | (None, Some(path)) => path.clone(),
+            (None, None) => String::new(),
+        }
+    }
+}
+
+/// Parse a unified diff, possibly covering several files, in `diff -u` or `git diff` form
+pub fn parse_patch(text: &str) -> Result<Vec<FilePatch>> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut patches: Vec<FilePatch> = Vec::new();
+    let mut current: Option<FilePatch> = None;
+    // A `diff --git` header was seen and its `---`/`+++` lines may still follow
+    let mut git_header_open = false;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            patches.extend(current.take());
+            let (old_path, new_path) = parse_git_header(rest);
+            current = Some(FilePatch { old_path, new_path, hunks: Vec::new() });
+            git_header_open = true;
+        } else if is_file_header(&lines, i) {
+            let old_path = parse_header_path(&line[4..], "a/");
+            let new_path = parse_header_path(&lines[i + 1][4..], "b/");
+            match current.as_mut() {
+                Some(patch) if git_header_open => {
+                    patch.old_path = old_path;
+                    patch.new_path = new_path;
+                }
+                _ => {
+                    patches.extend(current.take());
+                    current = Some(FilePatch { old_path, new_path, hunks: Vec::new() });
+                }
+            }
+            git_header_open = false;
+            i += 1;
+        } else if line.starts_with("@@") {
+            let Some(patch) = current.as_mut() else {
+                bail!("line {}: hunk header before any ---/+++ file header", i + 1);
+            };
+            git_header_open = false;
+            let (hunk, next) = parse_hunk(&lines, i)?;
+            patch.hunks.push(hunk);
+            i = next;
+            continue;
+        } else if line.starts_with("GIT binary patch") || (line.starts_with("Binary files ") && line.ends_with(" differ")) {
+            bail!("line {}: binary patches are not supported", i + 1);
+        } else if let Some(patch) = current.as_mut().filter(|_| git_header_open) {
+            if line.starts_with("new file mode") {
+                patch.old_path = None;
+            } else if line.starts_with("deleted file mode") {
+                patch.new_path = None;
+            } else if let Some(path) = line.strip_prefix("rename from ") {
+                patch.old_path = Some(path.trim().to_string());
+            } else if let Some(path) = line.strip_prefix("rename to ") {
+                patch.new_path = Some(path.trim().to_string());
+            }
+        }
+        // Anything else (index lines, commit messages, code fences) is ignored
+        i += 1;
+    }
+    patches.extend(current);
+
+    if patches.is_empty() {
+        bail!("no file headers found; expected a unified diff with ---/+++ lines and @@ hunks");
+    }
+    for patch in &patches {
+        if patch.old_path.is_none() && patch.new_path.is_none() {
+            bail!("a file patch has /dev/null as both its old and new path");
+        }
+    }
+    Ok(patches)
+}
+
+/// `---` immediately followed by `+++` starts a file
+fn is_file_header(lines: &[&str], i: usize) -> bool {
+    lines[i].starts_with("--- ") && lines.get(i + 1).is_some_and(|next| next.starts_with("+++ "))
+}
+
+/// Paths from `diff --git a/old b/new`; the `---`/`+++` or rename lines override them
+fn parse_git_header(rest: &str) -> (Option<String>, Option<String>) {
+    match rest.strip_prefix("a/").and_then(|rest| rest.split_once(" b/")) {
+        Some((old, new)) => (Some(old.to_string()), Some(new.to_string())),
+        None => (None, None),
+    }
+}
+
+/// A `---`/`+++` path with any timestamp and `a/`/`b/` prefix removed; `None` for /dev/null
+fn parse_header_path(raw: &str, prefix: &str) -> Option<String> {
+    let path = raw.split('\t').next().unwrap_or(raw).trim();
+    let path = path.strip_prefix('"').and_then(|p| p.strip_suffix('"')).unwrap_or(path);
+    if path == "/dev/null" {
+        return None;
+    }
+    Some(path.strip_prefix(prefix).unwrap_or(path).to_string())
+}
+
+/// `@@ -12,5 +12,7 @@` into (old start, old count, new count); counts default to 1
+fn parse_hunk_header(line: &str) -> Option<(usize, usize, usize)> {
+    let mut fields = line.trim_start_matches('@').split_whitespace();
+    let range = |field: Option<&str>, sign: char| -> Option<(usize, usize)> {
+        let field = field?.strip_prefix(sign)?;
+        match field.split_once(',') {
+            Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+            None => Some((field.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_count) = range(fields.next(), '-')?;
+    let (_, new_count) = range(fields.next(), '+')?;
+    Some((old_start, old_count, new_count))
+}
+
+/// Read the hunk whose header is at `start`; returns it and the index of the line after it
+fn parse_hunk(lines: &[&str], start: usize) -> Result<(Hunk, usize)> {
+    // Without usable counts the hunk simply runs until the next header
+    let (old_start, old_count, new_count) = match parse_hunk_header(lines[start]) {
+        Some((old_start, old_count, new_count)) => (Some(old_start), old_count, new_count),
+        None => (None, 0, 0),
+    };
+    let mut hunk = Hunk { old_start, lines: Vec::new(), old_missing_newline: false, new_missing_newline: false };
+    let (mut old_seen, mut new_seen) = (0, 0);
+    let is_body = |line: &str| line.starts_with([' ', '+', '-']);
+
+    let mut i = start + 1;
+    while i < lines.len() {
+        let line = lines[i];
+        let counts_met = old_seen >= old_count && new_seen >= new_count;
+        if line.starts_with("@@") || line.starts_with("diff --git ") || (counts_met && is_file_header(lines, i)) {
+            break;
+        }
+        let parsed = if line.is_empty() {
+            // Editors and models strip the space off blank context lines
+            if counts_met && !lines.get(i + 1).is_some_and(|next| is_body(next)) {
+                break;
+            }
+            HunkLine::Context(String::new())
+        } else if let Some(text) = line.strip_prefix(' ') {
+            HunkLine::Context(text.to_string())
+        } else if let Some(text) = line.strip_prefix('-') {
+            HunkLine::Remove(text.to_string())
+        } else if let Some(text) = line.strip_prefix('+') {
+            HunkLine::Add(text.to_string())
+        } else if line.starts_with('\\') {
+            match hunk.lines.last() {
+                Some(HunkLine::Remove(_)) => hunk.old_missing_newline = true,
+                Some(HunkLine::Add(_)) => hunk.new_missing_newline = true,
+                Some(HunkLine::Context(_)) => {
+                    hunk.old_missing_newline = true;
+                    hunk.new_missing_newline = true;
+                }
+                None => {}
+            }
+            i += 1;
+            continue;
+        } else {
+            break;
+        };
+        match parsed {
+            HunkLine::Context(_) => {
+                old_seen += 1;
+                new_seen += 1;
+            }
+            HunkLine::Remove(_) => old_seen += 1,
+            HunkLine::Add(_) => new_seen += 1,
+        }
+        hunk.lines.push(parsed);
+        i += 1;
+    }
+
+    if hunk.lines.is_empty() {
+        bail!("line {}: hunk has no lines", start + 1);
+    }
+    Ok((hunk, i))
+}
+
+/// How strictly lines must agree for a hunk to be placed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchMode {
+    Exact,
+    IgnoreTrailingWhitespace,
+    IgnoreWhitespace,
+}
+
+impl MatchMode {
+    const ALL: [MatchMode; 3] = [MatchMode::Exact, MatchMode::IgnoreTrailingWhitespace, MatchMode::IgnoreWhitespace];
+
+    fn lines_match(self, file_line: &str, patch_line: &str) -> bool {
+        match self {
+            MatchMode::Exact => file_line == patch_line,
+            MatchMode::IgnoreTrailingWhitespace => file_line.trim_end() == patch_line.trim_end(),
+            MatchMode::IgnoreWhitespace => file_line
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .eq(patch_line.chars().filter(|c| !c.is_whitespace())),
+        }
+    }
+}
+
+/// The result of applying one hunk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HunkOutcome {
+    /// Applied with its first line at `line` (1-based, in the file before the patch)
+    Applied { line: usize, offset: isize, mode: MatchMode, fuzz: usize },
+    Failed(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HunkReport {
+    /// 1-based position of the hunk in its file patch
+    pub index: usize,
+    pub outcome: HunkOutcome,
+}
+
+impl HunkReport {
+    pub fn applied(&self) -> bool {
+        matches!(self.outcome, HunkOutcome::Applied { .. })
+    }
+}
+
+impl fmt::Display for HunkReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.outcome {
+            HunkOutcome::Applied { line, offset, mode, fuzz } => {
+                write!(f, "hunk {}: applied at line {}", self.index, line)?;
+                let mut notes = Vec::new();
+                if *offset != 0 {
+                    notes.push(format!("offset {:+}", offset));
+                }
+                match mode {
+                    MatchMode::Exact => {}
+                    MatchMode::IgnoreTrailingWhitespace => notes.push("ignoring trailing whitespace".to_string()),
+                    MatchMode::IgnoreWhitespace => notes.push("ignoring whitespace".to_string()),
+                }
+                if *fuzz > 0 {
+                    notes.push(format!("fuzz {}", fuzz));
+                }
+                if !notes.is_empty() {
+                    write!(f, " ({})", notes.join(", "))?;
+                }
+                Ok(())
+            }
+            HunkOutcome::Failed(reason) => write!(f, "hunk {}: FAILED: {}", self.index, reason),
+        }
+    }
+}
+
+/// Apply hunks to a file's content in order
+///
+/// Returns the patched content and a report per hunk. Failed hunks are
+/// skipped, so the content is only meaningful when every hunk applied.
+/// Context lines keep the file's own text, so whitespace-insensitive matches
+/// do not rewrite the lines around a change.
+pub fn apply_hunks(original: &str, hunks: &[Hunk]) -> (String, Vec<HunkReport>) {
+    let eol = if original.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut trailing_newline = original.is_empty() || original.ends_with('\n');
+    let mut buffer: Vec<String> = original.lines().map(str::to_string).collect();
+    let mut reports = Vec::new();
+    // Lines added minus lines removed by the hunks applied so far
+    let mut delta: isize = 0;
+    // How far the previous hunk was from where its header said; later hunks usually drift alike
+    let mut drift: isize = 0;
+    // Hunks must not overlap or go backwards
+    let mut min_pos = 0;
+
+    for (idx, hunk) in hunks.iter().enumerate() {
+        let declared = hunk.old_start.map(|start| start.saturating_sub(1) as isize + delta);
+        let expected = declared.map(|pos| pos + drift).unwrap_or(min_pos as isize).max(0) as usize;
+
+        let Some(placement) = place_hunk(&buffer, hunk, expected, min_pos) else {
+            reports.push(HunkReport { index: idx + 1, outcome: HunkOutcome::Failed(explain_failure(&buffer, hunk, expected, min_pos, delta)) });
+            continue;
+        };
+
+        let mut replacement = Vec::new();
+        let mut cursor = placement.pos;
+        for line in &hunk.lines[placement.skip_front..hunk.lines.len() - placement.skip_back] {
+            match line {
+                HunkLine::Context(_) => {
+                    replacement.push(buffer[cursor].clone());
+                    cursor += 1;
+                }
+                HunkLine::Remove(_) => cursor += 1,
+                HunkLine::Add(text) => replacement.push(text.clone()),
+            }
+        }
+        let added = replacement.len();
+        let reaches_end = cursor == buffer.len();
+        buffer.splice(placement.pos..cursor, replacement);
+
+        let start = placement.pos as isize - placement.skip_front as isize;
+        let line = (start - delta).max(0) as usize + 1;
+        let offset = declared.map(|pos| start - pos).unwrap_or(0);
+        drift = offset;
+        delta += added as isize - (cursor - placement.pos) as isize;
+        min_pos = placement.pos + added;
+        if reaches_end {
+            if hunk.new_missing_newline {
+                trailing_newline = false;
+            } else if hunk.old_missing_newline {
+                trailing_newline = true;
+            }
+        }
+        reports.push(HunkReport {
+            index: idx + 1,
+            outcome: HunkOutcome::Applied { line, offset, mode: placement.mode, fuzz: placement.fuzz },
+        });
+    }
+
+    let mut content = buffer.join(eol);
+    if !buffer.is_empty() && trailing_newline {
+        content.push_str(eol);
+    }
+    (content, reports)
+}
+
+struct Placement {
+    /// Buffer index of the first line of the hunk that was matched
+    pos: usize,
+    /// Context lines dropped from the front and back of the hunk
+    skip_front: usize,
+    skip_back: usize,
+    mode: MatchMode,
+    fuzz: usize,
+}
+
+fn place_hunk(buffer: &[String], hunk: &Hunk, expected: usize, min_pos: usize) -> Option<Placement> {
+    let (leading, trailing) = hunk.context_around();
+    let has_old = !hunk.old_lines().is_empty();
+
+    for fuzz in 0..=MAX_FUZZ {
+        let skip_front = fuzz.min(leading);
+        let skip_back = fuzz.min(trailing);
+        if fuzz > 0 && skip_front < fuzz && skip_back < fuzz {
+            // Dropping more context than the hunk has changes nothing
+            break;
+        }
+        let lines = &hunk.lines[skip_front..hunk.lines.len() - skip_back];
+        let pattern: Vec<&str> = lines.iter().filter_map(|line| match line {
+            HunkLine::Context(text) | HunkLine::Remove(text) => Some(text.as_str()),
+            HunkLine::Add(_) => None,
+        }).collect();
+        if pattern.is_empty() {
+            if has_old {
+                break;
+            }
+            // Pure insertion (such as a new file): it goes where the header says
+            let pos = expected.clamp(min_pos, buffer.len().max(min_pos));
+            return Some(Placement { pos, skip_front, skip_back, mode: MatchMode::Exact, fuzz });
+        }
+        for mode in MatchMode::ALL {
+            if let Some(pos) = find_nearest(buffer, &pattern, expected + skip_front, min_pos, mode) {
+                return Some(Placement { pos, skip_front, skip_back, mode, fuzz });
+            }
+        }
+    }
+    None
+}
+
+/// The match closest to `expected` at or after `min_pos`, preferring later lines on ties
+fn find_nearest(buffer: &[String], pattern: &[&str], expected: usize, min_pos: usize, mode: MatchMode) -> Option<usize> {
+    if pattern.len() > buffer.len() || min_pos > buffer.len() - pattern.len() {
+        return None;
+    }
+    let max_pos = buffer.len() - pattern.len();
+    let expected = expected.clamp(min_pos, max_pos);
+    let matches_at = |pos: usize| {
+        pattern.iter().enumerate().all(|(k, line)| mode.lines_match(&buffer[pos + k], line))
+    };
+
+    for distance in 0..=(max_pos - min_pos) {
+        let after = expected + distance;
+        if after <= max_pos && matches_at(after) {
+            return Some(after);
+        }
+        if distance > 0 && expected >= min_pos + distance && matches_at(expected - distance) {
+            return Some(expected - distance);
+        }
+        if after > max_pos && expected < min_pos + distance {
+            break;
+        }
+    }
+    None
+}
+
+/// Why a hunk could not be placed, pointing at the closest near-miss
+fn explain_failure(buffer: &[String], hunk: &Hunk, expected: usize, min_pos: usize, delta: isize) -> String {
+    let old = hunk.old_lines();
+    let new = hunk.new_lines();
+    let original_line = |pos: usize| (pos as isize - delta).max(0) as usize + 1;
+    let near = match hunk.old_start {
+        Some(_) => format!(" near line {}", original_line(expected)),
+        None => String::new(),
+    };
+
+    if old != new && !new.is_empty() {
+        if let Some(pos) = find_nearest(buffer, &new, expected, 0, MatchMode::IgnoreWhitespace) {
+            return format!(
+                "no match for its {} old line(s){}; the hunk appears to be already applied (its new lines are at line {})",
+                old.len(), near, pos + 1
+            );
+        }
+    }
+    if old.len() > buffer.len() {
+        return format!("it expects {} old line(s) but the file only has {}", old.len(), buffer.len());
+    }
+    if let Some(pos) = MatchMode::ALL.iter().find_map(|&mode| find_nearest(buffer, &old, expected, 0, mode)) {
+        if pos < min_pos {
+            return format!(
+                "its old lines are at line {}, before the end of the previous hunk; hunks must be in file order and must not overlap",
+                original_line(pos)
+            );
+        }
+    }
+
+    // Score every position by how many lines agree, ignoring whitespace
+    let mut best: Option<(usize, usize)> = None;
+    for pos in 0..=(buffer.len() - old.len()) {
+        let score = old.iter().enumerate()
+            .filter(|(k, line)| MatchMode::IgnoreWhitespace.lines_match(&buffer[pos + k], line))
+            .count();
+        let closer = |best_pos: usize| pos.abs_diff(expected) < best_pos.abs_diff(expected);
+        if best.is_none_or(|(best_pos, best_score)| score > best_score || (score == best_score && closer(best_pos))) {
+            best = Some((pos, score));
+        }
+    }
+    match best {
+        Some((pos, score)) if score > 0 => {
+            let Some((k, (file_line, patch_line))) = buffer[pos..].iter().zip(&old).enumerate()
+                .find(|(_, (file_line, patch_line))| !MatchMode::IgnoreWhitespace.lines_match(file_line, patch_line))
+            else {
+                return format!(
+                    "its old lines are at line {}, which an earlier hunk has already changed or passed",
+                    original_line(pos)
+                );
+            };
+            format!(
+                "no match for its {} old line(s){}; closest is at line {} ({} of {} lines agree), first difference at line {}: expected {:?}, found {:?}",
+                old.len(), near, original_line(pos), score, old.len(), original_line(pos + k), patch_line, file_line
+            )
+        }
+        _ => format!("none of its {} old line(s) appear anywhere in the file", old.len()),
+    }
+}
@@ -19,7 +19,8 @@ impl Tool for ProjectAnalysisTool {
 
     fn parameters(&self) -> HashMap<String, ParameterDefinition> {
         HashMap::from([
-            param!("analysis_type", "string", "Type of analysis to perform (structure, dependencies, file_types)", required),
+            param!("analysis_type", "string", "Type of analysis to perform (structure, dependencies, file_types)", required,
+                schema: serde_json::json!({ "enum": ["structure", "dependencies", "file_types"] })),
             param!("target_path", "string", "Target path for analysis (optional)", optional),
         ])
     }
@@ -81,10 +82,15 @@ impl ProjectAnalysisTool {
         let mut result = String::new();
         let mut found_deps = false;
 
+        let dir = match context.resolve_path(path) {
+            Ok(resolved) => resolved.absolute,
+            Err(e) => return ToolResult::error(format!("{:#}", e)),
+        };
+
         // Look for common dependency files
         let dep_files = vec!["Cargo.toml", "package.json", "requirements.txt", "build.gradle"];
         for dep_file in dep_files {
-            let full_path = context.work_dir.join(path).join(dep_file);
+            let full_path = dir.join(dep_file);
             if full_path.exists() {
                 found_deps = true;
                 result.push_str(&format!("Found dependency file: {}\n", dep_file));
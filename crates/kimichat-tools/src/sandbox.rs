@@ -0,0 +1,272 @@
+//! Sandboxed execution for shell commands
+//!
+//! On Linux a command runs under bubblewrap when `bwrap` is installed, and
+//! otherwise under Landlock with a private network namespace. Either way the
+//! workspace stays writable, the rest of the filesystem is read-only, the network
+//! is off unless allowed, and CPU time, memory and wall-clock time are limited.
+
+use anyhow::Result;
+use kimichat_policy::SandboxConfig;
+use kimichat_toolcore::CommandOutput;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::process::Command as AsyncCommand;
+
+/// How a sandboxed command is confined
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SandboxBackend {
+    Bubblewrap(PathBuf),
+    Landlock,
+}
+
+impl std::fmt::Display for SandboxBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SandboxBackend::Bubblewrap(_) => write!(f, "bubblewrap"),
+            SandboxBackend::Landlock => write!(f, "landlock"),
+        }
+    }
+}
+
+impl SandboxBackend {
+    /// Bubblewrap if it is on the PATH, Landlock otherwise
+    pub fn detect() -> Self {
+        std::env::var_os("PATH")
+            .iter()
+            .flat_map(std::env::split_paths)
+            .map(|dir| dir.join("bwrap"))
+            .find(|path| path.is_file())
+            .map(SandboxBackend::Bubblewrap)
+            .unwrap_or(SandboxBackend::Landlock)
+    }
+}
+
+/// Confinement for one command
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    work_dir: PathBuf,
+    limits: SandboxConfig,
+    network: bool,
+    backend: SandboxBackend,
+}
+
+impl Sandbox {
+    pub fn new(work_dir: &Path, limits: SandboxConfig, network: bool) -> Self {
+        Self {
+            work_dir: work_dir.to_path_buf(),
+            limits,
+            network,
+            backend: SandboxBackend::detect(),
+        }
+    }
+
+    /// One line summary for tool output
+    pub fn describe(&self) -> String {
+        format!(
+            "{} (workspace writable, network {}, {} MiB memory, {}s CPU, {}s timeout)",
+            self.backend,
+            if self.network { "on" } else { "off" },
+            self.limits.memory_mb,
+            self.limits.cpu_secs,
+            self.limits.timeout_secs
+        )
+    }
+
+    /// Wall-clock limit for the command
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.limits.timeout_secs)
+    }
+
+    /// Why the sandbox most likely stopped the command, if it did
+    pub fn denial(&self, run: &CommandOutput) -> Option<String> {
+        if run.timed_out {
+            return Some(format!("killed after the {}s time limit", self.limits.timeout_secs));
+        }
+        if run.success() {
+            return None;
+        }
+        if run.signal == Some(SIGXCPU) {
+            return Some(format!("killed after the {}s CPU time limit", self.limits.cpu_secs));
+        }
+
+        let stderr = run.stderr.to_lowercase();
+        let mentions = |needles: &[&str]| needles.iter().any(|needle| stderr.contains(needle));
+        if mentions(&["cannot allocate memory", "out of memory", "memory allocation"]) {
+            return Some(format!("ran out of memory under the {} MiB limit", self.limits.memory_mb));
+        }
+        if !self.network
+            && mentions(&[
+                "network is unreachable",
+                "could not resolve",
+                "temporary failure in name resolution",
+                "name or service not known",
+            ])
+        {
+            return Some("network access is off in the sandbox; a network_access policy rule can allow it".to_string());
+        }
+        self.blocked_write(&run.stderr).map(|path| {
+            format!(
+                "writing {} was blocked; only {} is writable",
+                path.display(),
+                self.work_dir.display()
+            )
+        })
+    }
+
+    /// A path named in a permission error that the sandbox made read-only.
+    /// Paths in the writable places, and paths the command couldn't have
+    /// written without the sandbox either, are ordinary failures.
+    fn blocked_write(&self, stderr: &str) -> Option<PathBuf> {
+        stderr
+            .lines()
+            .filter(|line| {
+                let line = line.to_lowercase();
+                DENIALS.iter().any(|denial| line.contains(denial))
+            })
+            .flat_map(paths_in)
+            .find(|path| !self.is_writable(path) && writable_unconfined(path))
+    }
+
+    /// Inside the workspace or one of the other places the sandbox leaves writable
+    fn is_writable(&self, path: &Path) -> bool {
+        path.starts_with(&self.work_dir) || WRITABLE.iter().any(|dir| path.starts_with(dir))
+    }
+
+    /// Build `bash -c command` confined by the sandbox, starting in `cwd`
+    #[cfg(target_os = "linux")]
+    pub fn command(&self, command: &str, cwd: &Path) -> Result<AsyncCommand> {
+        let mut cmd = match &self.backend {
+            SandboxBackend::Bubblewrap(bwrap) => {
+                let work_dir = self.work_dir.to_string_lossy();
+                let mut cmd = AsyncCommand::new(bwrap);
+                cmd.args(["--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc", "--tmpfs", "/tmp"])
+                    .args(["--bind", &work_dir, &work_dir])
+                    .arg("--chdir")
+                    .arg(cwd)
+                    .args(["--unshare-pid", "--die-with-parent", "--new-session"]);
+                if !self.network {
+                    cmd.arg("--unshare-net");
+                }
+                cmd.args(["--", "bash", "-c", command]);
+                linux::confine(&mut cmd, &self.limits, true, None);
+                cmd
+            }
+            SandboxBackend::Landlock => {
+                let ruleset = linux::landlock_ruleset(&self.work_dir)?;
+                let mut cmd = AsyncCommand::new("bash");
+                cmd.args(["-c", command]);
+                linux::confine(&mut cmd, &self.limits, self.network, Some(ruleset));
+                cmd
+            }
+        };
+        cmd.current_dir(cwd);
+        cmd.env("KIMICHAT_SANDBOX", self.backend.to_string());
+        Ok(cmd)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn command(&self, _command: &str, _cwd: &Path) -> Result<AsyncCommand> {
+        anyhow::bail!("Sandboxed execution is only supported on Linux")
+    }
+}
+
+/// Places writable in the sandbox besides the workspace
+const WRITABLE: [&str; 2] = ["/tmp", "/dev"];
+
+/// How a blocked write shows up in stderr: bubblewrap's read-only bind gives
+/// EROFS, Landlock gives EACCES, and `chmod`-like calls give EPERM
+const DENIALS: [&str; 3] = ["read-only file system", "permission denied", "operation not permitted"];
+
+/// Absolute paths in a line of error output, without the quotes and colons
+/// tools put around them
+fn paths_in(line: &str) -> impl Iterator<Item = PathBuf> + '_ {
+    line.split_whitespace()
+        .map(|word| word.trim_matches(|c: char| matches!(c, '\'' | '"' | '`' | '\u{2018}' | '\u{2019}' | ':' | ',')))
+        .filter(|word| word.starts_with('/'))
+        .map(PathBuf::from)
+}
+
+/// Whether this process may write `path`, or create it in its nearest existing ancestor
+#[cfg(target_os = "linux")]
+fn writable_unconfined(path: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    let Some(existing) = path.ancestors().find(|ancestor| ancestor.exists()) else {
+        return false;
+    };
+    let Ok(existing) = std::ffi::CString::new(existing.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // SAFETY: `existing` is a valid C string for the duration of the call
+    unsafe { libc::access(existing.as_ptr(), libc::W_OK) == 0 }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn writable_unconfined(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(target_os = "linux")]
+const SIGXCPU: i32 = libc::SIGXCPU;
+#[cfg(not(target_os = "linux"))]
+const SIGXCPU: i32 = 24;
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use anyhow::{Context, Result};
+    use kimichat_policy::SandboxConfig;
+    use landlock::{path_beneath_rules, Access, AccessFs, Ruleset, RulesetAttr, RulesetCreatedAttr, ABI};
+    use std::io;
+    use std::os::fd::{AsRawFd, OwnedFd};
+    use std::path::Path;
+    use tokio::process::Command as AsyncCommand;
+
+    /// Read access everywhere, write access to the workspace, /tmp and /dev,
+    /// as the descriptor `landlock_restrict_self` takes
+    pub(super) fn landlock_ruleset(work_dir: &Path) -> Result<OwnedFd> {
+        let abi = ABI::V5;
+        let writable = [work_dir, Path::new("/tmp"), Path::new("/dev")];
+        let ruleset = Ruleset::default()
+            .handle_access(AccessFs::from_all(abi))?
+            .create()?
+            .add_rules(path_beneath_rules(["/"], AccessFs::from_read(abi)))?
+            .add_rules(path_beneath_rules(writable, AccessFs::from_all(abi)))?;
+        Option::<OwnedFd>::from(ruleset).context("Landlock is not supported by this kernel")
+    }
+
+    /// Set up the child between fork and exec: rlimits, a private network
+    /// namespace unless `network`, then the Landlock ruleset
+    pub(super) fn confine(cmd: &mut AsyncCommand, limits: &SandboxConfig, network: bool, ruleset: Option<OwnedFd>) {
+        let memory = limits.memory_mb.saturating_mul(1024 * 1024);
+        let cpu = limits.cpu_secs;
+
+        // SAFETY: after fork the closure must not allocate or take locks. It
+        // only makes system calls with values prepared before forking, and
+        // reports failure as a raw errno, which `io::Error` holds without
+        // allocating.
+        unsafe {
+            cmd.pre_exec(move || {
+                for (resource, value) in [(libc::RLIMIT_AS, memory), (libc::RLIMIT_CPU, cpu), (libc::RLIMIT_CORE, 0)] {
+                    let limit = libc::rlimit {
+                        rlim_cur: value as libc::rlim_t,
+                        rlim_max: value as libc::rlim_t,
+                    };
+                    if libc::setrlimit(resource, &limit) != 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+                if !network && libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                if let Some(ref ruleset) = ruleset {
+                    if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0
+                        || libc::syscall(libc::SYS_landlock_restrict_self, ruleset.as_raw_fd(), 0) != 0
+                    {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+                Ok(())
+            });
+        }
+    }
+}
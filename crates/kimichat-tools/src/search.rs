@@ -28,6 +28,10 @@ impl Tool for SearchFilesTool {
         ])
     }
 
+    fn read_only(&self) -> bool {
+        true
+    }
+
     async fn execute(&self, params: ToolParameters, context: &ToolContext) -> ToolResult {
         let query = match params.get_required::<String>("query") {
             Ok(query) => query,
@@ -50,6 +54,10 @@ impl Tool for SearchFilesTool {
             .unwrap_or(Some(50))
             .unwrap_or(50) as usize;
 
+        if let Err(denial) = context.check_agent_permission(kimichat_policy::ActionType::FileRead, &pattern) {
+            return ToolResult::error(denial);
+        }
+
         // Build search pattern
         let search_regex = if use_regex {
             match Regex::new(&query) {
@@ -22,6 +22,10 @@ impl Tool for LoadSkillTool {
         ])
     }
 
+    fn read_only(&self) -> bool {
+        true
+    }
+
     async fn execute(&self, params: ToolParameters, context: &ToolContext) -> ToolResult {
         let skill_name = match params.get_required::<String>("skill_name") {
             Ok(name) => name,
@@ -75,6 +79,10 @@ impl Tool for ListSkillsTool {
         HashMap::new()
     }
 
+    fn read_only(&self) -> bool {
+        true
+    }
+
     async fn execute(&self, _params: ToolParameters, context: &ToolContext) -> ToolResult {
         // Get skill registry from context
         let skill_registry = match &context.skill_registry {
@@ -120,6 +128,10 @@ impl Tool for FindRelevantSkillsTool {
         ])
     }
 
+    fn read_only(&self) -> bool {
+        true
+    }
+
     async fn execute(&self, params: ToolParameters, context: &ToolContext) -> ToolResult {
         let task_description = match params.get_required::<String>("task_description") {
             Ok(desc) => desc,
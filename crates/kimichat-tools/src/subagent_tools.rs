@@ -5,6 +5,20 @@ use std::collections::HashMap;
 use std::process::Command;
 use serde_json;
 
+/// Ask the policy whether a subagent may be launched for `task`
+async fn check_launch_permission(task: &str, context: &ToolContext) -> Result<(), ToolResult> {
+    match context.check_permission(
+        kimichat_policy::ActionType::SubagentLaunch,
+        task,
+        &format!("Launch a subagent for: {}? [Y/n]", task),
+    ).await {
+        Ok((true, _)) => Ok(()),
+        Ok((false, Some(reason))) => Err(ToolResult::error(format!("Subagent launch cancelled by user: {}", reason))),
+        Ok((false, None)) => Err(ToolResult::error("Subagent launch cancelled by user or policy".to_string())),
+        Err(e) => Err(ToolResult::error(format!("Permission check failed: {}", e))),
+    }
+}
+
 /// Tool for launching a subagent to execute a task independently
 pub struct LaunchSubagentTool;
 
@@ -33,6 +47,10 @@ impl Tool for LaunchSubagentTool {
             Err(e) => return ToolResult::error(e.to_string()),
         };
 
+        if let Err(error) = check_launch_permission(&task, context).await {
+            return error;
+        }
+
         let working_directory = params.get_optional::<String>("working_directory")
             .unwrap_or_else(|_| Some(context.work_dir.to_string_lossy().to_string()))
             .unwrap_or_else(|| context.work_dir.to_string_lossy().to_string());
@@ -137,6 +155,10 @@ impl Tool for LaunchSubagentPrettyTool {
             Err(e) => return ToolResult::error(e.to_string()),
         };
 
+        if let Err(error) = check_launch_permission(&task, context).await {
+            return error;
+        }
+
         let working_directory = params.get_optional::<String>("working_directory")
             .unwrap_or_else(|_| Some(context.work_dir.to_string_lossy().to_string()))
             .unwrap_or_else(|| context.work_dir.to_string_lossy().to_string());
@@ -1,11 +1,22 @@
-use kimichat_toolcore::{param, Tool, ToolParameters, ToolResult, ParameterDefinition};
+use kimichat_toolcore::{param, run_command, CommandOutput, Tool, ToolParameters, ToolResult, ParameterDefinition};
 use kimichat_toolcore::tool_context::ToolContext;
+use kimichat_policy::{ActionType, Decision, SandboxConfig};
+use crate::sandbox::Sandbox;
 use async_trait::async_trait;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tokio::process::Command as AsyncCommand;
 use colored::Colorize;
 use std::io::Write;
 
+/// Default limits for a command's run time and for the output returned to the model
+pub const DEFAULT_TIMEOUT_SECONDS: u64 = 600;
+pub const DEFAULT_MAX_OUTPUT_BYTES: usize = 30_000;
+
+/// Where full output is saved when it is truncated, relative to the workspace
+const OUTPUT_ARTIFACT_DIR: &str = ".kimichat/command-output";
+
 /// Tool for running shell commands
 pub struct RunCommandTool;
 
@@ -16,12 +27,19 @@ impl Tool for RunCommandTool {
     }
 
     fn description(&self) -> &str {
-        "Run a shell command and return the output"
+        "Run a shell command and return the output. Commands are killed after timeout_seconds; \
+         long output is cut to its head and tail with the full text saved to a file. \
+         With background=true the command runs as a job; use job_status, job_output and job_kill to follow it."
     }
 
     fn parameters(&self) -> HashMap<String, ParameterDefinition> {
         HashMap::from([
             param!("command", "string", "Shell command to execute", required),
+            param!("timeout_seconds", "integer", "Kill the command after this many seconds", optional, DEFAULT_TIMEOUT_SECONDS),
+            param!("max_output_bytes", "integer", "Return at most this much output; the rest is saved to a file", optional, DEFAULT_MAX_OUTPUT_BYTES),
+            param!("env", "object", "Extra environment variables, as name/value pairs", optional),
+            param!("cwd", "string", "Directory to run in, relative to the workspace (default: the workspace)", optional),
+            param!("background", "boolean", "Run as a background job and return its id", optional, false),
         ])
     }
 
@@ -30,6 +48,13 @@ impl Tool for RunCommandTool {
             Ok(command) => command,
             Err(e) => return ToolResult::error(e.to_string()),
         };
+        if command.trim().is_empty() {
+            return ToolResult::error("Empty command".to_string());
+        }
+        let options = match RunOptions::from_params(&params, context) {
+            Ok(options) => options,
+            Err(e) => return ToolResult::error(e.to_string()),
+        };
 
         // Basic security checks - prevent dangerous commands
         let dangerous_patterns = [
@@ -46,15 +71,18 @@ impl Tool for RunCommandTool {
             }
         }
 
-        // Check permission using policy system
-        print!("{} {} ", "Run command:".yellow(), command.cyan());
+        // Check permission using policy system. The environment is part of the
+        // target: variables such as PATH or LD_PRELOAD change what runs
+        let target = options.policy_target(&command);
+        print!("{} {} ", "Run command:".yellow(), target.cyan());
         std::io::stdout().flush().ok();
 
+        let action = ActionType::for_command(&command);
         let (approved, rejection_reason) = match context.check_permission(
-            kimichat_policy::ActionType::CommandExecution,
-            &command,
+            action.clone(),
+            &target,
             "Execute? (y/N):"
-        ) {
+        ).await {
             Ok((approved, reason)) => (approved, reason),
             Err(e) => return ToolResult::error(format!("Permission check failed: {}", e)),
         };
@@ -68,50 +96,199 @@ impl Tool for RunCommandTool {
             return ToolResult::error(error_msg);
         }
 
-        println!("{} {}", "Running:".green(), command.cyan());
+        let sandbox = context
+            .sandbox_for(&action, &target)
+            .map(|limits| sandbox_for_command(&command, limits, &options, context));
+        let (mut cmd, timeout) = match &sandbox {
+            Some(sandbox) => match sandbox.command(&command, &options.cwd) {
+                Ok(cmd) => (cmd, sandbox.timeout()),
+                Err(e) => return ToolResult::error(format!("Sandbox unavailable, command not run: {:#}", e)),
+            },
+            None => {
+                let mut cmd = AsyncCommand::new("bash");
+                cmd.args(["-c", &command]).current_dir(&options.cwd);
+                (cmd, options.timeout)
+            }
+        };
+        cmd.envs(&options.env);
 
-        // Parse command and arguments
-        let orig_command = command.clone();
-        let parts: Vec<&str> = command.trim().split_whitespace().collect();
-        if parts.is_empty() {
-            return ToolResult::error("Empty command".to_string());
+        if options.background {
+            let Some(job_manager) = &context.job_manager else {
+                return ToolResult::error("Background jobs are not available in this session".to_string());
+            };
+            return match job_manager.spawn(&command, cmd, timeout) {
+                Ok(job_id) => {
+                    println!("{} {} {}", "Started job:".green(), job_id.cyan(), command.cyan());
+                    ToolResult::success(format!(
+                        "Started background job {}: {}\nTime limit: {}s. Use job_status, job_output and job_kill with job_id \"{}\".",
+                        job_id,
+                        command,
+                        timeout.as_secs(),
+                        job_id
+                    ))
+                }
+                Err(e) => ToolResult::error(format!("Failed to execute command: {}", e)),
+            };
         }
 
-        let (cmd, args) = parts.split_first().unwrap();
+        match &sandbox {
+            Some(sandbox) => println!(
+                "{} {} {}",
+                "Running sandboxed:".green(),
+                command.cyan(),
+                format!("[{}]", sandbox.describe()).dimmed()
+            ),
+            None => println!("{} {}", "Running:".green(), command.cyan()),
+        }
 
-        // Execute command in work directory
-        let output = match AsyncCommand::new("bash")
-            .args(["-c", &orig_command])
-            .current_dir(&context.work_dir)
-            .output()
-            .await
-        {
+        let output = match run_command(cmd, timeout, context.output_sink.clone()).await {
             Ok(output) => output,
-            Err(e) => {
-                return ToolResult::error(format!("Failed to execute command: {}", e));
-            }
+            Err(e) => return ToolResult::error(format!("Failed to execute command: {}", e)),
         };
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let stderr = String::from_utf8_lossy(&output.stderr);
-
-        let result = if !stderr.is_empty() {
-            format!(
-                "Command: {}\nExit code: {}\nSTDOUT:\n{}\nSTDERR:\n{}",
-                command,
-                output.status.code().unwrap_or(-1),
-                stdout,
-                stderr
-            )
-        } else {
-            format!(
-                "Command: {}\nExit code: {}\nSTDOUT:\n{}",
-                command,
-                output.status.code().unwrap_or(-1),
-                stdout
-            )
+        let result = limit_output(&format_output(&command, &output), options.max_output_bytes, context);
+        match &sandbox {
+            Some(sandbox) => {
+                let result = format!("Sandbox: {}\n{}", sandbox.describe(), result);
+                match sandbox.denial(&output) {
+                    Some(denial) => ToolResult::error(format!("Sandbox denial: {}\n{}", denial, result)),
+                    None => ToolResult::success(result),
+                }
+            }
+            None if output.timed_out => ToolResult::error(format!(
+                "Command timed out after {}s and was killed\n{}",
+                timeout.as_secs(),
+                result
+            )),
+            None => ToolResult::success(result),
+        }
+    }
+}
+
+/// The optional parameters of run_command, checked
+struct RunOptions {
+    timeout: Duration,
+    max_output_bytes: usize,
+    env: BTreeMap<String, String>,
+    cwd: PathBuf,
+    background: bool,
+}
+
+impl RunOptions {
+    fn from_params(params: &ToolParameters, context: &ToolContext) -> anyhow::Result<Self> {
+        let timeout_seconds = params.get_optional::<u64>("timeout_seconds")?.unwrap_or(DEFAULT_TIMEOUT_SECONDS);
+        if timeout_seconds == 0 {
+            anyhow::bail!("timeout_seconds must be at least 1");
+        }
+        let cwd = match params.get_optional::<String>("cwd")? {
+            Some(cwd) => {
+                let resolved = context.resolve_path(&cwd)?;
+                if !resolved.absolute.is_dir() {
+                    anyhow::bail!("cwd '{}' is not a directory", cwd);
+                }
+                resolved.absolute
+            }
+            None => context.work_dir.clone(),
         };
+        let env: BTreeMap<String, String> = params.get_optional("env")?.unwrap_or_default();
+        if let Some(name) = env.keys().find(|name| !is_variable_name(name)) {
+            anyhow::bail!("'{}' is not a valid environment variable name", name);
+        }
+        Ok(Self {
+            timeout: Duration::from_secs(timeout_seconds),
+            max_output_bytes: params.get_optional::<usize>("max_output_bytes")?.unwrap_or(DEFAULT_MAX_OUTPUT_BYTES),
+            env,
+            cwd,
+            background: params.get_optional::<bool>("background")?.unwrap_or(false),
+        })
+    }
+
+    /// The command as the policy sees it: `NAME=value ... command`
+    fn policy_target(&self, command: &str) -> String {
+        let assignments = self.env.iter().map(|(name, value)| format!("{}={} ", name, shell_quote(value)));
+        assignments.chain(std::iter::once(command.to_string())).collect()
+    }
+}
+
+fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Quote a word for the shell unless it is plainly safe
+fn shell_quote(word: &str) -> String {
+    if !word.is_empty() && word.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:@%+,".contains(c)) {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+/// The sandbox for an approved command. The network is on only when the
+/// policy allows network access for the command line outright, and the
+/// requested timeout can only shorten the sandbox's.
+fn sandbox_for_command(command: &str, mut limits: SandboxConfig, options: &RunOptions, context: &ToolContext) -> Sandbox {
+    let network = context
+        .policy_manager
+        .explain(&ActionType::NetworkAccess, command, &context.policy_context)
+        .decision
+        == Decision::Allow;
+    limits.timeout_secs = limits.timeout_secs.min(options.timeout.as_secs());
+    Sandbox::new(&context.work_dir, limits, network)
+}
+
+pub(crate) fn format_output(command: &str, output: &CommandOutput) -> String {
+    let exit_code = output.exit_code.unwrap_or(-1);
+    if !output.stderr.is_empty() {
+        format!(
+            "Command: {}\nExit code: {}\nSTDOUT:\n{}\nSTDERR:\n{}",
+            command, exit_code, output.stdout, output.stderr
+        )
+    } else {
+        format!("Command: {}\nExit code: {}\nSTDOUT:\n{}", command, exit_code, output.stdout)
+    }
+}
+
+/// Keep the head and tail of `text` within `max_bytes`, saving the full text
+/// under the workspace so it can still be opened
+pub(crate) fn limit_output(text: &str, max_bytes: usize, context: &ToolContext) -> String {
+    if text.len() <= max_bytes {
+        return text.to_string();
+    }
+    let saved = match save_artifact(text, &context.work_dir) {
+        Ok(path) => format!("full output saved to {}", path),
+        Err(e) => format!("full output could not be saved: {}", e),
+    };
+    let head = floor_char_boundary(text, max_bytes / 2);
+    let tail = ceil_char_boundary(text, text.len() - (max_bytes - head));
+    format!(
+        "{}\n[... {} bytes omitted; {} ...]\n{}",
+        &text[..head],
+        tail - head,
+        saved,
+        &text[tail..]
+    )
+}
+
+fn save_artifact(text: &str, work_dir: &Path) -> std::io::Result<String> {
+    let name = format!("{}.log", chrono::Local::now().format("%Y%m%d-%H%M%S%.3f"));
+    let dir = work_dir.join(OUTPUT_ARTIFACT_DIR);
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join(&name), text)?;
+    Ok(format!("{}/{}", OUTPUT_ARTIFACT_DIR, name))
+}
+
+fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
 
-        ToolResult::success(result)
+fn ceil_char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index += 1;
     }
+    index
 }
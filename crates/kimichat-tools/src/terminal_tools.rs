@@ -6,6 +6,13 @@ use async_trait::async_trait;
 use std::collections::HashMap;
 use serde_json::json;
 
+fn rejection_message(what: &str, reason: Option<String>) -> String {
+    match reason {
+        Some(reason) => format!("{} cancelled by user: {}", what, reason),
+        None => format!("{} cancelled by user or policy", what),
+    }
+}
+
 /// Tool for launching a new PTY terminal session
 pub struct PtyLaunchTool;
 
@@ -36,11 +43,25 @@ impl Tool for PtyLaunchTool {
 
         // Resolve working directory
         let working_dir = if let Some(dir_str) = &working_dir_str {
-            Some(context.work_dir.join(dir_str).display().to_string())
+            match context.resolve_path(dir_str) {
+                Ok(resolved) => Some(resolved.absolute.display().to_string()),
+                Err(e) => return ToolResult::error(format!("{:#}", e)),
+            }
         } else {
             Some(context.work_dir.display().to_string())
         };
 
+        let target = command.clone().unwrap_or_else(|| "shell".to_string());
+        match context.check_permission(
+            kimichat_policy::ActionType::TerminalLaunch,
+            &target,
+            &format!("Launch a terminal session running '{}'? [Y/n]", target),
+        ).await {
+            Ok((true, _)) => {}
+            Ok((false, reason)) => return ToolResult::error(rejection_message("Terminal launch", reason)),
+            Err(e) => return ToolResult::error(format!("Permission check failed: {}", e)),
+        }
+
         // Generate unique session ID
         let session_id = format!("pty-{}", std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -108,6 +129,16 @@ impl Tool for PtySendKeysTool {
             Err(e) => return ToolResult::error(e.to_string()),
         };
 
+        match context.check_permission(
+            kimichat_policy::ActionType::TerminalInput,
+            &keys,
+            &format!("Type {:?} into terminal session {}? [Y/n]", keys, session_id),
+        ).await {
+            Ok((true, _)) => {}
+            Ok((false, reason)) => return ToolResult::error(rejection_message("Terminal input", reason)),
+            Err(e) => return ToolResult::error(format!("Permission check failed: {}", e)),
+        }
+
         // Get terminal manager from context
         let terminal_manager = match &context.terminal_manager {
             Some(tm) => tm,
@@ -144,6 +175,10 @@ impl Tool for PtyGetScreenTool {
         ])
     }
 
+    fn read_only(&self) -> bool {
+        true
+    }
+
     async fn execute(&self, params: ToolParameters, context: &ToolContext) -> ToolResult {
         let session_id = match params.get_required::<String>("session_id") {
             Ok(id) => id,
@@ -208,6 +243,10 @@ impl Tool for PtyListTool {
         HashMap::new()
     }
 
+    fn read_only(&self) -> bool {
+        true
+    }
+
     async fn execute(&self, _params: ToolParameters, context: &ToolContext) -> ToolResult {
         // Get terminal manager from context
         let terminal_manager = match &context.terminal_manager {
@@ -301,6 +340,10 @@ impl Tool for PtyGetCursorTool {
         ])
     }
 
+    fn read_only(&self) -> bool {
+        true
+    }
+
     async fn execute(&self, params: ToolParameters, context: &ToolContext) -> ToolResult {
         let session_id = match params.get_required::<String>("session_id") {
             Ok(id) => id,
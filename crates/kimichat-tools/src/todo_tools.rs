@@ -1,135 +1,97 @@
 use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::Deserialize;
 use std::collections::HashMap;
 
-use kimichat_toolcore::{param, Tool, ToolParameters, ToolResult, ParameterDefinition};
+use kimichat_toolcore::{tool, Tool, ToolParameters, ToolResult, ParameterDefinition};
 use kimichat_toolcore::tool_context::ToolContext;
 use kimichat_todo::{Task, TaskStatus};
 
-/// Tool for managing the todo list
-pub struct TodoWriteTool;
-
-#[async_trait]
-impl Tool for TodoWriteTool {
-    fn name(&self) -> &str {
-        "todo_write"
-    }
+/// Arguments of [`todo_write`]
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TodoWriteArgs {
+    /// The whole task list, replacing the current one
+    pub todos: Vec<TodoItem>,
+}
 
-    fn description(&self) -> &str {
-        "Create and manage a structured task list for tracking progress during complex operations.
-
-Use this tool to:
-- Track multi-step tasks and their progress
-- Show the user what you're working on
-- Update task status as you complete steps
-
-Task Status:
-- pending: Not yet started
-- in_progress: Currently working (only ONE task should be in_progress at a time)
-- completed: Successfully finished
-
-IMPORTANT RULES:
-1. Exactly ONE task should be in_progress at a time (not zero, not multiple)
-2. Mark tasks as completed IMMEDIATELY after finishing
-3. Only mark completed when FULLY accomplished (not if blocked/errored)
-4. Use for complex multi-step tasks (3+ steps)
-5. Don't use for single straightforward tasks
-
-Task Format:
-{
-  \"content\": \"Do the thing\",           // Imperative form
-  \"status\": \"pending\",                  // pending | in_progress | completed
-  \"activeForm\": \"Doing the thing\"       // Present continuous form
-}"
-    }
+/// One task in a `todo_write` call
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TodoItem {
+    /// What to do, in imperative form
+    pub content: String,
+    /// pending, in_progress or completed
+    #[schemars(with = "String", extend("enum" = ["pending", "in_progress", "completed"]))]
+    pub status: TaskStatus,
+    /// What is being done, in present continuous form
+    #[serde(rename = "activeForm")]
+    pub active_form: String,
+}
 
-    fn parameters(&self) -> HashMap<String, ParameterDefinition> {
-        HashMap::from([
-            param!("todos", "string", "JSON array of tasks with content (imperative form), status (pending/in_progress/completed), and activeForm (present continuous form)", required),
-        ])
+/// Create and manage a structured task list for tracking progress during complex operations.
+///
+/// Use this tool to:
+/// - Track multi-step tasks and their progress
+/// - Show the user what you're working on
+/// - Update task status as you complete steps
+///
+/// Task Status:
+/// - pending: Not yet started
+/// - in_progress: Currently working (only ONE task should be in_progress at a time)
+/// - completed: Successfully finished
+///
+/// IMPORTANT RULES:
+/// 1. Exactly ONE task should be in_progress at a time (not zero, not multiple)
+/// 2. Mark tasks as completed IMMEDIATELY after finishing
+/// 3. Only mark completed when FULLY accomplished (not if blocked/errored)
+/// 4. Use for complex multi-step tasks (3+ steps)
+/// 5. Don't use for single straightforward tasks
+///
+/// Task Format:
+/// {
+///   "content": "Do the thing",           // Imperative form
+///   "status": "pending",                  // pending | in_progress | completed
+///   "activeForm": "Doing the thing"       // Present continuous form
+/// }
+#[tool(categories = ["task_tracking"])]
+pub async fn todo_write(args: TodoWriteArgs, context: &ToolContext) -> ToolResult {
+    // Get todo manager from context
+    let todo_manager = match context.todo_manager.as_ref() {
+        Some(tm) => tm,
+        None => return ToolResult::error("Todo manager not available".to_string()),
+    };
+
+    let tasks: Vec<Task> = args
+        .todos
+        .into_iter()
+        .map(|todo| {
+            let mut task = Task::new(todo.content, todo.active_form);
+            task.status = todo.status;
+            task
+        })
+        .collect();
+
+    // Validate that exactly one task is in progress (or none)
+    let in_progress_count = tasks.iter().filter(|t| t.status == TaskStatus::InProgress).count();
+    if in_progress_count > 1 {
+        return ToolResult::error(format!(
+            "Invalid todo list: {} tasks are in_progress, should be exactly 1 or 0",
+            in_progress_count
+        ));
     }
 
-    async fn execute(&self, params: ToolParameters, context: &ToolContext) -> ToolResult {
-        // Get todo manager from context
-        let todo_manager = match context.todo_manager.as_ref() {
-            Some(tm) => tm,
-            None => return ToolResult::error("Todo manager not available".to_string()),
-        };
+    // Update todo manager
+    todo_manager.set_tasks(tasks.clone());
 
-        // Parse todos JSON string
-        let todos_str = match params.get_required::<String>("todos") {
-            Ok(todos) => todos,
-            Err(e) => return ToolResult::error(e.to_string()),
-        };
-
-        let todos_value: serde_json::Value = match serde_json::from_str(&todos_str) {
-            Ok(value) => value,
-            Err(e) => return ToolResult::error(format!("Failed to parse todos JSON: {}", e)),
-        };
-
-        let todos_array = match todos_value.as_array() {
-            Some(arr) => arr,
-            None => return ToolResult::error("Todos parameter must be a JSON array".to_string()),
-        };
+    // Display updated todos
+    todo_manager.display();
 
-        // Parse tasks
-        let mut tasks = Vec::new();
-        for (idx, todo_val) in todos_array.iter().enumerate() {
-            let content = match todo_val.get("content").and_then(|v| v.as_str()) {
-                Some(c) => c.to_string(),
-                None => return ToolResult::error(format!("Task {} missing 'content'", idx + 1)),
-            };
-
-            let status_str = match todo_val.get("status").and_then(|v| v.as_str()) {
-                Some(s) => s,
-                None => return ToolResult::error(format!("Task {} missing 'status'", idx + 1)),
-            };
-
-            let status = match status_str {
-                "pending" => TaskStatus::Pending,
-                "in_progress" => TaskStatus::InProgress,
-                "completed" => TaskStatus::Completed,
-                _ => return ToolResult::error(format!("Task {} has invalid status: {}", idx + 1, status_str)),
-            };
-
-            let active_form = match todo_val.get("activeForm").and_then(|v| v.as_str()) {
-                Some(af) => af.to_string(),
-                None => return ToolResult::error(format!("Task {} missing 'activeForm'", idx + 1)),
-            };
-
-            let mut task = Task::new(content, active_form);
-            task.status = status;
-            tasks.push(task);
-        }
-
-        // Validate that exactly one task is in progress (or none)
-        let in_progress_count = tasks.iter().filter(|t| t.status == TaskStatus::InProgress).count();
-        if in_progress_count > 1 {
-            return ToolResult::error(format!(
-                "Invalid todo list: {} tasks are in_progress, should be exactly 1 or 0",
-                in_progress_count
-            ));
-        }
-
-        // Update todo manager
-        todo_manager.set_tasks(tasks.clone());
-
-        // Display updated todos
-        todo_manager.display();
-
-        ToolResult::success(format!(
-            "Updated todo list: {} tasks ({} pending, {} in progress, {} completed)",
-            tasks.len(),
-            tasks.iter().filter(|t| t.status == TaskStatus::Pending).count(),
-            tasks.iter().filter(|t| t.status == TaskStatus::InProgress).count(),
-            tasks.iter().filter(|t| t.status == TaskStatus::Completed).count()
-        ))
-    }
-}
-
-impl TodoWriteTool {
-    pub fn new() -> Self {
-        Self
-    }
+    ToolResult::success(format!(
+        "Updated todo list: {} tasks ({} pending, {} in progress, {} completed)",
+        tasks.len(),
+        tasks.iter().filter(|t| t.status == TaskStatus::Pending).count(),
+        tasks.iter().filter(|t| t.status == TaskStatus::InProgress).count(),
+        tasks.iter().filter(|t| t.status == TaskStatus::Completed).count()
+    ))
 }
 
 /// Tool for viewing the current todo list
@@ -149,6 +111,10 @@ impl Tool for TodoListTool {
         HashMap::new()
     }
 
+    fn read_only(&self) -> bool {
+        true
+    }
+
     async fn execute(&self, _params: ToolParameters, context: &ToolContext) -> ToolResult {
         let todo_manager = match context.todo_manager.as_ref() {
             Some(tm) => tm,
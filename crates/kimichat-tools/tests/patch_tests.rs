@@ -0,0 +1,244 @@
+use kimichat_policy::PolicyManager;
+use kimichat_toolcore::{Tool, ToolContext, ToolParameters};
+use kimichat_tools::patch::{apply_hunks, parse_patch, HunkLine, HunkOutcome, MatchMode, PatchKind};
+use kimichat_tools::ApplyPatchTool;
+use std::fs;
+use tempfile::TempDir;
+
+/// `line 1` .. `line n`, one per line
+fn numbered(n: usize) -> String {
+    (1..=n).map(|i| format!("line {}\n", i)).collect()
+}
+
+fn create_test_context() -> (TempDir, ToolContext) {
+    let temp_dir = TempDir::new().unwrap();
+    let context = ToolContext::new(temp_dir.path().to_path_buf(), "test_session".to_string(), PolicyManager::allow_all());
+    (temp_dir, context)
+}
+
+async fn apply(context: &ToolContext, patch: &str) -> kimichat_toolcore::ToolResult {
+    let mut params = ToolParameters::new();
+    params.set("patch", patch);
+    ApplyPatchTool.execute(params, context).await
+}
+
+#[cfg(test)]
+mod patch_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_git_diff_with_create_delete_and_rename() {
+        let patch = "\
+diff --git a/src/old.rs b/src/new.rs
+similarity index 90%
+rename from src/old.rs
+rename to src/new.rs
+--- a/src/old.rs
++++ b/src/new.rs
+@@ -1,2 +1,2 @@
+ fn a() {}
+-fn b() {}
++fn c() {}
+diff --git a/added.txt b/added.txt
+new file mode 100644
+--- /dev/null
++++ b/added.txt
+@@ -0,0 +1,2 @@
++one
++two
+diff --git a/gone.txt b/gone.txt
+deleted file mode 100644
+--- a/gone.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-bye
+";
+        let patches = parse_patch(patch).unwrap();
+        let kinds: Vec<PatchKind> = patches.iter().map(|patch| patch.kind()).collect();
+        assert_eq!(kinds, vec![PatchKind::Rename, PatchKind::Create, PatchKind::Delete]);
+        assert_eq!(patches[0].display_path(), "src/old.rs -> src/new.rs");
+        assert_eq!(
+            patches[0].hunks[0].lines,
+            vec![
+                HunkLine::Context("fn a() {}".to_string()),
+                HunkLine::Remove("fn b() {}".to_string()),
+                HunkLine::Add("fn c() {}".to_string()),
+            ]
+        );
+        assert_eq!(patches[1].new_path.as_deref(), Some("added.txt"));
+        assert_eq!(patches[2].old_path.as_deref(), Some("gone.txt"));
+    }
+
+    #[test]
+    fn test_parse_is_lenient_about_headers_and_blank_lines() {
+        // Timestamps, a bare @@, a blank context line without its space and a missing final newline
+        let patch = "\
+--- notes.txt\t2024-01-01 00:00:00
++++ notes.txt\t2024-01-02 00:00:00
+@@
+ first
+
+-last
+\\ No newline at end of file
++end
+\\ No newline at end of file
+";
+        let patches = parse_patch(patch).unwrap();
+        assert_eq!(patches[0].old_path.as_deref(), Some("notes.txt"));
+        let hunk = &patches[0].hunks[0];
+        assert_eq!(hunk.old_start, None);
+        assert_eq!(hunk.lines[1], HunkLine::Context(String::new()));
+        assert!(hunk.old_missing_newline && hunk.new_missing_newline);
+
+        let (content, reports) = apply_hunks("first\n\nlast", &patches[0].hunks);
+        assert!(reports[0].applied());
+        assert_eq!(content, "first\n\nend");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse_patch("just some text").unwrap_err().to_string().contains("no file headers found"));
+        assert!(parse_patch("@@ -1 +1 @@\n-a\n+b\n").unwrap_err().to_string().contains("before any ---/+++ file header"));
+        let binary = "diff --git a/x.png b/x.png\nBinary files a/x.png and b/x.png differ\n";
+        assert!(parse_patch(binary).unwrap_err().to_string().contains("binary patches are not supported"));
+        assert!(parse_patch("--- /dev/null\n+++ /dev/null\n@@ -0,0 +1 @@\n+x\n").is_err());
+    }
+
+    #[test]
+    fn test_hunks_are_placed_by_offset_whitespace_and_fuzz() {
+        // Two lines were added above where the header says the hunk goes
+        let original = format!("new a\nnew b\n{}", numbered(10));
+        let patches = parse_patch("--- f\n+++ f\n@@ -4,3 +4,3 @@\n line 4\n-line 5\n+LINE 5\n line 6\n").unwrap();
+        let (content, reports) = apply_hunks(&original, &patches[0].hunks);
+        assert_eq!(reports[0].outcome, HunkOutcome::Applied { line: 6, offset: 2, mode: MatchMode::Exact, fuzz: 0 });
+        assert_eq!(reports[0].to_string(), "hunk 1: applied at line 6 (offset +2)");
+        assert!(content.contains("line 4\nLINE 5\nline 6\n"));
+
+        // Trailing whitespace in the file, indentation the model got wrong
+        let original = "fn main() {  \n    run();\n}\n";
+        let patches = parse_patch("--- f\n+++ f\n@@ -1,3 +1,3 @@\n fn main() {\n-  run();\n+  start();\n }\n").unwrap();
+        let (content, reports) = apply_hunks(original, &patches[0].hunks);
+        assert!(matches!(reports[0].outcome, HunkOutcome::Applied { mode: MatchMode::IgnoreWhitespace, .. }));
+        // Context keeps the file's own text
+        assert_eq!(content, "fn main() {  \n  start();\n}\n");
+
+        // The first context line is wrong, so it is dropped
+        let patches = parse_patch("--- f\n+++ f\n@@ -3,3 +3,3 @@\n not here\n-line 4\n+LINE 4\n line 5\n").unwrap();
+        let (content, reports) = apply_hunks(&numbered(8), &patches[0].hunks);
+        assert!(matches!(reports[0].outcome, HunkOutcome::Applied { fuzz: 1, .. }));
+        assert!(content.contains("line 3\nLINE 4\nline 5\n"));
+    }
+
+    #[test]
+    fn test_out_of_order_hunks_fail_without_panicking() {
+        let patch = "\
+--- f
++++ f
+@@ -9,3 +9,3 @@
+ line 9
+-line 10
++LINE 10
+ line 11
+@@ -2,3 +2,3 @@
+ line 2
+-line 3
++LINE 3
+ line 4
+";
+        let patches = parse_patch(patch).unwrap();
+        let (_, reports) = apply_hunks(&numbered(12), &patches[0].hunks);
+        assert!(reports[0].applied());
+        let HunkOutcome::Failed(reason) = &reports[1].outcome else {
+            panic!("expected the second hunk to fail");
+        };
+        assert!(reason.contains("at line 2, before the end of the previous hunk"), "{}", reason);
+        assert!(reason.contains("must be in file order"));
+    }
+
+    #[test]
+    fn test_failures_point_at_the_closest_match() {
+        let patches = parse_patch("--- f\n+++ f\n@@ -5,3 +5,3 @@\n line 5\n-line six\n+line 6!\n line 7\n").unwrap();
+        let (_, reports) = apply_hunks(&numbered(10), &patches[0].hunks);
+        let HunkOutcome::Failed(reason) = &reports[0].outcome else {
+            panic!("expected the hunk to fail");
+        };
+        assert!(reason.contains("closest is at line 5 (2 of 3 lines agree)"), "{}", reason);
+        assert!(reason.contains("expected \"line six\", found \"line 6\""), "{}", reason);
+
+        let patches = parse_patch("--- f\n+++ f\n@@ -2,2 +2,2 @@\n line 2\n-line 3\n+LINE 3\n").unwrap();
+        let (_, reports) = apply_hunks("line 1\nline 2\nLINE 3\n", &patches[0].hunks);
+        assert!(reports[0].to_string().contains("already applied"));
+    }
+
+    #[tokio::test]
+    async fn test_tool_creates_deletes_and_renames_files() {
+        let (dir, context) = create_test_context();
+        fs::write(dir.path().join("old.txt"), "keep\nchange\n").unwrap();
+        fs::write(dir.path().join("gone.txt"), "bye\n").unwrap();
+
+        let patch = "\
+diff --git a/old.txt b/moved/new.txt
+rename from old.txt
+rename to moved/new.txt
+--- a/old.txt
++++ b/moved/new.txt
+@@ -1,2 +1,2 @@
+ keep
+-change
++changed
+--- /dev/null
++++ b/added.txt
+@@ -0,0 +1 @@
++hello
+--- a/gone.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-bye
+";
+        let result = apply(&context, patch).await;
+        assert!(result.success, "{:?}", result.error);
+        assert!(!dir.path().join("old.txt").exists());
+        assert_eq!(fs::read_to_string(dir.path().join("moved/new.txt")).unwrap(), "keep\nchanged\n");
+        assert_eq!(fs::read_to_string(dir.path().join("added.txt")).unwrap(), "hello\n");
+        assert!(!dir.path().join("gone.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_tool_failure_messages_leave_files_untouched() {
+        let (dir, context) = create_test_context();
+        fs::write(dir.path().join("a.txt"), numbered(5)).unwrap();
+
+        // One good hunk and one that matches nothing: neither is written
+        let patch = "\
+--- a/a.txt
++++ b/a.txt
+@@ -1,2 +1,2 @@
+-line 1
++LINE 1
+ line 2
+@@ -4,2 +4,2 @@
+-nothing like this
++x
+ line 5
+";
+        let result = apply(&context, patch).await;
+        assert!(!result.success);
+        let error = result.error.unwrap();
+        assert!(error.starts_with("Patch not applied (1 of 1 file(s) failed, 1 of 2 hunk(s) failed); no files were changed."), "{}", error);
+        assert!(error.contains("hunk 1: applied at line 1"));
+        assert!(error.contains("hunk 2: FAILED"));
+        assert_eq!(fs::read_to_string(dir.path().join("a.txt")).unwrap(), numbered(5));
+
+        let error = apply(&context, "--- a/missing.txt\n+++ b/missing.txt\n@@ -1 +1 @@\n-a\n+b\n").await.error.unwrap();
+        assert!(error.contains("file not found: missing.txt"), "{}", error);
+
+        let error = apply(&context, "--- /dev/null\n+++ b/a.txt\n@@ -0,0 +1 @@\n+x\n").await.error.unwrap();
+        assert!(error.contains("a.txt already exists"), "{}", error);
+
+        let error = apply(&context, "--- a/a.txt\n+++ /dev/null\n@@ -1 +0,0 @@\n-line 1\n").await.error.unwrap();
+        assert!(error.contains("the patch deletes the file but 4 line(s) of it are not in the patch"), "{}", error);
+
+        let error = apply(&context, "not a diff").await.error.unwrap();
+        assert!(error.starts_with("Failed to parse patch: no file headers found"), "{}", error);
+    }
+}
@@ -0,0 +1,155 @@
+use kimichat_policy::SandboxConfig;
+use kimichat_toolcore::CommandOutput;
+use kimichat_tools::sandbox::Sandbox;
+use std::path::Path;
+use tempfile::TempDir;
+
+/// A failed run that printed `stderr`
+fn failed(stderr: &str) -> CommandOutput {
+    CommandOutput { exit_code: Some(1), stderr: stderr.to_string(), ..Default::default() }
+}
+
+/// Directories outside /tmp, which the sandbox leaves writable
+fn workspace_and_outside() -> (TempDir, TempDir) {
+    let tmp = env!("CARGO_TARGET_TMPDIR");
+    (TempDir::new_in(tmp).unwrap(), TempDir::new_in(tmp).unwrap())
+}
+
+/// Run `command` in the sandbox, or `None` when this machine can't sandbox
+/// (no bubblewrap, no Landlock, or user namespaces disabled)
+#[cfg(target_os = "linux")]
+async fn run_sandboxed(sandbox: &Sandbox, work_dir: &Path, command: &str) -> Option<std::process::Output> {
+    let probe = sandbox.command("true", work_dir).ok()?.output().await.ok()?;
+    if !probe.status.success() {
+        return None;
+    }
+    Some(sandbox.command(command, work_dir).unwrap().output().await.unwrap())
+}
+
+#[cfg(test)]
+mod sandbox_tests {
+    use super::*;
+
+    #[test]
+    fn test_limits_and_timeouts_are_reported() {
+        let (work_dir, _) = workspace_and_outside();
+        let sandbox = Sandbox::new(work_dir.path(), SandboxConfig::default(), false);
+
+        let timed_out = CommandOutput { timed_out: true, ..Default::default() };
+        assert!(sandbox.denial(&timed_out).unwrap().starts_with("killed after the"));
+        let out_of_memory = failed("fatal: Out of memory, malloc failed");
+        assert!(sandbox.denial(&out_of_memory).unwrap().starts_with("ran out of memory"));
+
+        // A successful run is never a denial, whatever it printed
+        let ok = CommandOutput { exit_code: Some(0), stderr: "touch: /etc/x: Permission denied".to_string(), ..Default::default() };
+        assert_eq!(sandbox.denial(&ok), None);
+    }
+
+    #[test]
+    fn test_network_errors_are_denials_only_with_the_network_off() {
+        let (work_dir, _) = workspace_and_outside();
+        let run = failed("curl: (6) Could not resolve host: example.com");
+
+        let offline = Sandbox::new(work_dir.path(), SandboxConfig::default(), false);
+        assert!(offline.denial(&run).unwrap().starts_with("network access is off"));
+        let online = Sandbox::new(work_dir.path(), SandboxConfig::default(), true);
+        assert_eq!(online.denial(&run), None);
+    }
+
+    #[test]
+    fn test_only_writes_the_sandbox_blocked_are_denials() {
+        let (work_dir, outside) = workspace_and_outside();
+        let sandbox = Sandbox::new(work_dir.path(), SandboxConfig::default(), false);
+
+        // Somewhere this process could write, but the sandbox made read-only
+        let blocked = outside.path().join("notes.txt");
+        for stderr in [
+            format!("bash: {}: Read-only file system", blocked.display()),
+            format!("touch: cannot touch '{}': Permission denied", blocked.display()),
+            format!("mkdir: cannot create directory \u{2018}{}\u{2019}: Read-only file system", blocked.display()),
+        ] {
+            let denial = sandbox.denial(&failed(&stderr)).expect(&stderr);
+            assert_eq!(
+                denial,
+                format!("writing {} was blocked; only {} is writable", blocked.display(), work_dir.path().display())
+            );
+        }
+
+        // Failures the sandbox had nothing to do with
+        let inside = work_dir.path().join("script.sh");
+        for stderr in [
+            format!("bash: {}: Permission denied", inside.display()),
+            format!("chmod: changing permissions of '{}': Operation not permitted", inside.display()),
+            "/tmp/cache: Permission denied".to_string(),
+            "ssh: Permission denied (publickey)".to_string(),
+            "error: could not open file".to_string(),
+        ] {
+            assert_eq!(sandbox.denial(&failed(&stderr)), None, "{}", stderr);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_paths_unwritable_anyway_are_not_denials() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Root may write anywhere, so nothing is unwritable to it
+        if unsafe { libc::geteuid() } == 0 {
+            return;
+        }
+        let (work_dir, outside) = workspace_and_outside();
+        let sandbox = Sandbox::new(work_dir.path(), SandboxConfig::default(), false);
+        let locked = outside.path().join("locked");
+        std::fs::create_dir(&locked).unwrap();
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o555)).unwrap();
+
+        let stderr = format!("cat: {}: Permission denied", locked.join("secret").display());
+        assert_eq!(sandbox.denial(&failed(&stderr)), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_writes_outside_the_workspace_fail() {
+        let (work_dir, outside) = workspace_and_outside();
+        let sandbox = Sandbox::new(work_dir.path(), SandboxConfig::default(), false);
+        let target = outside.path().join("escaped.txt");
+
+        let command = format!("echo inside > inside.txt && echo outside > '{}'", target.display());
+        let Some(output) = run_sandboxed(&sandbox, work_dir.path(), &command).await else {
+            eprintln!("skipping: sandboxing is not available here");
+            return;
+        };
+        assert!(!output.status.success());
+        assert_eq!(std::fs::read_to_string(work_dir.path().join("inside.txt")).unwrap(), "inside\n");
+        assert!(!target.exists());
+
+        let run = CommandOutput {
+            exit_code: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            ..Default::default()
+        };
+        let denial = sandbox.denial(&run).unwrap();
+        assert!(denial.starts_with(&format!("writing {} was blocked", target.display())), "{}", denial);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_network_connections_fail_with_the_network_off() {
+        let (work_dir, _) = workspace_and_outside();
+        // Reachable from here, so only the sandbox can stop the connection
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let command = format!("exec 3<>/dev/tcp/127.0.0.1/{}", port);
+
+        let offline = Sandbox::new(work_dir.path(), SandboxConfig::default(), false);
+        let Some(output) = run_sandboxed(&offline, work_dir.path(), &command).await else {
+            eprintln!("skipping: sandboxing is not available here");
+            return;
+        };
+        assert!(!output.status.success(), "connected from inside the sandbox");
+
+        let online = Sandbox::new(work_dir.path(), SandboxConfig::default(), true);
+        let output = run_sandboxed(&online, work_dir.path(), &command).await.unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    }
+}
@@ -0,0 +1,151 @@
+use kimichat_policy::{ActionType, Decision, PolicyConfig, PolicyManager, PolicyRule};
+use kimichat_toolcore::{
+    Confirmation, DiscardOutput, JobManager, ScriptedConfirmation, Tool, ToolContext, ToolParameters, ToolResult,
+};
+use kimichat_tools::{JobOutputTool, RunCommandTool};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+fn create_test_context() -> (TempDir, ToolContext) {
+    let temp_dir = TempDir::new().unwrap();
+    let context = ToolContext::new(temp_dir.path().to_path_buf(), "test_session".to_string(), PolicyManager::allow_all())
+        .with_output_sink(Arc::new(DiscardOutput));
+    (temp_dir, context)
+}
+
+fn params(value: Value) -> ToolParameters {
+    let data: HashMap<String, Value> = serde_json::from_value(value).unwrap();
+    ToolParameters { data }
+}
+
+async fn run(context: &ToolContext, value: Value) -> ToolResult {
+    RunCommandTool.execute(params(value), context).await
+}
+
+#[cfg(test)]
+mod system_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_long_output_keeps_head_and_tail_and_saves_the_rest() {
+        let (dir, context) = create_test_context();
+        let result = run(&context, json!({ "command": "seq 1 2000", "max_output_bytes": 200 })).await;
+        assert!(result.success, "{:?}", result.error);
+
+        let content = &result.content;
+        assert!(content.starts_with("Command: seq 1 2000\nExit code: 0\nSTDOUT:\n1\n2\n3\n"), "{}", content);
+        assert!(content.ends_with("1999\n2000\n"), "{}", content);
+        assert!(!content.contains("\n1000\n"));
+        assert!(content.len() < 400);
+
+        // The marker names the artifact, which holds everything
+        let marker = content.lines().find(|line| line.starts_with("[... ")).unwrap();
+        assert!(marker.contains(" bytes omitted; full output saved to .kimichat/command-output/"), "{}", marker);
+        let path = marker.split("saved to ").nth(1).unwrap().trim_end_matches(" ...]");
+        assert!(path.ends_with(".log"));
+        let saved = std::fs::read_to_string(dir.path().join(path)).unwrap();
+        assert!(saved.starts_with("Command: seq 1 2000\nExit code: 0\nSTDOUT:\n1\n"));
+        assert!(saved.contains("\n1000\n") && saved.ends_with("2000\n"));
+
+        // Output within the limit is returned whole and nothing is saved
+        let result = run(&context, json!({ "command": "echo short" })).await;
+        assert_eq!(result.content, "Command: echo short\nExit code: 0\nSTDOUT:\nshort\n");
+        assert_eq!(std::fs::read_dir(dir.path().join(".kimichat/command-output")).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_timeout_kills_the_command() {
+        let (_dir, context) = create_test_context();
+        let start = Instant::now();
+        let result = run(&context, json!({ "command": "echo started; sleep 30", "timeout_seconds": 1 })).await;
+
+        assert!(!result.success);
+        let error = result.error.unwrap();
+        assert!(error.starts_with("Command timed out after 1s and was killed"), "{}", error);
+        assert!(error.contains("started"));
+        assert!(start.elapsed() < Duration::from_secs(10));
+
+        let result = run(&context, json!({ "command": "true", "timeout_seconds": 0 })).await;
+        assert_eq!(result.error.unwrap(), "timeout_seconds must be at least 1");
+    }
+
+    #[tokio::test]
+    async fn test_cwd_stays_inside_the_workspace() {
+        let (dir, context) = create_test_context();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+
+        let result = run(&context, json!({ "command": "pwd", "cwd": "sub" })).await;
+        assert!(result.success, "{:?}", result.error);
+        let expected = dir.path().join("sub").canonicalize().unwrap();
+        assert!(result.content.contains(&format!("{}\n", expected.display())), "{}", result.content);
+
+        for cwd in ["..", "/", "sub/../../"] {
+            let result = run(&context, json!({ "command": "pwd", "cwd": cwd })).await;
+            assert!(!result.success, "ran in {}", cwd);
+        }
+        let result = run(&context, json!({ "command": "pwd", "cwd": "missing" })).await;
+        assert!(!result.success);
+    }
+
+    #[tokio::test]
+    async fn test_env_is_added_to_the_environment() {
+        let (_dir, context) = create_test_context();
+        let result = run(
+            &context,
+            json!({ "command": "echo \"$GREETING, $NAME\"; test -n \"$PATH\"", "env": { "GREETING": "hello", "NAME": "kimichat" } }),
+        )
+        .await;
+        assert!(result.success, "{:?}", result.error);
+        assert!(result.content.ends_with("STDOUT:\nhello, kimichat\n"), "{}", result.content);
+
+        let result = run(&context, json!({ "command": "true", "env": ["not", "an", "object"] })).await;
+        assert!(!result.success);
+    }
+
+    #[tokio::test]
+    async fn test_env_is_part_of_the_policy_target() {
+        let dir = TempDir::new().unwrap();
+        let policy_path = dir.path().join("policy.toml");
+        let mut config = PolicyConfig::default();
+        config.rules.push(PolicyRule::new(ActionType::CommandExecution, "echo *".to_string(), Decision::Allow));
+        config.save_to_file(&policy_path).unwrap();
+        let confirmation = Arc::new(ScriptedConfirmation::new([Confirmation::Rejected { reason: None }]));
+        let context = ToolContext::new(dir.path().to_path_buf(), "test_session".to_string(), PolicyManager::from_file(&policy_path, false).unwrap())
+            .with_output_sink(Arc::new(DiscardOutput))
+            .with_confirmation_provider(confirmation.clone());
+
+        let result = run(&context, json!({ "command": "echo hi" })).await;
+        assert!(result.success, "{:?}", result.error);
+
+        // The allow rule covers the command, not a command run with a preloaded library
+        let result = run(&context, json!({ "command": "echo hi", "env": { "LD_PRELOAD": "/tmp/evil.so", "LANG": "C" } })).await;
+        assert!(!result.success);
+        let requests = confirmation.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].target, "LANG=C LD_PRELOAD=/tmp/evil.so echo hi");
+
+        let result = run(&context, json!({ "command": "echo hi", "env": { "NOT VALID": "x" } })).await;
+        assert_eq!(result.error.unwrap(), "'NOT VALID' is not a valid environment variable name");
+    }
+
+    #[tokio::test]
+    async fn test_background_job_output_notes_dropped_output() {
+        let (_dir, context) = create_test_context();
+        let context = context.with_job_manager(Arc::new(JobManager::new().with_output_limit(100)));
+        let result = run(&context, json!({ "command": "seq 1 1000", "background": true })).await;
+        assert!(result.content.starts_with("Started background job job-1: seq 1 1000"), "{}", result.content);
+
+        let output = loop {
+            let output = JobOutputTool.execute(params(json!({ "job_id": "job-1" })), &context).await;
+            if !output.content.contains(": running (") {
+                break output.content;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        };
+        assert!(output.contains("[3793 bytes of earlier output were dropped; only the latest is kept]"), "{}", output);
+        assert!(output.ends_with("999\n1000\n"));
+    }
+}
@@ -4,6 +4,7 @@ use wasm_bindgen::closure::Closure;
 use web_sys::{Document, HtmlElement, HtmlTextAreaElement, Element};
 use std::rc::Rc;
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 use gloo_net::websocket::futures::WebSocket;
 use futures::{StreamExt, SinkExt};
 use crate::protocol::{ClientMessage, ServerMessage, Message};
@@ -11,6 +12,11 @@ use crate::dom;
 use crate::markdown;
 use crate::utils;
 
+/// The WebSocket's sending half. Sends are awaited, so it sits behind an async
+/// lock: a `RefCell` borrow held across the await would panic if another
+/// callback sent at the same time.
+type WsSink = futures::lock::Mutex<futures::stream::SplitSink<WebSocket, gloo_net::websocket::Message>>;
+
 pub struct ChatApp {
     session_id: String,
     document: Document,
@@ -23,8 +29,10 @@ struct ChatState {
     markdown_enabled: bool,
     current_assistant_message: Option<String>,
     current_message_element: Option<Element>,
+    /// Reasoning block being streamed, until the answer or a tool call starts
+    current_reasoning_element: Option<Element>,
     active_tasks: std::collections::HashMap<String, TaskInfo>,
-    sink: Option<Rc<RefCell<futures::stream::SplitSink<WebSocket, gloo_net::websocket::Message>>>>,
+    sink: Option<Rc<WsSink>>,
 }
 
 struct TaskInfo {
@@ -45,6 +53,7 @@ impl ChatApp {
             markdown_enabled: true,
             current_assistant_message: None,
             current_message_element: None,
+            current_reasoning_element: None,
             active_tasks: std::collections::HashMap::new(),
             sink: None,
         };
@@ -130,7 +139,7 @@ impl ChatApp {
             .map_err(|e| JsValue::from_str(&format!("Failed to send: {:?}", e)))?;
 
         // Set up message sender for UI events
-        let sink = Rc::new(RefCell::new(sink));
+        let sink = Rc::new(futures::lock::Mutex::new(sink));
         state.borrow_mut().sink = Some(sink.clone());
         self.setup_message_sender(sink.clone())?;
 
@@ -159,7 +168,7 @@ impl ChatApp {
         Ok(())
     }
 
-    fn setup_message_sender(&self, sink: Rc<RefCell<futures::stream::SplitSink<WebSocket, gloo_net::websocket::Message>>>) -> Result<(), JsValue> {
+    fn setup_message_sender(&self, sink: Rc<WsSink>) -> Result<(), JsValue> {
         let document = self.document.clone();
         let _state = self.state.clone();
 
@@ -282,6 +291,26 @@ impl ChatApp {
                 self.handle_message_complete(state)?;
             }
 
+            ServerMessage::ReasoningChunk { chunk } => {
+                self.handle_reasoning_chunk(document, state, chunk)?;
+            }
+
+            ServerMessage::Reasoning { content } => {
+                let container = dom::get_element_by_id(document, "messagesContainer")?;
+                let reasoning = self.create_reasoning_element(document, &content)?;
+                container.append_child(&reasoning)?;
+                dom::scroll_to_bottom(&container);
+            }
+
+            ServerMessage::ToolCallDelta {
+                index,
+                tool_call_id: _,
+                name,
+                arguments_delta,
+            } => {
+                self.handle_tool_call_delta(document, index, name, arguments_delta)?;
+            }
+
             ServerMessage::ToolCallRequest {
                 tool_call_id,
                 name,
@@ -313,6 +342,23 @@ impl ChatApp {
                 self.handle_tool_result(document, tool_call_id, result, success, formatted_result)?;
             }
 
+            ServerMessage::ToolOutput {
+                tool_call_id,
+                stream,
+                chunk,
+            } => {
+                self.handle_tool_output(document, tool_call_id, stream, chunk)?;
+            }
+
+            ServerMessage::ConfirmationRequest {
+                confirmation_id,
+                action,
+                target,
+                prompt,
+            } => {
+                self.handle_confirmation_request(document, state, confirmation_id, action, target, prompt)?;
+            }
+
             ServerMessage::TaskProgress {
                 task_id,
                 agent_name,
@@ -351,6 +397,10 @@ impl ChatApp {
                 self.update_token_display(prompt_tokens, completion_tokens, total_tokens, session_total)?;
             }
 
+            ServerMessage::CostUpdate { session_cost, budget, by_agent } => {
+                self.update_cost_display(session_cost, budget, &by_agent)?;
+            }
+
             ServerMessage::Error { message, recoverable } => {
                 self.show_error(&message, recoverable)?;
             }
@@ -371,6 +421,30 @@ impl ChatApp {
                 self.update_session_title(title)?;
             }
 
+            ServerMessage::Checkpoints { checkpoints } => {
+                if checkpoints.is_empty() {
+                    self.show_system_message("No checkpoints yet")?;
+                } else {
+                    let list: Vec<String> = checkpoints
+                        .iter()
+                        .map(|checkpoint| format!("#{} {} ({})", checkpoint.id, checkpoint.description, checkpoint.files.join(", ")))
+                        .collect();
+                    self.show_system_message(&format!("Checkpoints:\n{}", list.join("\n")))?;
+                }
+            }
+
+            ServerMessage::CheckpointRestored { checkpoint_id, files } => {
+                self.show_system_message(&format!("Restored checkpoint #{}: {}", checkpoint_id, files.join(", ")))?;
+            }
+
+            ServerMessage::CheckpointDiff { checkpoint_id, diff } => {
+                if diff.is_empty() {
+                    self.show_system_message(&format!("No changes since checkpoint #{}", checkpoint_id))?;
+                } else {
+                    self.show_system_message(&format!("Changes since checkpoint #{}:\n{}", checkpoint_id, diff))?;
+                }
+            }
+
             _ => {
                 log::warn!("Unhandled message type: {:?}", msg);
             }
@@ -448,6 +522,11 @@ impl ChatApp {
     ) -> Result<(), JsValue> {
         let container = dom::get_element_by_id(document, "messagesContainer")?;
 
+        if let Some(reasoning) = msg.reasoning.as_deref().filter(|r| !r.is_empty()) {
+            let reasoning = self.create_reasoning_element(document, reasoning)?;
+            container.append_child(&reasoning)?;
+        }
+
         let msg_div = document.create_element("div")?;
         msg_div.set_class_name(&format!("message {}", msg.role));
 
@@ -475,6 +554,7 @@ impl ChatApp {
         // First update the message
         {
             let mut s = state.borrow_mut();
+            s.current_reasoning_element = None;
             let current = s.current_assistant_message.get_or_insert(String::new());
             current.push_str(&chunk);
 
@@ -531,6 +611,88 @@ impl ChatApp {
         // Clear current message state
         s.current_assistant_message = None;
         s.current_message_element = None;
+        s.current_reasoning_element = None;
+
+        Ok(())
+    }
+
+    /// Collapsed block showing a response's reasoning
+    fn create_reasoning_element(&self, document: &Document, reasoning: &str) -> Result<Element, JsValue> {
+        let details = dom::create_element_with_class(document, "details", "reasoning")?;
+        details.set_inner_html(r#"<summary>💭 Reasoning</summary><div class="reasoning-content"></div>"#);
+        if let Some(content) = details.query_selector(".reasoning-content")? {
+            dom::set_text_content(&content, reasoning);
+        }
+        Ok(details)
+    }
+
+    fn handle_reasoning_chunk(
+        &self,
+        document: &Document,
+        state: &Rc<RefCell<ChatState>>,
+        chunk: String,
+    ) -> Result<(), JsValue> {
+        let container = dom::get_element_by_id(document, "messagesContainer")?;
+
+        // Create the block when the reasoning starts
+        let element = {
+            let mut s = state.borrow_mut();
+            match &s.current_reasoning_element {
+                Some(element) => element.clone(),
+                None => {
+                    let element = self.create_reasoning_element(document, "")?;
+                    container.append_child(&element)?;
+                    s.current_reasoning_element = Some(element.clone());
+                    element
+                }
+            }
+        };
+
+        // Append the fragment as plain text
+        if let Some(content) = element.query_selector(".reasoning-content")? {
+            let mut text = content.text_content().unwrap_or_default();
+            text.push_str(&chunk);
+            dom::set_text_content(&content, &text);
+        }
+
+        dom::scroll_to_bottom(&container);
+
+        Ok(())
+    }
+
+    fn handle_tool_call_delta(
+        &self,
+        document: &Document,
+        index: usize,
+        name: Option<String>,
+        arguments_delta: String,
+    ) -> Result<(), JsValue> {
+        let container = dom::get_element_by_id(document, "messagesContainer")?;
+        let element_id = format!("tool-forming-{}", index);
+
+        // Create the placeholder when the tool call starts
+        let tool_div = match document.get_element_by_id(&element_id) {
+            Some(element) => element,
+            None => {
+                let tool_div = dom::create_element_with_class(document, "div", "tool-call forming")?;
+                tool_div.set_id(&element_id);
+                tool_div.set_inner_html(&format!(
+                    r#"<div class="tool-header">🔧 Tool: {}</div><div class="tool-args"><pre><code></code></pre></div>"#,
+                    utils::escape_html(name.as_deref().unwrap_or(""))
+                ));
+                container.append_child(&tool_div)?;
+                tool_div
+            }
+        };
+
+        // Append the argument fragment as plain text
+        if let Some(code) = tool_div.query_selector("code")? {
+            let mut text = code.text_content().unwrap_or_default();
+            text.push_str(&arguments_delta);
+            dom::set_text_content(&code, &text);
+        }
+
+        dom::scroll_to_bottom(&container);
 
         Ok(())
     }
@@ -548,6 +710,17 @@ impl ChatApp {
         max_iterations: Option<usize>,
     ) -> Result<(), JsValue> {
         let container = dom::get_element_by_id(document, "messagesContainer")?;
+        state.borrow_mut().current_reasoning_element = None;
+
+        // The complete request replaces any placeholders from the stream
+        let forming = container.query_selector_all(".tool-call.forming")?;
+        for i in 0..forming.length() {
+            if let Some(node) = forming.item(i) {
+                if let Ok(element) = node.dyn_into::<Element>() {
+                    element.remove();
+                }
+            }
+        }
 
         let tool_div = document.create_element("div")?;
         tool_div.set_class_name("tool-call");
@@ -604,6 +777,42 @@ impl ChatApp {
         Ok(())
     }
 
+    /// Ask the user to approve an action a running tool wants to take
+    fn handle_confirmation_request(
+        &self,
+        document: &Document,
+        state: &Rc<RefCell<ChatState>>,
+        confirmation_id: String,
+        action: String,
+        target: String,
+        prompt: String,
+    ) -> Result<(), JsValue> {
+        let container = dom::get_element_by_id(document, "messagesContainer")?;
+
+        // Shaped like a tool call so the confirmation buttons work the same way
+        let confirm_div = dom::create_element_with_class(document, "div", "tool-call")?;
+        confirm_div.set_id(&format!("tool-{}", confirmation_id));
+        confirm_div.set_inner_html(&format!(
+            r#"<div class="tool-header">🔐 {}: {}</div>
+            <div class="tool-status">{}</div>
+            <div class="tool-confirmation-actions">
+                <button class="tool-confirm-btn confirm" data-tool-id="{}">✓ Approve</button>
+                <button class="tool-confirm-btn deny" data-tool-id="{}">✗ Deny</button>
+            </div>"#,
+            utils::escape_html(&action),
+            utils::escape_html(&target),
+            utils::escape_html(&prompt),
+            confirmation_id,
+            confirmation_id
+        ));
+        container.append_child(&confirm_div)?;
+
+        self.setup_tool_confirmation_buttons(document, state, &confirmation_id)?;
+        dom::scroll_to_bottom(&container);
+
+        Ok(())
+    }
+
     fn setup_tool_confirmation_buttons(
         &self,
         document: &Document,
@@ -643,7 +852,7 @@ impl ChatApp {
                         confirmed: true,
                     };
                     if let Ok(json) = serde_json::to_string(&msg) {
-                        let _ = sink.borrow_mut().send(gloo_net::websocket::Message::Text(json)).await;
+                        let _ = sink.lock().await.send(gloo_net::websocket::Message::Text(json)).await;
                     }
                 });
             }) as Box<dyn FnMut(_)>);
@@ -675,7 +884,7 @@ impl ChatApp {
                         confirmed: false,
                     };
                     if let Ok(json) = serde_json::to_string(&msg) {
-                        let _ = sink.borrow_mut().send(gloo_net::websocket::Message::Text(json)).await;
+                        let _ = sink.lock().await.send(gloo_net::websocket::Message::Text(json)).await;
                     }
                 });
             }) as Box<dyn FnMut(_)>);
@@ -687,6 +896,35 @@ impl ChatApp {
         Ok(())
     }
 
+    /// Append live output to the tool call's output block, creating it on the first chunk
+    fn handle_tool_output(
+        &self,
+        document: &Document,
+        tool_call_id: String,
+        stream: String,
+        chunk: String,
+    ) -> Result<(), JsValue> {
+        if let Some(tool_element) = document.get_element_by_id(&format!("tool-{}", tool_call_id)) {
+            let output = match tool_element.query_selector(".tool-output")? {
+                Some(output) => output,
+                None => {
+                    let output = document.create_element("pre")?;
+                    output.set_class_name("tool-output");
+                    tool_element.append_child(&output)?;
+                    output
+                }
+            };
+
+            let span = document.create_element("span")?;
+            span.set_class_name(&format!("tool-output-{}", stream));
+            span.set_text_content(Some(&chunk));
+            output.append_child(&span)?;
+            output.set_scroll_top(output.scroll_height());
+        }
+
+        Ok(())
+    }
+
     fn handle_tool_result(
         &self,
         document: &Document,
@@ -706,6 +944,10 @@ impl ChatApp {
             if let Ok(Some(status)) = tool_element.query_selector(".tool-status") {
                 status.remove();
             }
+            // The result repeats the live output
+            if let Ok(Some(output)) = tool_element.query_selector(".tool-output") {
+                output.remove();
+            }
 
             // Add result
             let result_div = document.create_element("div")?;
@@ -858,6 +1100,29 @@ impl ChatApp {
         Ok(())
     }
 
+    fn update_cost_display(
+        &self,
+        session_cost: f64,
+        budget: Option<f64>,
+        by_agent: &BTreeMap<String, f64>,
+    ) -> Result<(), JsValue> {
+        if let Ok(element) = dom::get_element_by_id(&self.document, "costUsage") {
+            let mut html = format!("💰 Estimated cost: ${:.4}", session_cost);
+            if let Some(budget) = budget {
+                html.push_str(&format!(" of ${:.2} budget", budget));
+            }
+            if by_agent.len() > 1 {
+                let breakdown: Vec<String> = by_agent.iter()
+                    .map(|(name, cost)| format!("{} ${:.4}", name, cost))
+                    .collect();
+                html.push_str(&format!(" ({})", breakdown.join(", ")));
+            }
+            element.set_inner_html(&html);
+            dom::show_element(&element.dyn_into::<HtmlElement>()?);
+        }
+        Ok(())
+    }
+
     fn show_error(&self, message: &str, _recoverable: bool) -> Result<(), JsValue> {
         let container = dom::get_element_by_id(&self.document, "messagesContainer")?;
 
@@ -892,7 +1157,7 @@ impl ChatApp {
 }
 
 async fn send_message_handler(
-    sink: Rc<RefCell<futures::stream::SplitSink<WebSocket, gloo_net::websocket::Message>>>,
+    sink: Rc<WsSink>,
     document: Document,
 ) -> Result<(), JsValue> {
     let input = dom::get_textarea_by_id(&document, "messageInput")?;
@@ -902,6 +1167,16 @@ async fn send_message_handler(
         return Ok(());
     }
 
+    // Checkpoint commands go to the server as their own messages
+    if let Some(msg) = checkpoint_command(content.trim()) {
+        input.set_value("");
+        let json = serde_json::to_string(&msg)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize: {}", e)))?;
+        sink.lock().await.send(gloo_net::websocket::Message::Text(json)).await
+            .map_err(|e| JsValue::from_str(&format!("Failed to send: {:?}", e)))?;
+        return Ok(());
+    }
+
     // Render user message immediately
     let container = dom::get_element_by_id(&document, "messagesContainer")?;
     let msg_div = document.create_element("div")?;
@@ -927,8 +1202,26 @@ async fn send_message_handler(
     let json = serde_json::to_string(&msg)
         .map_err(|e| JsValue::from_str(&format!("Failed to serialize: {}", e)))?;
 
-    sink.borrow_mut().send(gloo_net::websocket::Message::Text(json)).await
+    sink.lock().await.send(gloo_net::websocket::Message::Text(json)).await
         .map_err(|e| JsValue::from_str(&format!("Failed to send: {:?}", e)))?;
 
     Ok(())
 }
+
+/// `/checkpoints`, `/undo`, `/restore <id>` and `/diff <id>`, as in the REPL
+fn checkpoint_command(text: &str) -> Option<ClientMessage> {
+    let checkpoint_id = |arg: &str| arg.trim().trim_start_matches('#').parse::<u64>().ok();
+    match text {
+        "/checkpoints" => Some(ClientMessage::ListCheckpoints),
+        "/undo" => Some(ClientMessage::Undo),
+        _ => {
+            if let Some(arg) = text.strip_prefix("/restore ") {
+                checkpoint_id(arg).map(|checkpoint_id| ClientMessage::RestoreCheckpoint { checkpoint_id })
+            } else if let Some(arg) = text.strip_prefix("/diff ") {
+                checkpoint_id(arg).map(|checkpoint_id| ClientMessage::DiffCheckpoint { checkpoint_id })
+            } else {
+                None
+            }
+        }
+    }
+}
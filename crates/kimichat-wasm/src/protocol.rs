@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::BTreeMap;
 
 /// Session ID type (UUID as string in WASM)
 pub type SessionId = String;
@@ -42,6 +43,13 @@ pub enum ClientMessage {
 
     // Skill system
     InvokeSkill { skill_name: String },
+
+    // Checkpoints of files changed by tools
+    ListCheckpoints,
+    /// Roll back the latest checkpoint
+    Undo,
+    RestoreCheckpoint { checkpoint_id: u64 },
+    DiffCheckpoint { checkpoint_id: u64 },
 }
 
 /// Messages sent from server to client
@@ -79,8 +87,25 @@ pub enum ServerMessage {
         chunk: String,
     },
     AssistantMessageComplete,
+    /// Reasoning text of a streamed response, shown apart from the answer
+    ReasoningChunk {
+        chunk: String,
+    },
+    /// Complete reasoning of a response that was not streamed
+    Reasoning {
+        content: String,
+    },
 
     // Tool interactions
+    /// A tool call forming in a streamed response (arguments arrive in fragments)
+    ToolCallDelta {
+        index: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tool_call_id: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        arguments_delta: String,
+    },
     ToolCallRequest {
         tool_call_id: String,
         name: String,
@@ -100,6 +125,19 @@ pub enum ServerMessage {
         #[serde(skip_serializing_if = "Option::is_none")]
         formatted_result: Option<String>,
     },
+    /// Output of a running tool call as it is produced; `stream` is "stdout" or "stderr"
+    ToolOutput {
+        tool_call_id: String,
+        stream: String,
+        chunk: String,
+    },
+    /// A policy check inside a running tool asks for approval, answered with `ConfirmTool`
+    ConfirmationRequest {
+        confirmation_id: String,
+        action: String,
+        target: String,
+        prompt: String,
+    },
 
     // State updates
     ModelSwitched {
@@ -116,6 +154,14 @@ pub enum ServerMessage {
         total_tokens: usize,
         session_total: usize,
     },
+    /// Estimated spending so far, sent after every call that used tokens
+    CostUpdate {
+        session_cost: f64,
+        /// Hard cap in USD, if one is set
+        budget: Option<f64>,
+        /// Cost per agent (or "main", "title", "compaction" for other calls)
+        by_agent: BTreeMap<String, f64>,
+    },
 
     // Progress (multi-agent mode)
     TaskProgress {
@@ -131,6 +177,21 @@ pub enum ServerMessage {
         task_description: String,
     },
 
+    // Checkpoints
+    Checkpoints {
+        checkpoints: Vec<CheckpointInfo>,
+    },
+    CheckpointRestored {
+        checkpoint_id: u64,
+        /// Files put back; files the turns created were removed
+        files: Vec<String>,
+    },
+    /// Unified diff from the checkpoint to the current files
+    CheckpointDiff {
+        checkpoint_id: u64,
+        diff: String,
+    },
+
     // Errors
     Error {
         message: String,
@@ -138,6 +199,15 @@ pub enum ServerMessage {
     },
 }
 
+/// A checkpoint: the files one turn changed, as they were before it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckpointInfo {
+    pub id: u64,
+    pub created_at: String,
+    pub description: String,
+    pub files: Vec<String>,
+}
+
 /// Session information for listing
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionInfo {
@@ -17,10 +17,11 @@ pub(crate) async fn call_api(
     orig_messages: &[Message],
 ) -> Result<(Message, Option<Usage>, ModelColor)> {
     let current_model = chat.current_model.clone();
-    // Clone messages and strip reasoning field (only supported by some models like Groq)
+    // Clone messages and strip reasoning fields (only supported by some models like Groq)
     let messages: Vec<Message> = orig_messages.iter().map(|m| {
         let mut msg = m.clone();
         msg.reasoning = None; // Strip reasoning field to avoid compatibility issues
+        msg.reasoning_details = None;
         msg
     }).collect();
 
@@ -30,21 +31,28 @@ pub(crate) async fn call_api(
         (chat.client_config.get_api_url(ModelColor::GrnModel).as_ref().map(|u| u.contains("anthropic")).unwrap_or(false)) ||
         (chat.client_config.get_api_url(ModelColor::RedModel).as_ref().map(|u| u.contains("anthropic")).unwrap_or(false));
 
+    // Fallback chains and some backends are only handled by the LlmClient system
+    let requires_llm_client = chat.client_config.requires_llm_client(current_model);
+
     if chat.should_show_debug(1) {
         println!("🔧 DEBUG: current_model = {:?}", current_model);
         println!("🔧 DEBUG: should_use_anthropic = {}", should_use_anthropic);
+        println!("🔧 DEBUG: requires_llm_client = {}", requires_llm_client);
     }
-    if should_use_anthropic {
+    if should_use_anthropic || requires_llm_client {
         if chat.should_show_debug(1) {
-            println!("🔧 DEBUG: Using call_api_with_llm_client for Anthropic");
+            println!("🔧 DEBUG: Using call_api_with_llm_client");
         }
-        return call_api_with_llm_client(chat, &messages, &current_model).await;
+        // Anthropic needs the signed thinking blocks back, so pass the messages unchanged
+        return call_api_with_llm_client(chat, orig_messages, &current_model).await;
     } else {
         if chat.should_show_debug(1) {
             println!("🔧 DEBUG: Using regular OpenAI-style call_api");
         }
     }
 
+    let max_tokens = crate::chat::history::max_tokens_for_request(chat, &current_model);
+
     // Retry logic with exponential backoff
     let mut retry_count = 0;
     loop {
@@ -58,6 +66,8 @@ pub(crate) async fn call_api(
             tools: chat.get_tools(),
             tool_choice: "auto".to_string(),
             stream: None,
+            max_tokens: Some(max_tokens),
+            reasoning_effort: chat.client_config.get_provider(current_model).reasoning.map(|r| r.effort()),
         };
 
         // Get the appropriate API URL based on the current model
@@ -205,7 +215,8 @@ pub(crate) async fn call_api_with_llm_client(
             }),
             tool_call_id: msg.tool_call_id.clone(),
             name: msg.name.clone(),
-            reasoning: None,
+            reasoning: msg.reasoning.clone(),
+            reasoning_details: msg.reasoning_details.clone(),
         }
     }).collect();
 
@@ -223,10 +234,11 @@ pub(crate) async fn call_api_with_llm_client(
         model,
         &chat.client_config,
         &chat.api_key,
-    );
+    )?;
 
     // Make the API call
     let response = llm_client.chat(chat_messages, tools).await?;
+    *chat.last_provider.lock().unwrap() = response.provider.clone();
 
     // Convert the response back to the old format
     let message = Message {
@@ -244,13 +256,16 @@ pub(crate) async fn call_api_with_llm_client(
         }),
         tool_call_id: response.message.tool_call_id,
         name: response.message.name,
-        reasoning: None,
+        reasoning: response.message.reasoning,
+        reasoning_details: response.message.reasoning_details,
     };
 
     let usage = response.usage.map(|u| Usage {
         prompt_tokens: u.prompt_tokens as usize,
         completion_tokens: u.completion_tokens as usize,
         total_tokens: u.total_tokens as usize,
+        cache_creation_input_tokens: u.cache_creation_input_tokens as usize,
+        cache_read_input_tokens: u.cache_read_input_tokens as usize,
     });
 
     Ok((message, usage, model.clone()))
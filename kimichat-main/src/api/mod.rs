@@ -1,5 +1,5 @@
 mod streaming;
 mod client;
 
-pub(crate) use streaming::{call_api_streaming, call_api_streaming_with_llm_client};
+pub(crate) use streaming::{call_api_streaming_with_llm_client, stream_with_llm_client};
 pub(crate) use client::{call_api, call_api_with_llm_client};
@@ -0,0 +1,192 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use colored::Colorize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::cli::Cli;
+use crate::config::initialize_tool_registry;
+use crate::resolve_terminal_backend;
+use crate::KimiChat;
+use kimichat_mcp::{McpServer, Prompt, PromptArgument, Resource, ResourceContents, ServerContent};
+use kimichat_policy::{PolicyContext, SessionType};
+use kimichat_skills::SkillRegistry;
+use kimichat_terminal::{TerminalManager, MAX_CONCURRENT_SESSIONS};
+use kimichat_toolcore::{DenyAllConfirmation, DiscardOutput, JobManager, ReadTracker, ToolContext};
+
+const SKILL_SCHEME: &str = "skill://";
+const PTY_SCHEME: &str = "pty://";
+
+/// Serve the tool registry, skills and terminal screens to an MCP client on
+/// stdin/stdout until it disconnects. Tool calls are checked against the
+/// same policy as a chat session; calls the policy would ask about are
+/// refused, as nobody is there to answer.
+pub async fn run_mcp_serve(cli: &Cli, work_dir: PathBuf) -> Result<()> {
+    // stdout carries the protocol from here on
+    let protocol_out = take_stdout()?;
+
+    let policy_manager = crate::app::setup::policy_from_cli(cli, &work_dir);
+
+    let logs_dir = kimichat_logging::get_logs_dir()
+        .unwrap_or_else(|_| PathBuf::from("logs"))
+        .join("terminals");
+    let terminal_manager = Arc::new(Mutex::new(TerminalManager::with_backend(
+        logs_dir,
+        resolve_terminal_backend(cli)?,
+        MAX_CONCURRENT_SESSIONS,
+    )));
+
+    let skill_registry = match SkillRegistry::new(work_dir.join("skills")) {
+        Ok(registry) => Some(Arc::new(registry)),
+        Err(e) => {
+            eprintln!("{} Failed to load skills: {}", "⚠️".yellow(), e);
+            None
+        }
+    };
+
+    let session_id = format!("mcp-{}-{}", chrono::Local::now().format("%Y%m%d-%H%M%S"), std::process::id());
+    let mut context = ToolContext::new(work_dir.clone(), session_id.clone(), policy_manager)
+        .with_extra_roots(cli.allow_dirs.iter().map(|dir| work_dir.join(dir)).collect())
+        .with_terminal_manager(terminal_manager.clone())
+        .with_todo_manager(Arc::new(kimichat_todo::TodoManager::new()))
+        .with_job_manager(Arc::new(JobManager::new()))
+        .with_checkpoint_store(KimiChat::checkpoint_store_for(&session_id))
+        .with_read_tracker(Arc::new(ReadTracker::new()))
+        .with_confirmation_provider(Arc::new(DenyAllConfirmation))
+        .with_output_sink(Arc::new(DiscardOutput))
+        .with_policy_context(PolicyContext::new(None, Some(SessionType::Mcp)));
+    if let Some(ref registry) = skill_registry {
+        context = context.with_skill_registry(Arc::clone(registry));
+    }
+
+    // MCP servers from mcp.toml are not mounted: a client wanting them can
+    // connect to them directly
+    let registry = initialize_tool_registry();
+    eprintln!(
+        "{} Serving {} tools over MCP on stdio ({})",
+        "🔌".cyan(),
+        registry.get_all_tools().len(),
+        work_dir.display()
+    );
+
+    let content = ServeContent { skills: skill_registry, terminal_manager };
+    McpServer::new(registry, context)
+        .with_content(Arc::new(content))
+        .serve(tokio::io::stdin(), protocol_out)
+        .await
+}
+
+/// The protocol's end of stdout. Tools print progress with `println!`, so
+/// stdout itself is pointed at stderr to keep the protocol stream clean.
+#[cfg(unix)]
+fn take_stdout() -> Result<tokio::fs::File> {
+    use std::io::Write;
+    use std::os::fd::FromRawFd;
+
+    std::io::stdout().flush()?;
+    // SAFETY: plain descriptor calls; the duplicate is owned by the returned file
+    unsafe {
+        let protocol_fd = libc::dup(libc::STDOUT_FILENO);
+        if protocol_fd < 0 {
+            return Err(anyhow!("Failed to duplicate stdout: {}", std::io::Error::last_os_error()));
+        }
+        if libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) < 0 {
+            return Err(anyhow!("Failed to redirect stdout: {}", std::io::Error::last_os_error()));
+        }
+        Ok(tokio::fs::File::from_std(std::fs::File::from_raw_fd(protocol_fd)))
+    }
+}
+
+#[cfg(not(unix))]
+fn take_stdout() -> Result<tokio::io::Stdout> {
+    Ok(tokio::io::stdout())
+}
+
+/// Skills as prompts and `skill://` resources, terminal sessions as
+/// `pty://<id>/screen` resources
+struct ServeContent {
+    skills: Option<Arc<SkillRegistry>>,
+    terminal_manager: Arc<Mutex<TerminalManager>>,
+}
+
+#[async_trait]
+impl ServerContent for ServeContent {
+    async fn prompts(&self) -> Vec<Prompt> {
+        let Some(ref skills) = self.skills else {
+            return Vec::new();
+        };
+        skills
+            .list_skills()
+            .into_iter()
+            .filter_map(|name| skills.get_skill(&name))
+            .map(|skill| Prompt {
+                name: skill.name.clone(),
+                description: Some(skill.description.clone()),
+                arguments: vec![PromptArgument {
+                    name: "task".to_string(),
+                    description: Some("The task to apply the skill to".to_string()),
+                    required: false,
+                }],
+            })
+            .collect()
+    }
+
+    async fn get_prompt(&self, name: &str, arguments: &HashMap<String, String>) -> Result<String> {
+        let skill = self
+            .skills
+            .as_ref()
+            .and_then(|skills| skills.get_skill(name))
+            .ok_or_else(|| anyhow!("Unknown skill '{}'", name))?;
+        Ok(match arguments.get("task").filter(|task| !task.trim().is_empty()) {
+            Some(task) => format!("{}\n\n## Task\n\n{}", skill.content, task),
+            None => skill.content.clone(),
+        })
+    }
+
+    async fn resources(&self) -> Vec<Resource> {
+        let mut resources = Vec::new();
+        if let Some(ref skills) = self.skills {
+            for skill in skills.list_skills().iter().filter_map(|name| skills.get_skill(name)) {
+                resources.push(Resource {
+                    uri: format!("{}{}", SKILL_SCHEME, skill.name),
+                    name: skill.name.clone(),
+                    description: Some(skill.description.clone()),
+                    mime_type: Some("text/markdown".to_string()),
+                });
+            }
+        }
+
+        let sessions = self.terminal_manager.lock().await.list_sessions().await.unwrap_or_default();
+        for session in sessions {
+            resources.push(Resource {
+                uri: format!("{}{}/screen", PTY_SCHEME, session.id),
+                name: format!("Terminal {}: {}", session.id, session.command),
+                description: Some(format!("Screen of terminal session {} ({})", session.id, session.status)),
+                mime_type: Some("text/plain".to_string()),
+            });
+        }
+        resources
+    }
+
+    async fn read_resource(&self, uri: &str) -> Result<ResourceContents> {
+        if let Some(name) = uri.strip_prefix(SKILL_SCHEME) {
+            let skill = self
+                .skills
+                .as_ref()
+                .and_then(|skills| skills.get_skill(name))
+                .ok_or_else(|| anyhow!("Unknown skill '{}'", name))?;
+            return Ok(contents(uri, "text/markdown", skill.content.clone()));
+        }
+        if let Some(session_id) = uri.strip_prefix(PTY_SCHEME).and_then(|rest| rest.strip_suffix("/screen")) {
+            let screen = self.terminal_manager.lock().await.get_screen(session_id, false, false).await?;
+            return Ok(contents(uri, "text/plain", screen));
+        }
+        Err(anyhow!("Unknown resource '{}'", uri))
+    }
+}
+
+fn contents(uri: &str, mime_type: &str, text: String) -> ResourceContents {
+    ResourceContents { uri: uri.to_string(), mime_type: Some(mime_type.to_string()), text }
+}
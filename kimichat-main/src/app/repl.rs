@@ -42,6 +42,10 @@ pub async fn run_repl_mode(
         backend_type,
     );
 
+    if cli.deny_confirmations {
+        chat.confirmation_provider = std::sync::Arc::new(kimichat_toolcore::DenyAllConfirmation);
+    }
+
     // Comprehensive model configuration display
     println!("{}", "═".repeat(80).bright_black());
     println!("{}", "🤖 Model Configuration".bright_cyan().bold());
@@ -167,6 +171,7 @@ pub async fn run_repl_mode(
                         tool_call_id: None,
                         name: None,
                         reasoning: None,
+                        reasoning_details: None,
                     });
 
                     if cli.verbose {
@@ -204,6 +209,7 @@ pub async fn run_repl_mode(
             tool_call_id: None,
             name: None,
             reasoning: None,
+            reasoning_details: None,
         };
         // Log this system addition
         if let Some(logger) = &mut chat.logger {
@@ -307,6 +313,104 @@ pub async fn run_repl_mode(
                     continue;
                 }
 
+                // Handle /cost command
+                if line == "/cost" {
+                    println!("{} {}", "💰".bright_cyan(), chat.cost_tracker.report());
+                    continue;
+                }
+
+                // Handle checkpoint commands: list, undo the last turn, restore or diff a checkpoint
+                if line == "/checkpoints" {
+                    match chat.checkpoint_store.list() {
+                        Ok(checkpoints) if checkpoints.is_empty() => {
+                            println!("{} No checkpoints yet; one is taken before a turn first changes a file", "ℹ️".bright_blue());
+                        }
+                        Ok(checkpoints) => {
+                            println!("{} Checkpoints (files as they were before each turn):", "🕒".bright_cyan());
+                            for checkpoint in checkpoints {
+                                println!("  #{:<3} {}  {} file(s)  {}",
+                                    checkpoint.id,
+                                    checkpoint.created_at.with_timezone(&chrono::Local).format("%H:%M:%S"),
+                                    checkpoint.files.len(),
+                                    kimichat_logging::safe_truncate(&checkpoint.description, 60).bright_black()
+                                );
+                            }
+                            println!("{} /diff <id> shows changes since a checkpoint, /restore <id> rolls back to it", "💡".bright_yellow());
+                        }
+                        Err(e) => eprintln!("{} Failed to list checkpoints: {}", "❌".bright_red(), e),
+                    }
+                    continue;
+                }
+
+                let restore_arg = line.strip_prefix("/restore ");
+                if line == "/undo" || restore_arg.is_some() {
+                    let result = match restore_arg {
+                        None => chat.checkpoint_store.undo(),
+                        Some(arg) => match arg.trim().trim_start_matches('#').parse::<u64>() {
+                            Ok(id) => chat.checkpoint_store.restore(id).map(|files| Some((id, files))),
+                            Err(_) => Err(anyhow::anyhow!("Usage: /restore <checkpoint id>")),
+                        },
+                    };
+                    match result {
+                        Ok(Some((id, files))) => {
+                            println!("{} Restored {} file(s) to checkpoint #{}:", "⏪".bright_green(), files.len(), id);
+                            for file in files {
+                                let note = if file.removed { " (removed)" } else { "" };
+                                println!("  {}{}", file.display_path, note.bright_black());
+                            }
+                        }
+                        Ok(None) => println!("{} Nothing to undo", "ℹ️".bright_blue()),
+                        Err(e) => eprintln!("{} {}", "❌".bright_red(), e),
+                    }
+                    continue;
+                }
+
+                if let Some(arg) = line.strip_prefix("/diff ") {
+                    let diff = match arg.trim().trim_start_matches('#').parse::<u64>() {
+                        Ok(id) => chat.checkpoint_store.diff(id),
+                        Err(_) => Err(anyhow::anyhow!("Usage: /diff <checkpoint id>")),
+                    };
+                    match diff {
+                        Ok(diff) if diff.is_empty() => println!("{} No changes since that checkpoint", "ℹ️".bright_blue()),
+                        Ok(diff) => {
+                            for diff_line in diff.lines() {
+                                if diff_line.starts_with("+++") || diff_line.starts_with("---") {
+                                    println!("{}", diff_line.bold());
+                                } else if diff_line.starts_with('+') {
+                                    println!("{}", diff_line.green());
+                                } else if diff_line.starts_with('-') {
+                                    println!("{}", diff_line.red());
+                                } else if diff_line.starts_with("@@") {
+                                    println!("{}", diff_line.cyan());
+                                } else {
+                                    println!("{}", diff_line);
+                                }
+                            }
+                        }
+                        Err(e) => eprintln!("{} {}", "❌".bright_red(), e),
+                    }
+                    continue;
+                }
+
+                // Handle /thinking command: show the last reasoning, or expand/collapse it for future responses
+                if line == "/thinking" || line.starts_with("/thinking ") {
+                    match line["/thinking".len()..].trim() {
+                        "on" => {
+                            chat.show_reasoning = true;
+                            println!("{} Reasoning will be shown in full", "💭".bright_cyan());
+                        }
+                        "off" => {
+                            chat.show_reasoning = false;
+                            println!("{} Reasoning will be collapsed", "💭".bright_cyan());
+                        }
+                        _ => match chat.messages.iter().rev().find_map(|msg| msg.reasoning.as_deref()) {
+                            Some(reasoning) => println!("{} {}", "💭".bright_cyan(), reasoning.bright_black()),
+                            None => println!("{} No reasoning in this conversation yet", "💭".bright_cyan()),
+                        },
+                    }
+                    continue;
+                }
+
                 // Handle /session commands
                 if line == "/session" || line == "/session help" {
                     println!("{} Session commands:", "🖥️".bright_cyan());
@@ -403,6 +507,7 @@ pub async fn run_repl_mode(
                                     tool_call_id: None,
                                     name: None,
                                     reasoning: None,
+                                    reasoning_details: None,
                                 };
                                 chat.messages.push(skill_msg.clone());
 
@@ -438,6 +543,7 @@ pub async fn run_repl_mode(
                                     tool_call_id: None,
                                     name: None,
                                     reasoning: None,
+                                    reasoning_details: None,
                                 };
                                 chat.messages.push(skill_msg.clone());
 
@@ -473,6 +579,7 @@ pub async fn run_repl_mode(
                                     tool_call_id: None,
                                     name: None,
                                     reasoning: None,
+                                    reasoning_details: None,
                                 };
                                 chat.messages.push(skill_msg.clone());
 
@@ -511,6 +618,7 @@ pub async fn run_repl_mode(
                 }
 
                 rl.add_history_entry(line)?;
+                chat.checkpoint_store.begin_turn(line);
 
                 // Log the user message before sending
                 if let Some(logger) = &mut chat.logger {
@@ -659,11 +767,19 @@ mod repl_compact_tests {
             policy_manager: PolicyManager::new(),
             terminal_manager: Arc::new(Mutex::new(TerminalManager::new(work_dir))),
             skill_registry: None,
-            non_interactive: false,
+            confirmation_provider: Arc::new(kimichat_toolcore::TerminalConfirmation),
+            session_type: kimichat_policy::SessionType::Repl,
             todo_manager: Arc::new(TodoManager::new()),
+            job_manager: Arc::new(kimichat_toolcore::JobManager::new()),
+            checkpoint_store: Arc::new(kimichat_checkpoints::CheckpointStore::new(&std::env::temp_dir(), "test")),
+            read_tracker: Arc::new(kimichat_toolcore::ReadTracker::new()),
             stream_responses: false,
             verbose: false,
             debug_level: 0,
+            show_reasoning: false,
+            last_provider: std::sync::Mutex::new(None),
+            server_token_count: None,
+            cost_tracker: Arc::new(crate::cost::CostTracker::new(&crate::config::ClientConfig::new())),
         }
     }
 
@@ -682,6 +798,7 @@ mod repl_compact_tests {
             tool_call_id: None,
             name: None,
             reasoning: None,
+            reasoning_details: None,
         });
         
         // The compact command should not crash and should preserve system messages
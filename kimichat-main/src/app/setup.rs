@@ -1,14 +1,17 @@
 use anyhow::{Context, Result};
 use colored::Colorize;
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use crate::cli::Cli;
 use crate::config::{ClientConfig, BackendType};
-use kimichat_models::{ModelColor, ModelProvider, ModelConfig};
+use kimichat_models::{ModelColor, ModelProvider, ModelConfig, ReasoningConfig};
 use crate::config::helpers::get_model_config_from_env;
-use kimichat_policy::PolicyManager;
-use kimichat_llm_api::config::{parse_model_attings, GROQ_API_URL, ANTHROPIC_API_URL, OPENAI_API_URL, get_default_url_for_backend};
+use kimichat_mcp::{McpConfig, McpServers};
+use kimichat_policy::{AuditLog, PolicyContext, PolicyManager, SessionType};
+use kimichat_toolcore::{ConfirmationProvider, DenyAllConfirmation, TerminalConfirmation, ToolContext};
+use kimichat_llm_api::config::{parse_model_attings, split_provider_chain};
 
 /// Application configuration derived from CLI arguments and environment
 pub struct AppConfig {
@@ -50,8 +53,8 @@ fn resolve_api_key_for_backend(
                     .or_else(|| env::var(format!("GROQ_API_KEY_{}", color_name.to_uppercase())).ok())
                     .or_else(|| env::var("GROQ_API_KEY").ok())
             }
-            BackendType::Llama => {
-                // For Llama: CLI > env (no standard env var for llama)
+            BackendType::Llama | BackendType::Ollama | BackendType::OpenAICompatible => {
+                // For self-hosted servers: CLI > env (no standard env var, usually keyless)
                 cli_key.clone().or(env_key.clone())
             }
         }
@@ -76,7 +79,7 @@ fn process_model_config(
     env_config: (Option<BackendType>, Option<String>, Option<String>, Option<String>),
     global_model: &Option<String>,
     global_llama_url: &Option<String>,
-) -> (String, Option<BackendType>, Option<String>, Option<String>) {
+) -> (String, Option<BackendType>, Option<String>, Option<String>, Vec<ModelProvider>) {
     let color_name = match color {
         ModelColor::BluModel => "blu",
         ModelColor::GrnModel => "grn", 
@@ -84,6 +87,19 @@ fn process_model_config(
     };
     
     let (backend_env, url_env, key_env, model_env) = env_config;
+
+    // A comma-separated model spec is a fallback chain: the first entry is the
+    // primary provider and the rest are tried in order when it is unavailable
+    let (cli_model, cli_fallbacks) = split_model_spec(&cli_config.model);
+    let (model_env, env_fallbacks) = split_model_spec(&model_env);
+    let (global_model, global_fallbacks) = split_model_spec(global_model);
+    let fallback_specs = if cli_model.is_some() {
+        cli_fallbacks
+    } else if model_env.is_some() {
+        env_fallbacks
+    } else {
+        global_fallbacks
+    };
     
     // Resolve backend with precedence: CLI > env
     let backend = cli_config.backend.as_ref()
@@ -106,7 +122,7 @@ fn process_model_config(
     let (mut final_model_name, mut parsed_backend, mut parsed_url) = (None, None, None);
     
     // Check CLI model first and parse for @backend(url) syntax
-    if let Some(ref cli_model) = cli_config.model {
+    if let Some(ref cli_model) = cli_model {
         if cli_model.contains('@') {
             let (model_name, backend, url) = parse_model_attings(cli_model);
             final_model_name = Some(model_name);
@@ -162,8 +178,32 @@ fn process_model_config(
         &key_env,
         color_name,
     );
-    
-    (model_name, final_backend, final_api_url, final_api_key)
+
+    let fallbacks = fallback_specs.iter().map(|spec| {
+        let (fallback_model, fallback_backend, fallback_url) = parse_model_attings(spec);
+        if fallback_backend.is_none() && fallback_url.is_none() {
+            // A bare model name reuses the primary provider's backend and URL
+            return ModelProvider::with_config(
+                fallback_model,
+                final_backend.clone(),
+                final_api_url.clone(),
+                final_api_key.clone(),
+            );
+        }
+        let fallback_key = resolve_api_key_for_backend(&fallback_backend, &None, &None, color_name);
+        ModelProvider::with_config(fallback_model, fallback_backend, fallback_url, fallback_key)
+    }).collect();
+
+    (model_name, final_backend, final_api_url, final_api_key, fallbacks)
+}
+
+/// Split a model spec into its primary entry and any fallback entries
+fn split_model_spec(spec: &Option<String>) -> (Option<String>, Vec<String>) {
+    let Some(spec) = spec else {
+        return (None, Vec::new());
+    };
+    let mut entries = split_provider_chain(spec).into_iter();
+    (entries.next(), entries.collect())
 }
 
 /// Set up application configuration from CLI arguments
@@ -185,9 +225,10 @@ pub fn setup_from_cli(cli: &Cli) -> Result<AppConfig> {
     let mut backends = vec![None; ModelColor::COUNT];
     let mut api_urls = vec![None; ModelColor::COUNT];
     let mut api_keys = vec![None; ModelColor::COUNT];
+    let mut fallbacks = vec![Vec::new(); ModelColor::COUNT];
 
     for (i, color) in ModelColor::iter().enumerate() {
-        let (model_name, backend, api_url, api_key) = process_model_config(
+        let (model_name, backend, api_url, api_key, model_fallbacks) = process_model_config(
             color,
             &model_configs[i],
             env_configs[i].clone(),
@@ -198,6 +239,7 @@ pub fn setup_from_cli(cli: &Cli) -> Result<AppConfig> {
         backends[i] = backend;
         api_urls[i] = api_url;
         api_keys[i] = api_key;
+        fallbacks[i] = model_fallbacks;
     }
 
     // Extract individual variables for compatibility with existing code (but mark as unused since we use arrays directly)
@@ -246,6 +288,10 @@ pub fn setup_from_cli(cli: &Cli) -> Result<AppConfig> {
         } else {
             eprintln!("  {}_api_key_override_final: None", color.as_str_lowercase());
         }
+        if !fallbacks[i].is_empty() {
+            let labels: Vec<String> = fallbacks[i].iter().map(|p| p.label()).collect();
+            eprintln!("  {}_fallbacks_final: {:?}", color.as_str_lowercase(), labels);
+        }
     }
     eprintln!("  CLI global model: {:?}", cli.model);
 
@@ -267,13 +313,30 @@ pub fn setup_from_cli(cli: &Cli) -> Result<AppConfig> {
 
     // Create client configuration from CLI arguments
     // Priority: specific flags override general --model flag, but model@backend(url) format has highest precedence
+    let mut reasoning = [None; ModelColor::COUNT];
+    for (i, color) in ModelColor::iter().enumerate() {
+        if let Some(spec) = &model_configs[i].reasoning {
+            reasoning[i] = Some(ReasoningConfig::parse(spec).with_context(|| format!(
+                "Invalid reasoning '{}' for {}_model: expected low, medium, high or a token budget",
+                spec, color.as_str_lowercase()
+            ))?);
+        }
+    }
+
     let model_providers: [ModelProvider; ModelColor::COUNT] = ModelColor::iter().enumerate().map(|(i, color)| {
-        ModelProvider::with_config(
+        let mut provider = ModelProvider::with_config(
             model_names[i].clone(),
             backends[i].clone(),
             api_urls[i].clone(),
             api_keys[i].clone(),
-        )
+        );
+        provider.fallbacks = fallbacks[i].clone();
+        // The color's reasoning setting applies to every provider in its chain
+        provider.reasoning = reasoning[i];
+        for fallback in &mut provider.fallbacks {
+            fallback.reasoning = reasoning[i];
+        }
+        provider
     }).collect::<Vec<_>>().try_into().unwrap_or_else(|_| {
         // This should never happen since we know the array size matches ModelColor::COUNT
         panic!("Failed to create model providers array")
@@ -282,6 +345,11 @@ pub fn setup_from_cli(cli: &Cli) -> Result<AppConfig> {
     let client_config = ClientConfig {
         api_key: api_key.clone(),
         model_providers,
+        model_registry: std::sync::Arc::new(crate::config::load_model_registry()),
+        max_session_cost: cli.max_cost,
+        extra_roots: cli.allow_dirs.iter().map(|dir| work_dir.join(dir)).collect(),
+        max_parallel_tools: cli.max_parallel_tools.max(1),
+        mcp_servers: std::sync::Arc::new(McpServers::default()),
     };
 
     // Inform user about auto-detected Anthropic configuration
@@ -294,35 +362,115 @@ pub fn setup_from_cli(cli: &Cli) -> Result<AppConfig> {
         }
     }
 
-    // Create policy manager based on CLI arguments
+    let policy_manager = policy_from_cli(cli, &work_dir);
+
+    Ok(AppConfig {
+        client_config,
+        policy_manager,
+        work_dir,
+        api_key,
+    })
+}
+
+/// Policy from `--auto-confirm` or the layered policy files, auditing every decision
+pub(crate) fn policy_from_cli(cli: &Cli, work_dir: &Path) -> PolicyManager {
     let policy_manager = if cli.auto_confirm {
         eprintln!("{} Auto-confirm mode enabled - all actions will be approved automatically", "🚀".green());
         PolicyManager::allow_all()
-    } else if cli.policy_file.is_some() || cli.learn_policies {
-        let policy_file = cli.policy_file.clone().unwrap_or_else(|| "policies.toml".to_string());
-        let policy_path = work_dir.join(&policy_file);
-        match PolicyManager::from_file(&policy_path, cli.learn_policies) {
-            Ok(pm) => {
-                eprintln!("{} Loaded policy file: {}", "📋".cyan(), policy_path.display());
-                if cli.learn_policies {
-                    eprintln!("{} Policy learning enabled - user decisions will be saved to policy file", "📚".cyan());
-                }
-                pm
-            }
-            Err(e) => {
-                eprintln!("{} Failed to load policy file: {}", "⚠️".yellow(), e);
-                eprintln!("{} Using default policy (ask for confirmation)", "📋".cyan());
-                PolicyManager::new()
+    } else {
+        let pm = load_layered_policy(cli, work_dir);
+        for layer in pm.layers().iter().filter(|layer| layer.loaded) {
+            if let Some(ref path) = layer.path {
+                let note = if layer.tighten_only { " (can only tighten your policy; trust it with --policy-file)" } else { "" };
+                eprintln!("{} Loaded {} policy file: {}{}", "📋".cyan(), layer.scope, path.display(), note);
             }
         }
+        if let Some(path) = pm.policy_file().filter(|_| pm.is_learning()) {
+            eprintln!("{} Policy learning enabled - user decisions will be saved to {}", "📚".cyan(), path.display());
+        }
+        pm
+    };
+    match kimichat_logging::get_logs_dir() {
+        Ok(logs_dir) => policy_manager.with_audit_log(
+            AuditLog::new(logs_dir.join("policy-audit.jsonl")).with_workspace(work_dir),
+        ),
+        Err(_) => policy_manager,
+    }
+}
+
+/// Project policy file: `--policy-file` (relative to the workspace) or `.kimichat/policy.toml`
+pub(crate) fn project_policy_path(cli: &Cli, work_dir: &Path) -> PathBuf {
+    match cli.policy_file {
+        Some(ref policy_file) => work_dir.join(policy_file),
+        None => work_dir.join(".kimichat").join("policy.toml"),
+    }
+}
+
+/// Merge `~/.okaychat/policy.toml` under the project policy file
+///
+/// `.kimichat/policy.toml` comes with the workspace, so it can only tighten the
+/// user's policy; a file named with `--policy-file` is trusted. Files that fail
+/// to load are reported and left out, and the rest still apply.
+pub(crate) fn load_layered_policy(cli: &Cli, work_dir: &Path) -> PolicyManager {
+    let user_file = kimichat_logging::get_okaychat_dir()
+        .ok()
+        .map(|dir| dir.join("policy.toml"));
+    let trust_project = cli.policy_file.is_some();
+    let policy_manager = PolicyManager::layered(user_file, project_policy_path(cli, work_dir), trust_project, cli.learn_policies);
+    for error in policy_manager.load_errors() {
+        eprintln!("{} {}", "⚠️".yellow(), error);
+    }
+    policy_manager
+}
+
+/// Start the MCP servers in `~/.okaychat/mcp.toml` and `.kimichat/mcp.toml`
+/// (the project file wins for a server in both). Each launch is checked against
+/// the `mcp_server_launch` policy, and asked about on the terminal when the
+/// session can prompt. Servers that are refused or fail to start are reported
+/// and left out.
+pub async fn connect_mcp_servers(cli: &Cli, work_dir: &Path, policy_manager: &PolicyManager) -> McpServers {
+    let user_file = kimichat_logging::get_okaychat_dir()
+        .ok()
+        .map(|dir| dir.join("mcp.toml"));
+    let config = match McpConfig::layered(user_file, work_dir.join(".kimichat").join("mcp.toml")) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{} {:#}", "⚠️".yellow(), e);
+            eprintln!("{} MCP tools will not be available", "⚠️".yellow());
+            return McpServers::default();
+        }
+    };
+    if config.enabled_servers().next().is_none() {
+        return McpServers::default();
+    }
+
+    // Subagents print their summary on stdout, so they can't prompt
+    let subagent = cli.task.is_some() && !cli.agents;
+    let session_type = if subagent {
+        SessionType::Subagent
+    } else if cli.task.is_some() {
+        SessionType::Task
+    } else if cli.web {
+        SessionType::Web
     } else {
-        PolicyManager::new()
+        SessionType::Repl
     };
+    let confirmation_provider: Arc<dyn ConfirmationProvider> = if cli.deny_confirmations || subagent {
+        Arc::new(DenyAllConfirmation)
+    } else {
+        Arc::new(TerminalConfirmation)
+    };
+    let context = ToolContext::new(work_dir.to_path_buf(), "mcp-startup".to_string(), policy_manager.clone())
+        .with_confirmation_provider(confirmation_provider)
+        .with_policy_context(PolicyContext::new(None, Some(session_type)));
 
-    Ok(AppConfig {
-        client_config,
-        policy_manager,
-        work_dir,
-        api_key,
-    })
+    let servers = McpServers::connect(&config, &context).await;
+    for error in servers.errors() {
+        eprintln!("{} {}", "⚠️".yellow(), error);
+    }
+    for client in servers.clients() {
+        let tools = servers.tools().iter().filter(|tool| tool.server() == client.name()).count();
+        eprintln!("{} MCP server '{}': {} tools", "🔌".cyan(), client.name(), tools);
+    }
+    servers
 }
@@ -54,8 +54,9 @@ pub async fn run_subagent_mode(
         backend_type,
     );
 
-    // Mark as non-interactive to prevent prompts
-    subagent.non_interactive = true;
+    // Nobody can answer prompts: stdout carries the JSON summary
+    subagent.confirmation_provider = std::sync::Arc::new(kimichat_toolcore::DenyAllConfirmation);
+    subagent.session_type = kimichat_policy::SessionType::Subagent;
 
     // Disable logging for subagent mode to avoid clutter
     subagent.logger = None;
@@ -105,6 +106,7 @@ pub async fn run_subagent_mode(
             "agents_used": subagent.use_agents,
             "model_used": subagent.current_model.display_name(),
             "work_directory": work_dir.to_string_lossy().to_string(),
+            "cost": subagent.cost_tracker.snapshot(),
         }),
     };
 
@@ -291,6 +293,21 @@ fn analyze_changes(
                             }
                         }
                     }
+                    "apply_patch" => {
+                        if let Ok(args) = serde_json::from_str::<serde_json::Value>(&function.arguments) {
+                            let patch = args.get("patch").and_then(|v| v.as_str()).unwrap_or_default();
+                            for file_patch in kimichat_tools::patch::parse_patch(patch).unwrap_or_default() {
+                                for file_path in file_patch.old_path.into_iter().chain(file_patch.new_path) {
+                                    let full_path = work_dir.join(file_path);
+                                    if let Some(path_str) = full_path.to_str() {
+                                        if !files_modified.contains(&path_str.to_string()) {
+                                            files_modified.push(path_str.to_string());
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
                     _ => {}
                 }
             }
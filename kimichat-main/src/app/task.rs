@@ -39,6 +39,12 @@ pub async fn run_task_mode(
         backend_type,
     );
 
+    chat.session_type = kimichat_policy::SessionType::Task;
+
+    if cli.deny_confirmations {
+        chat.confirmation_provider = std::sync::Arc::new(kimichat_toolcore::DenyAllConfirmation);
+    }
+
     // Initialize logger for task mode
     chat.logger = match ConversationLogger::new_task_mode(&chat.work_dir).await {
         Ok(l) => Some(l),
@@ -48,6 +54,8 @@ pub async fn run_task_mode(
         }
     };
 
+    chat.checkpoint_store.begin_turn(&task_text);
+
     let response = if chat.use_agents && chat.agent_coordinator.is_some() {
         // Use agent system
         match chat.process_with_agents(&task_text, None).await {
@@ -13,35 +13,134 @@ pub fn calculate_conversation_size(messages: &[Message]) -> usize {
     }
 }
 
-/// Get maximum session size based on model type (different models have different context limits)
-pub fn get_max_session_size(model: &ModelColor) -> usize {
-    match model {
-        ModelColor::GrnModel => 150_000,  // Conservative for Groq (~8K tokens)
-        ModelColor::BluModel => 400_000,  // Moderate for Claude (~100K tokens)
-        ModelColor::RedModel => 600_000,  // Larger for local models
+/// Smallest completion worth sending a request for
+const MIN_REPLY_TOKENS: usize = 1_024;
+
+/// Prompt size the server reported for the first `message_count` messages
+///
+/// Server counts include the chat template and tool definitions, so later
+/// estimates start from this number and only tokenize the messages added since.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ServerTokenCount {
+    model: ModelColor,
+    message_count: usize,
+    fingerprint: u64,
+    prompt_tokens: usize,
+}
+
+fn fingerprint(messages: &[Message]) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    serde_json::to_string(messages).unwrap_or_default().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Remember the prompt token count the server reported for a request
+pub(crate) fn record_server_token_count(chat: &mut KimiChat, model: ModelColor, message_count: usize, prompt_tokens: usize) {
+    if prompt_tokens == 0 || message_count > chat.messages.len() {
+        return;
+    }
+    chat.server_token_count = Some(ServerTokenCount {
+        model,
+        message_count,
+        fingerprint: fingerprint(&chat.messages[..message_count]),
+        prompt_tokens,
+    });
+}
+
+/// Estimate how many prompt tokens the current conversation takes up on a model
+///
+/// Uses the server's count for the last request when the conversation has
+/// only grown since, and the model's tokenizer for everything else.
+pub fn estimate_conversation_tokens(chat: &KimiChat, model: &ModelColor) -> usize {
+    let registry = &chat.client_config.model_registry;
+    let tokenizer = registry.tokenizer(chat.client_config.get_model_name(*model));
+    for notice in registry.take_tokenizer_notices() {
+        println!("{} {}", "🔢".cyan(), notice);
+    }
+
+    if let Some(count) = chat.server_token_count {
+        if count.model == *model
+            && count.message_count <= chat.messages.len()
+            && count.fingerprint == fingerprint(&chat.messages[..count.message_count])
+        {
+            return count.prompt_tokens + tokenizer.count_message_tokens(&chat.messages[count.message_count..]);
+        }
     }
+
+    let tools = serde_json::to_string(&chat.get_tools()).unwrap_or_default();
+    tokenizer.count_message_tokens(&chat.messages) + tokenizer.count_tokens(&tools)
 }
 
-/// Determine if a session should be compacted based on its current size and model type
+/// Get the prompt token budget for a model, from its context window in the model registry
+///
+/// Room for a full response is reserved, so a conversation within this
+/// budget can always be answered with the model's maximum output.
+pub fn get_context_token_budget(chat: &KimiChat, model: &ModelColor) -> usize {
+    chat.client_config.capabilities(*model).input_token_budget()
+}
+
+/// Determine if a session should be compacted because it has outgrown the model's token budget
 pub fn should_compact_session(chat: &KimiChat, model: &ModelColor) -> bool {
-    let conversation_size = calculate_conversation_size(&chat.messages);
-    let max_size = get_max_session_size(model);
-    
-    // Add a 25% buffer before triggering compaction
-    conversation_size > (max_size * 125) / 100
+    estimate_conversation_tokens(chat, model) > get_context_token_budget(chat, model)
+}
+
+/// Completion token limit for the next request: the model's maximum output,
+/// reduced when the prompt leaves less room than that in the context window
+pub(crate) fn max_tokens_for_request(chat: &KimiChat, model: &ModelColor) -> usize {
+    let capabilities = chat.client_config.capabilities(*model);
+    let prompt_tokens = estimate_conversation_tokens(chat, model);
+    capabilities.max_output_tokens
+        .min(capabilities.context_window.saturating_sub(prompt_tokens))
+        .max(1)
+}
+
+/// Pre-flight check that the next request fits the current model's context window
+///
+/// Compacts the conversation when it is over budget, and fails instead of
+/// sending a request the server would reject for being too long.
+pub(crate) async fn ensure_request_fits(chat: &mut KimiChat, current_tool_iteration: usize) -> Result<()> {
+    let model = chat.current_model;
+    if !should_compact_session(chat, &model) {
+        return Ok(());
+    }
+
+    println!(
+        "{} Conversation is ~{} tokens, over the {}-token budget for {}, compacting...",
+        "🗜️".yellow(),
+        estimate_conversation_tokens(chat, &model),
+        get_context_token_budget(chat, &model),
+        model.display_name()
+    );
+    if let Err(e) = intelligent_compaction(chat, current_tool_iteration).await {
+        eprintln!("{} Intelligent compaction failed: {}", "⚠️".yellow(), e);
+    }
+
+    let prompt_tokens = estimate_conversation_tokens(chat, &model);
+    let context_window = chat.client_config.capabilities(model).context_window;
+    if prompt_tokens + MIN_REPLY_TOKENS > context_window {
+        anyhow::bail!(
+            "Conversation is ~{} tokens, which leaves no room for a reply in {}'s {}-token context window",
+            prompt_tokens,
+            model.display_name(),
+            context_window
+        );
+    }
+    Ok(())
 }
 
 /// Intelligent compaction that preserves recent tool call context while summarizing older messages
 /// This is designed to work during tool-calling loops without losing recent context
 pub async fn intelligent_compaction(chat: &mut KimiChat, current_tool_iteration: usize) -> Result<()> {
-    const MIN_COMPACT_SIZE: usize = 100_000; // Only compact if above 100KB
+    const MIN_COMPACT_TOKENS: usize = 25_000; // Only compact if above ~25K tokens
     const PRESERVE_RECENT_MESSAGES: usize = 15; // Keep more recent messages than regular summarization
     const PRESERVE_RECENT_TOOL_CALLS: usize = 10; // Keep last 10 tool calls
     
     let conversation_size = calculate_conversation_size(&chat.messages);
+    let conversation_tokens = estimate_conversation_tokens(chat, &chat.current_model);
     
     // Don't compact small conversations
-    if conversation_size <= MIN_COMPACT_SIZE || chat.messages.len() <= PRESERVE_RECENT_MESSAGES * 2 {
+    if conversation_tokens <= MIN_COMPACT_TOKENS || chat.messages.len() <= PRESERVE_RECENT_MESSAGES * 2 {
         return Ok(());
     }
     
@@ -135,6 +234,7 @@ pub async fn intelligent_compaction(chat: &mut KimiChat, current_tool_iteration:
         tool_call_id: None,
         name: None,
         reasoning: None,
+        reasoning_details: None,
     }];
     
     // Format the conversation to summarize (more concise during tool execution)
@@ -175,6 +275,7 @@ pub async fn intelligent_compaction(chat: &mut KimiChat, current_tool_iteration:
         tool_call_id: None,
         name: None,
         reasoning: None,
+        reasoning_details: None,
     });
     
     // Call API to get summary using the OTHER model
@@ -188,6 +289,8 @@ pub async fn intelligent_compaction(chat: &mut KimiChat, current_tool_iteration:
         tools: vec![],
         tool_choice: "none".to_string(),
         stream: None,
+        max_tokens: None,
+        reasoning_effort: None,
     };
     
     // Get the appropriate API URL for the summary model
@@ -203,18 +306,22 @@ pub async fn intelligent_compaction(chat: &mut KimiChat, current_tool_iteration:
         .header("Content-Type", "application/json")
         .json(&request)
         .send()
-        .await?;
+        .await;
     
-    if !response.status().is_success() {
-        // If summarization fails, do simple trimming
-        println!("{} Intelligent compaction failed, doing simple trim", "⚠️".yellow());
-        chat.messages = vec![system_message.unwrap()];
-        chat.messages.extend(recent_messages);
-        return Ok(());
-    }
+    // If summarization fails (unreachable or an error status), do simple trimming
+    let response = match response {
+        Ok(response) if response.status().is_success() => response,
+        _ => {
+            println!("{} Intelligent compaction failed, doing simple trim", "⚠️".yellow());
+            chat.messages = vec![system_message.unwrap()];
+            chat.messages.extend(recent_messages);
+            return Ok(());
+        }
+    };
     
     let response_text = response.text().await?;
     let chat_response: ChatResponse = serde_json::from_str(&response_text)?;
+    chat.cost_tracker.record_response("compaction", &request.model, chat_response.usage.as_ref());
     
     if let Some(summary_msg) = chat_response.choices.into_iter().next().map(|c| c.message) {
         let summary = summary_msg.content;
@@ -238,6 +345,7 @@ pub async fn intelligent_compaction(chat: &mut KimiChat, current_tool_iteration:
             tool_call_id: None,
             name: None,
             reasoning: None,
+            reasoning_details: None,
         });
         
         // Add recent messages (including recent tool context)
@@ -264,15 +372,14 @@ pub async fn intelligent_compaction(chat: &mut KimiChat, current_tool_iteration:
 /// Summarize and trim conversation history when it gets too long
 /// Uses another model to summarize the middle portion of the conversation
 pub(crate) async fn summarize_and_trim_history(chat: &mut KimiChat) -> Result<()> {
-    // Use dynamic size limits based on model type
-    let max_size = get_max_session_size(&chat.current_model);
+    // Use the current model's context window to size the limit
+    let token_budget = get_context_token_budget(chat, &chat.current_model);
     const KEEP_RECENT_MESSAGES: usize = 5;
 
-    // Calculate conversation size by serializing to JSON
-    let conversation_size = calculate_conversation_size(&chat.messages);
+    let conversation_tokens = estimate_conversation_tokens(chat, &chat.current_model);
 
-    // Only summarize if conversation exceeds size limit
-    if conversation_size <= max_size {
+    // Only summarize if conversation exceeds the token budget
+    if conversation_tokens <= token_budget {
         return Ok(());
     }
 
@@ -284,11 +391,11 @@ pub(crate) async fn summarize_and_trim_history(chat: &mut KimiChat) -> Result<()
     };
 
     println!(
-        "{} History getting large ({:.1} KB, {} messages) - exceeds {} KB limit for {}. Asking {} to summarize...",
+        "{} History getting large (~{} tokens, {} messages) - exceeds {}-token budget for {}. Asking {} to summarize...",
         "📝".yellow(),
-        conversation_size as f64 / 1024.0,
+        conversation_tokens,
         chat.messages.len(),
-        max_size as f64 / 1024.0,
+        token_budget,
         chat.current_model.display_name(),
         summary_model.display_name()
     );
@@ -325,6 +432,7 @@ pub(crate) async fn summarize_and_trim_history(chat: &mut KimiChat) -> Result<()
         tool_call_id: None,
         name: None,
         reasoning: None,
+        reasoning_details: None,
     }];
 
     // Format the conversation to summarize
@@ -353,6 +461,7 @@ pub(crate) async fn summarize_and_trim_history(chat: &mut KimiChat) -> Result<()
         tool_call_id: None,
         name: None,
         reasoning: None,
+        reasoning_details: None,
     });
 
     // Call API to get summary using the OTHER model
@@ -366,6 +475,8 @@ pub(crate) async fn summarize_and_trim_history(chat: &mut KimiChat) -> Result<()
         tools: vec![],
         tool_choice: "none".to_string(),
         stream: None,
+        max_tokens: None,
+        reasoning_effort: None,
     };
 
     // Get the appropriate API URL for the summary model
@@ -393,6 +504,7 @@ pub(crate) async fn summarize_and_trim_history(chat: &mut KimiChat) -> Result<()
 
     let response_text = response.text().await?;
     let chat_response: ChatResponse = serde_json::from_str(&response_text)?;
+    chat.cost_tracker.record_response("compaction", &request.model, chat_response.usage.as_ref());
 
     if let Some(summary_msg) = chat_response.choices.into_iter().next().map(|c| c.message) {
         let full_response = summary_msg.content;
@@ -423,6 +535,7 @@ pub(crate) async fn summarize_and_trim_history(chat: &mut KimiChat) -> Result<()
             tool_call_id: None,
             name: None,
             reasoning: None,
+            reasoning_details: None,
         });
 
         // Add recent messages
@@ -464,6 +577,7 @@ pub(crate) async fn summarize_and_trim_history(chat: &mut KimiChat) -> Result<()
                         tool_call_id: None,
                         name: None,
                         reasoning: None,
+                        reasoning_details: None,
                     },
                     Message {
                         role: "user".to_string(),
@@ -479,6 +593,7 @@ pub(crate) async fn summarize_and_trim_history(chat: &mut KimiChat) -> Result<()
                         tool_call_id: None,
                         name: None,
                         reasoning: None,
+                        reasoning_details: None,
                     },
                 ];
 
@@ -492,6 +607,8 @@ pub(crate) async fn summarize_and_trim_history(chat: &mut KimiChat) -> Result<()
                     tools: vec![],
                     tool_choice: "none".to_string(),
                     stream: None,
+                    max_tokens: None,
+                    reasoning_effort: None,
                 };
 
                 // Get the appropriate API URL for the current model
@@ -512,6 +629,7 @@ pub(crate) async fn summarize_and_trim_history(chat: &mut KimiChat) -> Result<()
                 if decision_response.status().is_success() {
                     let decision_text = decision_response.text().await?;
                     if let Ok(decision_chat) = serde_json::from_str::<ChatResponse>(&decision_text) {
+                        chat.cost_tracker.record_response("compaction", &decision_request.model, decision_chat.usage.as_ref());
                         if let Some(decision_msg) = decision_chat.choices.into_iter().next().map(|c| c.message) {
                             let decision = decision_msg.content;
                             println!("{} {} says: {}", "💬".bright_green(), chat.current_model.display_name(), decision);
@@ -532,6 +650,7 @@ pub(crate) async fn summarize_and_trim_history(chat: &mut KimiChat) -> Result<()
                                     tool_call_id: None,
                                     name: None,
                                     reasoning: None,
+                                    reasoning_details: None,
                                 });
                             } else {
                                 println!(
@@ -1,9 +1,11 @@
 use anyhow::Result;
 use colored::Colorize;
+use std::sync::Arc;
 
 use crate::KimiChat;
 use kimichat_models::{ModelColor, Message};
 use kimichat_logging::safe_truncate;
+use kimichat_toolcore::{ConfirmationProvider, OutputSink, SerialConfirmation, TerminalOutput};
 
 /// Main chat loop - handles user messages, tool calls, and model interactions
 pub(crate) async fn chat(
@@ -18,6 +20,7 @@ pub(crate) async fn chat(
             tool_call_id: None,
             name: None,
             reasoning: None,
+            reasoning_details: None,
         });
 
         // Summarize ONCE before starting the tool-calling loop, not during it
@@ -60,6 +63,12 @@ pub(crate) async fn chat(
                 }
             }
 
+            // Stop spending once the session's cost cap is reached
+            if let Err(e) = chat.cost_tracker.check_budget() {
+                eprintln!("{} {}", "💸".red().bold(), e);
+                return Ok(format!("{}. Stopping before the next model call.", e));
+            }
+
             // Validate and fix tool calls in the conversation history before sending to API
             // This ensures fixes are permanent and consistent across requests (preserving cache)
             if let Ok(fixed) = crate::tools_execution::validation::validate_and_fix_tool_calls_in_place(chat) {
@@ -68,27 +77,20 @@ pub(crate) async fn chat(
                 }
             }
 
+            // Compact (or fail early) instead of sending a request that overflows the context window
+            crate::chat::history::ensure_request_fits(chat, tool_call_iterations).await?;
+            let sent_message_count = chat.messages.len();
+
+            // Fall back to a regular request when the model's API can't stream
+            let stream_responses = chat.stream_responses
+                && chat.client_config.capabilities(chat.current_model).supports_streaming;
+
             // Race API call against cancellation token
             let (response, usage, current_model) = if let Some(ref token) = cancellation_token {
                 tokio::select! {
                     result = async {
-                        if chat.stream_responses {
-                            // Check if this should use the new streaming system
-                            // The new system works with all model types now
-                            let should_use_anthropic =
-                                (chat.client_config.get_api_url(ModelColor::BluModel).as_ref().map(|u| u.contains("anthropic")).unwrap_or(false)) ||
-                                (chat.client_config.get_api_url(ModelColor::GrnModel).as_ref().map(|u| u.contains("anthropic")).unwrap_or(false));
-
-                            if should_use_anthropic {
-                                // Use the new streaming implementation for Anthropic-compatible APIs
-                                if chat.should_show_debug(1) {
-                                    println!("🔧 DEBUG: Using Anthropic-compatible streaming with format translation");
-                                }
-                                crate::api::call_api_streaming_with_llm_client(chat, &chat.messages, &chat.current_model).await
-                            } else {
-                                // Use old streaming for OpenAI-compatible APIs
-                                crate::api::call_api_streaming(chat, &chat.messages).await
-                            }
+                        if stream_responses {
+                            crate::api::call_api_streaming_with_llm_client(chat, &chat.messages, &chat.current_model).await
                         } else {
                             crate::api::call_api(chat, &chat.messages).await
                         }
@@ -99,28 +101,37 @@ pub(crate) async fn chat(
                 }
             } else {
                 // No cancellation token, call normally
-                if chat.stream_responses {
-                    // Check if this should use the new streaming system
-                    // The new system works with all model types now
-                    let should_use_anthropic =
-                        (chat.client_config.get_api_url(ModelColor::BluModel).as_ref().map(|u| u.contains("anthropic")).unwrap_or(false)) ||
-                        (chat.client_config.get_api_url(ModelColor::GrnModel).as_ref().map(|u| u.contains("anthropic")).unwrap_or(false));
-
-                    if should_use_anthropic {
-                        // Use the new streaming implementation for Anthropic-compatible APIs
-                        if chat.should_show_debug(1) {
-                            println!("🔧 DEBUG: Using Anthropic-compatible streaming with format translation");
-                        }
-                        crate::api::call_api_streaming_with_llm_client(chat, &chat.messages, &chat.current_model).await?
-                    } else {
-                        // Use old streaming for OpenAI-compatible APIs
-                        crate::api::call_api_streaming(chat, &chat.messages).await?
-                    }
+                if stream_responses {
+                    crate::api::call_api_streaming_with_llm_client(chat, &chat.messages, &chat.current_model).await?
                 } else {
                     crate::api::call_api(chat, &chat.messages).await?
                 }
             };
 
+            // Record which provider answered when a fallback chain is configured
+            let provider = chat.last_provider.lock().unwrap().take();
+            if let Some(ref provider) = provider {
+                if chat.should_show_debug(1) {
+                    println!("🔧 DEBUG: response provided by {}", provider);
+                }
+            }
+            if let Some(logger) = &mut chat.logger {
+                logger.set_provider(provider);
+            }
+
+            // Reasoning stays collapsed unless expanded with /thinking on (streaming already printed it then)
+            if let Some(reasoning) = &response.reasoning {
+                if !chat.show_reasoning {
+                    println!(
+                        "{} Thought for {} words (/thinking to expand)",
+                        "💭".bright_black(),
+                        reasoning.split_whitespace().count()
+                    );
+                } else if !stream_responses {
+                    println!("{} {}", "💭".bright_black(), reasoning.bright_black());
+                }
+            }
+
             if chat.current_model != current_model {
                 println!("Forced model switch: {:?} -> {:?}", &chat.current_model, &current_model);
                 chat.current_model = current_model.clone();
@@ -133,12 +144,14 @@ pub(crate) async fn chat(
                     tool_call_id: None,
                     name: None,
                     reasoning: None,
+                    reasoning_details: None,
                 });
             }
 
             // Display token usage
             if let Some(usage) = &usage {
                 chat.total_tokens_used += usage.total_tokens;
+                crate::chat::history::record_server_token_count(chat, current_model, sent_message_count, usage.prompt_tokens);
                 println!(
                     "{} Prompt: {} | Completion: {} | Total: {} | Session: {}",
                     "📊".bright_black(),
@@ -147,33 +160,30 @@ pub(crate) async fn chat(
                     usage.total_tokens.to_string().bright_black(),
                     chat.total_tokens_used.to_string().cyan()
                 );
+                if usage.cache_read_input_tokens > 0 || usage.cache_creation_input_tokens > 0 {
+                    println!(
+                        "{} Prompt cache: {} read | {} written",
+                        "🗄️".bright_black(),
+                        usage.cache_read_input_tokens.to_string().bright_black(),
+                        usage.cache_creation_input_tokens.to_string().bright_black()
+                    );
+                }
+
+                let model_name = chat.client_config.get_model_name(current_model).to_string();
+                let cost = chat.cost_tracker.record("main", &model_name, usage);
+                if chat.client_config.capabilities(current_model).has_pricing() {
+                    println!(
+                        "{} Estimated cost: ${:.4} | Session: ${:.4}",
+                        "💰".bright_black(),
+                        cost,
+                        chat.cost_tracker.total_cost()
+                    );
+                }
             }
 
             if let Some(tool_calls) = &response.tool_calls {
                 tool_call_iterations += 1;
 
-                // Progressive session size management - check periodically during tool execution
-                const PROGRESSIVE_CHECK_INTERVAL: usize = 25; // Check every 25 tool calls
-                const MID_LOOP_SIZE_THRESHOLD: usize = 400_000; // 400KB for mid-loop compaction
-                
-                if tool_call_iterations % PROGRESSIVE_CHECK_INTERVAL == 0 {
-                    let conversation_size = crate::chat::history::calculate_conversation_size(&chat.messages);
-                    if conversation_size > MID_LOOP_SIZE_THRESHOLD {
-                        println!(
-                            "{} Session size reached {:.1} KB during tool execution (iteration {}), performing intelligent compaction...", 
-                            "🗜️".yellow(), 
-                            conversation_size as f64 / 1024.0,
-                            tool_call_iterations
-                        );
-                        
-                        // Perform intelligent compaction that preserves recent tool context
-                        if let Err(e) = crate::chat::history::intelligent_compaction(chat, tool_call_iterations).await {
-                            eprintln!("{} Intelligent compaction failed: {}", "⚠️".yellow(), e);
-                            // Continue without compaction if it fails
-                        }
-                    }
-                }
-
                 // Enhanced loop detection with lower false positive rate
                 let tool_signature = tool_calls.iter()
                     .map(|tc| format!("{}:{}", tc.function.name, tc.function.arguments))
@@ -244,6 +254,7 @@ pub(crate) async fn chat(
                         tool_call_id: None,
                         name: None,
                         reasoning: None,
+                        reasoning_details: None,
                     });
                     return Ok("Repeated tool call pattern detected. Please refine your request.".to_string());
                 }
@@ -307,6 +318,7 @@ pub(crate) async fn chat(
                                         tool_call_id: None,
                                         name: None,
                                         reasoning: None,
+                                        reasoning_details: None,
                                     });
                                     return Ok("Intelligent progress evaluation suggested stopping this approach.".to_string());
                                 }
@@ -324,6 +336,7 @@ pub(crate) async fn chat(
                                         tool_call_id: None,
                                         name: None,
                                         reasoning: None,
+                                        reasoning_details: None,
                                     });
                                 } else {
                                     // should_continue is true and no strategy change needed
@@ -356,6 +369,7 @@ pub(crate) async fn chat(
                         tool_call_id: None,
                         name: None,
                         reasoning: None,
+                        reasoning_details: None,
                     });
                     return Ok(format!(
                         "Reached maximum tool call limit ({} iterations). Please simplify your request.",
@@ -389,124 +403,150 @@ pub(crate) async fn chat(
                     ).await;
                 }
 
-                for tool_call in tool_calls {
-                    println!(
-                        "{} {} with args: {} (iteration {}/{})",
-                        "🔧 Calling tool:".yellow(),
-                        tool_call.function.name.cyan(),
-                        tool_call.function.arguments.bright_black(),
-                        tool_call_iterations,
-                        MAX_TOOL_ITERATIONS
-                    );
+                // Consecutive read-only calls run together; everything else runs alone, in order
+                let batches = crate::tools_execution::parallel::batch_tool_calls(tool_calls, |name| chat.is_read_only_tool(name));
+                for batch in batches {
+                    for tool_call in &batch {
+                        println!(
+                            "{} {} with args: {} (iteration {}/{})",
+                            "🔧 Calling tool:".yellow(),
+                            tool_call.function.name.cyan(),
+                            tool_call.function.arguments.bright_black(),
+                            tool_call_iterations,
+                            MAX_TOOL_ITERATIONS
+                        );
+                    }
 
                     let tool_start_time = std::time::Instant::now();
-                    let result = match chat.execute_tool(
-                        &tool_call.function.name,
-                        &tool_call.function.arguments,
-                    ).await {
-                        Ok(r) => r,
-                        Err(e) => {
-                            let error_msg = e.to_string();
-
-                            // Track error for progress evaluation
-                            errors_encountered.push(format!("{}: {}", tool_call.function.name, error_msg));
-                            // Make cancellation errors very explicit to the model
-                            if error_msg.contains("cancelled by user") ||
-                               error_msg.contains("Edit cancelled") ||
-                               error_msg.contains("Command cancelled") {
-                                // Extract user's comment if present
-                                let user_feedback = if error_msg.contains(" - ") {
-                                    error_msg.split(" - ").skip(1).collect::<Vec<_>>().join(" - ")
-                                } else {
-                                    String::new()
-                                };
+                    let outcomes = if batch.len() > 1 {
+                        // Any confirmations the batch needs are asked one at a time
+                        let confirmation_provider: Arc<dyn ConfirmationProvider> =
+                            Arc::new(SerialConfirmation::new(Arc::clone(&chat.confirmation_provider)));
+                        let calls = batch
+                            .iter()
+                            .map(|tool_call| {
+                                (*tool_call, Arc::clone(&confirmation_provider), Arc::new(TerminalOutput) as Arc<dyn OutputSink>)
+                            })
+                            .collect();
+                        chat.execute_read_only_tools(calls).await
+                    } else {
+                        vec![chat.execute_tool(&batch[0].function.name, &batch[0].function.arguments).await]
+                    };
+                    let duration = tool_start_time.elapsed();
 
-                                let feedback_section = if !user_feedback.is_empty() {
-                                    format!("\n\nUSER'S FEEDBACK: {}\nThis feedback explains why the operation was cancelled. Address this concern in your next approach.", user_feedback)
+                    for (tool_call, outcome) in batch.into_iter().zip(outcomes) {
+                        let result = match outcome {
+                            Ok(r) => r,
+                            Err(e) => {
+                                let error_msg = e.to_string();
+
+                                // Track error for progress evaluation
+                                errors_encountered.push(format!("{}: {}", tool_call.function.name, error_msg));
+                                // Make cancellation errors very explicit to the model
+                                if error_msg.contains("cancelled by user") ||
+                                   error_msg.contains("Edit cancelled") ||
+                                   error_msg.contains("Command cancelled") {
+                                    // Extract user's comment if present
+                                    let user_feedback = if error_msg.contains(" - ") {
+                                        error_msg.split(" - ").skip(1).collect::<Vec<_>>().join(" - ")
+                                    } else {
+                                        String::new()
+                                    };
+
+                                    let feedback_section = if !user_feedback.is_empty() {
+                                        format!("\n\nUSER'S FEEDBACK: {}\nThis feedback explains why the operation was cancelled. Address this concern in your next approach.", user_feedback)
+                                    } else {
+                                        String::new()
+                                    };
+
+                                    format!(
+                                        "OPERATION CANCELLED BY USER. The user explicitly cancelled this operation. \
+                                        DO NOT retry this same approach. Please acknowledge the cancellation and either:\n\
+                                        1. Ask the user what they would like to do instead\n\
+                                        2. Try a completely different approach that addresses the user's concerns\n\
+                                        3. Stop if this was the only viable option\
+                                        {}\n\
+                                        \nOriginal message: {}",
+                                        feedback_section,
+                                        error_msg
+                                    )
                                 } else {
-                                    String::new()
-                                };
-
-                                format!(
-                                    "OPERATION CANCELLED BY USER. The user explicitly cancelled this operation. \
-                                    DO NOT retry this same approach. Please acknowledge the cancellation and either:\n\
-                                    1. Ask the user what they would like to do instead\n\
-                                    2. Try a completely different approach that addresses the user's concerns\n\
-                                    3. Stop if this was the only viable option\
-                                    {}\n\
-                                    \nOriginal message: {}",
-                                    feedback_section,
-                                    error_msg
-                                )
-                            } else {
-                                format!("Error: {}", error_msg)
+                                    format!("Error: {}", error_msg)
+                                }
                             }
-                        }
-                    };
+                        };
 
-                    // Display result to user (truncate for file reading tools)
-                    let display_result = if tool_call.function.name == "open_file" || tool_call.function.name == "read_file" {
-                        let lines: Vec<&str> = result.lines().collect();
-                        if lines.len() > 10 {
-                            let first_10 = lines[..10].join("\n");
-                            let remaining = lines.len() - 10;
-                            format!("{}\n\n...and {} more lines", first_10, remaining)
+                        // Display result to user (truncate for file reading tools)
+                        let display_result = if tool_call.function.name == "open_file" || tool_call.function.name == "read_file" {
+                            let lines: Vec<&str> = result.lines().collect();
+                            if lines.len() > 10 {
+                                let first_10 = lines[..10].join("\n");
+                                let remaining = lines.len() - 10;
+                                format!("{}\n\n...and {} more lines", first_10, remaining)
+                            } else {
+                                result.clone()
+                            }
                         } else {
                             result.clone()
-                        }
-                    } else {
-                        result.clone()
-                    };
+                        };
 
-                    println!("{} {}", "📋 Result:".green(), display_result.bright_black());
+                        println!("{} {}", "📋 Result:".green(), display_result.bright_black());
 
-                    // Log tool result
-                    if let Some(logger) = &mut chat.logger {
-                        if std::env::var("DEBUG_LOG").is_ok() {
-                            eprintln!("[DEBUG] Logging tool result for {}", tool_call.function.name);
+                        // Log tool result
+                        if let Some(logger) = &mut chat.logger {
+                            if std::env::var("DEBUG_LOG").is_ok() {
+                                eprintln!("[DEBUG] Logging tool result for {}", tool_call.function.name);
+                            }
+                            logger.log_tool_result(
+                                &result,
+                                &tool_call.id,
+                                &tool_call.function.name,
+                            ).await;
                         }
-                        logger.log_tool_result(
-                            &result,
-                            &tool_call.id,
-                            &tool_call.function.name,
-                        ).await;
-                    }
 
-                    // Track tool call for progress evaluation
-                    let duration = tool_start_time.elapsed();
-                    let result_summary = if result.chars().count() > 200 {
-                        format!("{} (truncated)", safe_truncate(&result, 200))
-                    } else {
-                        result.clone()
-                    };
+                        // Track tool call for progress evaluation
+                        let result_summary = if result.chars().count() > 200 {
+                            format!("{} (truncated)", safe_truncate(&result, 200))
+                        } else {
+                            result.clone()
+                        };
 
-                    // Track files that were changed
-                    if tool_call.function.name.contains("write_file") ||
-                       tool_call.function.name.contains("edit_file") {
-                        if let Ok(args) = serde_json::from_str::<serde_json::Value>(&tool_call.function.arguments) {
-                            if let Some(file_path) = args.get("file_path").and_then(|v| v.as_str()) {
-                                files_changed.insert(file_path.to_string());
+                        // Track files that were changed
+                        if tool_call.function.name.contains("write_file") ||
+                           tool_call.function.name.contains("edit_file") {
+                            if let Ok(args) = serde_json::from_str::<serde_json::Value>(&tool_call.function.arguments) {
+                                if let Some(file_path) = args.get("file_path").and_then(|v| v.as_str()) {
+                                    files_changed.insert(file_path.to_string());
+                                }
+                            }
+                        } else if tool_call.function.name == "apply_patch" {
+                            if let Ok(args) = serde_json::from_str::<serde_json::Value>(&tool_call.function.arguments) {
+                                let patch = args.get("patch").and_then(|v| v.as_str()).unwrap_or_default();
+                                for file_patch in kimichat_tools::patch::parse_patch(patch).unwrap_or_default() {
+                                    files_changed.extend(file_patch.old_path.into_iter().chain(file_patch.new_path));
+                                }
                             }
                         }
-                    }
-
-                    let call_info = kimichat_agents::progress_evaluator::ToolCallInfo {
-                        tool_name: tool_call.function.name.clone(),
-                        parameters: tool_call.function.arguments.clone(),
-                        success: !result.contains("failed") && !result.contains("cancelled"),
-                        duration_ms: duration.as_millis() as u64,
-                        result_summary: Some(result_summary),
-                    };
-                    tool_call_history.push(call_info);
 
-                    chat.messages.push(Message {
-                        role: "tool".to_string(),
-                        content: result,
-                        tool_calls: None,
-                        tool_call_id: Some(tool_call.id.clone()),
-                        name: Some(tool_call.function.name.clone()),
-                        reasoning: None,
-                    });
+                        let call_info = kimichat_agents::progress_evaluator::ToolCallInfo {
+                            tool_name: tool_call.function.name.clone(),
+                            parameters: tool_call.function.arguments.clone(),
+                            success: !result.contains("failed") && !result.contains("cancelled"),
+                            duration_ms: duration.as_millis() as u64,
+                            result_summary: Some(result_summary),
+                        };
+                        tool_call_history.push(call_info);
+
+                        chat.messages.push(Message {
+                            role: "tool".to_string(),
+                            content: result,
+                            tool_calls: None,
+                            tool_call_id: Some(tool_call.id.clone()),
+                            name: Some(tool_call.function.name.clone()),
+                            reasoning: None,
+                            reasoning_details: None,
+                        });
+                    }
                 }
             } else {
                 chat.messages.push(response.clone());
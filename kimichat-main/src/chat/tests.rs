@@ -1,13 +1,14 @@
 #[cfg(test)]
 mod tests {
-    use crate::chat::{calculate_conversation_size, get_max_session_size, should_compact_session, intelligent_compaction};
+    use crate::chat::history::{calculate_conversation_size, estimate_conversation_tokens, get_context_token_budget, should_compact_session, intelligent_compaction};
+    use crate::chat::history::{max_tokens_for_request, record_server_token_count};
     use crate::{KimiChat, ClientConfig};
-    use kimichat_models::{Message, ModelColor, ToolCall, FunctionCall};
+    use kimichat_models::{Message, ModelCapabilities, ModelColor, ToolCall, FunctionCall};
     use std::sync::Arc;
     use tokio::sync::Mutex;
     use kimichat_terminal::TerminalManager;
     use kimichat_policy::PolicyManager;
-    use kimichat_toolcore::ToolRegistry;
+    use kimichat_toolcore::{param, ParameterDefinition, ToolRegistry};
     use kimichat_todo::TodoManager;
     use tempfile::TempDir;
 
@@ -31,11 +32,19 @@ mod tests {
             policy_manager: PolicyManager::new(),
             terminal_manager: Arc::new(Mutex::new(TerminalManager::new(work_dir))),
             skill_registry: None,
-            non_interactive: false,
+            confirmation_provider: Arc::new(kimichat_toolcore::TerminalConfirmation),
+            session_type: kimichat_policy::SessionType::Repl,
             todo_manager: Arc::new(TodoManager::new()),
+            job_manager: Arc::new(kimichat_toolcore::JobManager::new()),
+            checkpoint_store: Arc::new(kimichat_checkpoints::CheckpointStore::new(&std::env::temp_dir(), "test")),
+            read_tracker: Arc::new(kimichat_toolcore::ReadTracker::new()),
             stream_responses: false,
             verbose: false,
             debug_level: 0,
+            show_reasoning: false,
+            last_provider: std::sync::Mutex::new(None),
+            server_token_count: None,
+            cost_tracker: Arc::new(crate::cost::CostTracker::new(&ClientConfig::new())),
         }
     }
 
@@ -48,6 +57,7 @@ mod tests {
             tool_call_id: None,
             name: None,
             reasoning: None,
+            reasoning_details: None,
         }
     }
 
@@ -69,6 +79,7 @@ mod tests {
             tool_call_id: None,
             name: None,
             reasoning: None,
+            reasoning_details: None,
         }
     }
 
@@ -84,6 +95,7 @@ mod tests {
             tool_call_id: None,
             name: None,
             reasoning: None,
+            reasoning_details: None,
         }
     }
 
@@ -116,11 +128,60 @@ mod tests {
         assert!(size < 15 * 1024); // But not too much larger due to JSON overhead
     }
 
+    // Helper function to point a model color at a model with the given limits
+    fn use_model(chat: &mut KimiChat, color: ModelColor, name: &str, context_window: usize, max_output_tokens: usize) {
+        Arc::make_mut(&mut chat.client_config.model_registry).insert(name, ModelCapabilities {
+            context_window,
+            max_output_tokens,
+            ..ModelCapabilities::default()
+        });
+        chat.client_config.set_model_name(color, name.to_string());
+    }
+
+    #[test]
+    fn test_get_context_token_budget_by_model() {
+        let mut chat = create_test_kimichat();
+        use_model(&mut chat, ModelColor::GrnModel, "small-model", 32_768, 4_096);
+        use_model(&mut chat, ModelColor::RedModel, "large-model", 200_000, 8_000);
+
+        // Context window minus room for a full response
+        assert_eq!(get_context_token_budget(&chat, &ModelColor::GrnModel), 28_672);
+        assert_eq!(get_context_token_budget(&chat, &ModelColor::RedModel), 192_000);
+
+        // Unknown models get the registry's conservative default
+        chat.client_config.set_model_name(ModelColor::BluModel, "unknown-model".to_string());
+        assert_eq!(get_context_token_budget(&chat, &ModelColor::BluModel), 32_768 - 4_096);
+    }
+
+    #[test]
+    fn test_estimate_conversation_tokens_builds_on_server_count() {
+        let mut chat = create_test_kimichat();
+        chat.messages.push(create_test_message("user", "12345678"));
+
+        // 3 per message + 2 for the content + 3 reply priming + 1 for the empty tool list
+        assert_eq!(estimate_conversation_tokens(&chat, &ModelColor::GrnModel), 9);
+
+        record_server_token_count(&mut chat, ModelColor::GrnModel, 1, 500);
+        chat.messages.push(create_test_message("assistant", "1234"));
+        assert_eq!(estimate_conversation_tokens(&chat, &ModelColor::GrnModel), 500 + 3 + 1 + 3);
+
+        // The server count only applies to the model it came from
+        assert_eq!(estimate_conversation_tokens(&chat, &ModelColor::BluModel), 13);
+
+        // and only while the counted messages are unchanged
+        chat.messages[0].content = "rewritten".to_string();
+        assert!(estimate_conversation_tokens(&chat, &ModelColor::GrnModel) < 500);
+    }
+
     #[test]
-    fn test_get_max_session_size_by_model() {
-        assert_eq!(get_max_session_size(&ModelColor::GrnModel), 150_000);
-        assert_eq!(get_max_session_size(&ModelColor::BluModel), 400_000);
-        assert_eq!(get_max_session_size(&ModelColor::RedModel), 600_000);
+    fn test_max_tokens_shrinks_to_fit_context_window() {
+        let mut chat = create_test_kimichat();
+        use_model(&mut chat, ModelColor::GrnModel, "small-model", 32_768, 4_096);
+        chat.messages.push(create_test_message("user", "Hello"));
+        assert_eq!(max_tokens_for_request(&chat, &ModelColor::GrnModel), 4_096);
+
+        record_server_token_count(&mut chat, ModelColor::GrnModel, 1, 30_000);
+        assert_eq!(max_tokens_for_request(&chat, &ModelColor::GrnModel), 32_768 - 30_003);
     }
 
     #[test]
@@ -134,6 +195,8 @@ mod tests {
     #[test]
     fn test_should_compact_session_above_threshold() {
         let mut chat = create_test_kimichat();
+        use_model(&mut chat, ModelColor::GrnModel, "small-model", 32_768, 4_096);
+        use_model(&mut chat, ModelColor::RedModel, "large-model", 200_000, 8_000);
         
         // Add messages to exceed the threshold
         for i in 0..100 {
@@ -143,10 +206,10 @@ mod tests {
         let size = calculate_conversation_size(&chat.messages);
         assert!(size > 150_000, "Conversation should be above threshold");
         
-        // Should need compaction for GrnModel (150KB threshold)
+        // Should need compaction for the small GrnModel (~29K token budget)
         assert!(should_compact_session(&chat, &ModelColor::GrnModel));
         
-        // But not for RedModel (600KB threshold)
+        // But not for the large RedModel (192K token budget)
         assert!(!should_compact_session(&chat, &ModelColor::RedModel));
     }
 
@@ -319,4 +382,77 @@ mod tests {
         
         assert_eq!(not_truncated, short_text);
     }
+
+    /// Read-only tool that takes a while and counts how many calls overlap
+    struct SlowReadTool {
+        running: Arc<std::sync::atomic::AtomicUsize>,
+        most_running: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl kimichat_toolcore::Tool for SlowReadTool {
+        fn name(&self) -> &str {
+            "slow_read"
+        }
+
+        fn description(&self) -> &str {
+            "Read slowly"
+        }
+
+        fn parameters(&self) -> std::collections::HashMap<String, ParameterDefinition> {
+            std::collections::HashMap::from([param!("n", "integer", "Call number", required)])
+        }
+
+        fn read_only(&self) -> bool {
+            true
+        }
+
+        async fn execute(&self, params: kimichat_toolcore::ToolParameters, _context: &kimichat_toolcore::ToolContext) -> kimichat_toolcore::ToolResult {
+            use std::sync::atomic::Ordering;
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.most_running.fetch_max(running, Ordering::SeqCst);
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            kimichat_toolcore::ToolResult::success(format!("read {}", params.get_required::<i64>("n").unwrap()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_only_tools_run_concurrently_up_to_the_limit() {
+        let mut chat = create_test_kimichat();
+        let most_running = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        chat.tool_registry.register(SlowReadTool {
+            running: Arc::new(std::sync::atomic::AtomicUsize::new(0)),
+            most_running: Arc::clone(&most_running),
+        });
+        chat.client_config.max_parallel_tools = 2;
+        assert!(chat.is_read_only_tool("slow_read"));
+
+        let calls: Vec<ToolCall> = (1..=5)
+            .map(|n| ToolCall {
+                id: format!("call-{}", n),
+                tool_type: "function".to_string(),
+                function: FunctionCall { name: "slow_read".to_string(), arguments: format!("{{\"n\": {}}}", n) },
+            })
+            .collect();
+        let batch = calls
+            .iter()
+            .map(|call| {
+                (
+                    call,
+                    Arc::new(kimichat_toolcore::DenyAllConfirmation) as Arc<dyn kimichat_toolcore::ConfirmationProvider>,
+                    Arc::new(kimichat_toolcore::TerminalOutput) as Arc<dyn kimichat_toolcore::OutputSink>,
+                )
+            })
+            .collect();
+
+        let results: Vec<String> = chat
+            .execute_read_only_tools(batch)
+            .await
+            .into_iter()
+            .map(|result| result.unwrap())
+            .collect();
+        assert_eq!(results, vec!["read 1", "read 2", "read 3", "read 4", "read 5"]);
+        assert_eq!(most_running.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
 }
\ No newline at end of file
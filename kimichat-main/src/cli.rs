@@ -6,7 +6,7 @@ use std::future::Future;
 use std::pin::Pin;
 
 use kimichat_toolcore::{Tool, ToolParameters, ToolContext};
-use kimichat_policy::PolicyManager;
+use kimichat_policy::{ActionType, Explanation, Matcher, PolicyContext, PolicyLayer, PolicyManager, PolicyRule, PolicyScope, SessionType};
 use kimichat_tools::{
     OpenFileTool, ReadFileTool, WriteFileTool, EditFileTool, ListFilesTool,
     RunCommandTool, SearchFilesTool,
@@ -68,14 +68,17 @@ pub struct Cli {
     pub api_url_red_model: Option<String>,
 
     /// Override the 'blu_model' model with a custom model name (supports model@backend[url] syntax)
+    /// A comma-separated list sets a fallback chain, e.g. kimi@groq,llama3@llama(http://localhost:8080)
     #[arg(long, value_name = "MODEL")]
     pub model_blu_model: Option<String>,
 
     /// Override the 'grn_model' model with a custom model name (supports model@backend[url] syntax)
+    /// A comma-separated list sets a fallback chain, e.g. kimi@groq,llama3@llama(http://localhost:8080)
     #[arg(long, value_name = "MODEL")]
     pub model_grn_model: Option<String>,
 
     /// Override the 'red_model' model with a custom model name (supports model@backend[url] syntax)
+    /// A comma-separated list sets a fallback chain, e.g. kimi@groq,llama3@llama(http://localhost:8080)
     #[arg(long, value_name = "MODEL")]
     pub model_red_model: Option<String>,
 
@@ -86,18 +89,30 @@ pub struct Cli {
     #[arg(long, value_name = "MODEL")]
     pub model: Option<String>,
 
-    /// Backend type for blu_model (groq, anthropic, llama, openai)
+    /// Backend type for blu_model (groq, anthropic, llama, openai, ollama, openai-compatible)
     #[arg(long, value_name = "BACKEND")]
     pub blu_backend: Option<String>,
 
-    /// Backend type for grn_model (groq, anthropic, llama, openai)
+    /// Backend type for grn_model (groq, anthropic, llama, openai, ollama, openai-compatible)
     #[arg(long, value_name = "BACKEND")]
     pub grn_backend: Option<String>,
 
-    /// Backend type for red_model (groq, anthropic, llama, openai)
+    /// Backend type for red_model (groq, anthropic, llama, openai, ollama, openai-compatible)
     #[arg(long, value_name = "BACKEND")]
     pub red_backend: Option<String>,
 
+    /// Reasoning for blu_model: an effort level (low, medium, high) or a thinking budget in tokens
+    #[arg(long, value_name = "EFFORT|TOKENS", env = "KIMICHAT_BLU_REASONING")]
+    pub blu_reasoning: Option<String>,
+
+    /// Reasoning for grn_model: an effort level (low, medium, high) or a thinking budget in tokens
+    #[arg(long, value_name = "EFFORT|TOKENS", env = "KIMICHAT_GRN_REASONING")]
+    pub grn_reasoning: Option<String>,
+
+    /// Reasoning for red_model: an effort level (low, medium, high) or a thinking budget in tokens
+    #[arg(long, value_name = "EFFORT|TOKENS", env = "KIMICHAT_RED_REASONING")]
+    pub red_reasoning: Option<String>,
+
     /// API key for blu_model
     #[arg(long, value_name = "KEY")]
     pub blu_key: Option<String>,
@@ -114,7 +129,8 @@ pub struct Cli {
     #[arg(long)]
     pub auto_confirm: bool,
 
-    /// Path to policy file (default: policies.toml in project root)
+    /// Policy file relative to the workspace, trusted to loosen ~/.okaychat/policy.toml
+    /// (default: .kimichat/policy.toml, which can only tighten it)
     #[arg(long, value_name = "PATH")]
     pub policy_file: Option<String>,
 
@@ -122,10 +138,26 @@ pub struct Cli {
     #[arg(long)]
     pub learn_policies: bool,
 
+    /// Deny every action the policy would ask about instead of prompting (for CI)
+    #[arg(long, conflicts_with = "auto_confirm")]
+    pub deny_confirmations: bool,
+
     /// Enable streaming mode - show AI responses as they're generated
     #[arg(long)]
     pub stream: bool,
 
+    /// Stop calling models once the session's estimated cost reaches this many USD
+    #[arg(long, value_name = "USD", env = "KIMICHAT_MAX_COST")]
+    pub max_cost: Option<f64>,
+
+    /// Let file tools reach this directory outside the work directory (repeatable)
+    #[arg(long = "allow-dir", value_name = "DIR")]
+    pub allow_dirs: Vec<String>,
+
+    /// Run at most this many read-only tool calls from one model turn at once (1 runs them one by one)
+    #[arg(long, value_name = "N", default_value_t = crate::config::DEFAULT_MAX_PARALLEL_TOOLS, env = "KIMICHAT_MAX_PARALLEL_TOOLS")]
+    pub max_parallel_tools: usize,
+
     /// Enable verbose debug output (shows HTTP requests, responses, headers, etc.)
     #[arg(long, short = 'v')]
     pub verbose: bool,
@@ -232,6 +264,30 @@ pub enum Commands {
         #[command(subcommand)]
         command: TerminalCommands,
     },
+    /// Inspect the layered policy
+    Policy {
+        #[command(subcommand)]
+        command: PolicyCommands,
+    },
+    /// Serve kimichat's tools, skills and terminal screens over MCP on stdio
+    McpServe,
+}
+
+#[derive(Subcommand)]
+pub enum PolicyCommands {
+    /// Show which rule decides an action and why
+    Explain {
+        /// Action type (e.g. file_write, command_execution, git_operation)
+        action: String,
+        /// Target the policy matches: a workspace-relative path, command line, URL, ...
+        target: String,
+        /// Agent making the call, for rules with an agent condition
+        #[arg(long)]
+        agent: Option<String>,
+        /// Session type (repl, task, web, subagent, mcp), for rules with a session condition
+        #[arg(long)]
+        session_type: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -281,18 +337,21 @@ impl Cli {
                 api_url: self.api_url_blu_model.clone(),
                 api_key: self.blu_key.clone(),
                 model: self.model_blu_model.clone(),
+                reasoning: self.blu_reasoning.clone(),
             },
             ModelConfig {
                 backend: self.grn_backend.clone(),
                 api_url: self.api_url_grn_model.clone(),
                 api_key: self.grn_key.clone(),
                 model: self.model_grn_model.clone(),
+                reasoning: self.grn_reasoning.clone(),
             },
             ModelConfig {
                 backend: self.red_backend.clone(),
                 api_url: self.api_url_red_model.clone(),
                 api_key: self.red_key.clone(),
                 model: self.model_red_model.clone(),
+                reasoning: self.red_reasoning.clone(),
             },
         ]
     }
@@ -487,10 +546,138 @@ impl Commands {
                     Err(anyhow::anyhow!("Terminal commands require special handling"))
                 })
             }
+            Commands::Policy { .. } => {
+                // Policy commands need the CLI's policy options, handled in main.rs
+                Box::pin(async move {
+                    Err(anyhow::anyhow!("Policy commands require special handling"))
+                })
+            }
+            Commands::McpServe => {
+                // The MCP server owns stdin/stdout, handled in main.rs
+                Box::pin(async move {
+                    Err(anyhow::anyhow!("The MCP server requires special handling"))
+                })
+            }
+        }
+    }
+}
+
+impl PolicyCommands {
+    pub fn execute(&self, policy_manager: &PolicyManager) -> Result<String> {
+        match self {
+            PolicyCommands::Explain { action, target, agent, session_type } => {
+                let action: ActionType = action.parse()?;
+                let session_type = session_type.as_deref().map(str::parse::<SessionType>).transpose()?;
+                let context = PolicyContext::new(agent.clone(), session_type);
+                // A built-in agent's declared permissions apply on top of the policy files
+                let policy_manager = match agent.as_deref().and_then(builtin_agent_permissions) {
+                    Some(permissions) => policy_manager.with_agent_layer(permissions.to_policy_rules()),
+                    None => policy_manager.clone(),
+                };
+                let explanation = policy_manager.explain(&action, target, &context);
+                Ok(format_explanation(&explanation, &policy_manager.layers()))
+            }
         }
     }
 }
 
+fn builtin_agent_permissions(name: &str) -> Option<kimichat_agents::agent_config::AgentPermissions> {
+    let config = kimichat_agents::embedded_configs::get_embedded_agent_configs().get(name).copied()?;
+    serde_json::from_str::<kimichat_agents::agent_config::AgentConfig>(config)
+        .ok()
+        .map(|config| config.permissions)
+}
+
+fn format_explanation(explanation: &Explanation, layers: &[PolicyLayer]) -> String {
+    let mut out = format!("Decision: {} ({} on '{}')\n", explanation.decision, explanation.action, explanation.target);
+    let source = |scope: PolicyScope| {
+        layers
+            .iter()
+            .find(|layer| layer.scope == scope)
+            .and_then(|layer| layer.path.as_ref())
+            .map(|path| format!(" in {}", path.display()))
+            .unwrap_or_default()
+    };
+
+    match &explanation.rule {
+        Some(winner) => {
+            out.push_str(&format!("Decided by: {} rule #{}{}\n", winner.scope, winner.index + 1, source(winner.scope)));
+            out.push_str(&format!("  {}\n", describe_rule(&winner.rule)));
+        }
+        None if explanation.scope == PolicyScope::Builtin => {
+            out.push_str("Decided by: built-in default (no policy file exists and no rule matched)\n");
+        }
+        None => {
+            out.push_str(&format!("Decided by: default of the {} policy{} (no rule matched)\n", explanation.scope, source(explanation.scope)));
+        }
+    }
+
+    out.push_str("\nLayers (first matching rule wins):\n");
+    for layer in layers {
+        let state = match (&layer.path, layer.loaded) {
+            (None, _) => format!("in memory, {} rules", layer.config.rules.len()),
+            (Some(path), true) if layer.tighten_only => {
+                format!("{}, {} rules, can only tighten", path.display(), layer.config.rules.len())
+            }
+            (Some(path), true) => format!("{}, {} rules", path.display(), layer.config.rules.len()),
+            (Some(path), false) => format!("{} (not found)", path.display()),
+        };
+        out.push_str(&format!("  {:<8} {}\n", layer.scope.to_string(), state));
+    }
+
+    if !explanation.matches.is_empty() {
+        let first_applicable = |scope: PolicyScope| {
+            explanation.matches.iter().find(|m| m.scope == scope && m.applies).map(|m| m.index)
+        };
+        out.push_str("\nMatching rules:\n");
+        for rule_match in &explanation.matches {
+            let is_winner = explanation.rule.as_ref().map(|w| (w.scope, w.index)) == Some((rule_match.scope, rule_match.index));
+            let note = if is_winner {
+                "wins".to_string()
+            } else if !rule_match.applies {
+                "skipped: conditions do not hold".to_string()
+            } else if rule_match.scope == PolicyScope::Agent {
+                "permitted by the agent, the policy still decides".to_string()
+            } else if layers.iter().any(|layer| layer.scope == rule_match.scope && layer.tighten_only)
+                && first_applicable(rule_match.scope) == Some(rule_match.index)
+            {
+                "not stricter than the rest of the policy".to_string()
+            } else {
+                "shadowed".to_string()
+            };
+            out.push_str(&format!("  {} rule #{}: {} ({})\n", rule_match.scope, rule_match.index + 1, describe_rule(&rule_match.rule), note));
+        }
+    }
+
+    out.trim_end().to_string()
+}
+
+fn describe_rule(rule: &PolicyRule) -> String {
+    let mut parts = vec![format!("{} '{}' -> {}", rule.action, rule.pattern, rule.decision)];
+    if rule.matcher != Matcher::Auto {
+        parts.push(format!("matcher {:?}", rule.matcher).to_lowercase());
+    }
+    if rule.allow_chaining {
+        parts.push("chaining allowed".to_string());
+    }
+    if let Some(ref agent) = rule.agent {
+        parts.push(format!("agent {}", agent));
+    }
+    if let Some(session_type) = rule.session_type {
+        parts.push(format!("session {}", session_type));
+    }
+    if let Some(ref sandbox) = rule.sandbox {
+        parts.push(format!(
+            "sandboxed ({} MiB, {}s CPU, {}s timeout)",
+            sandbox.memory_mb, sandbox.cpu_secs, sandbox.timeout_secs
+        ));
+    }
+    if let Some(ref description) = rule.description {
+        parts.push(format!("\"{}\"", description));
+    }
+    parts.join(", ")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -511,6 +698,7 @@ mod tests {
         assert_eq!(cli.web_bind, "127.0.0.1");
         assert!(!cli.web_attachable);
         assert!(!cli.learn_policies);
+        assert!(!cli.deny_confirmations);
         
         Ok(())
     }
@@ -582,6 +770,52 @@ mod tests {
         
         Ok(())
     }
+
+    #[test]
+    fn test_mcp_serve_command() -> Result<(), Box<dyn std::error::Error>> {
+        let cli = Cli::try_parse_from(["kimichat", "--auto-confirm", "--allow-dir", "../shared", "mcp-serve"])?;
+
+        assert!(matches!(cli.command, Some(Commands::McpServe)));
+        assert!(cli.auto_confirm);
+        assert_eq!(cli.allow_dirs, vec!["../shared".to_string()]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_policy_explain_command() -> Result<(), Box<dyn std::error::Error>> {
+        let cli = Cli::try_parse_from(["kimichat", "policy", "explain", "command_execution", "cargo build", "--agent", "main"])?;
+
+        match cli.command {
+            Some(Commands::Policy { command }) => {
+                let output = command.execute(&PolicyManager::new())?;
+                assert!(output.starts_with("Decision: ask (command_execution on 'cargo build')"));
+                assert!(output.contains("built-in default"));
+
+                let bad = PolicyCommands::Explain {
+                    action: "file_rename".to_string(),
+                    target: "a".to_string(),
+                    agent: None,
+                    session_type: None,
+                };
+                assert!(bad.execute(&PolicyManager::new()).is_err());
+
+                // Built-in agents carry their declared permissions
+                let readonly = PolicyCommands::Explain {
+                    action: "file_write".to_string(),
+                    target: "src/main.rs".to_string(),
+                    agent: Some("code_analyzer".to_string()),
+                    session_type: None,
+                };
+                let output = readonly.execute(&PolicyManager::allow_all())?;
+                assert!(output.starts_with("Decision: deny"));
+                assert!(output.contains("Decided by: agent rule"));
+            }
+            _ => panic!("Expected Policy command"),
+        }
+
+        Ok(())
+    }
 }
 
 impl TerminalCommands {
@@ -1,11 +1,14 @@
 use std::env;
 use std::sync::Arc;
 
+use anyhow::{Context, Result};
+
 use crate::config::{ClientConfig, normalize_api_url};
-use kimichat_models::ModelColor;
+use kimichat_models::{ModelColor, ModelProvider, ModelRegistry, ReasoningConfig};
 use kimichat_llm_api::{
-    LlmClient, BackendType, GROQ_API_URL,
-    client::{anthropic::AnthropicLlmClient, groq::GroqLlmClient, llama_cpp::LlamaCppClient},
+    LlmClient, BackendType, GROQ_API_URL, OLLAMA_API_URL, FallbackLlmClient, RetryConfig, RetryingLlmClient,
+    detect_backend_from_url,
+    client::{anthropic::AnthropicLlmClient, groq::GroqLlmClient, llama_cpp::LlamaCppClient, ollama::OllamaClient},
 };
 use colored::Colorize;
 
@@ -33,12 +36,36 @@ pub fn get_system_prompt(client_config: &crate::config::ClientConfig) -> String
     IMPORTANT: You have been provided with a set of tools (functions) that you can use. \
     Only use the tools that are provided to you - do not make up tool names or attempt to use tools that are not available. \
     When making multiple file edits, use plan_edits to create a complete plan, then apply_edit_plan to execute all changes atomically. \
-    This prevents issues where you lose track of file state between sequential edits.\n\n\
+    This prevents issues where you lose track of file state between sequential edits. \
+    apply_patch takes a unified diff instead, and tolerates shifted line numbers and whitespace differences.\n\n\
     Model switches may happen automatically during the conversation based on tool usage and errors. \
     The currently active model will be indicated in system messages as the conversation progresses.",
     grn_model_name, blu_model_name, red_model_name)
 }
 
+/// Load the model capability registry
+///
+/// Starts from the embedded defaults and overlays ~/.okaychat/models.toml
+/// when it exists. A broken user file is reported and ignored. Tokenizer
+/// rank files are looked up in ~/.okaychat/tokenizers; the counter each model
+/// ends up with is reported when its tokens are first counted.
+pub fn load_model_registry() -> ModelRegistry {
+    let mut registry = ModelRegistry::with_defaults();
+
+    if let Ok(okaychat_dir) = kimichat_logging::get_okaychat_dir() {
+        registry.set_tokenizer_dir(okaychat_dir.join("tokenizers"));
+
+        let path = okaychat_dir.join("models.toml");
+        if path.exists() {
+            if let Err(e) = registry.load_file(&path) {
+                eprintln!("{} {:#}", "⚠️  Ignoring model registry overrides:".yellow(), e);
+            }
+        }
+    }
+
+    registry
+}
+
 /// Get the API URL to use based on the current model and client configuration
 pub fn get_api_url(client_config: &ClientConfig, model: &ModelColor) -> String {
     let url = client_config.get_api_url(*model)
@@ -77,47 +104,50 @@ pub fn get_model_config_from_env(model_name: &str) -> (Option<BackendType>, Opti
 
 /// Create an LLM client based on a ModelColor enum value
 /// This is the highest-level helper that maps ModelColor to the appropriate client
+///
+/// When the color has fallback providers configured, the returned client tries
+/// them in order whenever the primary provider is unavailable. Providers in a
+/// chain are not retried individually, so an unreachable provider is skipped
+/// at once; the chain as a whole is retried when every provider fails. Fails
+/// when any provider in the chain is missing required configuration.
 pub fn create_client_for_model_color(
     model: &ModelColor,
     client_config: &ClientConfig,
     default_api_key: &str,
-) -> Arc<dyn LlmClient> {
-    match model {
-        ModelColor::BluModel => {
-            create_model_client(
-                "blu",
-                client_config.get_backend(ModelColor::BluModel).cloned(),
-                client_config.get_api_url(ModelColor::BluModel).cloned(),
-                client_config.get_api_key(ModelColor::BluModel).cloned(),
-                client_config.get_model_override(ModelColor::BluModel).cloned(),
-                default_api_key,
-            )
-        }
-        ModelColor::GrnModel => {
-            create_model_client(
-                "grn",
-                client_config.get_backend(ModelColor::GrnModel).cloned(),
-                client_config.get_api_url(ModelColor::GrnModel).cloned(),
-                client_config.get_api_key(ModelColor::GrnModel).cloned(),
-                client_config.get_model_override(ModelColor::GrnModel).cloned(),
-                default_api_key,
-            )
-        }
-        ModelColor::RedModel => {
-            create_model_client(
-                "red",
-                client_config.get_backend(ModelColor::RedModel).cloned(),
-                client_config.get_api_url(ModelColor::RedModel).cloned(),
-                client_config.get_api_key(ModelColor::RedModel).cloned(),
-                client_config.get_model_override(ModelColor::RedModel).cloned(),
-                default_api_key,
-            )
-        }
-    }
+) -> Result<Arc<dyn LlmClient>> {
+    let model_name = model.as_str_lowercase();
+    let provider = client_config.get_provider(*model);
+
+    let create = |provider: &ModelProvider| create_model_client(
+        model_name,
+        provider.backend.clone(),
+        provider.api_url.clone(),
+        provider.api_key.clone(),
+        Some(provider.model_name.clone()),
+        default_api_key,
+        client_config.model_registry.get(&provider.model_name).max_output_tokens,
+        provider.reasoning,
+    );
+
+    let client = if provider.has_fallbacks() {
+        let chain = provider.chain()
+            .map(|provider| Ok((provider.label(), create(provider)?)))
+            .collect::<Result<Vec<_>>>()?;
+        Arc::new(FallbackLlmClient::new(chain))
+    } else {
+        create(provider)?
+    };
+
+    // Rate limits and transient provider errors are retried with backoff
+    let agent_name = format!("{}_model", model_name);
+    Ok(Arc::new(RetryingLlmClient::new(client, RetryConfig::default(), provider.model_name.clone(), agent_name)))
 }
 
 /// Create an LLM client for a specific model based on configuration
 /// This centralizes the logic for creating clients across all three models (blu, grn, red)
+///
+/// The client is not retried; `create_client_for_model_color` adds retrying
+/// around the whole provider chain.
 pub fn create_model_client(
     model_name: &str,              // "blu", "grn", or "red"
     backend: Option<BackendType>,
@@ -125,7 +155,9 @@ pub fn create_model_client(
     api_key: Option<String>,
     model_override: Option<String>,
     default_api_key: &str,
-) -> Arc<dyn LlmClient> {
+    max_output_tokens: usize,
+    reasoning: Option<ReasoningConfig>,
+) -> Result<Arc<dyn LlmClient>> {
     let model_name_upper = model_name.to_uppercase();
 
     // Get the model string - either from override or default
@@ -145,6 +177,8 @@ pub fn create_model_client(
         if let Some(ref url) = api_url {
             if url.contains("anthropic") {
                 BackendType::Anthropic
+            } else if detect_backend_from_url(url) == Some(BackendType::Ollama) {
+                BackendType::Ollama
             } else {
                 BackendType::Llama
             }
@@ -159,7 +193,8 @@ pub fn create_model_client(
         }
     });
 
-    match detected_backend {
+    let agent_name = format!("{}_model", model_name);
+    let client: Arc<dyn LlmClient> = match detected_backend {
         BackendType::Anthropic => {
             let url = api_url.unwrap_or_else(|| {
                 // Check for the global ANTHROPIC_BASE_URL environment variable
@@ -172,27 +207,28 @@ pub fn create_model_client(
             println!("{} Using Anthropic API for '{}_model' at: {}", "🧠".cyan(), model_name, url);
             Arc::new(AnthropicLlmClient::new(
                 key,
-                model_str,
+                model_str.clone(),
                 url,
-                format!("{}_model", model_name)
-            ))
+                agent_name.clone()
+            ).with_max_tokens(max_output_tokens).with_reasoning(reasoning))
         }
         BackendType::Llama => {
-            let url = api_url.expect(&format!("llama.cpp backend requires api_url_{}_model", model_name));
+            let url = api_url
+                .with_context(|| format!("llama.cpp backend requires api_url_{}_model", model_name))?;
             println!("{} Using llama.cpp for '{}_model' at: {}", "🦙".cyan(), model_name, url);
             Arc::new(LlamaCppClient::new(
                 url,
-                model_str
+                model_str.clone()
             ))
         }
         BackendType::Groq => {
             println!("{} Using Groq API for '{}_model'", "🚀".cyan(), model_name);
             Arc::new(GroqLlmClient::new(
                 default_api_key.to_string(),
-                model_str,
+                model_str.clone(),
                 GROQ_API_URL.to_string(),
-                format!("{}_model", model_name)
-            ))
+                agent_name.clone()
+            ).with_reasoning(reasoning))
         }
         BackendType::OpenAI => {
             let url = api_url.unwrap_or_else(|| "https://api.openai.com/v1/chat/completions".to_string());
@@ -201,10 +237,36 @@ pub fn create_model_client(
             // Use GroqLlmClient as it's OpenAI-compatible
             Arc::new(GroqLlmClient::new(
                 key,
-                model_str,
+                model_str.clone(),
+                url,
+                agent_name.clone()
+            ).with_reasoning(reasoning))
+        }
+        BackendType::Ollama => {
+            let url = api_url
+                .or_else(|| env::var("OLLAMA_HOST").ok())
+                .unwrap_or_else(|| OLLAMA_API_URL.to_string());
+            println!("{} Using Ollama for '{}_model' at: {}", "🦙".cyan(), model_name, url);
+            Arc::new(OllamaClient::new(
                 url,
-                format!("{}_model", model_name)
+                model_str.clone()
             ))
         }
-    }
+        BackendType::OpenAICompatible => {
+            let url = api_url
+                .with_context(|| format!("OpenAI-compatible backend requires api_url_{}_model", model_name))?;
+            let url = normalize_api_url(&url);
+            // Local servers usually need no key; only send one if it was configured
+            let key = api_key.unwrap_or_default();
+            println!("{} Using OpenAI-compatible server for '{}_model' at: {}", "🔌".cyan(), model_name, url);
+            Arc::new(GroqLlmClient::new(
+                key,
+                model_str.clone(),
+                url,
+                agent_name.clone()
+            ).with_reasoning(reasoning))
+        }
+    };
+
+    Ok(client)
 }
\ No newline at end of file
@@ -1,21 +1,27 @@
 use anyhow::Result;
 use colored::Colorize;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use kimichat_agents::{
     PlanningCoordinator, AgentFactory,
 };
+use kimichat_mcp::McpServers;
 use kimichat_toolcore::ToolRegistry;
 use kimichat_policy::PolicyManager;
 use kimichat_tools::*;
-use kimichat_models::{ModelColor, ModelProvider};
+use crate::cost::CostTracker;
+use kimichat_models::{ModelCapabilities, ModelColor, ModelProvider, ModelRegistry};
 
 pub mod helpers;
-pub use helpers::{get_system_prompt, get_api_url, get_api_key, create_model_client, create_client_for_model_color};
+pub use helpers::{get_system_prompt, get_api_url, get_api_key, create_client_for_model_color, load_model_registry};
 
 // Re-export types from kimichat-llm-api
 pub use kimichat_llm_api::{BackendType, GROQ_API_URL, normalize_api_url};
 
+/// Read-only tool calls from one model turn that run at once, by default
+pub const DEFAULT_MAX_PARALLEL_TOOLS: usize = 4;
+
 /// Configuration for KimiChat client
 #[derive(Debug, Clone)]
 pub struct ClientConfig {
@@ -24,6 +30,21 @@ pub struct ClientConfig {
 
     /// Model providers indexed by color [blu, grn, red]
     pub model_providers: [ModelProvider; ModelColor::COUNT],
+
+    /// Context window, tool support and pricing for known models
+    pub model_registry: Arc<ModelRegistry>,
+
+    /// Hard cap in USD on the estimated cost of a session's LLM calls
+    pub max_session_cost: Option<f64>,
+
+    /// Directories outside the work directory that file tools may access
+    pub extra_roots: Vec<PathBuf>,
+
+    /// How many read-only tool calls from one model turn may run at once
+    pub max_parallel_tools: usize,
+
+    /// MCP servers started for this run; every session mounts their tools
+    pub mcp_servers: Arc<McpServers>,
 }
 
 impl ClientConfig {
@@ -36,6 +57,11 @@ impl ClientConfig {
                 ModelProvider::new(ModelColor::GrnModel.default_model()),
                 ModelProvider::new(ModelColor::RedModel.default_model()),
             ],
+            model_registry: Arc::new(ModelRegistry::with_defaults()),
+            max_session_cost: None,
+            extra_roots: Vec::new(),
+            max_parallel_tools: DEFAULT_MAX_PARALLEL_TOOLS,
+            mcp_servers: Arc::new(McpServers::default()),
         }
     }
     
@@ -54,6 +80,21 @@ impl ClientConfig {
         self.model_providers[color as usize] = provider;
     }
     
+    /// Get the capabilities of the model configured for a specific model color
+    pub fn capabilities(&self, color: ModelColor) -> &ModelCapabilities {
+        self.model_registry.get(self.get_model_name(color))
+    }
+
+    /// Whether requests for this color have to go through the LlmClient system
+    ///
+    /// The legacy OpenAI-style request path knows nothing about fallback chains,
+    /// Ollama's native API, or keyless OpenAI-compatible servers.
+    pub fn requires_llm_client(&self, color: ModelColor) -> bool {
+        let provider = self.get_provider(color);
+        provider.has_fallbacks()
+            || matches!(provider.backend, Some(BackendType::Ollama) | Some(BackendType::OpenAICompatible))
+    }
+
     // Legacy convenience methods for backward compatibility
     /// Get backend for a specific model color
     pub fn get_backend(&self, color: ModelColor) -> Option<&BackendType> {
@@ -117,6 +158,7 @@ pub fn initialize_tool_registry() -> ToolRegistry {
     registry.register_with_categories(ReadFileTool, vec!["file_ops".to_string()]);
     registry.register_with_categories(WriteFileTool, vec!["file_ops".to_string()]);
     registry.register_with_categories(EditFileTool, vec!["file_ops".to_string()]);
+    registry.register_with_categories(ApplyPatchTool, vec!["file_ops".to_string()]);
     registry.register_with_categories(ListFilesTool, vec!["file_ops".to_string()]);
 
     // Register search tools
@@ -124,6 +166,9 @@ pub fn initialize_tool_registry() -> ToolRegistry {
 
     // Register system tools
     registry.register_with_categories(RunCommandTool, vec!["system".to_string()]);
+    registry.register_with_categories(JobStatusTool, vec!["system".to_string()]);
+    registry.register_with_categories(JobOutputTool, vec!["system".to_string()]);
+    registry.register_with_categories(JobKillTool, vec!["system".to_string()]);
 
     // Register model management tools
     registry.register_with_categories(SwitchModelTool::new(), vec!["model_management".to_string()]);
@@ -131,7 +176,7 @@ pub fn initialize_tool_registry() -> ToolRegistry {
     registry.register_with_categories(ApplyEditPlanTool, vec!["model_management".to_string()]);
 
     // Register iteration control tools
-    registry.register_with_categories(RequestMoreIterationsTool, vec!["agent_control".to_string()]);
+    registry.register(RequestMoreIterationsTool);
 
     // Register skill tools
     registry.register_with_categories(LoadSkillTool, vec!["skills".to_string()]);
@@ -143,7 +188,7 @@ pub fn initialize_tool_registry() -> ToolRegistry {
     registry.register_with_categories(LaunchSubagentPrettyTool, vec!["agent_control".to_string()]);
 
     // Register todo/task tracking tools
-    registry.register_with_categories(TodoWriteTool::new(), vec!["task_tracking".to_string()]);
+    registry.register(TodoWriteTool);
     registry.register_with_categories(TodoListTool::new(), vec!["task_tracking".to_string()]);
 
     // Register PTY terminal tools
@@ -163,52 +208,37 @@ pub fn initialize_tool_registry() -> ToolRegistry {
 }
 
 /// Initialize the agent system with configuration files
-pub fn initialize_agent_system(client_config: &ClientConfig, tool_registry: &ToolRegistry, policy_manager: &PolicyManager) -> Result<PlanningCoordinator> {
+pub fn initialize_agent_system(
+    client_config: &ClientConfig,
+    tool_registry: &ToolRegistry,
+    policy_manager: &PolicyManager,
+    cost_tracker: &Arc<CostTracker>,
+) -> Result<PlanningCoordinator> {
     println!("{} Initializing agent system...", "🤖".blue());
 
     // Create agent factory
     let tool_registry_arc = Arc::new((*tool_registry).clone());
     let mut agent_factory = AgentFactory::new(tool_registry_arc, policy_manager.clone());
 
-    // Determine model names from providers
-    let blu_model = client_config.get_model_name(ModelColor::BluModel).to_string();
-    let grn_model = client_config.get_model_name(ModelColor::GrnModel).to_string();
-    let red_model = client_config.get_model_name(ModelColor::RedModel).to_string();
-
     // Register LLM clients based on per-model configuration
-    // Use the centralized helper function to create clients for all three models
-
-    let blu_model_client = create_model_client(
-        "blu",
-        client_config.get_backend(ModelColor::BluModel).cloned(),
-        client_config.get_api_url(ModelColor::BluModel).cloned(),
-        client_config.get_api_key(ModelColor::BluModel).cloned(),
-        Some(blu_model.clone()),
-        &client_config.api_key,
-    );
-
-    let grn_model_client = create_model_client(
-        "grn",
-        client_config.get_backend(ModelColor::GrnModel).cloned(),
-        client_config.get_api_url(ModelColor::GrnModel).cloned(),
-        client_config.get_api_key(ModelColor::GrnModel).cloned(),
-        Some(grn_model.clone()),
-        &client_config.api_key,
-    );
-
-    let red_model_client = create_model_client(
-        "red",
-        client_config.get_backend(ModelColor::RedModel).cloned(),
-        client_config.get_api_url(ModelColor::RedModel).cloned(),
-        client_config.get_api_key(ModelColor::RedModel).cloned(),
-        Some(red_model.clone()),
-        &client_config.api_key,
-    );
+    // Use the centralized helper so agents get the same retry and fallback behaviour
+    let blu_model_client = create_client_for_model_color(&ModelColor::BluModel, client_config, &client_config.api_key)?;
+    let grn_model_client = create_client_for_model_color(&ModelColor::GrnModel, client_config, &client_config.api_key)?;
+    let red_model_client = create_client_for_model_color(&ModelColor::RedModel, client_config, &client_config.api_key)?;
 
     agent_factory.register_llm_client("blu_model".to_string(), blu_model_client);
     agent_factory.register_llm_client("grn_model".to_string(), grn_model_client);
     agent_factory.register_llm_client("red_model".to_string(), red_model_client);
 
+    // Let the factory route agents away from models that can't use tools
+    for color in ModelColor::iter() {
+        let key = format!("{}_model", color.as_str_lowercase());
+        agent_factory.register_model_capabilities(key, client_config.capabilities(color).clone());
+    }
+
+    // Meter every agent (planner included) into the session's cost totals
+    agent_factory.set_usage_recorder(Arc::clone(cost_tracker) as Arc<dyn kimichat_llm_api::UsageRecorder>);
+
     // Create coordinator
     let agent_factory_arc = Arc::new(agent_factory);
     let mut coordinator = PlanningCoordinator::new(agent_factory_arc);
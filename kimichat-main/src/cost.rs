@@ -0,0 +1,259 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use kimichat_llm_api::{TokenUsage, UsageRecorder};
+use kimichat_models::{ModelColor, ModelRegistry, Usage};
+
+use crate::config::ClientConfig;
+
+/// Token counts and estimated cost of a group of LLM calls
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UsageTotals {
+    pub requests: usize,
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    /// Prompt tokens written to the provider's prompt cache
+    pub cache_write_tokens: usize,
+    /// Prompt tokens served from the provider's prompt cache
+    pub cache_read_tokens: usize,
+    /// Estimated cost in USD, from the model registry's prices
+    pub cost: f64,
+    /// What prompt caching saved compared to paying the full input price
+    pub cache_savings: f64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, usage: &Usage, cost: f64, cache_savings: f64) {
+        self.requests += 1;
+        self.prompt_tokens += usage.prompt_tokens;
+        self.completion_tokens += usage.completion_tokens;
+        self.cache_write_tokens += usage.cache_creation_input_tokens;
+        self.cache_read_tokens += usage.cache_read_input_tokens;
+        self.cost += cost;
+        self.cache_savings += cache_savings;
+    }
+}
+
+/// Point-in-time view of a session's spending
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CostSnapshot {
+    pub total: UsageTotals,
+    /// Totals per agent; calls made outside the agent system are listed under
+    /// what made them ("main", "title", "compaction", ...)
+    pub by_agent: BTreeMap<String, UsageTotals>,
+    /// Totals per model name
+    pub by_model: BTreeMap<String, UsageTotals>,
+    /// Hard cap in USD, if one is set
+    pub budget: Option<f64>,
+}
+
+/// Accumulates the token usage of every LLM call in a session and prices it
+///
+/// Agents are metered through `UsageRecorder`; the main chat loop and helper
+/// calls (title generation, compaction) record their usage directly.
+#[derive(Debug)]
+pub struct CostTracker {
+    registry: Arc<ModelRegistry>,
+    /// Model names behind the agent factory's "<color>_model" client keys
+    aliases: HashMap<String, String>,
+    budget: Option<f64>,
+    state: Mutex<CostSnapshot>,
+}
+
+impl CostTracker {
+    pub fn new(client_config: &ClientConfig) -> Self {
+        let aliases = ModelColor::iter()
+            .map(|color| (
+                format!("{}_model", color.as_str_lowercase()),
+                client_config.get_model_name(color).to_string(),
+            ))
+            .collect();
+
+        Self {
+            registry: Arc::clone(&client_config.model_registry),
+            aliases,
+            budget: client_config.max_session_cost,
+            state: Mutex::new(CostSnapshot {
+                budget: client_config.max_session_cost,
+                ..CostSnapshot::default()
+            }),
+        }
+    }
+
+    /// Record one call and return its estimated cost in USD
+    pub fn record(&self, source: &str, model: &str, usage: &Usage) -> f64 {
+        let model = self.aliases.get(model).map(String::as_str).unwrap_or(model);
+        let capabilities = self.registry.get(model);
+        let cost = capabilities.cost_with_cache(
+            usage.prompt_tokens,
+            usage.completion_tokens,
+            usage.cache_creation_input_tokens,
+            usage.cache_read_input_tokens,
+        );
+        let cache_savings = capabilities.cost(usage.prompt_tokens, usage.completion_tokens) - cost;
+
+        let mut state = self.state.lock().unwrap();
+        state.total.add(usage, cost, cache_savings);
+        state.by_agent.entry(source.to_string()).or_default().add(usage, cost, cache_savings);
+        state.by_model.entry(model.to_string()).or_default().add(usage, cost, cache_savings);
+        cost
+    }
+
+    /// Record a raw API response's usage, if the server reported one
+    pub fn record_response(&self, source: &str, model: &str, usage: Option<&Usage>) {
+        if let Some(usage) = usage {
+            self.record(source, model, usage);
+        }
+    }
+
+    pub fn snapshot(&self) -> CostSnapshot {
+        self.state.lock().unwrap().clone()
+    }
+
+    /// Estimated cost of the session so far
+    pub fn total_cost(&self) -> f64 {
+        self.state.lock().unwrap().total.cost
+    }
+
+    pub fn budget_exceeded(&self) -> bool {
+        self.budget.is_some_and(|budget| self.total_cost() >= budget)
+    }
+
+    /// Fail once the estimated cost has reached the budget
+    pub fn check_budget(&self) -> Result<()> {
+        match self.budget {
+            Some(budget) if self.budget_exceeded() => anyhow::bail!(
+                "Session cost budget of ${:.2} reached (spent ${:.4})",
+                budget,
+                self.total_cost()
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Human-readable breakdown, as shown by `/cost`
+    pub fn report(&self) -> String {
+        let snapshot = self.snapshot();
+        let line = |name: &str, totals: &UsageTotals| {
+            format!(
+                "  {:<24} ${:>9.4}  {:>4} calls  {:>9} prompt  {:>8} completion",
+                name, totals.cost, totals.requests, totals.prompt_tokens, totals.completion_tokens
+            )
+        };
+
+        let mut lines = vec![format!("Session cost: ${:.4}", snapshot.total.cost)];
+        if let Some(budget) = snapshot.budget {
+            lines.push(format!("Budget: ${:.4} (${:.4} remaining)", budget, (budget - snapshot.total.cost).max(0.0)));
+        }
+        if snapshot.total.cache_read_tokens > 0 || snapshot.total.cache_write_tokens > 0 {
+            lines.push(format!(
+                "Prompt cache: {} tokens read, {} written, saved ${:.4}",
+                snapshot.total.cache_read_tokens, snapshot.total.cache_write_tokens, snapshot.total.cache_savings
+            ));
+        }
+        if !snapshot.by_agent.is_empty() {
+            lines.push("By agent:".to_string());
+            lines.extend(snapshot.by_agent.iter().map(|(name, totals)| line(name, totals)));
+            lines.push("By model:".to_string());
+            lines.extend(snapshot.by_model.iter().map(|(name, totals)| line(name, totals)));
+        }
+        lines.join("\n")
+    }
+}
+
+impl UsageRecorder for CostTracker {
+    fn record_usage(&self, source: &str, model: &str, usage: &TokenUsage) {
+        self.record(source, model, &Usage {
+            prompt_tokens: usage.prompt_tokens as usize,
+            completion_tokens: usage.completion_tokens as usize,
+            total_tokens: usage.total_tokens as usize,
+            cache_creation_input_tokens: usage.cache_creation_input_tokens as usize,
+            cache_read_input_tokens: usage.cache_read_input_tokens as usize,
+        });
+    }
+
+    fn check_budget(&self) -> Result<()> {
+        CostTracker::check_budget(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kimichat_models::ModelCapabilities;
+
+    fn tracker(budget: Option<f64>) -> CostTracker {
+        let mut registry = ModelRegistry::new();
+        registry.insert("priced", ModelCapabilities {
+            input_price_per_mtok: 1.0,
+            output_price_per_mtok: 2.0,
+            cache_read_price_per_mtok: Some(0.1),
+            ..ModelCapabilities::default()
+        });
+
+        let mut config = ClientConfig::new();
+        config.model_registry = Arc::new(registry);
+        config.set_model_name(ModelColor::BluModel, "priced".to_string());
+        config.max_session_cost = budget;
+        CostTracker::new(&config)
+    }
+
+    fn usage(prompt_tokens: usize, completion_tokens: usize) -> Usage {
+        Usage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+            ..Usage::default()
+        }
+    }
+
+    #[test]
+    fn test_usage_is_priced_and_grouped() {
+        let tracker = tracker(None);
+        assert!((tracker.record("main", "priced", &usage(1_000_000, 500_000)) - 2.0).abs() < 1e-9);
+        // Agents report the factory's color key, which resolves to the model name
+        tracker.record("planner", "blu_model", &usage(500_000, 0));
+        tracker.record("title", "free-model", &usage(10_000, 10_000));
+
+        let snapshot = tracker.snapshot();
+        assert!((snapshot.total.cost - 2.5).abs() < 1e-9);
+        assert_eq!(snapshot.total.requests, 3);
+        assert_eq!(snapshot.by_agent["planner"].prompt_tokens, 500_000);
+        assert_eq!(snapshot.by_model["priced"].requests, 2);
+        assert_eq!(snapshot.by_model["free-model"].cost, 0.0);
+        assert!(tracker.report().contains("Session cost: $2.5000"));
+    }
+
+    #[test]
+    fn test_budget_cap_refuses_further_calls() {
+        let capped = tracker(Some(1.0));
+        assert!(capped.check_budget().is_ok());
+
+        capped.record("main", "priced", &usage(600_000, 0));
+        assert!(!capped.budget_exceeded());
+        capped.record("main", "priced", &usage(400_000, 0));
+        assert!(capped.budget_exceeded());
+        assert!(capped.check_budget().unwrap_err().to_string().contains("budget of $1.00 reached"));
+
+        assert!(!tracker(None).budget_exceeded());
+    }
+
+    #[test]
+    fn test_cache_reads_are_discounted() {
+        let tracker = tracker(None);
+        let cached = Usage {
+            cache_read_input_tokens: 900_000,
+            cache_creation_input_tokens: 50_000,
+            ..usage(1_000_000, 0)
+        };
+
+        // 50k uncached + 50k written at $1, 900k read at $0.10
+        assert!((tracker.record("main", "priced", &cached) - 0.19).abs() < 1e-9);
+        let snapshot = tracker.snapshot();
+        assert!((snapshot.total.cache_savings - 0.81).abs() < 1e-9);
+        assert_eq!(snapshot.total.cache_read_tokens, 900_000);
+        assert!(tracker.report().contains("Prompt cache: 900000 tokens read, 50000 written, saved $0.8100"));
+    }
+}
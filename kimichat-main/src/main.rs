@@ -10,6 +10,7 @@ use clap::Parser;
 
 
 mod preview;
+mod cost;
 mod tools_execution;
 mod cli;
 mod config;
@@ -24,15 +25,17 @@ use kimichat_agents::{
     ChatMessage, ExecutionContext,
 };
 use kimichat_logging::ConversationLogger;
-use kimichat_policy::PolicyManager;
+use kimichat_checkpoints::CheckpointStore;
+use kimichat_policy::{ActionType, PolicyContext, PolicyManager, SessionType};
 use kimichat_terminal::{TerminalManager, TerminalBackendType, MAX_CONCURRENT_SESSIONS};
-use kimichat_toolcore::{ToolRegistry, ToolParameters, ToolContext};
+use kimichat_toolcore::{ToolRegistry, ToolParameters, ToolContext, ConfirmationProvider, TerminalConfirmation, JobManager, OutputSink, ReadTracker, TerminalOutput};
 use cli::{Cli, Commands};
-use config::{ClientConfig, GROQ_API_URL, initialize_tool_registry, initialize_agent_system};
+use config::{ClientConfig, DEFAULT_MAX_PARALLEL_TOOLS, GROQ_API_URL, initialize_tool_registry, initialize_agent_system};
 use chat::{save_state, load_state};
+use tools_execution::parallel::ToolCallRun;
 use app::{setup_from_cli, run_task_mode, run_subagent_mode, run_repl_mode};
 use kimichat_models::{
-    ModelColor, Message, ToolCall, FunctionCall, ModelProvider,
+    ModelColor, Message, ToolCall, FunctionCall, ModelProvider, ModelRegistry,
     SwitchModelArgs,
     Tool, FunctionDef,
     ChatResponse,
@@ -62,16 +65,32 @@ pub(crate) struct KimiChat {
     pub(crate) terminal_manager: Arc<Mutex<TerminalManager>>,
     // Skill registry
     pub(crate) skill_registry: Option<Arc<kimichat_skills::SkillRegistry>>,
-    // Non-interactive mode (web/API)
-    pub(crate) non_interactive: bool,
+    // Answers policy "ask" decisions (terminal by default, web UI or deny-all otherwise)
+    pub(crate) confirmation_provider: Arc<dyn ConfirmationProvider>,
+    // Kind of session, for policy rules that only apply to some of them
+    pub(crate) session_type: SessionType,
     // Todo manager for task tracking
     pub(crate) todo_manager: Arc<kimichat_todo::TodoManager>,
+    // Background jobs started by run_command
+    pub(crate) job_manager: Arc<JobManager>,
+    // Files as they were before each turn changed them, for /undo and /restore
+    pub(crate) checkpoint_store: Arc<CheckpointStore>,
+    // What the model has read of each file, so edits to files it has not seen as they are can be refused
+    pub(crate) read_tracker: Arc<ReadTracker>,
     // Streaming mode
     pub(crate) stream_responses: bool,
     // Verbose debug mode
     pub(crate) verbose: bool,
     // Debug level for controlling debug output (0=off, 1=basic, 2=detailed, etc.)
     pub(crate) debug_level: u32,
+    // Print reasoning in full instead of collapsed (toggled with /thinking on|off)
+    pub(crate) show_reasoning: bool,
+    // Label of the provider that answered the last LLM call, when a fallback chain is in use
+    pub(crate) last_provider: std::sync::Mutex<Option<String>>,
+    // Prompt token count the server reported for the last request
+    pub(crate) server_token_count: Option<chat::history::ServerTokenCount>,
+    // Token usage and estimated cost of every LLM call in this session
+    pub(crate) cost_tracker: Arc<cost::CostTracker>,
 }
 
 impl KimiChat {
@@ -83,6 +102,11 @@ impl KimiChat {
                 ModelProvider::new(ModelColor::GrnModel.default_model()),
                 ModelProvider::new(ModelColor::RedModel.default_model()),
             ],
+            model_registry: Arc::new(ModelRegistry::with_defaults()),
+            max_session_cost: None,
+            extra_roots: Vec::new(),
+            max_parallel_tools: DEFAULT_MAX_PARALLEL_TOOLS,
+            mcp_servers: Arc::new(kimichat_mcp::McpServers::default()),
         };
         let policy_manager = PolicyManager::new();
         Self::new_with_config(
@@ -104,6 +128,11 @@ impl KimiChat {
                 ModelProvider::new(ModelColor::GrnModel.default_model()),
                 ModelProvider::new(ModelColor::RedModel.default_model()),
             ],
+            model_registry: Arc::new(ModelRegistry::with_defaults()),
+            max_session_cost: None,
+            extra_roots: Vec::new(),
+            max_parallel_tools: DEFAULT_MAX_PARALLEL_TOOLS,
+            mcp_servers: Arc::new(kimichat_mcp::McpServers::default()),
         };
         let policy_manager = PolicyManager::new();
         Self::new_with_config(
@@ -141,7 +170,8 @@ impl KimiChat {
         verbose: bool,
         backend_type: TerminalBackendType,
     ) -> Self {
-        let tool_registry = initialize_tool_registry();
+        let mut tool_registry = initialize_tool_registry();
+        client_config.mcp_servers.register_tools(&mut tool_registry);
 
         // Initialize skill registry
         let skills_dir = work_dir.join("skills");
@@ -154,8 +184,10 @@ impl KimiChat {
             }
         };
 
+        let cost_tracker = Arc::new(cost::CostTracker::new(&client_config));
+
         let agent_coordinator = if use_agents {
-            match initialize_agent_system(&client_config, &tool_registry, &policy_manager) {
+            match initialize_agent_system(&client_config, &tool_registry, &policy_manager, &cost_tracker) {
                 Ok(coordinator) => Some(coordinator),
                 Err(e) => {
                     eprintln!("{} Failed to initialize agent system: {}", "❌".red(), e);
@@ -206,10 +238,22 @@ impl KimiChat {
             terminal_manager,
             skill_registry,
             todo_manager,
+            job_manager: Arc::new(JobManager::new()),
+            checkpoint_store: Self::checkpoint_store_for(&format!(
+                "{}-{}",
+                chrono::Local::now().format("%Y%m%d-%H%M%S"),
+                std::process::id()
+            )),
+            read_tracker: Arc::new(ReadTracker::new()),
             stream_responses,
             verbose,
             debug_level: 0, // Default debug level is 0 (off)
-            non_interactive: false, // Default to interactive mode
+            show_reasoning: false,
+            last_provider: std::sync::Mutex::new(None),
+            server_token_count: None,
+            cost_tracker,
+            confirmation_provider: Arc::new(TerminalConfirmation),
+            session_type: SessionType::Repl,
         };
 
         chat.messages.push(Message {
@@ -219,6 +263,7 @@ impl KimiChat {
             tool_call_id: None,
             name: None,
             reasoning: None,
+            reasoning_details: None,
         });
 
         // Add initial model notification
@@ -229,6 +274,7 @@ impl KimiChat {
             tool_call_id: None,
             name: None,
             reasoning: None,
+            reasoning_details: None,
         });
 
         chat
@@ -287,6 +333,7 @@ impl KimiChat {
                     tool_call_id: msg.tool_call_id.clone(),
                     name: msg.name.clone(),
                     reasoning: None,
+                    reasoning_details: None,
                 }
             }).collect();
 
@@ -299,7 +346,13 @@ impl KimiChat {
                 terminal_manager: Some(self.terminal_manager.clone()),
                 skill_registry: self.skill_registry.clone(),
                 todo_manager: Some(self.todo_manager.clone()),
+                job_manager: Some(self.job_manager.clone()),
+                checkpoint_store: Some(self.checkpoint_store.clone()),
                 cancellation_token,
+                extra_roots: self.client_config.extra_roots.clone(),
+                confirmation_provider: Arc::clone(&self.confirmation_provider),
+                session_type: Some(self.session_type),
+                visibility_manager: None,
             };
 
             // Debug: Log current model
@@ -318,6 +371,7 @@ impl KimiChat {
                 tool_call_id: None,
                 name: None,
                 reasoning: None,
+                reasoning_details: None,
             });
 
             self.messages.push(Message {
@@ -327,6 +381,7 @@ impl KimiChat {
                 tool_call_id: None,
                 name: None,
                 reasoning: None,
+                reasoning_details: None,
             });
 
             Ok(result.content)
@@ -373,6 +428,7 @@ impl KimiChat {
             tool_call_id: None,
             name: None,
             reasoning: None,
+            reasoning_details: None,
         });
 
         Ok(format!(
@@ -404,46 +460,113 @@ impl KimiChat {
         ))
     }
 
+    /// Checkpoints under ~/.okaychat, or the temp directory when there is no home
+    pub(crate) fn checkpoint_store_for(session_id: &str) -> Arc<CheckpointStore> {
+        let store = CheckpointStore::for_session(session_id).unwrap_or_else(|_| {
+            CheckpointStore::new(&std::env::temp_dir().join("okaychat-checkpoints"), session_id)
+        });
+        Arc::new(store)
+    }
+
     async fn execute_tool(&mut self, name: &str, arguments: &str) -> Result<String> {
+        let confirmation_provider = Arc::clone(&self.confirmation_provider);
+        self.execute_tool_with_confirmation(name, arguments, confirmation_provider, Arc::new(TerminalOutput)).await
+    }
+
+    /// Execute a tool, sending any confirmation its policy checks need to `confirmation_provider`
+    /// and its live output to `output_sink`
+    pub(crate) async fn execute_tool_with_confirmation(
+        &mut self,
+        name: &str,
+        arguments: &str,
+        confirmation_provider: Arc<dyn ConfirmationProvider>,
+        output_sink: Arc<dyn OutputSink>,
+    ) -> Result<String> {
+        let context = self.tool_context(confirmation_provider, output_sink);
+
         // For backward compatibility, handle special tools that need main application state
         match name {
             "switch_model" => {
                 let args: SwitchModelArgs = serde_json::from_str(arguments)?;
+                let (approved, reason) = context.check_permission(
+                    ActionType::ModelSwitch,
+                    &args.model,
+                    &format!("Switch to {} ({})? [Y/n]", args.model, args.reason),
+                ).await?;
+                if !approved {
+                    anyhow::bail!(
+                        "Model switch cancelled: {}",
+                        reason.unwrap_or_else(|| "denied by user or policy".to_string())
+                    );
+                }
                 self.switch_model(&args.model, &args.reason)
             }
-            _ => {
-                // Use the tool registry for all tools (including plan_edits and apply_edit_plan)
-                let params = ToolParameters::from_json(arguments)
-                    .with_context(|| format!("Failed to parse tool arguments for '{}': {}", name, arguments))?;
-
-                let mut context = ToolContext::new(
-                    self.work_dir.clone(),
-                    format!("session_{}", chrono::Utc::now().timestamp()),
-                    self.policy_manager.clone()
-                )
-                .with_terminal_manager(self.terminal_manager.clone())
-                .with_todo_manager(self.todo_manager.clone())
-                .with_non_interactive(self.non_interactive);
-
-                // Add skill registry if available
-                if let Some(ref registry) = self.skill_registry {
-                    context = context.with_skill_registry(Arc::clone(registry));
-                }
+            _ => self.execute_registry_tool(name, arguments, &context).await,
+        }
+    }
 
-                let context = context;
+    /// Execute read-only tool calls concurrently, at most `max_parallel_tools` at a time,
+    /// with the results in call order
+    pub(crate) async fn execute_read_only_tools(&self, calls: Vec<ToolCallRun<'_>>) -> Vec<Result<String>> {
+        let slots = tokio::sync::Semaphore::new(self.client_config.max_parallel_tools.max(1));
+        let mut runs = Vec::new();
+        for (tool_call, confirmation_provider, output_sink) in calls {
+            let slots = &slots;
+            runs.push(async move {
+                let _slot = slots.acquire().await;
+                let context = self.tool_context(confirmation_provider, output_sink);
+                self.execute_registry_tool(&tool_call.function.name, &tool_call.function.arguments, &context).await
+            });
+        }
+        futures::future::join_all(runs).await
+    }
 
-                let result = self.tool_registry.execute_tool(name, params, &context).await;
+    /// Whether calls to the tool `name` may run alongside each other
+    pub(crate) fn is_read_only_tool(&self, name: &str) -> bool {
+        self.tool_registry.get_tool(name).is_some_and(|tool| tool.read_only())
+    }
 
-                if result.success {
-                    Ok(result.content)
-                } else {
-                    Err(anyhow::anyhow!("Tool '{}' failed: {}", name, result.error.unwrap_or_else(|| "Unknown error".to_string())))
-                }
-            }
+    fn tool_context(
+        &self,
+        confirmation_provider: Arc<dyn ConfirmationProvider>,
+        output_sink: Arc<dyn OutputSink>,
+    ) -> ToolContext {
+        let mut context = ToolContext::new(
+            self.work_dir.clone(),
+            format!("session_{}", chrono::Utc::now().timestamp()),
+            self.policy_manager.clone()
+        )
+        .with_extra_roots(self.client_config.extra_roots.clone())
+        .with_terminal_manager(self.terminal_manager.clone())
+        .with_todo_manager(self.todo_manager.clone())
+        .with_job_manager(self.job_manager.clone())
+        .with_checkpoint_store(self.checkpoint_store.clone())
+        .with_read_tracker(self.read_tracker.clone())
+        .with_confirmation_provider(confirmation_provider)
+        .with_output_sink(output_sink)
+        .with_policy_context(PolicyContext::new(Some("main".to_string()), Some(self.session_type)));
+
+        // Add skill registry if available
+        if let Some(ref registry) = self.skill_registry {
+            context = context.with_skill_registry(Arc::clone(registry));
         }
+
+        context
     }
 
+    async fn execute_registry_tool(&self, name: &str, arguments: &str, context: &ToolContext) -> Result<String> {
+        // Use the tool registry for all tools (including plan_edits and apply_edit_plan)
+        let params = ToolParameters::from_json(arguments)
+            .with_context(|| format!("Failed to parse tool arguments for '{}': {}", name, arguments))?;
 
+        let result = self.tool_registry.execute_tool(name, params, context).await;
+
+        if result.success {
+            Ok(result.content)
+        } else {
+            Err(anyhow::anyhow!("Tool '{}' failed: {}", name, result.error.unwrap_or_else(|| "Unknown error".to_string())))
+        }
+    }
 }
 
 /// Resolve terminal backend type from CLI args and environment variable
@@ -489,6 +612,11 @@ async fn main() -> Result<()> {
     // Parse CLI arguments
     let cli = Cli::parse();
 
+    // The MCP server talks over stdout, so nothing else may print there
+    if let Some(Commands::McpServe) = cli.command {
+        return app::run_mcp_serve(&cli, env::current_dir()?).await;
+    }
+
     // If a subcommand was provided, execute it and exit
     if let Some(ref command) = cli.command {
         // Special handling for commands that need KimiChat or TerminalManager
@@ -510,6 +638,10 @@ async fn main() -> Result<()> {
                 ));
                 terminal_cmd.execute(terminal_manager).await?
             }
+            Commands::Policy { command: policy_cmd } => {
+                let policy_manager = app::setup::load_layered_policy(&cli, &work_dir);
+                policy_cmd.execute(&policy_manager)?
+            }
             _ => command.execute().await?
         };
         println!("{}", result);
@@ -517,7 +649,14 @@ async fn main() -> Result<()> {
     }
 
     // Set up application configuration from CLI
-    let app_config = setup_from_cli(&cli)?;
+    let mut app_config = setup_from_cli(&cli)?;
+
+    // Start MCP servers once; every session mounts their tools
+    app_config.client_config.mcp_servers = Arc::new(app::setup::connect_mcp_servers(
+        &cli,
+        &app_config.work_dir,
+        &app_config.policy_manager,
+    ).await);
 
     // Handle task mode if requested
     if let Some(task_text) = cli.task.clone() {
@@ -1,3 +1,4 @@
-// Tools execution module - tool validation
+// Tools execution module - tool validation and batching of concurrent calls
 // Note: Tool parsing (parse_xml_tool_calls) has been moved to kimichat-toolcore
+pub mod parallel;
 pub mod validation;
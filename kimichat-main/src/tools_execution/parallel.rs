@@ -0,0 +1,59 @@
+use kimichat_models::ToolCall;
+use kimichat_toolcore::{ConfirmationProvider, OutputSink};
+use std::sync::Arc;
+
+/// A tool call to run, with where its confirmations and live output go
+pub(crate) type ToolCallRun<'a> = (&'a ToolCall, Arc<dyn ConfirmationProvider>, Arc<dyn OutputSink>);
+
+/// Split one turn's tool calls into steps that run one after another
+///
+/// Consecutive read-only calls share a step and may run concurrently. Every
+/// other call is a step of its own, so changes happen in the order the model
+/// asked for them and a read after a change sees it.
+pub(crate) fn batch_tool_calls(tool_calls: &[ToolCall], is_read_only: impl Fn(&str) -> bool) -> Vec<Vec<&ToolCall>> {
+    let mut batches: Vec<Vec<&ToolCall>> = Vec::new();
+    let mut last_read_only = false;
+    for tool_call in tool_calls {
+        let read_only = is_read_only(&tool_call.function.name);
+        match batches.last_mut() {
+            Some(batch) if read_only && last_read_only => batch.push(tool_call),
+            _ => batches.push(vec![tool_call]),
+        }
+        last_read_only = read_only;
+    }
+    batches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kimichat_models::FunctionCall;
+
+    fn tool_call(id: &str, name: &str) -> ToolCall {
+        ToolCall {
+            id: id.to_string(),
+            tool_type: "function".to_string(),
+            function: FunctionCall { name: name.to_string(), arguments: "{}".to_string() },
+        }
+    }
+
+    #[test]
+    fn test_reads_between_writes_are_batched() {
+        let calls = vec![
+            tool_call("1", "open_file"),
+            tool_call("2", "read_file"),
+            tool_call("3", "edit_file"),
+            tool_call("4", "write_file"),
+            tool_call("5", "open_file"),
+            tool_call("6", "search_files"),
+            tool_call("7", "open_file"),
+        ];
+        let batches = batch_tool_calls(&calls, |name| name != "edit_file" && name != "write_file");
+
+        let ids: Vec<Vec<&str>> = batches
+            .iter()
+            .map(|batch| batch.iter().map(|call| call.id.as_str()).collect())
+            .collect();
+        assert_eq!(ids, vec![vec!["1", "2"], vec!["3"], vec!["4"], vec!["5", "6", "7"]]);
+    }
+}
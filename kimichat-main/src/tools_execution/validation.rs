@@ -41,6 +41,7 @@ pub(crate) async fn repair_tool_call_with_model(
                 tool_call_id: None,
                 name: None,
                 reasoning: None,
+                reasoning_details: None,
             },
             Message {
                 role: "user".to_string(),
@@ -49,11 +50,14 @@ pub(crate) async fn repair_tool_call_with_model(
                 tool_call_id: None,
                 name: None,
                 reasoning: None,
+                reasoning_details: None,
             },
         ],
         tools: vec![], // No tools for repair request
         tool_choice: "none".to_string(),
         stream: None,
+        max_tokens: None,
+        reasoning_effort: None,
     };
 
     // Make API call using BluModel's API URL
@@ -91,6 +95,7 @@ pub(crate) async fn repair_tool_call_with_model(
     let _ = log_raw_response_to_file(&response_text, request_timestamp, &ModelColor::BluModel);
 
     let api_response: ChatResponse = serde_json::from_str(&response_text)?;
+    chat.cost_tracker.record_response("tool_repair", &repair_request.model, api_response.usage.as_ref());
 
     if let Some(choice) = api_response.choices.first() {
         let repaired_json = choice.message.content.trim();
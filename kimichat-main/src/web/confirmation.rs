@@ -0,0 +1,87 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Weak;
+use std::time::Duration;
+use uuid::Uuid;
+
+use kimichat_toolcore::{Confirmation, ConfirmationProvider, ConfirmationRequest};
+use crate::web::protocol::ServerMessage;
+use crate::web::session_manager::Session;
+
+/// Same limit the chat loop gives tool call confirmations
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Asks the clients of a web session, through the same pending confirmations as tool calls
+pub struct WebConfirmation {
+    session: Weak<Session>,
+}
+
+impl WebConfirmation {
+    pub fn new(session: Weak<Session>) -> Self {
+        Self { session }
+    }
+}
+
+impl std::fmt::Debug for WebConfirmation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let session_id = self.session.upgrade().map(|session| session.id);
+        f.debug_struct("WebConfirmation").field("session_id", &session_id).finish()
+    }
+}
+
+#[async_trait]
+impl ConfirmationProvider for WebConfirmation {
+    async fn confirm(&self, request: &ConfirmationRequest) -> Result<Confirmation> {
+        let Some(session) = self.session.upgrade() else {
+            return Ok(Confirmation::Rejected {
+                reason: Some("The web session has closed".to_string()),
+            });
+        };
+
+        let confirmation_id = format!("confirm-{}", Uuid::new_v4());
+        let response = session
+            .register_confirmation(confirmation_id.clone(), request.action.to_string(), request.target.clone())
+            .await;
+        session
+            .broadcast(ServerMessage::ConfirmationRequest {
+                confirmation_id: confirmation_id.clone(),
+                action: request.action.to_string(),
+                target: request.target.clone(),
+                prompt: request.prompt.clone(),
+            })
+            .await;
+
+        match tokio::time::timeout(CONFIRMATION_TIMEOUT, response).await {
+            Ok(Ok(true)) => Ok(Confirmation::Approved),
+            Ok(Ok(false)) => Ok(Confirmation::Rejected { reason: None }),
+            Ok(Err(_)) => Ok(Confirmation::Rejected {
+                reason: Some("The confirmation was abandoned".to_string()),
+            }),
+            Err(_) => {
+                session.pending_confirmations.write().await.remove(&confirmation_id);
+                Ok(Confirmation::Rejected {
+                    reason: Some("Nobody answered the confirmation within 5 minutes".to_string()),
+                })
+            }
+        }
+    }
+
+    fn name(&self) -> &str {
+        "web"
+    }
+}
+
+/// Approves the policy checks of a tool call the user already confirmed in the web UI
+#[derive(Debug)]
+pub struct ConfirmedToolCall;
+
+#[async_trait]
+impl ConfirmationProvider for ConfirmedToolCall {
+    async fn confirm(&self, _request: &ConfirmationRequest) -> Result<Confirmation> {
+        Ok(Confirmation::Approved)
+    }
+
+    fn name(&self) -> &str {
+        "web_tool_call"
+    }
+}
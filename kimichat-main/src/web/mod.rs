@@ -4,4 +4,6 @@ pub mod session_manager;
 pub mod routes;
 pub mod server;
 pub mod persistence;
+pub mod confirmation;
+pub mod output;
 
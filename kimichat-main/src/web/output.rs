@@ -0,0 +1,43 @@
+use async_trait::async_trait;
+use std::sync::Weak;
+
+use kimichat_toolcore::{OutputSink, OutputStream};
+use crate::web::protocol::ServerMessage;
+use crate::web::session_manager::Session;
+
+/// Streams a tool call's output to the clients of a web session
+pub struct WebOutput {
+    session: Weak<Session>,
+    tool_call_id: String,
+}
+
+impl WebOutput {
+    pub fn new(session: Weak<Session>, tool_call_id: String) -> Self {
+        Self { session, tool_call_id }
+    }
+}
+
+impl std::fmt::Debug for WebOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebOutput").field("tool_call_id", &self.tool_call_id).finish()
+    }
+}
+
+#[async_trait]
+impl OutputSink for WebOutput {
+    async fn output(&self, stream: OutputStream, chunk: &str) {
+        if let Some(session) = self.session.upgrade() {
+            let stream = match stream {
+                OutputStream::Stdout => "stdout",
+                OutputStream::Stderr => "stderr",
+            };
+            session
+                .broadcast(ServerMessage::ToolOutput {
+                    tool_call_id: self.tool_call_id.clone(),
+                    stream: stream.to_string(),
+                    chunk: chunk.to_string(),
+                })
+                .await;
+        }
+    }
+}
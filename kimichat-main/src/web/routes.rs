@@ -14,10 +14,12 @@ use tokio::sync::mpsc;
 use uuid::Uuid;
 
 use kimichat_models::Message as ChatMessage;
+use kimichat_agents::StreamEvent;
 use crate::{
-    api::call_api,
+    api::{call_api, stream_with_llm_client},
+    cost::CostTracker,
     web::{
-        protocol::{ClientMessage, ServerMessage, SessionConfig, SessionId, SessionInfo},
+        protocol::{CheckpointInfo, ClientMessage, ServerMessage, SessionConfig, SessionId, SessionInfo},
         session_manager::SessionManager,
     },
 };
@@ -212,6 +214,9 @@ async fn handle_client_message(
         UpdateSessionTitle { title } => {
             handle_update_session_title(title, session, state).await;
         }
+        ListCheckpoints | Undo | RestoreCheckpoint { .. } | DiffCheckpoint { .. } => {
+            handle_checkpoint_message(client_id, message, session).await;
+        }
         _ => {
             // TODO: Implement other message handlers
             eprintln!("Unhandled client message: {:?}", message);
@@ -256,6 +261,13 @@ async fn check_tool_confirmation(
             }
             (true, None)
         }
+        "apply_patch" => {
+            // The patch itself is the diff to show
+            let patch = serde_json::from_str::<serde_json::Value>(tool_args)
+                .ok()
+                .and_then(|args| args.get("patch").and_then(|v| v.as_str()).map(str::to_string));
+            (true, patch)
+        }
         "write_file" | "edit_file" => (true, None), // These also need confirmation but no pre-extracted diff
         _ => (false, None),
     }
@@ -271,21 +283,69 @@ async fn handle_chat_with_broadcast(
 
     loop {
         let kimichat = session.kimichat.lock().await;
+        let streamed = kimichat.stream_responses;
 
-        // Make API call
-        let (response, usage, _model) = call_api(
-            &kimichat,
-            &kimichat.messages,
-        )
-        .await?;
+        // Stop spending once the session's cost cap is reached
+        if let Err(e) = kimichat.cost_tracker.check_budget() {
+            drop(kimichat);
+            session.broadcast(ServerMessage::Error {
+                message: e.to_string(),
+                recoverable: false,
+            }).await;
+            break;
+        }
+
+        // Make API call, forwarding stream events to clients as they arrive
+        let (response, usage, model) = if streamed {
+            let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+            let forward_session = Arc::clone(session);
+            let forwarder = tokio::spawn(async move {
+                while let Some(msg) = event_rx.recv().await {
+                    forward_session.broadcast(msg).await;
+                }
+            });
+
+            let current_model = kimichat.current_model;
+            let result = stream_with_llm_client(
+                &kimichat,
+                &kimichat.messages,
+                &current_model,
+                // Owning the sender lets the forwarder finish once the stream ends
+                move |event| {
+                    if let Some(msg) = stream_event_message(event) {
+                        let _ = event_tx.send(msg);
+                    }
+                },
+            )
+            .await;
+
+            // Make sure every chunk is out before tool requests are broadcast
+            let _ = forwarder.await;
+            result?
+        } else {
+            call_api(&kimichat, &kimichat.messages).await?
+        };
 
         drop(kimichat); // Release lock
 
-        // Broadcast token usage
+        // Streamed reasoning already went out chunk by chunk
+        if let Some(reasoning) = response.reasoning.clone().filter(|_| !streamed) {
+            session.broadcast(ServerMessage::Reasoning { content: reasoning }).await;
+        }
+
+        // Close the streamed text bubble before any tool call UI
+        if streamed && !response.content.is_empty() {
+            session.broadcast(ServerMessage::AssistantMessageComplete).await;
+        }
+
+        // Broadcast token usage and cost
         if let Some(usage) = &usage {
             let mut kimichat = session.kimichat.lock().await;
             kimichat.total_tokens_used += usage.total_tokens;
             let session_total = kimichat.total_tokens_used;
+            let model_name = kimichat.client_config.get_model_name(model).to_string();
+            kimichat.cost_tracker.record("main", &model_name, usage);
+            let cost_msg = cost_update_message(&kimichat.cost_tracker);
             drop(kimichat);
 
             let token_msg = ServerMessage::TokenUsage {
@@ -295,6 +355,7 @@ async fn handle_chat_with_broadcast(
                 session_total,
             };
             session.broadcast(token_msg).await;
+            session.broadcast(cost_msg).await;
         }
 
         // Add assistant response to history
@@ -304,142 +365,154 @@ async fn handle_chat_with_broadcast(
         if let Some(tool_calls) = &response.tool_calls {
             tool_call_iterations += 1;
 
-            for tool_call in tool_calls {
-                // Check if tool requires confirmation
-                let work_dir = session.kimichat.lock().await.work_dir.clone();
-                let (requires_confirmation, diff) = check_tool_confirmation(
-                    &tool_call.function.name,
-                    &tool_call.function.arguments,
-                    &work_dir,
-                )
-                .await;
-
-                // Broadcast tool call request
-                let tool_msg = ServerMessage::ToolCallRequest {
-                    tool_call_id: tool_call.id.clone(),
-                    name: tool_call.function.name.clone(),
-                    arguments: serde_json::from_str(&tool_call.function.arguments)
-                        .unwrap_or(serde_json::json!({})),
-                    requires_confirmation,
-                    diff,
-                    iteration: Some(tool_call_iterations),
-                    max_iterations: Some(MAX_TOOL_ITERATIONS),
-                };
-                session.broadcast(tool_msg).await;
-
-                // If requires confirmation, wait for user response
-                if requires_confirmation {
-                    eprintln!("⏳ Registering confirmation for tool_call_id: {}", tool_call.id);
-                    let confirmation_rx = session
-                        .register_confirmation(
-                            tool_call.id.clone(),
-                            tool_call.function.name.clone(),
-                            tool_call.function.arguments.clone(),
-                        )
-                        .await;
+            // Consecutive read-only calls run together; everything else runs alone, in order
+            let batches = {
+                let kimichat = session.kimichat.lock().await;
+                crate::tools_execution::parallel::batch_tool_calls(tool_calls, |name| kimichat.is_read_only_tool(name))
+            };
 
-                    eprintln!("⏳ Waiting for user confirmation...");
-                    // Wait for confirmation (with timeout)
-                    let confirmed = match tokio::time::timeout(
-                        std::time::Duration::from_secs(300), // 5 minute timeout
-                        confirmation_rx,
+            for batch in batches {
+                // Show the whole batch and get its confirmations before running any of it.
+                // Each call is approved (and whether the user confirmed it) or denied.
+                let mut approvals: Vec<Option<bool>> = Vec::new();
+                for tool_call in &batch {
+                    // Check if tool requires confirmation
+                    let work_dir = session.kimichat.lock().await.work_dir.clone();
+                    let (requires_confirmation, diff) = check_tool_confirmation(
+                        &tool_call.function.name,
+                        &tool_call.function.arguments,
+                        &work_dir,
                     )
-                    .await
-                    {
-                        Ok(Ok(confirmed)) => {
-                            eprintln!("✅ Received confirmation: {}", confirmed);
-                            confirmed
-                        }
-                        Ok(Err(_)) => {
-                            eprintln!("❌ Confirmation channel closed");
-                            false
-                        }
-                        Err(_) => {
-                            // Timeout
-                            eprintln!("⏱️  Confirmation timeout");
-                            let error_msg = ServerMessage::Error {
-                                message: "Tool confirmation timeout (5 minutes)".to_string(),
-                                recoverable: true,
-                            };
-                            session.broadcast(error_msg).await;
-                            false
-                        }
-                    };
-
-                    if !confirmed {
-                        eprintln!("🚫 Tool execution denied");
+                    .await;
 
-                        // User denied, send error result
-                        let error_str = "Tool execution cancelled by user".to_string();
-                        let result_msg = ServerMessage::ToolCallResult {
-                            tool_call_id: tool_call.id.clone(),
-                            result: error_str.clone(),
-                            success: false,
-                            formatted_result: Some(error_str.clone()),
+                    // Broadcast tool call request
+                    let tool_msg = ServerMessage::ToolCallRequest {
+                        tool_call_id: tool_call.id.clone(),
+                        name: tool_call.function.name.clone(),
+                        arguments: serde_json::from_str(&tool_call.function.arguments)
+                            .unwrap_or(serde_json::json!({})),
+                        requires_confirmation,
+                        diff,
+                        iteration: Some(tool_call_iterations),
+                        max_iterations: Some(MAX_TOOL_ITERATIONS),
+                    };
+                    session.broadcast(tool_msg).await;
+
+                    // If requires confirmation, wait for user response
+                    if requires_confirmation {
+                        eprintln!("⏳ Registering confirmation for tool_call_id: {}", tool_call.id);
+                        let confirmation_rx = session
+                            .register_confirmation(
+                                tool_call.id.clone(),
+                                tool_call.function.name.clone(),
+                                tool_call.function.arguments.clone(),
+                            )
+                            .await;
+
+                        eprintln!("⏳ Waiting for user confirmation...");
+                        // Wait for confirmation (with timeout)
+                        let confirmed = match tokio::time::timeout(
+                            std::time::Duration::from_secs(300), // 5 minute timeout
+                            confirmation_rx,
+                        )
+                        .await
+                        {
+                            Ok(Ok(confirmed)) => {
+                                eprintln!("✅ Received confirmation: {}", confirmed);
+                                confirmed
+                            }
+                            Ok(Err(_)) => {
+                                eprintln!("❌ Confirmation channel closed");
+                                false
+                            }
+                            Err(_) => {
+                                // Timeout
+                                eprintln!("⏱️  Confirmation timeout");
+                                let error_msg = ServerMessage::Error {
+                                    message: "Tool confirmation timeout (5 minutes)".to_string(),
+                                    recoverable: true,
+                                };
+                                session.broadcast(error_msg).await;
+                                false
+                            }
                         };
-                        session.broadcast(result_msg).await;
-
-                        // Add cancellation to history
-                        session.kimichat.lock().await.messages.push(ChatMessage {
-                            role: "tool".to_string(),
-                            content: error_str,
-                            tool_calls: None,
-                            tool_call_id: Some(tool_call.id.clone()),
-                            name: Some(tool_call.function.name.clone()),
-                            reasoning: None,
-                        });
-                        continue; // Skip to next tool call
+
+                        if !confirmed {
+                            eprintln!("🚫 Tool execution denied");
+                            approvals.push(None);
+                            continue;
+                        }
                     }
+                    approvals.push(Some(requires_confirmation));
                 }
 
-                // Execute tool (either confirmed or doesn't need confirmation)
+                // Execute the approved calls. A confirmed call has the user's approval for
+                // its own policy checks; any other "ask" goes to the session's clients, one
+                // question at a time.
                 let mut kimichat = session.kimichat.lock().await;
-                let result = kimichat
-                    .execute_tool(&tool_call.function.name, &tool_call.function.arguments)
-                    .await;
-                drop(kimichat);
-
-                // Broadcast tool result
-                match result {
-                    Ok(result_str) => {
-                        let result_msg = ServerMessage::ToolCallResult {
-                            tool_call_id: tool_call.id.clone(),
-                            result: result_str.clone(),
-                            success: true,
-                            formatted_result: Some(result_str.clone()),
+                let session_confirmation: Arc<dyn kimichat_toolcore::ConfirmationProvider> = Arc::new(
+                    kimichat_toolcore::SerialConfirmation::new(Arc::clone(&kimichat.confirmation_provider)),
+                );
+                let calls: Vec<_> = batch
+                    .iter()
+                    .zip(&approvals)
+                    .filter_map(|(tool_call, approval)| {
+                        let confirmation_provider: Arc<dyn kimichat_toolcore::ConfirmationProvider> = match approval {
+                            None => return None,
+                            Some(true) => Arc::new(crate::web::confirmation::ConfirmedToolCall),
+                            Some(false) => Arc::clone(&session_confirmation),
                         };
-                        session.broadcast(result_msg).await;
-
-                        // Add tool result to history
-                        session.kimichat.lock().await.messages.push(ChatMessage {
-                            role: "tool".to_string(),
-                            content: result_str,
-                            tool_calls: None,
-                            tool_call_id: Some(tool_call.id.clone()),
-                            name: Some(tool_call.function.name.clone()),
-                            reasoning: None,
-                        });
-                    }
-                    Err(e) => {
-                        let error_str = format!("Error: {}", e);
-                        let result_msg = ServerMessage::ToolCallResult {
-                            tool_call_id: tool_call.id.clone(),
-                            result: error_str.clone(),
-                            success: false,
-                            formatted_result: Some(error_str.clone()),
-                        };
-                        session.broadcast(result_msg).await;
-
-                        // Add error to history
-                        session.kimichat.lock().await.messages.push(ChatMessage {
-                            role: "tool".to_string(),
-                            content: error_str,
-                            tool_calls: None,
-                            tool_call_id: Some(tool_call.id.clone()),
-                            name: Some(tool_call.function.name.clone()),
-                            reasoning: None,
-                        });
+                        let output_sink: Arc<dyn kimichat_toolcore::OutputSink> = Arc::new(
+                            crate::web::output::WebOutput::new(Arc::downgrade(session), tool_call.id.clone()),
+                        );
+                        Some((*tool_call, confirmation_provider, output_sink))
+                    })
+                    .collect();
+                let results = if calls.len() > 1 {
+                    kimichat.execute_read_only_tools(calls).await
+                } else {
+                    let mut results = Vec::new();
+                    for (tool_call, confirmation_provider, output_sink) in calls {
+                        results.push(
+                            kimichat
+                                .execute_tool_with_confirmation(
+                                    &tool_call.function.name,
+                                    &tool_call.function.arguments,
+                                    confirmation_provider,
+                                    output_sink,
+                                )
+                                .await,
+                        );
                     }
+                    results
+                };
+                drop(kimichat);
+
+                // Broadcast the results and add them to history, in call order
+                let mut results = results.into_iter();
+                for (tool_call, approval) in batch.iter().zip(approvals) {
+                    let (content, success) = match approval.and_then(|_| results.next()) {
+                        Some(Ok(result_str)) => (result_str, true),
+                        Some(Err(e)) => (format!("Error: {}", e), false),
+                        None => ("Tool execution cancelled by user".to_string(), false),
+                    };
+                    let result_msg = ServerMessage::ToolCallResult {
+                        tool_call_id: tool_call.id.clone(),
+                        result: content.clone(),
+                        success,
+                        formatted_result: Some(content.clone()),
+                    };
+                    session.broadcast(result_msg).await;
+
+                    session.kimichat.lock().await.messages.push(ChatMessage {
+                        role: "tool".to_string(),
+                        content,
+                        tool_calls: None,
+                        tool_call_id: Some(tool_call.id.clone()),
+                        name: Some(tool_call.function.name.clone()),
+                        reasoning: None,
+                        reasoning_details: None,
+                    });
                 }
             }
 
@@ -458,18 +531,60 @@ async fn handle_chat_with_broadcast(
         }
 
         // No tool calls - send final response and complete
-        let msg = ServerMessage::AssistantMessage {
-            content: response.content,
-            streaming: false,
-        };
-        session.broadcast(msg).await;
-        session.broadcast(ServerMessage::AssistantMessageComplete).await;
+        if !streamed {
+            let msg = ServerMessage::AssistantMessage {
+                content: response.content,
+                streaming: false,
+            };
+            session.broadcast(msg).await;
+            session.broadcast(ServerMessage::AssistantMessageComplete).await;
+        } else if response.content.is_empty() {
+            session.broadcast(ServerMessage::AssistantMessageComplete).await;
+        }
         break;
     }
 
     Ok(())
 }
 
+/// Map a stream event to the message clients should see, if any
+fn stream_event_message(event: &StreamEvent) -> Option<ServerMessage> {
+    match event {
+        StreamEvent::TextDelta(text) => Some(ServerMessage::AssistantMessageChunk {
+            chunk: text.clone(),
+        }),
+        StreamEvent::ReasoningDelta(text) => Some(ServerMessage::ReasoningChunk {
+            chunk: text.clone(),
+        }),
+        StreamEvent::ToolCallStart { index, id, name } => Some(ServerMessage::ToolCallDelta {
+            index: *index,
+            tool_call_id: Some(id.clone()),
+            name: Some(name.clone()),
+            arguments_delta: String::new(),
+        }),
+        StreamEvent::ToolCallArgumentsDelta { index, arguments } => Some(ServerMessage::ToolCallDelta {
+            index: *index,
+            tool_call_id: None,
+            name: None,
+            arguments_delta: arguments.clone(),
+        }),
+        _ => None,
+    }
+}
+
+/// Current spending of a session, as sent to clients
+fn cost_update_message(cost_tracker: &CostTracker) -> ServerMessage {
+    let snapshot = cost_tracker.snapshot();
+    ServerMessage::CostUpdate {
+        session_cost: snapshot.total.cost,
+        budget: snapshot.budget,
+        by_agent: snapshot.by_agent
+            .into_iter()
+            .map(|(name, totals)| (name, totals.cost))
+            .collect(),
+    }
+}
+
 /// Generate a title for the session based on the first user message
 async fn generate_session_title(
     first_message: &str,
@@ -488,13 +603,16 @@ async fn generate_session_title(
             tool_call_id: None,
             name: None,
             reasoning: None,
+            reasoning_details: None,
         }
     ];
 
     // Make an isolated API call
     let kimichat = session.kimichat.lock().await;
     match call_api(&kimichat, &title_prompt).await {
-        Ok((response, _, _)) => {
+        Ok((response, usage, model)) => {
+            let model_name = kimichat.client_config.get_model_name(model);
+            kimichat.cost_tracker.record_response("title", model_name, usage.as_ref());
             let title = response.content.trim().to_string();
             // Remove quotes if present
             let title = title.trim_matches('"').trim_matches('\'').to_string();
@@ -529,8 +647,12 @@ async fn handle_send_message(
         tool_call_id: None,
         name: None,
         reasoning: None,
+        reasoning_details: None,
     });
 
+    // Files this message leads the tools to change go into a new checkpoint
+    kimichat.checkpoint_store.begin_turn(&content);
+
     // Capture the use_agents flag before dropping the lock
     let use_agents = kimichat.use_agents;
 
@@ -578,6 +700,10 @@ async fn handle_send_message(
                 session.broadcast(error_msg).await;
             }
         }
+
+        // Agents meter their own calls; report what the whole run cost
+        let cost_msg = cost_update_message(&session.kimichat.lock().await.cost_tracker);
+        session.broadcast(cost_msg).await;
     } else {
         // Single LLM mode - use custom loop with broadcasts
         if let Err(e) = handle_chat_with_broadcast(session).await {
@@ -625,6 +751,55 @@ async fn handle_switch_model(
     }
 }
 
+/// Handle the checkpoint messages. Restores go to every client, since they
+/// change the files under everyone; lists and diffs only to the one asking.
+async fn handle_checkpoint_message(
+    client_id: Uuid,
+    message: ClientMessage,
+    session: &Arc<crate::web::session_manager::Session>,
+) {
+    let store = Arc::clone(&session.kimichat.lock().await.checkpoint_store);
+    let restored = |checkpoint_id: u64, files: Vec<kimichat_checkpoints::RestoredFile>| ServerMessage::CheckpointRestored {
+        checkpoint_id,
+        files: files.into_iter().map(|file| file.display_path).collect(),
+    };
+
+    let result = match message {
+        ClientMessage::ListCheckpoints => store.list().map(|checkpoints| ServerMessage::Checkpoints {
+            checkpoints: checkpoints
+                .into_iter()
+                .map(|checkpoint| CheckpointInfo {
+                    id: checkpoint.id,
+                    created_at: checkpoint.created_at.to_rfc3339(),
+                    description: checkpoint.description,
+                    files: checkpoint.files.into_iter().map(|file| file.display_path).collect(),
+                })
+                .collect(),
+        }),
+        ClientMessage::Undo => match store.undo() {
+            Ok(Some((checkpoint_id, files))) => Ok(restored(checkpoint_id, files)),
+            Ok(None) => Err(anyhow::anyhow!("Nothing to undo")),
+            Err(e) => Err(e),
+        },
+        ClientMessage::RestoreCheckpoint { checkpoint_id } => {
+            store.restore(checkpoint_id).map(|files| restored(checkpoint_id, files))
+        }
+        ClientMessage::DiffCheckpoint { checkpoint_id } => store
+            .diff(checkpoint_id)
+            .map(|diff| ServerMessage::CheckpointDiff { checkpoint_id, diff }),
+        _ => return,
+    };
+
+    match result {
+        Ok(msg @ ServerMessage::CheckpointRestored { .. }) => session.broadcast(msg).await,
+        Ok(msg) => session.send_to_client(client_id, msg).await,
+        Err(e) => {
+            let msg = ServerMessage::Error { message: e.to_string(), recoverable: true };
+            session.send_to_client(client_id, msg).await;
+        }
+    }
+}
+
 /// Handle UpdateSessionTitle
 async fn handle_update_session_title(
     title: Option<String>,
@@ -11,6 +11,7 @@ use crate::config::ClientConfig;
 use kimichat_policy::PolicyManager;
 use crate::web::protocol::{ServerMessage, SessionConfig, SessionInfo};
 use crate::web::persistence::{SessionPersistence, PersistentSession};
+use crate::web::confirmation::WebConfirmation;
 use crate::chat::state::ChatState;
 use crate::KimiChat;
 
@@ -257,7 +258,6 @@ impl SessionManager {
         );
 
         kimichat.current_model = model;
-        kimichat.non_interactive = true; // Web sessions should not prompt for input
 
         // Create session
         let session = Arc::new(Session::new(
@@ -266,6 +266,14 @@ impl SessionManager {
             kimichat,
         ));
 
+        // Web sessions ask their clients instead of the terminal
+        {
+            let mut kimichat = session.kimichat.lock().await;
+            kimichat.confirmation_provider = Arc::new(WebConfirmation::new(Arc::downgrade(&session)));
+            kimichat.session_type = kimichat_policy::SessionType::Web;
+            kimichat.checkpoint_store = KimiChat::checkpoint_store_for(&session.id.to_string());
+        }
+
         // Store session
         self.sessions.write().await.insert(session_id, session.clone());
 
@@ -344,7 +352,6 @@ impl SessionManager {
                         kimichat.messages = persistent_session.chat_state.messages;
                         kimichat.current_model = persistent_session.chat_state.current_model;
                         kimichat.total_tokens_used = persistent_session.chat_state.total_tokens_used;
-                        kimichat.non_interactive = true;
 
                         // Parse timestamps
                         let created_at = match DateTime::parse_from_rfc3339(&persistent_session.created_at) {
@@ -369,8 +376,16 @@ impl SessionManager {
                         *session.last_activity.lock().await = last_activity;
                         *session.title.write().await = persistent_session.title;
 
+                        let session = Arc::new(session);
+                        {
+                            let mut kimichat = session.kimichat.lock().await;
+                            kimichat.confirmation_provider = Arc::new(WebConfirmation::new(Arc::downgrade(&session)));
+                            kimichat.session_type = kimichat_policy::SessionType::Web;
+            kimichat.checkpoint_store = KimiChat::checkpoint_store_for(&session.id.to_string());
+                        }
+
                         // Store session
-                        self.sessions.write().await.insert(session_id, Arc::new(session));
+                        self.sessions.write().await.insert(session_id, session);
                         loaded_count += 1;
                     }
                     Err(e) => {